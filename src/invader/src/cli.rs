@@ -3,11 +3,13 @@
 use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use ringhopper::primitives::engine::Engine;
 use ringhopper::primitives::tag::ParseStrictness;
+use ringhopper::tag::dependency::index::DependencyIndex;
 use ringhopper::tag::tree::VirtualTagsDirectory;
 use ringhopper_engines::ALL_SUPPORTED_ENGINES;
-use super::util::get_tty_metadata;
+use super::util::{get_tty_metadata, make_stdout_logger};
 
 pub struct CommandLineParser {
     description: &'static str,
@@ -173,6 +175,7 @@ impl CommandLineParser {
     fn add_help_with_callback(mut self, callback: fn(&CommandLineParser) -> Result<(), String>) -> Self {
         let p = Parameter {
             values: None,
+            occurrences: Vec::new(),
             name: "help",
             short: Some('h'),
             description: "Show help for this verb.",
@@ -198,6 +201,7 @@ impl CommandLineParser {
     pub fn add_tags(mut self, multiple: bool) -> Self {
         let p = Parameter {
             values: None,
+            occurrences: Vec::new(),
             name: "tags",
             short: Some('t'),
             description: match multiple {
@@ -220,6 +224,7 @@ impl CommandLineParser {
     pub fn add_overwrite(mut self) -> Self {
         let p = Parameter {
             values: None,
+            occurrences: Vec::new(),
             name: "overwrite",
             short: Some('o'),
             description: "Overwrite if the output file already exists.",
@@ -239,6 +244,7 @@ impl CommandLineParser {
     pub fn add_cow_tags(mut self) -> Self {
         let p = Parameter {
             values: None,
+            occurrences: Vec::new(),
             name: "cow",
             short: Some('c'),
             description: "Set a copy-on-write directory for outputting tags.",
@@ -258,6 +264,7 @@ impl CommandLineParser {
     pub fn add_no_safeguards(mut self) -> Self {
         let p = Parameter {
             values: None,
+            occurrences: Vec::new(),
             name: "no-safeguards",
             short: Some('n'),
             description: "Allow all tag data to be edited (proceed at your own risk)",
@@ -274,9 +281,30 @@ impl CommandLineParser {
         self
     }
 
+    pub fn add_dependency_index(mut self) -> Self {
+        let p = Parameter {
+            values: None,
+            occurrences: Vec::new(),
+            name: "dependency-index",
+            short: Some('D'),
+            description: "Use a dependency index file to speed up dependency lookups. It will be created or updated as needed.",
+            default_values: None,
+            value_type: Some(CommandLineValueType::Path),
+            required: false,
+            value_count: 1,
+            usage: "<file>",
+            multiple: false,
+        };
+
+        assert!(self.standard_parameters.get(&StandardParameterType::DependencyIndex).is_none());
+        self.standard_parameters.insert(StandardParameterType::DependencyIndex, p);
+        self
+    }
+
     pub fn add_jobs(mut self) -> Self {
         let p = Parameter {
             values: None,
+            occurrences: Vec::new(),
             name: "jobs",
             short: Some('j'),
             description: "Set number of threads for this task.",
@@ -296,6 +324,7 @@ impl CommandLineParser {
     pub fn add_data(mut self) -> Self {
         let p = Parameter {
             values: None,
+            occurrences: Vec::new(),
             name: "data",
             short: Some('d'),
             description: "Set a data directory. Default: data",
@@ -315,6 +344,7 @@ impl CommandLineParser {
     pub fn add_engine(mut self) -> Self {
         let p = Parameter {
             values: None,
+            occurrences: Vec::new(),
             name: "engine",
             short: Some('e'),
             description: "Set an engine. This parameter is required.",
@@ -334,6 +364,7 @@ impl CommandLineParser {
     pub fn add_maps(mut self) -> Self {
         let p = Parameter {
             values: None,
+            occurrences: Vec::new(),
             name: "maps",
             short: Some('m'),
             description: "Set a maps directory. Default: maps",
@@ -374,6 +405,7 @@ impl CommandLineParser {
     }
 
     pub fn parse<T>(mut self, mut args: T) -> Result<CommandLineArgs, String> where T: Iterator<Item=String> {
+        let mut occurrence = 0;
         'arg_iter: loop {
             let next_arg = if let Some(n) = args.next() {
                 n
@@ -410,6 +442,8 @@ impl CommandLineParser {
                     };
                     values.push(parsed_argument);
                 }
+                p.occurrences.push(occurrence);
+                occurrence += 1;
                 if p.multiple {
                     if let Some(n) = &mut p.values {
                         n.append(&mut values);
@@ -536,6 +570,33 @@ impl CommandLineArgs {
         dir
     }
 
+    /// Load and update the dependency index, if one was given, and set it on the tags directory.
+    ///
+    /// Panics if Dependency Index was not added.
+    pub fn load_dependency_index(&self, tags: &mut VirtualTagsDirectory) -> Result<(), String> {
+        let Some(path) = self.standard_parameters
+            .get(&StandardParameterType::DependencyIndex)
+            .expect("dependency index not added as standard parameter")
+            .values
+            .as_ref()
+            .map(|v| v[0].path()) else {
+            return Ok(())
+        };
+
+        let mut index = DependencyIndex::load(path, tags).map_err(|e| format!("Failed to load the dependency index: {e}"))?;
+        index.update(tags);
+
+        let mut unindexed: Vec<_> = index.get_unindexed_tags().iter().collect();
+        unindexed.sort_by(|a, b| a.0.cmp(b.0));
+        let logger = make_stdout_logger();
+        for (tag, error) in unindexed {
+            logger.warning_fmt_ln(format_args!("Warning: Could not index {tag}: {error}"));
+        }
+        index.save(path).map_err(|e| format!("Failed to save the dependency index: {e}"))?;
+        tags.set_dependency_index(Some(Arc::new(index)));
+        Ok(())
+    }
+
     /// Get the Engine parameter.
     ///
     /// Panics if Engine was not added.
@@ -619,6 +680,26 @@ impl CommandLineArgs {
         self.custom_parameters.get(what).expect("custom parameter not added on init but expected").values.as_ref().map(Vec::as_slice)
     }
 
+    /// Get the values of each time the given custom parameters were passed, in the order they were passed.
+    ///
+    /// Panics if any were not added.
+    pub fn get_custom_in_order(&self, what: &[&'static str]) -> Vec<(&'static str, &[CommandLineValue])> {
+        let mut occurrences = Vec::new();
+        for name in what {
+            let parameter = self.custom_parameters.get(name).expect("custom parameter not added on init but expected");
+            let Some(values) = parameter.values.as_ref() else {
+                continue
+            };
+            for (index, chunk) in values.chunks(parameter.value_count.max(1)).enumerate() {
+                if let Some(occurrence) = parameter.occurrences.get(index) {
+                    occurrences.push((*occurrence, *name, chunk));
+                }
+            }
+        }
+        occurrences.sort_by_key(|o| o.0);
+        occurrences.into_iter().map(|(_, name, values)| (name, values)).collect()
+    }
+
     /// Get the extra parameters.
     pub fn get_extra(&self) -> &[String] {
         self.extra_parameters.as_slice()
@@ -636,7 +717,10 @@ pub struct Parameter {
     default_values: Option<Vec<CommandLineValue>>,
     value_count: usize,
     multiple: bool,
-    required: bool
+    required: bool,
+
+    /// Position of each time this parameter was passed among all parameters passed.
+    occurrences: Vec<usize>
 }
 impl Parameter {
     pub fn new(
@@ -664,7 +748,8 @@ impl Parameter {
             value_count,
             multiple,
             usage,
-            required
+            required,
+            occurrences: Vec::new()
         }
     }
 
@@ -683,6 +768,7 @@ enum StandardParameterType {
     Overwrite,
     Jobs,
     Engine,
+    DependencyIndex,
     NoSafeguards,
 }
 
@@ -54,3 +54,25 @@ fn test_argument_custom() {
     assert!(parser_data_set2.get_custom("test-take-string").is_some_and(|t| t[0].string() == "hello world"));
     assert!(parser_data_set2.get_custom("test-take-path").is_some_and(|t| t[0].path().to_str().unwrap() == "something.txt"));
 }
+
+#[test]
+fn test_argument_custom_in_order() {
+    let parser = CommandLineParser::new("Test", "Test")
+        .add_custom_parameter(Parameter::new("first", 'f', "test", "", Some(CommandLineValueType::String), 1, None, true, false))
+        .add_custom_parameter(Parameter::new("second", 's', "test", "", Some(CommandLineValueType::String), 2, None, true, false))
+        .add_custom_parameter(Parameter::new("unused", 'u', "test", "", Some(CommandLineValueType::String), 1, None, true, false))
+        .parse_strs(&["--second", "a", "b", "--first", "c", "-sf", "d", "e", "f"]).unwrap();
+
+    let in_order: Vec<(&str, Vec<&str>)> = parser
+        .get_custom_in_order(&["first", "second", "unused"])
+        .into_iter()
+        .map(|(name, values)| (name, values.iter().map(|v| v.string()).collect()))
+        .collect();
+
+    assert_eq!(in_order, vec![
+        ("second", vec!["a", "b"]),
+        ("first", vec!["c"]),
+        ("second", vec!["d", "e"]),
+        ("first", vec!["f"]),
+    ]);
+}
@@ -19,6 +19,7 @@ mod tag_collection;
 mod nudge;
 mod compare;
 mod convert;
+mod convert_scenario_type;
 mod extract;
 mod list_engines;
 mod undefault;
@@ -31,6 +32,19 @@ mod bludgeon;
 mod recompress_vertices;
 mod dependency_tree;
 mod refactor_paths;
+mod model_animations;
+mod collision_geometry;
+mod physics;
+mod structure;
+mod lightmaps;
+mod import_lightmaps;
+mod bsp_info;
+mod merge_scenario;
+mod clean_scenario;
+mod placements;
+mod tag_json;
+mod edit;
+mod query;
 
 pub struct Verb {
     pub name: &'static str,
@@ -50,21 +64,37 @@ pub const ALL_VERBS: &'static [Verb] = &[
     Verb::new("archive-scenario", "Create a .7z of a map's tag structure", archive::archive_scenario),
     Verb::new("archive-tag", "Create a .7z of a tag and its dependencies", archive::archive_tag),
     Verb::new("bludgeon", "Automatically repair common issues with tags", bludgeon::bludgeon),
+    Verb::new("bsp-info", "Display cluster, portal, and palette information for scenario_structure_bsp tags", bsp_info::bsp_info),
+    Verb::new("clean-scenario", "Remove unused palette entries and object names from scenario tags", clean_scenario::clean_scenario),
+    Verb::new("collision-geometry", "Generate model_collision_geometry tags from JMS data", collision_geometry::collision_geometry),
     Verb::new("compare", "Compare tags between two tag sources", compare::compare),
     Verb::new("convert", "Convert tags to another tag group", convert::convert),
+    Verb::new("convert-scenario-type", "Convert scenarios between singleplayer, multiplayer, and user interface", convert_scenario_type::convert_scenario_type),
     Verb::new("dependency-list", "View dependencies of tags", dependency_list::dependency_list),
     Verb::new("dependency-tree", "View dependencies of a tag in a recursive tree", dependency_tree::dependency_tree),
+    Verb::new("edit", "Get, set, insert, and delete fields in tags", edit::edit),
+    Verb::new("export-placements", "Export scenario placements to JSON or CSV", placements::export_placements),
     Verb::new("extract", "Extract tags from a map", extract::extract),
+    Verb::new("import-lightmaps", "Import externally baked lightmaps into scenario_structure_bsp tags", import_lightmaps::import_lightmaps),
+    Verb::new("import-placements", "Import scenario placements from JSON or CSV", placements::import_placements),
+    Verb::new("json-to-tag", "Generate tags from JSON in data", tag_json::json_to_tag),
+    Verb::new("lightmaps", "Bake lightmaps for scenario_structure_bsp tags", lightmaps::lightmaps),
     Verb::new("list-engines", "List all available engine targets", list_engines::list_engines),
     Verb::new("list-scenario-tags", "View all tags needed to build a scenario into a map", dependency_list::list_scenario_tags),
+    Verb::new("merge-scenario", "Merge child scenarios into scenario tags", merge_scenario::merge_scenario),
+    Verb::new("model-animations", "Generate model_animations tags from animation source data", model_animations::model_animations),
     Verb::new("nudge", "Fix floating point precision errors from tag extraction", nudge::nudge),
+    Verb::new("physics", "Generate physics tags from JMS mass point markers", physics::physics),
     Verb::new("plate", "Generate color plates for bitmaps", plate::plate),
+    Verb::new("query", "Search tags for fields matching predicates", query::query),
     Verb::new("recompress-vertices", "Recompress model vertices", recompress_vertices::recompress_vertices),
     Verb::new("recover", "Recover data from tags", recover::recover),
     Verb::new("refactor-groups", "Batch refactor dependencies by tag group if the new dependency exists", refactor_groups::refactor_groups),
     Verb::new("refactor-paths", "Batch refactor dependencies by tag path (file extensions cannot be changed)", refactor_paths::refactor_paths),
     Verb::new("strip", "Clean tags", strip::strip),
+    Verb::new("structure", "Generate scenario_structure_bsp tags from JMS level data", structure::structure),
     Verb::new("tag-collection", "Generate tag_collection tags from data", tag_collection::tag_collection),
+    Verb::new("tag-to-json", "Write tags as JSON to data", tag_json::tag_to_json),
     Verb::new("ui-widget-collection", "Generate ui_widget_collection tags from data", tag_collection::ui_widget_collection),
     Verb::new("undefault", "Strip default values from tags", undefault::undefault),
     Verb::new("unicode-strings", "Generate unicode_string_list tags from data", unicode_strings::unicode_strings),
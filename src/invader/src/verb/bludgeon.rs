@@ -1,6 +1,6 @@
 use std::env::Args;
 use crate::cli::CommandLineParser;
-use ringhopper::{primitives::tag::ParseStrictness, tag::{bludgeon::{self, BludgeonResult}, tree::TagTree}};
+use ringhopper::{error::Error, primitives::tag::ParseStrictness, tag::{bludgeon::{self, BludgeonResult}, tree::TagTree}};
 use crate::threading::{DisplayMode, do_with_threads, ProcessSuccessType};
 use crate::util::make_stdout_logger;
 
@@ -17,10 +17,24 @@ pub fn bludgeon(args: Args, description: &'static str) -> Result<(), String> {
     let mut directory = parser.get_virtual_tags_directory();
     directory.set_strictness(ParseStrictness::Relaxed);
 
-    do_with_threads(parser.get_virtual_tags_directory(), parser, &tag, None, (), DisplayMode::ShowAll, make_stdout_logger(), |context, path, _, _| {
+    do_with_threads(directory, parser, &tag, None, (), DisplayMode::ShowAll, make_stdout_logger(), |context, path, _, logger| {
+        let mut changes = Vec::new();
+        if let Some((_, file_path)) = context.tags_directory.path_for_tag(path) {
+            let file = std::fs::read(&file_path).map_err(|e| Error::FailedToReadFile(file_path, e))?;
+            bludgeon::find_values_reset_on_parse(&file, &mut changes)?;
+        }
+
         let mut tag = context.tags_directory.open_tag_copy(&path)?;
+        let result = bludgeon::bludgeon_tag(tag.as_mut(), path, &mut changes);
+
+        if !changes.is_empty() {
+            let locked = logger.lock();
+            for change in changes {
+                locked.neutral_fmt_ln(format_args!("{path}: {change}"));
+            }
+        }
 
-        match bludgeon::bludgeon_tag(tag.as_mut(), path) {
+        match result {
             BludgeonResult::CannotRepair => Ok(ProcessSuccessType::Skipped("cannot repair; tag is FUBAR")),
             BludgeonResult::Done => ProcessSuccessType::wrap_write_result(context.tags_directory.write_tag(path, tag.as_ref()))
         }
@@ -0,0 +1,59 @@
+use std::env::Args;
+use crate::cli::{CommandLineParser, CommandLineValueType, Parameter};
+use ringhopper::definitions::ScenarioStructureBSP;
+use ringhopper::primitives::primitive::{TagGroup, TagPath};
+use ringhopper::tag::scenario_structure_bsp::clusters::analyze_clusters;
+use ringhopper::tag::tree::TagTree;
+use crate::util::make_stdout_logger;
+
+pub fn bsp_info(args: Args, description: &'static str) -> Result<(), String> {
+    let parser = CommandLineParser::new(description, "<scenario_structure_bsp> [args]")
+        .add_tags(true)
+        .add_help()
+        .add_custom_parameter(Parameter::new("dot", 'D', "Write the cluster graph to a Graphviz DOT file.", "<file>", Some(CommandLineValueType::Path), 1, None, false, false))
+        .set_required_extra_parameters(1)
+        .parse(args)?;
+
+    let tags = parser.get_virtual_tags_directory();
+    let tag_path = str_unwrap!(TagPath::new(&parser.get_extra()[0], TagGroup::ScenarioStructureBSP), "Invalid tag path: {error}");
+    let tag = str_unwrap!(tags.open_tag_copy(&tag_path), "Failed to open tag: {error}");
+    let bsp: &ScenarioStructureBSP = tag.get_ref().unwrap();
+    let report = analyze_clusters(bsp);
+
+    let logger = make_stdout_logger();
+    logger.neutral_fmt_ln(format_args!("Clusters: {}", report.clusters.len()));
+    logger.neutral_fmt_ln(format_args!("Portals: {}", bsp.cluster_portals.items.len()));
+    logger.neutral_fmt_ln(format_args!("Triangles: {}", bsp.surfaces.items.len()));
+
+    let or_none = |s: &Option<String>| s.clone().unwrap_or_else(|| "none".to_owned());
+    for (index, cluster) in report.clusters.iter().enumerate() {
+        logger.neutral_ln("");
+        logger.neutral_fmt_ln(format_args!("Cluster #{index}"));
+        logger.neutral_fmt_ln(format_args!("    Triangles: {}", cluster.triangle_count));
+        logger.neutral_fmt_ln(format_args!("    Subclusters: {}", cluster.subcluster_count));
+        logger.neutral_fmt_ln(format_args!("    Sky: {}", cluster.sky.map(|s| format!("#{s}")).unwrap_or_else(|| "none".to_owned())));
+        logger.neutral_fmt_ln(format_args!("    Fog: {}", or_none(&cluster.fog)));
+        logger.neutral_fmt_ln(format_args!("    Weather: {}", or_none(&cluster.weather)));
+        logger.neutral_fmt_ln(format_args!("    Background sound: {}", or_none(&cluster.background_sound)));
+        logger.neutral_fmt_ln(format_args!("    Sound environment: {}", or_none(&cluster.sound_environment)));
+        let portals: Vec<String> = cluster.portals.iter().map(|(portal, other)| format!("#{other} (portal #{portal})")).collect();
+        logger.neutral_fmt_ln(format_args!("    Connects to: {}", if portals.is_empty() { "nothing".to_owned() } else { portals.join(", ") }));
+    }
+
+    if !report.notes.is_empty() || !report.problems.is_empty() {
+        logger.neutral_ln("");
+        for note in &report.notes {
+            logger.neutral_fmt_ln(format_args!("Note: {note}"));
+        }
+        for problem in &report.problems {
+            logger.warning_fmt_ln(format_args!("Warning: {problem}"));
+        }
+    }
+
+    if let Some(dot) = parser.get_custom("dot") {
+        let file = dot[0].path();
+        str_unwrap!(std::fs::write(file, report.to_dot()), "Failed to write {}: {error}", file.display());
+    }
+
+    Ok(())
+}
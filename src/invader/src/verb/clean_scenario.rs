@@ -0,0 +1,36 @@
+use std::env::Args;
+use crate::cli::CommandLineParser;
+use ringhopper::definitions::Scenario;
+use ringhopper::primitives::primitive::TagGroup;
+use ringhopper::tag::scenario::clean::clean_scenario as clean_scenario_tag;
+use ringhopper::tag::tree::TagTree;
+use crate::threading::{DisplayMode, do_with_threads, ProcessSuccessType};
+use crate::util::make_stdout_logger;
+
+pub fn clean_scenario(args: Args, description: &'static str) -> Result<(), String> {
+    let parser = CommandLineParser::new(description, "<scenario*> [args]")
+        .add_tags(false)
+        .add_help()
+        .add_cow_tags()
+        .add_jobs()
+        .set_required_extra_parameters(1)
+        .parse(args)?;
+
+    let tag = parser.get_extra()[0].clone();
+    do_with_threads(parser.get_virtual_tags_directory(), parser, &tag, Some(TagGroup::Scenario), (), DisplayMode::ShowAll, make_stdout_logger(), |context, path, _, logger| {
+        let mut scenario = context.tags_directory.open_tag_copy(path)?.get_ref::<Scenario>().unwrap().to_owned();
+        let report = clean_scenario_tag(&mut scenario)?;
+        if report.is_empty() {
+            return Ok(ProcessSuccessType::Skipped("nothing to clean"))
+        }
+
+        for (palette, reference) in &report.removed_palette_entries {
+            logger.neutral_fmt_ln(format_args!("{path}: removed unused {palette} entry {reference}"));
+        }
+        for name in &report.removed_object_names {
+            logger.neutral_fmt_ln(format_args!("{path}: removed unused object name `{name}`"));
+        }
+
+        ProcessSuccessType::wrap_write_result(context.tags_directory.write_tag(path, &scenario))
+    })
+}
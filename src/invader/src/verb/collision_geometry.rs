@@ -0,0 +1,69 @@
+use std::env::Args;
+use std::path::{Path, PathBuf};
+use crate::cli::CommandLineParser;
+use ringhopper::data::jms::JMS;
+use ringhopper::definitions::ModelCollisionGeometry;
+use ringhopper::error::{Error, RinghopperResult};
+use ringhopper::primitives::primitive::TagGroup;
+use ringhopper::tag::model_collision_geometry::compile_collision_geometry;
+use ringhopper::tag::tree::TagTree;
+use crate::threading::{DisplayMode, do_with_threads, ProcessSuccessType};
+use crate::util::{make_stdout_logger, read_file};
+
+pub fn collision_geometry(args: Args, description: &'static str) -> Result<(), String> {
+    let parser = CommandLineParser::new(description, "<model_collision_geometry*> [args]")
+        .add_tags(false)
+        .add_data()
+        .add_help()
+        .add_cow_tags()
+        .add_jobs()
+        .set_required_extra_parameters(1)
+        .parse(args)?;
+
+    let tag = parser.get_extra()[0].clone();
+    do_with_threads(parser.get_virtual_tags_directory(), parser, &tag, Some(TagGroup::ModelCollisionGeometry), (), DisplayMode::ShowAll, make_stdout_logger(), |context, path, _, _| {
+        let data_path = context.args.get_data().join(path.to_native_path());
+        let physics_dir = data_path.parent().unwrap().join("physics");
+
+        let mut permutations = Vec::new();
+        for (name, file) in find_jms_files(&physics_dir)? {
+            let text = read_file(&file)?;
+            let text = std::str::from_utf8(&text).map_err(|_| Error::Other(format!("{} is not valid UTF-8", file.display())))?;
+            let jms = JMS::from_text(text).map_err(|e| Error::Other(format!("failed to read {}: {e}", file.display())))?;
+            permutations.push((name, jms));
+        }
+        if permutations.is_empty() {
+            return Err(Error::Other(format!("no JMS files found in {}", physics_dir.display())))
+        }
+
+        let existing = if context.tags_directory.contains(path) {
+            Some(context.tags_directory.open_tag_copy(path)?.get_ref::<ModelCollisionGeometry>().unwrap().to_owned())
+        }
+        else {
+            None
+        };
+
+        let tag = compile_collision_geometry(&permutations, existing.as_ref())?;
+        ProcessSuccessType::wrap_write_result(context.tags_directory.write_tag(path, &tag))
+    })
+}
+
+fn find_jms_files(directory: &Path) -> RinghopperResult<Vec<(String, PathBuf)>> {
+    let entries = std::fs::read_dir(directory).map_err(|e| Error::FailedToReadFile(directory.to_owned(), e))?;
+
+    let mut files = Vec::new();
+    for entry in entries {
+        let file = entry.map_err(|e| Error::FailedToReadFile(directory.to_owned(), e))?.path();
+        if !file.extension().is_some_and(|e| e.eq_ignore_ascii_case("jms")) {
+            continue
+        }
+        let Some(name) = file.file_stem().and_then(|n| n.to_str()) else {
+            continue
+        };
+        files.push((name.to_owned(), file));
+    }
+
+    // Sort so permutations are always in the same order.
+    files.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(files)
+}
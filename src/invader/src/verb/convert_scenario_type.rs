@@ -0,0 +1,69 @@
+use std::env::Args;
+use crate::cli::CommandLineParser;
+use ringhopper::definitions::{Scenario, ScenarioType};
+use ringhopper::primitives::dynamic::DynamicEnumImpl;
+use ringhopper::primitives::engine::Engine;
+use ringhopper::primitives::primitive::TagGroup;
+use ringhopper::tag::scenario::convert_type::{convert_scenario_type as convert_scenario_tag_type, get_missing_required_tags};
+use ringhopper::tag::tree::TagTree;
+use crate::threading::{DisplayMode, do_with_threads, ProcessSuccessType};
+use crate::util::make_stdout_logger;
+
+#[derive(Clone)]
+struct UserData {
+    engine: &'static Engine,
+    scenario_type: ScenarioType
+}
+
+pub fn convert_scenario_type(args: Args, description: &'static str) -> Result<(), String> {
+    let parser = CommandLineParser::new(description, "<scenario*> <type> [args]")
+        .add_tags(false)
+        .add_help()
+        .add_cow_tags()
+        .add_engine()
+        .add_jobs()
+        .set_required_extra_parameters(2)
+        .parse(args)?;
+
+    let tag = parser.get_extra()[0].clone();
+    let type_str = &parser.get_extra()[1];
+    let scenario_type = ScenarioType::from_str(type_str)
+        .ok_or_else(|| format!("{type_str} is not a valid scenario type (expected one of: {})", ScenarioType::str_vals().join(", ")))?;
+
+    let user_data = UserData {
+        engine: parser.get_engine(),
+        scenario_type
+    };
+
+    let logger = make_stdout_logger();
+    do_with_threads(parser.get_virtual_tags_directory(), parser, &tag, Some(TagGroup::Scenario), user_data, DisplayMode::ShowAll, logger, |context, path, user_data, logger| {
+        let mut scenario = context.tags_directory.open_tag_copy(path)?.get_ref::<Scenario>().unwrap().to_owned();
+
+        for missing in get_missing_required_tags(&context.tags_directory, user_data.engine, user_data.scenario_type) {
+            logger.warning_fmt_ln(format_args!("Warning: {path}: required tag {missing} is missing for {} scenarios", user_data.scenario_type.to_str()));
+        }
+
+        if scenario._type == user_data.scenario_type {
+            return Ok(ProcessSuccessType::Skipped("already the requested type"))
+        }
+
+        let conversion = convert_scenario_tag_type(&mut scenario, user_data.scenario_type);
+        let removed = [
+            (conversion.removed_netgame_flags, "netgame flag(s)"),
+            (conversion.removed_netgame_equipment, "netgame equipment"),
+            (conversion.removed_starting_equipment, "starting equipment"),
+            (conversion.removed_player_starting_locations, "player starting location(s)")
+        ];
+        for (count, what) in removed.into_iter().filter(|(count, _)| *count > 0) {
+            logger.neutral_fmt_ln(format_args!("{path}: removed {count} {what}"));
+        }
+        if conversion.updated_player_starting_locations > 0 {
+            logger.neutral_fmt_ln(format_args!("{path}: updated spawn types of {} player starting location(s)", conversion.updated_player_starting_locations));
+        }
+        for warning in &conversion.warnings {
+            logger.warning_fmt_ln(format_args!("Warning: {path}: {warning}"));
+        }
+
+        ProcessSuccessType::wrap_write_result(context.tags_directory.write_tag(path, &scenario))
+    })
+}
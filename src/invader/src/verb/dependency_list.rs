@@ -9,6 +9,7 @@ use crate::util::make_stdout_logger;
 pub fn dependency_list(args: Args, description: &'static str) -> Result<(), String> {
     let parser = CommandLineParser::new(description, "<tag> [args]")
         .add_tags(true)
+        .add_dependency_index()
         .add_custom_parameter(Parameter::single(
             "recursive",
             'r',
@@ -27,7 +28,8 @@ pub fn dependency_list(args: Args, description: &'static str) -> Result<(), Stri
         .set_required_extra_parameters(1)
         .parse(args)?;
 
-    let tags = parser.get_virtual_tags_directory();
+    let mut tags = parser.get_virtual_tags_directory();
+    parser.load_dependency_index(&mut tags)?;
     let tag_path = str_unwrap!(TagPath::from_path(&parser.get_extra()[0]), "Invalid tag path: {error}");
     let recursive = parser.get_custom("recursive").is_some();
     let reverse = parser.get_custom("reverse").is_some();
@@ -37,7 +39,20 @@ pub fn dependency_list(args: Args, description: &'static str) -> Result<(), Stri
     }
 
     let result = if reverse {
-        str_unwrap!(get_reverse_dependencies_for_tag(&tag_path, &tags), "Failed to get reverse dependencies: {error}")
+        match tags.get_dependency_index() {
+            Some(index) => {
+                // Tags that could not be indexed have to be read to know if they depend on it
+                let mut result = index.get_reverse_dependencies(&tag_path).cloned().unwrap_or_default();
+                for tag in index.get_unindexed_tags().keys() {
+                    let references = str_unwrap!(tags.open_tag_references(tag), "Failed to get reverse dependencies: {error}");
+                    if references.iter().any(|r| r.path() == Some(&tag_path)) {
+                        result.insert(tag.to_owned());
+                    }
+                }
+                result
+            },
+            None => str_unwrap!(get_reverse_dependencies_for_tag(&tag_path, &tags), "Failed to get reverse dependencies: {error}")
+        }
     }
     else {
         if recursive {
@@ -45,8 +60,8 @@ pub fn dependency_list(args: Args, description: &'static str) -> Result<(), Stri
             result.into_values().flatten().collect()
         }
         else {
-            let tag = str_unwrap!(tags.open_tag_copy(&tag_path), "Failed to open tag: {error}");
-            get_tag_dependencies_for_block(tag.as_ref().as_dynamic())
+            let references = str_unwrap!(tags.open_tag_references(&tag_path), "Failed to open tag: {error}");
+            get_tag_dependencies_for_references(references)
         }
     };
 
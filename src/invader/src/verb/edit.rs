@@ -0,0 +1,90 @@
+use std::env::Args;
+use crate::cli::{CommandLineParser, CommandLineValueType, Parameter};
+use ringhopper::tag::edit::{delete_entries, get_fields, insert_entries, list_fields, set_fields};
+use ringhopper::tag::tree::TagTree;
+use crate::threading::{DisplayMode, do_with_threads, ProcessSuccessType};
+use crate::util::make_stdout_logger;
+
+#[derive(Clone)]
+enum Operation {
+    List(String),
+    Get(String),
+    Modify(Vec<Modification>)
+}
+
+#[derive(Clone)]
+enum Modification {
+    Insert(String),
+    Delete(String),
+    Set(String, String)
+}
+
+pub fn edit(args: Args, description: &'static str) -> Result<(), String> {
+    let parser = CommandLineParser::new(description, "<tag.group*> [args]")
+        .add_tags(false)
+        .add_help()
+        .add_cow_tags()
+        .add_jobs()
+        .add_custom_parameter(Parameter::new("list", 'l', "List the fields or entries matched by the matcher. Use \"\" for the root of the tag.", "<matcher>", Some(CommandLineValueType::String), 1, None, false, false))
+        .add_custom_parameter(Parameter::new("get", 'g', "Get the values matched by the matcher as JSON.", "<matcher>", Some(CommandLineValueType::String), 1, None, false, false))
+        .add_custom_parameter(Parameter::new("insert", 'i', "Insert a default entry into each matched reflexive, at the end or at the given index (e.g. triggers[1]). Can be used multiple times.", "<matcher>", Some(CommandLineValueType::String), 1, None, true, false))
+        .add_custom_parameter(Parameter::new("delete", 'x', "Delete the matched reflexive entries (e.g. triggers[1-e]). Can be used multiple times.", "<matcher>", Some(CommandLineValueType::String), 1, None, true, false))
+        .add_custom_parameter(Parameter::new("set", 's', "Set the matched fields to the value, given as JSON or as a string. Can be used multiple times.", "<matcher> <value>", Some(CommandLineValueType::String), 2, None, true, false))
+        .set_required_extra_parameters(1)
+        .parse(args)?;
+
+    let strings = |name: &'static str| -> Vec<String> {
+        parser.get_custom(name).unwrap_or_default().iter().map(|v| v.string().to_owned()).collect()
+    };
+
+    let list = strings("list");
+    let get = strings("get");
+
+    // Modifications are applied in the order they were passed, as later ones may depend on earlier ones.
+    let modifications: Vec<Modification> = parser.get_custom_in_order(&["insert", "delete", "set"]).into_iter().map(|(name, values)| match name {
+        "insert" => Modification::Insert(values[0].string().to_owned()),
+        "delete" => Modification::Delete(values[0].string().to_owned()),
+        "set" => Modification::Set(values[0].string().to_owned(), values[1].string().to_owned()),
+        _ => unreachable!()
+    }).collect();
+    let modifying = !modifications.is_empty();
+
+    let operation = match (list.into_iter().next(), get.into_iter().next(), modifying) {
+        (Some(matcher), None, false) => Operation::List(matcher),
+        (None, Some(matcher), false) => Operation::Get(matcher),
+        (None, None, true) => Operation::Modify(modifications),
+        (None, None, false) => return Err("Nothing to do; use --list, --get, --insert, --delete, or --set".to_owned()),
+        _ => return Err("--list and --get cannot be combined with each other or with modifications".to_owned())
+    };
+
+    let display_mode = match operation {
+        Operation::Modify(_) => DisplayMode::ShowAll,
+        _ => DisplayMode::Silent
+    };
+
+    let tag = parser.get_extra()[0].clone();
+    do_with_threads(parser.get_virtual_tags_directory(), parser, &tag, None, operation, display_mode, make_stdout_logger(), |context, path, operation, logger| {
+        let mut tag = context.tags_directory.open_tag_copy(path)?;
+
+        let lines = match operation {
+            Operation::List(matcher) => list_fields(tag.as_dynamic(), matcher)?,
+            Operation::Get(matcher) => get_fields(tag.as_dynamic(), matcher)?,
+            Operation::Modify(modifications) => {
+                for modification in modifications {
+                    match modification {
+                        Modification::Insert(matcher) => insert_entries(tag.as_mut_dynamic(), matcher)?,
+                        Modification::Delete(matcher) => delete_entries(tag.as_mut_dynamic(), matcher)?,
+                        Modification::Set(matcher, value) => set_fields(tag.as_mut_dynamic(), matcher, value)?
+                    };
+                }
+                return ProcessSuccessType::wrap_write_result(context.tags_directory.write_tag(path, tag.as_ref()))
+            }
+        };
+
+        let locked = logger.lock();
+        for line in lines {
+            locked.neutral_fmt_ln(format_args!("{path}: {line}"));
+        }
+        Ok(ProcessSuccessType::Success)
+    })
+}
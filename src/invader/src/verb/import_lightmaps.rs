@@ -0,0 +1,53 @@
+use std::env::Args;
+use crate::cli::CommandLineParser;
+use ringhopper::data::bitmap::{autodetect_image_extension, load_image_from_path};
+use ringhopper::data::jms::JMS;
+use ringhopper::definitions::ScenarioStructureBSP;
+use ringhopper::error::Error;
+use ringhopper::primitives::primitive::{TagGroup, TagPath, TagReference};
+use ringhopper::tag::scenario_structure_bsp::lightmap::import_lightmaps as import_lightmaps_into_bsp;
+use ringhopper::tag::tree::TagTree;
+use crate::threading::{DisplayMode, do_with_threads, ProcessSuccessType};
+use crate::util::{make_stdout_logger, read_file};
+
+pub fn import_lightmaps(args: Args, description: &'static str) -> Result<(), String> {
+    let parser = CommandLineParser::new(description, "<scenario_structure_bsp*> [args]")
+        .add_tags(false)
+        .add_data()
+        .add_help()
+        .add_cow_tags()
+        .add_jobs()
+        .set_required_extra_parameters(1)
+        .parse(args)?;
+
+    let tag = parser.get_extra()[0].clone();
+    do_with_threads(parser.get_virtual_tags_directory(), parser, &tag, Some(TagGroup::ScenarioStructureBSP), (), DisplayMode::ShowAll, make_stdout_logger(), |context, path, _, _| {
+        // Lightmap geometry is in <data>/<level directory>/models/<bsp name> lightmap.jms, same as when recovering, and
+        // pages are in <data>/<level directory>/lightmaps/<bsp name>_<page>.<ext>
+        let data_path = context.args.get_data().join(path.to_native_path());
+        let level_directory = data_path.parent().unwrap();
+
+        let file = level_directory.join("models").join(format!("{} lightmap.jms", path.base_name()));
+        let text = read_file(&file)?;
+        let text = std::str::from_utf8(&text).map_err(|_| Error::Other(format!("{} is not valid UTF-8", file.display())))?;
+        let jms = JMS::from_text(text).map_err(|e| Error::Other(format!("failed to read {}: {e}", file.display())))?;
+
+        let mut pages = Vec::new();
+        while let Some(image) = autodetect_image_extension(&level_directory.join("lightmaps").join(format!("{}_{}", path.base_name(), pages.len()))) {
+            pages.push(load_image_from_path(image)?);
+        }
+
+        let mut bsp = context.tags_directory.open_tag_copy(path)?.get_ref::<ScenarioStructureBSP>().unwrap().to_owned();
+        let bitmap = import_lightmaps_into_bsp(&mut bsp, &jms, &pages)?;
+
+        // Put the lightmap bitmap next to the BSP unless it already has one.
+        let bitmap_path = match bsp.lightmaps_bitmap.path() {
+            Some(p) => p.to_owned(),
+            None => TagPath::new(path.path(), TagGroup::Bitmap)?
+        };
+        bsp.lightmaps_bitmap = TagReference::Set(bitmap_path.clone());
+
+        context.tags_directory.write_tag(&bitmap_path, &bitmap)?;
+        ProcessSuccessType::wrap_write_result(context.tags_directory.write_tag(path, &bsp))
+    })
+}
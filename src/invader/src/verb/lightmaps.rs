@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+use std::env::Args;
+use crate::cli::{CommandLineParser, CommandLineValue, CommandLineValueType, Parameter};
+use ringhopper::definitions::{ScenarioStructureBSP, Sky};
+use ringhopper::primitives::primitive::{TagGroup, TagPath, TagReference};
+use ringhopper::tag::scenario_structure_bsp::lightmap::{bake_lightmaps, LightmapLighting, LightmapSettings, LightmapSky, shader_emitted_light};
+use ringhopper::tag::shader::downcast_base_shader;
+use ringhopper::tag::tree::{TagFilter, TagTree};
+use crate::threading::{DisplayMode, do_with_threads, ProcessSuccessType};
+use crate::util::make_stdout_logger;
+
+#[derive(Clone)]
+struct LightmapsOptions {
+    sky: Option<TagPath>,
+    settings: LightmapSettings
+}
+
+pub fn lightmaps(args: Args, description: &'static str) -> Result<(), String> {
+    let defaults = LightmapSettings::default();
+    let parser = CommandLineParser::new(description, "<scenario_structure_bsp*> [args]")
+        .add_tags(false)
+        .add_help()
+        .add_cow_tags()
+        .add_jobs()
+        .add_custom_parameter(Parameter::new("sky", 's', "Sky tag to take outdoor lighting from. If unset, only emissive shaders light the level.", "<tag.sky>", Some(CommandLineValueType::String), 1, None, false, false))
+        .add_custom_parameter(Parameter::new("density", 'd', "Lightmap texels per world unit. Default: 8", "<texels>", Some(CommandLineValueType::Float), 1, Some(vec![CommandLineValue::Float(defaults.texels_per_world_unit)]), false, false))
+        .add_custom_parameter(Parameter::new("samples", 'S', "Rays cast from each texel for ambient and bounced light. Default: 64", "<count>", Some(CommandLineValueType::UInteger), 1, Some(vec![CommandLineValue::UInteger(defaults.samples as u32)]), false, false))
+        .add_custom_parameter(Parameter::new("bounces", 'b', "Number of light bounces. Default: 2", "<count>", Some(CommandLineValueType::UInteger), 1, Some(vec![CommandLineValue::UInteger(defaults.bounces as u32)]), false, false))
+        .set_required_extra_parameters(1)
+        .parse(args)?;
+
+    let sky = match parser.get_custom("sky") {
+        Some(s) => Some(TagPath::new(s[0].string(), TagGroup::Sky).map_err(|e| format!("Invalid sky path: {e}"))?),
+        None => None
+    };
+    let settings = LightmapSettings {
+        texels_per_world_unit: parser.get_custom("density").unwrap()[0].float(),
+        samples: parser.get_custom("samples").unwrap()[0].uinteger() as usize,
+        bounces: parser.get_custom("bounces").unwrap()[0].uinteger() as usize,
+        ..defaults
+    };
+
+    let tag = parser.get_extra()[0].clone();
+    let tags = parser.get_virtual_tags_directory();
+
+    // do_with_threads already processes up to `jobs` tags at once, so split the jobs between them
+    let tag_count = if TagFilter::is_filter(&tag) {
+        tags.get_all_tags_with_filter(Some(&TagFilter::new(&tag, Some(TagGroup::ScenarioStructureBSP)))).len()
+    }
+    else {
+        1
+    };
+    let settings = LightmapSettings {
+        threads: (parser.get_jobs() / tag_count.max(1)).max(1),
+        ..settings
+    };
+
+    let options = LightmapsOptions { sky, settings };
+
+    do_with_threads(tags, parser, &tag, Some(TagGroup::ScenarioStructureBSP), options, DisplayMode::ShowAll, make_stdout_logger(), |context, path, options, _| {
+        let mut bsp = context.tags_directory.open_tag_copy(path)?.get_ref::<ScenarioStructureBSP>().unwrap().to_owned();
+
+        let mut lighting = LightmapLighting::default();
+        if let Some(sky) = &options.sky {
+            let sky = context.tags_directory.open_tag_copy(sky)?;
+            lighting.sky = LightmapSky::from_sky(sky.get_ref::<Sky>().unwrap());
+        }
+
+        let mut shader_emission = HashMap::new();
+        for shader in bsp.lightmaps.items.iter().flat_map(|l| l.materials.items.iter()).filter_map(|m| m.shader.path()) {
+            if shader_emission.contains_key(shader) || !context.tags_directory.contains(shader) {
+                continue
+            }
+            let tag = context.tags_directory.open_tag_copy(shader)?;
+            if let Some(emission) = downcast_base_shader(tag.as_ref()).and_then(shader_emitted_light) {
+                shader_emission.insert(shader.to_owned(), emission);
+            }
+        }
+        lighting.shader_emission = shader_emission;
+
+        let bitmap = bake_lightmaps(&mut bsp, &lighting, &options.settings)?;
+
+        // Put the lightmap bitmap next to the BSP unless it already has one.
+        let bitmap_path = match bsp.lightmaps_bitmap.path() {
+            Some(p) => p.to_owned(),
+            None => TagPath::new(path.path(), TagGroup::Bitmap)?
+        };
+        bsp.lightmaps_bitmap = TagReference::Set(bitmap_path.clone());
+
+        context.tags_directory.write_tag(&bitmap_path, &bitmap)?;
+        ProcessSuccessType::wrap_write_result(context.tags_directory.write_tag(path, &bsp))
+    })
+}
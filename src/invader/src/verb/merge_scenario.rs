@@ -0,0 +1,28 @@
+use std::env::Args;
+use crate::cli::CommandLineParser;
+use ringhopper::definitions::Scenario;
+use ringhopper::primitives::primitive::TagGroup;
+use ringhopper::tag::scenario::merge::merge_child_scenarios;
+use ringhopper::tag::tree::TagTree;
+use crate::threading::{DisplayMode, do_with_threads, ProcessSuccessType};
+use crate::util::make_stdout_logger;
+
+pub fn merge_scenario(args: Args, description: &'static str) -> Result<(), String> {
+    let parser = CommandLineParser::new(description, "<scenario*> [args]")
+        .add_tags(false)
+        .add_help()
+        .add_cow_tags()
+        .add_jobs()
+        .set_required_extra_parameters(1)
+        .parse(args)?;
+
+    let tag = parser.get_extra()[0].clone();
+    do_with_threads(parser.get_virtual_tags_directory(), parser, &tag, Some(TagGroup::Scenario), (), DisplayMode::ShowAll, make_stdout_logger(), |context, path, _, _| {
+        let mut scenario = context.tags_directory.open_tag_copy(path)?.get_ref::<Scenario>().unwrap().to_owned();
+        if scenario.child_scenarios.items.is_empty() {
+            return Ok(ProcessSuccessType::Skipped("no child scenarios"))
+        }
+        merge_child_scenarios(&mut scenario, &context.tags_directory)?;
+        ProcessSuccessType::wrap_write_result(context.tags_directory.write_tag(path, &scenario))
+    })
+}
@@ -0,0 +1,83 @@
+use std::env::Args;
+use std::path::Path;
+use crate::cli::CommandLineParser;
+use ringhopper::data::jma::JMA;
+use ringhopper::definitions::{GBXModel, Model, ModelAnimations};
+use ringhopper::error::{Error, RinghopperResult};
+use ringhopper::primitives::primitive::{TagGroup, TagPath};
+use ringhopper::tag::model_animations::jma::{AnimationSourceType, check_jma_nodes_against_model, import_jma_into_tag};
+use ringhopper::tag::tree::TagTree;
+use crate::threading::{DisplayMode, do_with_threads, ProcessSuccessType};
+use crate::util::{make_stdout_logger, read_file};
+
+pub fn model_animations(args: Args, description: &'static str) -> Result<(), String> {
+    let parser = CommandLineParser::new(description, "<model_animations*> [args]")
+        .add_tags(false)
+        .add_data()
+        .add_help()
+        .add_cow_tags()
+        .add_jobs()
+        .set_required_extra_parameters(1)
+        .parse(args)?;
+
+    let tag = parser.get_extra()[0].clone();
+    do_with_threads(parser.get_virtual_tags_directory(), parser, &tag, Some(TagGroup::ModelAnimations), (), DisplayMode::ShowAll, make_stdout_logger(), |context, path, _, _| {
+        let data_path = context.args.get_data().join(path.to_native_path());
+        let animations_dir = data_path.parent().unwrap().join("animations");
+        let sources = find_animation_sources(&animations_dir)?;
+        if sources.is_empty() {
+            return Err(Error::Other(format!("no animation source files found in {}", animations_dir.display())))
+        }
+
+        let mut tag = if context.tags_directory.contains(path) {
+            context.tags_directory.open_tag_copy(path)?.get_ref::<ModelAnimations>().unwrap().to_owned()
+        }
+        else {
+            ModelAnimations::default()
+        };
+
+        let model_checksum = |jma: &JMA| -> RinghopperResult<Option<i32>> {
+            let gbxmodel_path = TagPath::new(path.path(), TagGroup::GBXModel)?;
+            if context.tags_directory.contains(&gbxmodel_path) {
+                let model = context.tags_directory.open_tag_copy(&gbxmodel_path)?;
+                return check_jma_nodes_against_model(jma, model.get_ref::<GBXModel>().unwrap()).map(Some)
+            }
+            let model_path = TagPath::new(path.path(), TagGroup::Model)?;
+            if context.tags_directory.contains(&model_path) {
+                let model = context.tags_directory.open_tag_copy(&model_path)?;
+                return check_jma_nodes_against_model(jma, model.get_ref::<Model>().unwrap()).map(Some)
+            }
+            Ok(None)
+        };
+
+        for (name, source_type, file) in sources {
+            let text = read_file(&file)?;
+            let text = std::str::from_utf8(&text).map_err(|_| Error::Other(format!("{} is not valid UTF-8", file.display())))?;
+            let jma = JMA::from_text(text).map_err(|e| Error::Other(format!("failed to read {}: {e}", file.display())))?;
+            let checksum = model_checksum(&jma)?.unwrap_or(jma.node_list_checksum);
+            import_jma_into_tag(&mut tag, &name, source_type, &jma, checksum)?;
+        }
+
+        ProcessSuccessType::wrap_write_result(context.tags_directory.write_tag(path, &tag))
+    })
+}
+
+fn find_animation_sources(directory: &Path) -> RinghopperResult<Vec<(String, AnimationSourceType, std::path::PathBuf)>> {
+    let entries = std::fs::read_dir(directory).map_err(|e| Error::FailedToReadFile(directory.to_owned(), e))?;
+
+    let mut sources = Vec::new();
+    for entry in entries {
+        let file = entry.map_err(|e| Error::FailedToReadFile(directory.to_owned(), e))?.path();
+        let Some(source_type) = file.extension().and_then(|e| e.to_str()).and_then(AnimationSourceType::from_extension) else {
+            continue
+        };
+        let Some(name) = file.file_stem().and_then(|n| n.to_str()) else {
+            continue
+        };
+        sources.push((name.to_owned(), source_type, file));
+    }
+
+    // Sort so animations are always imported in the same order.
+    sources.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(sources)
+}
@@ -0,0 +1,48 @@
+use std::env::Args;
+use crate::cli::CommandLineParser;
+use ringhopper::data::jms::JMS;
+use ringhopper::definitions::Physics;
+use ringhopper::error::Error;
+use ringhopper::primitives::primitive::TagGroup;
+use ringhopper::tag::physics::{calculate_mass_distribution, import_mass_points_from_jms};
+use ringhopper::tag::tree::TagTree;
+use crate::threading::{DisplayMode, do_with_threads, ProcessSuccessType};
+use crate::util::{make_stdout_logger, read_file};
+
+pub fn physics(args: Args, description: &'static str) -> Result<(), String> {
+    let parser = CommandLineParser::new(description, "<physics*> [args]")
+        .add_tags(false)
+        .add_data()
+        .add_help()
+        .add_cow_tags()
+        .add_jobs()
+        .set_required_extra_parameters(1)
+        .parse(args)?;
+
+    let tag = parser.get_extra()[0].clone();
+    do_with_threads(parser.get_virtual_tags_directory(), parser, &tag, Some(TagGroup::Physics), (), DisplayMode::ShowAll, make_stdout_logger(), |context, path, _, _| {
+        let file = context.args.get_data().join(path.to_native_path()).with_extension("jms");
+        let text = read_file(&file)?;
+        let text = std::str::from_utf8(&text).map_err(|_| Error::Other(format!("{} is not valid UTF-8", file.display())))?;
+        let jms = JMS::from_text(text).map_err(|e| Error::Other(format!("failed to read {}: {e}", file.display())))?;
+
+        let mut tag = if context.tags_directory.contains(path) {
+            context.tags_directory.open_tag_copy(path)?.get_ref::<Physics>().unwrap().to_owned()
+        }
+        else {
+            // These are expected to be tuned afterward.
+            Physics {
+                mass: 1.0,
+                density: 1.0,
+                moment_scale: 1.0,
+                gravity_scale: 1.0,
+                ..Default::default()
+            }
+        };
+
+        import_mass_points_from_jms(&mut tag, &jms)?;
+        calculate_mass_distribution(&mut tag)?;
+
+        ProcessSuccessType::wrap_write_result(context.tags_directory.write_tag(path, &tag))
+    })
+}
@@ -0,0 +1,74 @@
+use std::env::Args;
+use std::path::Path;
+use crate::cli::CommandLineParser;
+use ringhopper::definitions::Scenario;
+use ringhopper::primitives::primitive::{TagGroup, TagPath};
+use ringhopper::tag::scenario::placement::{
+    export_placements as export_scenario_placements,
+    import_placements as import_scenario_placements,
+    placements_from_csv,
+    placements_from_json,
+    placements_to_csv,
+    placements_to_json
+};
+use ringhopper::tag::tree::TagTree;
+use crate::threading::{DisplayMode, do_with_threads, ProcessSuccessType};
+use crate::util::{make_stdout_logger, read_file};
+
+fn is_csv(file: &Path) -> Result<bool, String> {
+    match file.extension().and_then(|e| e.to_str()).map(|e| e.to_ascii_lowercase()).as_deref() {
+        Some("csv") => Ok(true),
+        Some("json") => Ok(false),
+        _ => Err(format!("Placement file {} must end in .json or .csv", file.display()))
+    }
+}
+
+pub fn export_placements(args: Args, description: &'static str) -> Result<(), String> {
+    let parser = CommandLineParser::new(description, "<scenario> <file.json|file.csv> [args]")
+        .add_tags(true)
+        .add_help()
+        .set_required_extra_parameters(2)
+        .parse(args)?;
+
+    let extra = parser.get_extra();
+    let file = Path::new(&extra[1]);
+    let csv = is_csv(file)?;
+
+    let tags = parser.get_virtual_tags_directory();
+    let path = str_unwrap!(TagPath::new(&extra[0], TagGroup::Scenario), "Invalid tag path: {error}");
+    let tag = str_unwrap!(tags.open_tag_copy(&path), "Failed to open tag: {error}");
+    let placements = export_scenario_placements(tag.get_ref::<Scenario>().unwrap());
+
+    let output = if csv { placements_to_csv(&placements) } else { placements_to_json(&placements) };
+    str_unwrap!(std::fs::write(file, output), "Failed to write {}: {error}", file.display());
+    make_stdout_logger().success_fmt_ln(format_args!("Exported {} placement(s) to {}", placements.len(), file.display()));
+
+    Ok(())
+}
+
+pub fn import_placements(args: Args, description: &'static str) -> Result<(), String> {
+    let parser = CommandLineParser::new(description, "<scenario> <file.json|file.csv> [args]")
+        .add_tags(false)
+        .add_help()
+        .add_cow_tags()
+        .set_required_extra_parameters(2)
+        .parse(args)?;
+
+    let extra = parser.get_extra();
+    let file = Path::new(&extra[1]);
+    let csv = is_csv(file)?;
+    let data = str_unwrap!(read_file(file), "{error}");
+    let text = str_unwrap!(std::str::from_utf8(&data), "{} is not valid UTF-8: {error}", file.display());
+    let placements = match csv {
+        true => placements_from_csv(text),
+        false => placements_from_json(text)
+    };
+    let placements = str_unwrap!(placements, "Failed to read {}: {error}", file.display());
+
+    let tag = extra[0].clone();
+    do_with_threads(parser.get_virtual_tags_directory(), parser, &tag, Some(TagGroup::Scenario), placements, DisplayMode::ShowAll, make_stdout_logger(), |context, path, placements, _| {
+        let mut scenario = context.tags_directory.open_tag_copy(path)?.get_ref::<Scenario>().unwrap().to_owned();
+        import_scenario_placements(&mut scenario, placements)?;
+        ProcessSuccessType::wrap_write_result(context.tags_directory.write_tag(path, &scenario))
+    })
+}
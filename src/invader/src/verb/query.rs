@@ -0,0 +1,78 @@
+use std::env::Args;
+use std::sync::{Arc, Mutex, PoisonError};
+use crate::cli::{CommandLineParser, CommandLineValue, CommandLineValueType, Parameter};
+use ringhopper::error::Error;
+use ringhopper::tag::query::{Aggregate, Predicate, Query, QueryMatch};
+use ringhopper::tag::tree::TagTree;
+use crate::threading::{DisplayMode, do_with_threads, ProcessSuccessType};
+use crate::util::make_stdout_logger;
+
+#[derive(Clone)]
+struct UserData {
+    query: Query,
+    matches: Arc<Mutex<Vec<QueryMatch>>>
+}
+
+pub fn query(args: Args, description: &'static str) -> Result<(), String> {
+    let parser = CommandLineParser::new(description, "<tag.group*> [args]")
+        .add_tags(true)
+        .add_help()
+        .add_jobs()
+        .add_custom_parameter(Parameter::new("where", 'w', "Only match tags where the predicate holds (e.g. \"initial_velocity > 10\"). Can be used multiple times.", "<predicate>", Some(CommandLineValueType::String), 1, None, true, false))
+        .add_custom_parameter(Parameter::new("select", 's', "Output the fields reached by the matcher. Can be used multiple times.", "<matcher>", Some(CommandLineValueType::String), 1, None, true, false))
+        .add_custom_parameter(Parameter::new("aggregate", 'a', "Output a single row aggregating the selected fields across all matches. Can be count, sum, min, max, or mean.", "<aggregate>", Some(CommandLineValueType::String), 1, None, false, false))
+        .add_custom_parameter(Parameter::new("format", 'f', "Set the output format. Can be table or json. Default: table", "<format>", Some(CommandLineValueType::String), 1, Some(vec![CommandLineValue::String("table".to_owned())]), false, false))
+        .set_required_extra_parameters(1)
+        .parse(args)?;
+
+    let strings = |name: &'static str| -> Vec<String> {
+        parser.get_custom(name).unwrap_or_default().iter().map(|v| v.string().to_owned()).collect()
+    };
+
+    let predicates = strings("where")
+        .iter()
+        .map(|p| p.parse())
+        .collect::<Result<Vec<Predicate>, Error>>()
+        .map_err(|e| e.to_string())?;
+    let columns = strings("select");
+
+    let aggregate: Option<Aggregate> = match parser.get_custom("aggregate") {
+        Some(a) => Some(a[0].string().parse().map_err(|e: Error| e.to_string())?),
+        None => None
+    };
+    if aggregate.is_some_and(|a| a != Aggregate::Count) && columns.is_empty() {
+        return Err("--aggregate requires at least one field to be selected with --select".to_owned())
+    }
+
+    let json = match parser.get_custom("format").unwrap()[0].string() {
+        "table" => false,
+        "json" => true,
+        n => return Err(format!("Invalid format `{n}`; expected table or json"))
+    };
+
+    let user_data = UserData {
+        query: Query { predicates, columns },
+        matches: Arc::new(Mutex::new(Vec::new()))
+    };
+
+    let matches = user_data.matches.clone();
+    let query = user_data.query.clone();
+
+    let tag = parser.get_extra()[0].clone();
+    do_with_threads(parser.get_virtual_tags_directory(), parser, &tag, None, user_data, DisplayMode::Silent, make_stdout_logger(), |context, path, user_data, _| {
+        let tag = context.tags_directory.open_tag_copy(path)?;
+        if let Some(m) = user_data.query.evaluate(path, tag.as_dynamic()) {
+            user_data.matches.lock().unwrap().push(m);
+        }
+        Ok(ProcessSuccessType::Success)
+    })?;
+
+    // Other clones of the user data may outlive do_with_threads, so take the matches rather than unwrapping the Arc.
+    let mut matches = std::mem::take(&mut *matches.lock().unwrap_or_else(PoisonError::into_inner));
+    matches.sort_by(|a, b| a.path().cmp(b.path()));
+
+    let output = if json { query.format_json(&matches, aggregate) } else { query.format_table(&matches, aggregate) };
+    print!("{output}");
+
+    Ok(())
+}
@@ -85,14 +85,18 @@ fn recover_tag(user_data: &UserData, path: &TagPath, context: &ThreadingContext<
         None => return Ok(ProcessSuccessType::Ignored)
     };
 
-    let fs = match func(path, &context.tags_directory.open_tag_copy(path)?)? {
+    let recovered = match func(path, &context.tags_directory.open_tag_copy(path)?)? {
         Some(n) => n,
         None => return Ok(ProcessSuccessType::Skipped("no recoverable data in tag"))
     };
 
+    for warning in &recovered.warnings {
+        user_data.logger.warning_fmt_ln(format_args!("Warning when recovering {path}: {warning}"));
+    }
+
     let mut anything_saved = false;
 
-    for (dpath, ddata) in fs {
+    for (dpath, ddata) in recovered.files {
         let full_data_path = user_data.data.join(&dpath);
         let path_as_utf8 = dpath.to_str().unwrap();
 
@@ -12,6 +12,7 @@ use crate::cli::*;
 pub fn refactor_paths(args: Args, description: &'static str) -> Result<(), String> {
     let parser = CommandLineParser::new(description, "<find> <replace> [args]")
         .add_tags(true)
+        .add_dependency_index()
         .add_help()
         .add_jobs()
         .add_custom_parameter(Parameter::new(
@@ -50,6 +51,9 @@ pub fn refactor_paths(args: Args, description: &'static str) -> Result<(), Strin
         .set_required_extra_parameters(2)
         .parse(args)?;
 
+    let mut tags = parser.get_virtual_tags_directory();
+    parser.load_dependency_index(&mut tags)?;
+
     let logger = make_stdout_logger();
     let logger = logger.lock();
     logger.neutral_ln("Refactoring... this might take a while (do not cancel this or you might break something)");
@@ -60,7 +64,7 @@ pub fn refactor_paths(args: Args, description: &'static str) -> Result<(), Strin
     match refactor_paths_for_tag_tree(
         parser.get_extra()[0].as_str(),
         parser.get_extra()[1].as_str(),
-        &parser.get_virtual_tags_directory(),
+        &tags,
         NonZeroUsize::new(parser.get_jobs()).unwrap(),
         match parser.get_custom("replace-type").unwrap()[0].string() {
             "start" => ReplaceType::Start,
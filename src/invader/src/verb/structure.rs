@@ -0,0 +1,68 @@
+use std::env::Args;
+use crate::cli::CommandLineParser;
+use ringhopper::data::jms::JMS;
+use ringhopper::definitions::ScenarioStructureBSP;
+use ringhopper::error::{Error, RinghopperResult};
+use ringhopper::primitives::primitive::{TagGroup, TagPath};
+use ringhopper::tag::scenario_structure_bsp::compile::compile_structure_bsp;
+use ringhopper::tag::tree::TagTree;
+use crate::threading::{DisplayMode, do_with_threads, ProcessSuccessType};
+use crate::util::{make_stdout_logger, read_file};
+
+pub fn structure(args: Args, description: &'static str) -> Result<(), String> {
+    let parser = CommandLineParser::new(description, "<scenario_structure_bsp*> [args]")
+        .add_tags(false)
+        .add_data()
+        .add_help()
+        .add_cow_tags()
+        .add_jobs()
+        .set_required_extra_parameters(1)
+        .parse(args)?;
+
+    let tag = parser.get_extra()[0].clone();
+    do_with_threads(parser.get_virtual_tags_directory(), parser, &tag, Some(TagGroup::ScenarioStructureBSP), (), DisplayMode::ShowAll, make_stdout_logger(), |context, path, _, _| {
+        // Level geometry is in <data>/<level directory>/models/<bsp name>.jms
+        let data_path = context.args.get_data().join(path.to_native_path());
+        let file = data_path
+            .parent()
+            .unwrap()
+            .join("models")
+            .join(path.base_name())
+            .with_extension("jms");
+        let text = read_file(&file)?;
+        let text = std::str::from_utf8(&text).map_err(|_| Error::Other(format!("{} is not valid UTF-8", file.display())))?;
+        let jms = JMS::from_text(text).map_err(|e| Error::Other(format!("failed to read {}: {e}", file.display())))?;
+
+        let existing = if context.tags_directory.contains(path) {
+            Some(context.tags_directory.open_tag_copy(path)?.get_ref::<ScenarioStructureBSP>().unwrap().to_owned())
+        }
+        else {
+            None
+        };
+
+        // Shaders in the level's shaders directory take precedence over shaders anywhere else.
+        let shaders: Vec<TagPath> = context.tags_directory
+            .get_all_tags_with_filter(None)
+            .into_iter()
+            .filter(|t| t.group() == TagGroup::Shader || t.group().subgroup() == Some(TagGroup::Shader))
+            .collect();
+        let level_shaders_directory = match path.path().rfind('\\') {
+            Some(n) => format!("{}\\shaders\\", &path.path()[..n]),
+            None => "shaders\\".to_owned()
+        };
+        let resolve_shader = |name: &str| -> RinghopperResult<TagPath> {
+            let matches: Vec<&TagPath> = shaders.iter().filter(|s| s.base_name() == name).collect();
+            if let Some(local) = matches.iter().find(|s| s.path().starts_with(&level_shaders_directory)) {
+                return Ok((*local).clone())
+            }
+            match matches.as_slice() {
+                [] => Err(Error::Other(format!("shader `{name}` not found"))),
+                [only] => Ok((*only).clone()),
+                _ => Err(Error::Other(format!("shader `{name}` is ambiguous ({} matches)", matches.len())))
+            }
+        };
+
+        let tag = compile_structure_bsp(&jms, existing.as_ref(), &resolve_shader)?;
+        ProcessSuccessType::wrap_write_result(context.tags_directory.write_tag(path, &tag))
+    })
+}
@@ -0,0 +1,58 @@
+use std::env::Args;
+use std::path::PathBuf;
+use crate::cli::CommandLineParser;
+use ringhopper::error::Error;
+use ringhopper::primitives::primitive::TagPath;
+use ringhopper::tag::json::{tag_from_json, tag_to_json as tag_to_json_text};
+use ringhopper::tag::tree::TagTree;
+use crate::threading::{DisplayMode, do_with_threads, ProcessSuccessType};
+use crate::util::{make_stdout_logger, read_file};
+
+fn json_path(data: &std::path::Path, path: &TagPath) -> PathBuf {
+    data.join(format!("{}.json", path.to_native_path()))
+}
+
+pub fn tag_to_json(args: Args, description: &'static str) -> Result<(), String> {
+    let parser = CommandLineParser::new(description, "<tag.group*> [args]")
+        .add_tags(true)
+        .add_data()
+        .add_help()
+        .add_jobs()
+        .set_required_extra_parameters(1)
+        .parse(args)?;
+
+    let tag = parser.get_extra()[0].clone();
+    do_with_threads(parser.get_virtual_tags_directory(), parser, &tag, None, (), DisplayMode::ShowAll, make_stdout_logger(), |context, path, _, _| {
+        let tag = context.tags_directory.open_tag_copy(path)?;
+        let json_file = json_path(context.args.get_data(), path);
+
+        let parent = json_file.parent().unwrap();
+        std::fs::create_dir_all(parent).map_err(|e| Error::FailedToWriteFile(parent.to_path_buf(), e))?;
+        std::fs::write(&json_file, tag_to_json_text(tag.as_ref()))
+            .map(|_| ProcessSuccessType::Success)
+            .map_err(|e| Error::FailedToWriteFile(json_file, e))
+    })
+}
+
+pub fn json_to_tag(args: Args, description: &'static str) -> Result<(), String> {
+    let parser = CommandLineParser::new(description, "<tag.group*> [args]")
+        .add_tags(false)
+        .add_data()
+        .add_help()
+        .add_cow_tags()
+        .add_jobs()
+        .set_required_extra_parameters(1)
+        .parse(args)?;
+
+    let tag = parser.get_extra()[0].clone();
+    do_with_threads(parser.get_virtual_tags_directory(), parser, &tag, None, (), DisplayMode::ShowAll, make_stdout_logger(), |context, path, _, _| {
+        let json_file = json_path(context.args.get_data(), path);
+        let json = read_file(&json_file)?;
+        let json = std::str::from_utf8(&json).map_err(|e| Error::Other(format!("{}: {e}", json_file.display())))?;
+        let tag = tag_from_json(json).map_err(|e| Error::Other(format!("{}: {e}", json_file.display())))?;
+        if tag.group() != path.group() {
+            return Err(Error::Other(format!("{} contains a {} tag, not a {} tag", json_file.display(), tag.group(), path.group())))
+        }
+        ProcessSuccessType::wrap_write_result(context.tags_directory.write_tag(path, tag.as_ref()))
+    })
+}
@@ -19,6 +19,7 @@ struct UserData {
 pub fn verify_scenario(args: Args, description: &'static str) -> Result<(), String> {
     let parser = CommandLineParser::new(description, "<scenario*> [args]")
         .add_tags(true)
+        .add_dependency_index()
         .add_help()
         .add_engine()
         .add_jobs()
@@ -32,7 +33,9 @@ pub fn verify_scenario(args: Args, description: &'static str) -> Result<(), Stri
     };
 
     let logger = data.logger.clone();
-    let tree = Arc::new(CachingTagTree::new(parser.get_virtual_tags_directory(), CachingTagTreeWriteStrategy::Manual));
+    let mut tags = parser.get_virtual_tags_directory();
+    parser.load_dependency_index(&mut tags)?;
+    let tree = Arc::new(CachingTagTree::new(tags, CachingTagTreeWriteStrategy::Manual));
 
     do_with_threads(tree, parser, &tag, Some(TagGroup::Scenario), data, DisplayMode::Silent, logger, |context, scenario_path, user_data, _| {
         let threads = unsafe { NonZeroUsize::new_unchecked(2) }; // TODO: add subjobs later?
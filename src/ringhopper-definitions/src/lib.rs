@@ -17,3 +17,14 @@ pub fn load_all_definitions() -> ParsedDefinitions {
 
     parsed
 }
+
+/// Get the name of a field, enum option, or bitfield flag as it is accessed at runtime, such as with `DynamicTagData`
+/// and in the JSON tag format.
+///
+/// Dashes and spaces become underscores, quotes and parentheses are removed, and everything is lowercase.
+pub fn matcher_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| !['\'', '"', '(', ')'].contains(c))
+        .map(|c| if c == '-' || c == ' ' { '_' } else { c.to_ascii_lowercase() })
+        .collect()
+}
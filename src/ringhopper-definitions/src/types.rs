@@ -109,10 +109,21 @@ pub struct StructField {
     /// Limits
     pub limit: Option<HashMap<LimitType, usize>>,
 
+    /// For indices, the reflexive being indexed; for enums, the reflexive the value relates to
+    pub index_reference: Option<IndexReference>,
+
     /// Flags
     pub flags: Flags
 }
 
+pub struct IndexReference {
+    /// Name of the reflexive field being indexed
+    pub reflexive: String,
+
+    /// Name of the struct containing the reflexive; the nearest parent of this type is used
+    pub struct_name: String
+}
+
 impl SizeableObject for StructField {
     fn size(&self, parsed_tag_data: &ParsedDefinitions) -> usize {
         self.field_type.size(parsed_tag_data) * self.count.field_count()
@@ -271,6 +282,12 @@ pub struct Flags {
     /// The value must be set.
     pub non_null: bool,
 
+    /// The value should be unit length.
+    pub normalize: bool,
+
+    /// The lower bound may exceed the upper bound (e.g. initial and final values).
+    pub unordered_bounds: bool,
+
     /// Supported engines for the field
     pub supported_engines: SupportedEngines,
 
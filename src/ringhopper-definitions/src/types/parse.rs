@@ -418,6 +418,23 @@ impl ParsedDefinitions {
                             _ => ()
                         }
 
+                        // Indices point to reflexives
+                        if let Some(n) = &f.index_reference {
+                            let parent = match self.objects.get(&n.struct_name) {
+                                Some(NamedObject::Struct(s)) => s,
+                                _ => panic!("{object_name}::{field_name} index refers to struct {} which does not exist", n.struct_name)
+                            };
+                            let reflexive = parent.fields.iter().find(|p| p.name == n.reflexive);
+                            if !matches!(reflexive.map(|r| &r.field_type), Some(StructFieldType::Object(ObjectType::Reflexive(_)))) {
+                                panic!("{object_name}::{field_name} index refers to {}::{} which is not a reflexive", n.struct_name, n.reflexive);
+                            }
+                            if let StructFieldType::Object(ObjectType::NamedObject(o)) = &f.field_type {
+                                if !matches!(self.objects.get(o), Some(NamedObject::Enum(_))) {
+                                    panic!("{object_name}::{field_name} refers to a reflexive but is not an index or an enum");
+                                }
+                            }
+                        }
+
                         // Limits point to engines
                         if let Some(n) = &f.limit {
                             for (k, _) in n {
@@ -615,6 +632,8 @@ impl LoadFromSerdeJSON for Flags {
             supported_engines: SupportedEngines::load_from_json(object),
             shifted_by_one: get_flag("shifted_by_one"),
             non_null: get_flag("non_null"),
+            normalize: get_flag("normalize"),
+            unordered_bounds: get_flag("unordered_bounds"),
             comment: get_str("comment"),
             developer_note: get_str("developer_note"),
             description: get_str("description")
@@ -635,7 +654,8 @@ impl LoadFromSerdeJSON for StructField {
                 flags: Flags::default(),
                 maximum: None,
                 minimum: None,
-                limit: None
+                limit: None,
+                index_reference: None
             },
             StructFieldType::EditorSection(e) => return Self {
                 name: e.name.clone(),
@@ -645,7 +665,8 @@ impl LoadFromSerdeJSON for StructField {
                 flags: Flags::default(),
                 maximum: None,
                 minimum: None,
-                limit: None
+                limit: None,
+                index_reference: None
             },
         };
 
@@ -717,7 +738,8 @@ impl LoadFromSerdeJSON for StructField {
                         editor_limit = Some(editor_limit.unwrap_or_default().max(v))
                     }
 
-                    default_limit.unwrap_or_else(|| panic!("No default limit set for {name}"));
+                    let default_limit = default_limit.unwrap_or_else(|| panic!("No default limit set for {name}"));
+                    map.insert(LimitType::Default, default_limit);
 
                     let editor_limit = editor_limit.unwrap_or_else(|| panic!("Unable to establish an editor limit for {name} (no limits maybe?)"));
                     map.insert(LimitType::Editor, editor_limit);
@@ -728,7 +750,19 @@ impl LoadFromSerdeJSON for StructField {
             }
         });
 
+        // Enums can also refer to a reflexive (e.g. an addressee which is one of the participants); these are validated
+        // to be enums once all objects are loaded.
+        let index_reference = match (&field_type, object.get("reflexive")) {
+            (StructFieldType::Object(ObjectType::Index | ObjectType::NamedObject(_)), Some(reflexive)) => Some(IndexReference {
+                reflexive: reflexive.as_str().unwrap_or_else(|| panic!("{name}::reflexive is not a string")).to_owned(),
+                struct_name: oget_str!(object, "struct").to_owned()
+            }),
+            (_, Some(_)) => panic!("{name} has a reflexive but is not an index or an enum"),
+            _ => None
+        };
+
         StructField {
+            index_reference,
             minimum: get_static_value("minimum"),
             maximum: get_static_value("maximum"),
             limit,
@@ -884,6 +918,7 @@ impl LoadFromSerdeJSON for Struct {
                 minimum: None,
                 maximum: None,
                 limit: None,
+                index_reference: None,
                 flags: Flags::default()
             })
         }
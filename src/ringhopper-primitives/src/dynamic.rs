@@ -1,4 +1,5 @@
-use std::any::Any;
+use std::any::{Any, TypeId};
+use crate::engine::Engine;
 use crate::error::{Error, RinghopperResult};
 use crate::parse::TagData;
 use crate::primitive::{parse_range, TagGroup};
@@ -78,7 +79,67 @@ pub struct TagFieldMetadata {
     pub read_only: bool,
     pub cache_only: bool,
     pub non_cached: bool,
-    pub allowed_references: Option<&'static [TagGroup]>
+    pub allowed_references: Option<&'static [TagGroup]>,
+
+    /// The value should be unit length.
+    pub normalize: bool,
+
+    /// The lower bound may exceed the upper bound (e.g. initial and final values).
+    pub unordered_bounds: bool,
+
+    /// Minimum value, or minimum number of entries for reflexives.
+    pub minimum: Option<f64>,
+
+    /// Maximum value, or maximum number of entries for reflexives.
+    pub maximum: Option<f64>,
+
+    /// Maximum number of entries for reflexives, or maximum length in bytes for data.
+    pub limit: Option<TagFieldLimit>,
+
+    /// For indices, the reflexive being indexed.
+    pub index_reference: Option<TagIndexReference>
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct TagIndexReference {
+    /// Field name of the reflexive being indexed.
+    pub reflexive: &'static str,
+
+    /// Types of structs which contain the reflexive.
+    ///
+    /// The reflexive is in the nearest parent of one of these types.
+    pub parents: &'static [fn() -> TypeId]
+}
+
+impl TagIndexReference {
+    /// Return `true` if `data` is a struct containing the reflexive.
+    pub fn is_parent(&self, data: &dyn DynamicTagData) -> bool {
+        let type_id = data.as_any().type_id();
+        self.parents.iter().any(|p| p() == type_id)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TagFieldLimit {
+    /// Limit for engines without a specific limit.
+    pub default: usize,
+
+    /// Highest limit of any engine.
+    pub editor: usize,
+
+    /// Limits for specific engines by engine name, including engines which inherit them.
+    pub engines: &'static [(&'static str, usize)]
+}
+
+impl TagFieldLimit {
+    /// Get the limit for the given engine.
+    pub fn for_engine(&self, engine: &Engine) -> usize {
+        self.engines
+            .iter()
+            .find(|(name, _)| *name == engine.name)
+            .map(|(_, limit)| *limit)
+            .unwrap_or(self.default)
+    }
 }
 
 /// Trait for dynamically accessing an array of fields, including reflexives.
@@ -119,6 +180,13 @@ pub trait DynamicReflexive: DynamicTagDataArray {
     ///
     /// Panics if `index` > `len()`
     fn insert_moved(&mut self, index: usize, item: &mut dyn DynamicTagData);
+
+    /// Remove the item at index `index`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` >= `len()`
+    fn remove(&mut self, index: usize);
 }
 
 #[derive(PartialEq, Debug)]
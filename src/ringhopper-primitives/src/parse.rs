@@ -9,6 +9,7 @@ use crate::dynamic::{DynamicTagData, DynamicTagDataType, SimplePrimitiveType};
 
 use crate::error::RinghopperResult;
 use crate::map::{DomainType, Map};
+use crate::primitive::TagReference;
 
 /// Maximum length for an array.
 ///
@@ -43,6 +44,49 @@ pub trait TagData: TagDataDefaults {
 
     /// Read data from the map.
     fn read_from_map<M: Map>(map: &M, address: usize, domain_type: &DomainType) -> RinghopperResult<Self> where Self: Sized;
+
+    /// Collect all tag references from the tag without reading anything else.
+    ///
+    /// Parameters are the same as [`TagData::read_from_tag_file`], and the `extra_data_cursor` is advanced by the same
+    /// amount, so this can be used to skip over data.
+    ///
+    /// By default, this reads the data and discards it, so types that contain extra data should override this.
+    fn scan_tag_references_from_tag_file(data: &[u8], at: usize, struct_end: usize, extra_data_cursor: &mut usize, references: &mut Vec<TagReference>) -> RinghopperResult<()> where Self: Sized {
+        let _ = references;
+        Self::read_from_tag_file(data, at, struct_end, extra_data_cursor).map(|_| ())
+    }
+
+    /// Find all values that are reset when the data is read, such as invalid enum values, appending them to `values`.
+    ///
+    /// Parameters are the same as [`TagData::read_from_tag_file`], and `path` is the matcher path to this data.
+    ///
+    /// By default, this reads the data and discards it, so types that contain such values should override this.
+    fn find_reset_values_in_tag_file(data: &[u8], at: usize, struct_end: usize, extra_data_cursor: &mut usize, path: &mut String, values: &mut Vec<ResetValue>) -> RinghopperResult<()> where Self: Sized {
+        let _ = (path, values);
+        Self::read_from_tag_file(data, at, struct_end, extra_data_cursor).map(|_| ())
+    }
+}
+
+/// Describes a value that was reset when read from a tag file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResetValue {
+    /// Matcher path to the field
+    pub path: String,
+
+    /// Description of what was reset
+    pub description: String
+}
+
+/// Call `f` with `field` appended to the matcher path.
+pub fn with_field_path<R, F: FnOnce(&mut String) -> R>(path: &mut String, field: &str, f: F) -> R {
+    let len = path.len();
+    if len != 0 {
+        path.push('.');
+    }
+    *path += field;
+    let result = f(path);
+    path.truncate(len);
+    result
 }
 
 /// Functionality for defaulting zeroed values.
@@ -76,6 +120,16 @@ pub trait SimpleTagData: TagDataDefaults + Sized {
     /// In all other error cases, it will panic.
     fn write<B: ByteOrder>(&self, data: &mut [u8], at: usize, struct_end: usize) -> RinghopperResult<()>;
 
+    /// Find all values that are reset when the data is read, such as invalid enum values, appending them to `values`.
+    ///
+    /// Parameters are the same as [`SimpleTagData::read`], and `path` is the matcher path to this data.
+    ///
+    /// By default, nothing is reset.
+    fn find_reset_values<B: ByteOrder>(data: &[u8], at: usize, struct_end: usize, path: &mut String, values: &mut Vec<ResetValue>) -> RinghopperResult<()> {
+        let _ = (path, values);
+        Self::read::<B>(data, at, struct_end).map(|_| ())
+    }
+
     /// Iterate on bytes for an array of structs.
     ///
     /// Returns an error if `data` is not divisible by `simple_size`.
@@ -240,6 +294,13 @@ impl<T: SimpleTagData + TagDataDefaults + Sized> TagData for T {
     fn read_from_tag_file(data: &[u8], at: usize, struct_end: usize, _: &mut usize) -> RinghopperResult<Self> {
         T::read::<BE>(data, at, struct_end)
     }
+    fn scan_tag_references_from_tag_file(data: &[u8], at: usize, struct_end: usize, _: &mut usize, _: &mut Vec<TagReference>) -> RinghopperResult<()> {
+        // Simple data has no tag references or extra data, so there is nothing to read.
+        tag_data_fits::<T>(at, struct_end, data.len()).map(|_| ())
+    }
+    fn find_reset_values_in_tag_file(data: &[u8], at: usize, struct_end: usize, _: &mut usize, path: &mut String, values: &mut Vec<ResetValue>) -> RinghopperResult<()> {
+        T::find_reset_values::<BE>(data, at, struct_end, path, values)
+    }
     fn write_to_tag_file(&self, data: &mut Vec<u8>, at: usize, struct_end: usize) -> RinghopperResult<()> {
         self.write::<BE>(data, at, struct_end)
     }
@@ -5,6 +5,7 @@ use crate::dynamic::*;
 use crate::parse::*;
 use crate::error::*;
 use crate::map::{DomainType, Map};
+use super::TagReference;
 
 /// Defines the lower and upper bound with fields.
 #[derive(Copy, Clone, PartialEq, Debug)]
@@ -94,6 +95,10 @@ impl<T: TagData> TagData for Bounds<T> {
             upper: T::read_from_tag_file(data, at.add_overflow_checked(T::size())?, struct_end, extra_data_cursor)?
         })
     }
+    fn scan_tag_references_from_tag_file(data: &[u8], at: usize, struct_end: usize, extra_data_cursor: &mut usize, references: &mut Vec<TagReference>) -> RinghopperResult<()> {
+        T::scan_tag_references_from_tag_file(data, at, struct_end, extra_data_cursor, references)?;
+        T::scan_tag_references_from_tag_file(data, at.add_overflow_checked(T::size())?, struct_end, extra_data_cursor, references)
+    }
     fn write_to_tag_file(&self, data: &mut Vec<u8>, at: usize, struct_end: usize) -> RinghopperResult<()> {
         self.lower.write_to_tag_file(data, at, struct_end)?;
         self.upper.write_to_tag_file(data, at.add_overflow_checked(T::size())?, struct_end)?;
@@ -225,6 +230,21 @@ impl<T: DynamicTagData + SimpleTagData + Sized, const U: usize> SimpleTagData fo
         })
     }
 
+    fn find_reset_values<B: ByteOrder>(data: &[u8], at: usize, struct_end: usize, path: &mut String, values: &mut Vec<ResetValue>) -> RinghopperResult<()> {
+        let element_length = T::size();
+        let mut at_offset = at;
+
+        for i in 0..U {
+            let len = path.len();
+            *path += &format!("[{i}]");
+            T::find_reset_values::<B>(data, at_offset, struct_end, path, values)?;
+            path.truncate(len);
+            at_offset = at_offset.add_overflow_checked(element_length)?;
+        }
+
+        Ok(())
+    }
+
     fn write<B: ByteOrder>(&self, data: &mut [u8], at: usize, struct_end: usize) -> RinghopperResult<()> {
         let element_length = T::size();
         let mut at_offset = at;
@@ -7,6 +7,7 @@ use byteorder::*;
 use std::fmt::Display;
 use crate::dynamic::{DynamicReflexive, DynamicTagData, DynamicTagDataArray, DynamicTagDataType, SimplePrimitiveType, TagFieldMetadata};
 use crate::map::{DomainType, Map, ResourceMapType};
+use super::TagReference;
 
 /// 16-bit index type
 pub type Index = Option<u16>;
@@ -377,6 +378,12 @@ macro_rules! make_tag_data_implementation_for_datadata {
                 DataData::from_bytes(&data[data_location..*extra_data_cursor])
             }
 
+            fn scan_tag_references_from_tag_file(data: &[u8], at: usize, struct_end: usize, extra_data_cursor: &mut usize, _: &mut Vec<TagReference>) -> RinghopperResult<()> {
+                let c_primitive = DataC::read_from_tag_file(data, at, struct_end, extra_data_cursor)?;
+                *extra_data_cursor = fits(c_primitive.size as usize, *extra_data_cursor, data.len())?;
+                Ok(())
+            }
+
             fn write_to_tag_file(&self, data: &mut Vec<u8>, at: usize, struct_end: usize) -> RinghopperResult<()> {
                 let bytes = self.get_bytes();
                 (DataC {
@@ -612,6 +619,47 @@ impl<T: TagData + Sized> TagData for Reflexive<T> {
         Ok(result)
     }
 
+    fn scan_tag_references_from_tag_file(data: &[u8], at: usize, struct_end: usize, extra_data_cursor: &mut usize, references: &mut Vec<TagReference>) -> RinghopperResult<()> {
+        let c_primitive = ReflexiveC::<T>::read_from_tag_file(data, at, struct_end, extra_data_cursor)?;
+
+        let count = c_primitive.count as usize;
+        let item_size = T::size();
+        let total_length = count.mul_overflow_checked(item_size)?;
+
+        let mut item_offset = *extra_data_cursor;
+        *extra_data_cursor = fits(total_length, item_offset, data.len())?;
+
+        for _ in 0..count {
+            let struct_end = item_offset + item_size;
+            T::scan_tag_references_from_tag_file(data, item_offset, struct_end, extra_data_cursor, references)?;
+            item_offset = struct_end;
+        }
+
+        Ok(())
+    }
+
+    fn find_reset_values_in_tag_file(data: &[u8], at: usize, struct_end: usize, extra_data_cursor: &mut usize, path: &mut String, values: &mut Vec<ResetValue>) -> RinghopperResult<()> {
+        let c_primitive = ReflexiveC::<T>::read_from_tag_file(data, at, struct_end, extra_data_cursor)?;
+
+        let count = c_primitive.count as usize;
+        let item_size = T::size();
+        let total_length = count.mul_overflow_checked(item_size)?;
+
+        let mut item_offset = *extra_data_cursor;
+        *extra_data_cursor = fits(total_length, item_offset, data.len())?;
+
+        for i in 0..count {
+            let struct_end = item_offset + item_size;
+            let len = path.len();
+            *path += &format!("[{i}]");
+            T::find_reset_values_in_tag_file(data, item_offset, struct_end, extra_data_cursor, path, values)?;
+            path.truncate(len);
+            item_offset = struct_end;
+        }
+
+        Ok(())
+    }
+
     fn write_to_tag_file(&self, data: &mut Vec<u8>, at: usize, struct_end: usize) -> RinghopperResult<()> {
         ReflexiveC::<T>::with_params(self.items.len().into_u32()?, Address::default()).write_to_tag_file(data, at, struct_end)?;
 
@@ -720,8 +768,13 @@ impl SimplePrimitive for Address {
     }
 }
 
-pub(crate) type ReflexiveAccessRange = (usize, usize); // [start, end]
-pub(crate) fn parse_range(matcher: &str, len: usize) -> Result<Vec<ReflexiveAccessRange>, &'static str> {
+/// Inclusive range of indices, as `(start, end)`.
+pub type ReflexiveAccessRange = (usize, usize);
+
+/// Parse the range part of a matcher (the part between `[` and `]`) for an array of length `len`.
+///
+/// Ranges are returned sorted, with overlapping ranges combined.
+pub fn parse_range(matcher: &str, len: usize) -> Result<Vec<ReflexiveAccessRange>, &'static str> {
     const RANGE_SEPARATOR: u8 = ',' as u8;
     const START_END_SEPARATOR: u8 = '-' as u8;
     const EVERYTHING: u8 = '*' as u8;
@@ -864,6 +917,10 @@ impl<T: DynamicTagData + Sized + Default + Clone> DynamicTagData for Reflexive<T
     fn as_array_mut(&mut self) -> Option<&mut dyn DynamicTagDataArray> {
         Some(self as &mut dyn DynamicTagDataArray)
     }
+
+    fn as_reflexive_mut(&mut self) -> Option<&mut dyn DynamicReflexive> {
+        Some(self as &mut dyn DynamicReflexive)
+    }
 }
 
 impl<T: DynamicTagData + Sized + Default + Clone> DynamicTagDataArray for Reflexive<T> {
@@ -894,6 +951,10 @@ impl<T: DynamicTagData + Sized + Default + Clone> DynamicReflexive for Reflexive
         let item = std::mem::take(item.as_any_mut().downcast_mut::<T>().unwrap());
         self.items.insert(index, item);
     }
+
+    fn remove(&mut self, index: usize) {
+        self.items.remove(index);
+    }
 }
 
 /// Lower level C implementation of a reflexive.
@@ -215,8 +215,10 @@ impl TagGroup {
     ///
     /// Returns an error `Error::NoSuchTagGroup` if `str` doesn't correspond to a group.
     pub fn from_str(str: &str) -> RinghopperResult<TagGroup> {
-        ALL_GROUPS.binary_search_by(|probe| probe.0.cmp(str))
-            .map(|n| ALL_GROUPS[n].1)
+        // <unset> is last and out of order, so it has to be excluded from the search
+        let groups = &ALL_GROUPS[..ALL_GROUPS.len() - 1];
+        groups.binary_search_by(|probe| probe.0.cmp(str))
+            .map(|n| groups[n].1)
             .map_err(|_| Error::InvalidFourCC)
     }
 
@@ -329,3 +331,6 @@ impl SimplePrimitive for TagGroup {
         SimplePrimitiveType::TagGroup
     }
 }
+
+#[cfg(test)]
+mod test;
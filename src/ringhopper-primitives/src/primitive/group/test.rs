@@ -0,0 +1,13 @@
+use crate::primitive::TagGroup;
+use super::ALL_GROUPS;
+
+#[test]
+fn tag_group_from_str() {
+    for (name, group, _) in &ALL_GROUPS[..ALL_GROUPS.len() - 1] {
+        assert_eq!(*group, TagGroup::from_str(name).unwrap(), "{name}");
+        assert_eq!(*name, group.as_str());
+    }
+
+    assert!(TagGroup::from_str("<unset>").is_err());
+    assert!(TagGroup::from_str("not_a_group").is_err());
+}
@@ -491,6 +491,11 @@ impl TagData for TagReference {
         ))
     }
 
+    fn scan_tag_references_from_tag_file(data: &[u8], at: usize, struct_end: usize, extra_data_cursor: &mut usize, references: &mut Vec<TagReference>) -> RinghopperResult<()> {
+        references.push(TagReference::read_from_tag_file(data, at, struct_end, extra_data_cursor)?);
+        Ok(())
+    }
+
     fn write_to_tag_file(&self, data: &mut Vec<u8>, at: usize, struct_end: usize) -> RinghopperResult<()> {
         let construct_to_write = match self {
             TagReference::Null(group) => {
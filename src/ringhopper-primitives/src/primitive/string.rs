@@ -209,6 +209,13 @@ impl UTF16String {
         self.data.as_slice()
     }
 
+    /// Create a UTF-16 string from raw UTF-16 data as-is.
+    ///
+    /// Unlike [`UTF16String::from_str`], the data is not validated and may be corrupt.
+    pub fn from_utf16_bytes(bytes: Vec<u8>) -> Self {
+        Self { data: bytes, cache: Mutex::new(None) }
+    }
+
     fn try_parse(&self) -> VerifiedString {
         let mut lock = self.cache.lock().unwrap();
         if let Some(n) = lock.as_ref() {
@@ -245,6 +245,26 @@ impl Vector for Vector3D {
     }
 }
 
+impl Vector3D {
+    /// Return the cross product of this and another vector.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use ringhopper_primitives::primitive::Vector3D;
+    ///
+    /// let x = Vector3D { x: 1.0, y: 0.0, z: 0.0 };
+    /// let y = Vector3D { x: 0.0, y: 1.0, z: 0.0 };
+    /// assert_eq!(x.cross(&y), Vector3D { x: 0.0, y: 0.0, z: 1.0 });
+    /// ```
+    pub fn cross(&self, with: &Self) -> Self {
+        Self {
+            x: self.y * with.z - self.z * with.y,
+            y: self.z * with.x - self.x * with.z,
+            z: self.x * with.y - self.y * with.x
+        }
+    }
+}
+
 impl VectorMathOps for Vector3D {
     fn add(&self, of: &Self) -> Self {
         Self {
@@ -325,6 +345,48 @@ impl Vector for Quaternion {
     }
 }
 
+impl Quaternion {
+    /// Return the Hamilton product of this and another quaternion.
+    ///
+    /// The resulting rotation is equivalent to rotating by `by` first and then by this quaternion.
+    pub fn multiply(&self, by: &Self) -> Self {
+        Self {
+            x: self.w * by.x + self.x * by.w + self.y * by.z - self.z * by.y,
+            y: self.w * by.y - self.x * by.z + self.y * by.w + self.z * by.x,
+            z: self.w * by.z + self.x * by.y - self.y * by.x + self.z * by.w,
+            w: self.w * by.w - self.x * by.x - self.y * by.y - self.z * by.z
+        }
+    }
+
+    /// Return the conjugate of this quaternion.
+    ///
+    /// For unit quaternions, this is the inverse rotation.
+    pub fn conjugate(&self) -> Self {
+        Self {
+            x: -self.x,
+            y: -self.y,
+            z: -self.z,
+            w: self.w
+        }
+    }
+
+    /// Rotate a vector by this quaternion.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use ringhopper_primitives::primitive::{Quaternion, Vector, Vector3D};
+    ///
+    /// let half_turn = Quaternion { x: 0.0, y: 0.0, z: 1.0, w: 0.0 };
+    /// let rotated = half_turn.rotate_vector(Vector3D { x: 1.0, y: 0.0, z: 0.0 });
+    /// assert!(rotated.distance_squared(&Vector3D { x: -1.0, y: 0.0, z: 0.0 }) < 0.000001);
+    /// ```
+    pub fn rotate_vector(&self, vector: Vector3D) -> Vector3D {
+        let v = Quaternion { x: vector.x, y: vector.y, z: vector.z, w: 0.0 };
+        let result = self.multiply(&v).multiply(&self.conjugate());
+        Vector3D { x: result.x, y: result.y, z: result.z }
+    }
+}
+
 impl VectorMathOps for Quaternion {
     fn add(&self, of: &Self) -> Self {
         Self {
@@ -388,6 +450,48 @@ pub struct Matrix3x3 {
     pub vectors: [Vector3D; 3]
 }
 
+impl Matrix3x3 {
+    /// Return the determinant of the matrix.
+    pub fn determinant(&self) -> f64 {
+        let [a, b, c] = self.vectors;
+        a.dot(&b.cross(&c))
+    }
+
+    /// Return the inverse of the matrix, or `None` if it is singular.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use ringhopper_primitives::primitive::{Matrix3x3, Vector3D};
+    ///
+    /// let matrix = Matrix3x3 { vectors: [
+    ///     Vector3D { x: 2.0, y: 0.0, z: 0.0 },
+    ///     Vector3D { x: 0.0, y: 4.0, z: 0.0 },
+    ///     Vector3D { x: 0.0, y: 0.0, z: 0.5 }
+    /// ] };
+    /// let inverse = matrix.inverse().unwrap();
+    /// assert_eq!(inverse.vectors[0].x, 0.5);
+    /// assert_eq!(inverse.vectors[1].y, 0.25);
+    /// assert_eq!(inverse.vectors[2].z, 2.0);
+    ///
+    /// assert!(Matrix3x3::default().inverse().is_none());
+    /// ```
+    pub fn inverse(&self) -> Option<Self> {
+        let determinant = self.determinant();
+        if determinant.abs() < f64::EPSILON || !determinant.is_finite() {
+            return None
+        }
+
+        // The rows of the inverse are the columns of the adjugate, which are the cross products of the rows.
+        let [a, b, c] = self.vectors;
+        let columns = [b.cross(&c), c.cross(&a), a.cross(&b)];
+        let row = |n: usize| {
+            let get = |v: &Vector3D| [v.x, v.y, v.z][n] / determinant;
+            Vector3D { x: get(&columns[0]), y: get(&columns[1]), z: get(&columns[2]) }
+        };
+        Some(Self { vectors: [row(0), row(1), row(2)] })
+    }
+}
+
 impl Display for Matrix3x3 {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         f.write_fmt(format_args!("{{ vectors[0] = {}; vectors[1] = {}; vectors[2] = {} }}", self.vectors[0], self.vectors[1], self.vectors[2]))
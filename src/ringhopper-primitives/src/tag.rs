@@ -23,6 +23,18 @@ pub trait PrimaryTagStruct: DynamicTagData + TagData + Send {
 
     /// Get a mutable reference to the metadata for the tag.
     fn metadata_mut(&mut self) -> &mut PrimaryTagStructMetadata;
+
+    /// Read a single field of the tag data without reading the rest of the tag, passing it to `f`.
+    ///
+    /// `field` is the name of the field as used by [`DynamicTagData::get_field`], and `data` is the tag data after the
+    /// header.
+    ///
+    /// Returns `Ok(None)` if there is no such field. By default, this reads the whole tag.
+    fn read_field_from_tag_file<R, F: FnOnce(&dyn DynamicTagData) -> R>(data: &[u8], field: &str, f: F) -> RinghopperResult<Option<R>> where Self: Sized {
+        let mut cursor = Self::size();
+        let tag = Self::read_from_tag_file(data, 0, Self::size(), &mut cursor)?;
+        Ok(tag.get_field(field).map(f))
+    }
 }
 
 /// Metadata for tag data.
@@ -245,7 +257,8 @@ impl TagFile {
     }
 
     fn validate_crc32(header: &TagFileHeader, data_after_header: &[u8], strictness: ParseStrictness) -> RinghopperResult<()> {
-        let actual_crc32 = if header.crc32 == IGNORED_CRC32 {
+        // Relaxed parsing ignores the checksum anyway, so don't spend time calculating it
+        let actual_crc32 = if header.crc32 == IGNORED_CRC32 || strictness == ParseStrictness::Relaxed {
             None
         }
         else {
@@ -268,12 +281,20 @@ impl TagFile {
         Ok(())
     }
 
-    /// Returns the header and everything after the header without parsing the actual tag  data.
+    /// Returns the header without checking the rest of the tag file.
     ///
     /// Return `Err` if parsing the header fails.
-    pub fn load_header_and_data(file: &[u8], strictness: ParseStrictness) -> RinghopperResult<(TagFileHeader, &[u8])> {
+    pub fn load_header(file: &[u8]) -> RinghopperResult<TagFileHeader> {
         let header = TagFileHeader::read_from_tag_file(file, 0, 0x40, &mut 0)?;
         header.validate()?;
+        Ok(header)
+    }
+
+    /// Returns the header and everything after the header without parsing the actual tag  data.
+    ///
+    /// Return `Err` if parsing the header fails.
+    pub fn load_header_and_data(file: &[u8], strictness: ParseStrictness) -> RinghopperResult<(TagFileHeader, &[u8])> {
+        let header = Self::load_header(file)?;
 
         let data_after_header = &file[<TagFileHeader as TagData>::size()..];
         Self::validate_crc32(&header, data_after_header, strictness)?;
@@ -306,6 +327,56 @@ impl TagFile {
 
         Ok(result)
     }
+
+    /// Get all tag references in the tag file buffer without reading the rest of the tag.
+    ///
+    /// The CRC32 is not checked, as calculating it would take longer than the scan itself. A corrupted tag will still
+    /// fail to parse when it is actually read.
+    ///
+    /// Returns `Err` if the tag data is invalid or does not correspond to `T`.
+    pub fn scan_tag_references_from_file_buffer<T: PrimaryTagStruct>(file: &[u8]) -> RinghopperResult<Vec<TagReference>> {
+        let (header, data_after_header) = Self::load_header_and_data(file, ParseStrictness::Relaxed)?;
+        header.verify_group_matches::<T>()?;
+
+        let mut references = Vec::new();
+        let mut cursor = T::size();
+        T::scan_tag_references_from_tag_file(data_after_header, 0, T::size(), &mut cursor, &mut references)?;
+
+        // Same as read_tag_from_file_buffer; references read from a tag with unaccounted data cannot be trusted.
+        let actual_data_size = data_after_header.len();
+        if cursor != actual_data_size {
+            return Err(Error::TagParseFailure(format!("leftover data after parsing - 0x{cursor:08X} parsed, 0x{actual_data_size:08X} actual tag size")));
+        }
+
+        Ok(references)
+    }
+
+    /// Find all values in the tag file buffer that are reset when it is read, such as invalid enum values and unknown
+    /// bitfield bits.
+    ///
+    /// Returns `Err` if the tag data is invalid, corrupt, or does not correspond to `T`.
+    pub fn find_reset_values_in_file_buffer<T: PrimaryTagStruct>(file: &[u8], strictness: ParseStrictness) -> RinghopperResult<Vec<ResetValue>> {
+        let (header, data_after_header) = Self::load_header_and_data(file, strictness)?;
+        header.verify_group_matches::<T>()?;
+
+        let mut values = Vec::new();
+        T::find_reset_values_in_tag_file(data_after_header, 0, T::size(), &mut T::size(), &mut String::new(), &mut values)?;
+
+        Ok(values)
+    }
+
+    /// Read a single field of the tag file buffer without reading the rest of the tag, passing it to `f`.
+    ///
+    /// Unlike [`TagFile::read_tag_from_file_buffer`], data after the field is not checked.
+    ///
+    /// Returns `Ok(None)` if `T` has no such field, or `Err` if the tag data is invalid, corrupt, or does not
+    /// correspond to `T`.
+    pub fn read_field_from_file_buffer<T: PrimaryTagStruct, R, F: FnOnce(&dyn DynamicTagData) -> R>(file: &[u8], field: &str, strictness: ParseStrictness, f: F) -> RinghopperResult<Option<R>> {
+        let (header, data_after_header) = Self::load_header_and_data(file, strictness)?;
+        header.verify_group_matches::<T>()?;
+
+        T::read_field_from_tag_file(data_after_header, field, f)
+    }
 }
 
 /// Iterate through each [`DynamicTagData`] of a block.
@@ -0,0 +1,68 @@
+//! Generates a JSON Schema for each tag group and a browsable Markdown reference from the Ringhopper definitions.
+//!
+//! The schemas describe the JSON tag text format used by `invader tag-to-json` and `invader json-to-tag`, and the
+//! Markdown reference documents every group, struct, enum, and bitfield.
+
+extern crate ringhopper_definitions;
+extern crate serde_json;
+
+mod markdown;
+mod schema;
+
+use std::path::Path;
+use std::process::ExitCode;
+use ringhopper_definitions::{load_all_definitions, NamedObject, ParsedDefinitions, TagGroup};
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().collect();
+    let [_, output] = args.as_slice() else {
+        eprintln!("Usage: {} <output-directory>", args.first().map(|a| a.as_str()).unwrap_or("ringhopper-reference"));
+        return ExitCode::FAILURE
+    };
+
+    let definitions = load_all_definitions();
+    let output = Path::new(output);
+
+    match write_all(&definitions, output) {
+        Ok(()) => {
+            println!("Wrote the schemas and reference to {}", output.display());
+            ExitCode::SUCCESS
+        },
+        Err(e) => {
+            eprintln!("Failed to write to {}: {e}", output.display());
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn write_all(definitions: &ParsedDefinitions, output: &Path) -> std::io::Result<()> {
+    let schema_dir = output.join("schema");
+    std::fs::create_dir_all(&schema_dir)?;
+    for group in sorted_groups(definitions) {
+        let schema = schema::group_schema(group, definitions);
+        std::fs::write(schema_dir.join(format!("{}.schema.json", group.name)), serde_json::to_string_pretty(&schema).unwrap() + "\n")?;
+    }
+
+    for (path, page) in markdown::all_pages(definitions) {
+        let path = output.join("reference").join(path);
+        std::fs::create_dir_all(path.parent().unwrap())?;
+        std::fs::write(path, page)?;
+    }
+
+    Ok(())
+}
+
+fn sorted_groups(definitions: &ParsedDefinitions) -> Vec<&TagGroup> {
+    let mut groups: Vec<&TagGroup> = definitions.groups.values().collect();
+    groups.sort_by(|a, b| a.name.cmp(&b.name));
+    groups
+}
+
+fn sorted_objects(definitions: &ParsedDefinitions) -> Vec<&NamedObject> {
+    let mut objects: Vec<&NamedObject> = definitions.objects.values().collect();
+    objects.sort_by(|a, b| a.name().cmp(b.name()));
+    objects
+}
+
+#[cfg(test)]
+mod test;
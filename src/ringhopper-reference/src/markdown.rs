@@ -0,0 +1,347 @@
+use std::fmt::Write;
+use std::path::PathBuf;
+use ringhopper_definitions::*;
+use crate::{sorted_groups, sorted_objects};
+
+/// Generate every page of the Markdown reference, returning each page's path relative to the reference root.
+pub fn all_pages(definitions: &ParsedDefinitions) -> Vec<(PathBuf, String)> {
+    let mut pages = vec![(PathBuf::from("index.md"), index_page(definitions))];
+
+    for group in sorted_groups(definitions) {
+        pages.push((PathBuf::from("groups").join(format!("{}.md", group.name)), group_page(group, definitions)));
+    }
+
+    for object in sorted_objects(definitions) {
+        let page = match object {
+            NamedObject::Struct(s) => struct_page(s, definitions),
+            NamedObject::Enum(e) => enum_page(e),
+            NamedObject::Bitfield(b) => bitfield_page(b)
+        };
+        pages.push((PathBuf::from(object_link(object)), page));
+    }
+
+    pages
+}
+
+/// Get the path of the object's page relative to the reference root.
+fn object_link(object: &NamedObject) -> String {
+    let directory = match object {
+        NamedObject::Struct(_) => "structs",
+        NamedObject::Enum(_) => "enums",
+        NamedObject::Bitfield(_) => "bitfields"
+    };
+    format!("{directory}/{}.md", object.name())
+}
+
+fn index_page(definitions: &ParsedDefinitions) -> String {
+    let mut page = String::new();
+    writeln!(page, "# Tag definitions reference\n").unwrap();
+    writeln!(page, "Generated from the Ringhopper definitions. A JSON Schema for each group can be found in `schema/`.\n").unwrap();
+
+    writeln!(page, "## Groups\n").unwrap();
+    writeln!(page, "| Group | Struct | Supergroup | Version |").unwrap();
+    writeln!(page, "| --- | --- | --- | --- |").unwrap();
+    for group in sorted_groups(definitions) {
+        let supergroup = group.supergroup.as_ref().map(|s| format!("[{s}](groups/{s}.md)")).unwrap_or_default();
+        writeln!(page, "| [{name}](groups/{name}.md) | [{s}](structs/{s}.md) | {supergroup} | {version} |", name = group.name, s = group.struct_name, version = group.version).unwrap();
+    }
+
+    for (heading, kind) in [("Structs", "structs"), ("Enums", "enums"), ("Bitfields", "bitfields")] {
+        writeln!(page, "\n## {heading}\n").unwrap();
+        for object in sorted_objects(definitions) {
+            let link = object_link(object);
+            if link.starts_with(kind) {
+                writeln!(page, "* [{}]({link})", object.name()).unwrap();
+            }
+        }
+    }
+
+    page
+}
+
+fn group_page(group: &TagGroup, definitions: &ParsedDefinitions) -> String {
+    let mut page = String::new();
+    writeln!(page, "# {}\n", group.name).unwrap();
+    writeln!(page, "* Struct: [{s}](../structs/{s}.md)", s = group.struct_name).unwrap();
+    writeln!(page, "* Version: {}", group.version).unwrap();
+    if let Some(s) = &group.supergroup {
+        writeln!(page, "* Supergroup: [{s}]({s}.md)").unwrap();
+    }
+
+    let mut subgroups: Vec<&str> = definitions.groups.values().filter(|g| g.supergroup.as_ref() == Some(&group.name)).map(|g| g.name.as_str()).collect();
+    subgroups.sort();
+    if !subgroups.is_empty() {
+        let subgroups: Vec<String> = subgroups.iter().map(|s| format!("[{s}]({s}.md)")).collect();
+        writeln!(page, "* Subgroups: {}", subgroups.join(", ")).unwrap();
+    }
+    if let Some(engines) = engines_note(&group.supported_engines, definitions) {
+        writeln!(page, "* {engines}").unwrap();
+    }
+    writeln!(page, "* JSON Schema: `schema/{}.schema.json`", group.name).unwrap();
+
+    if let NamedObject::Struct(s) = &definitions.objects[&group.struct_name] {
+        writeln!(page).unwrap();
+        write_struct_fields(&mut page, s, definitions);
+    }
+
+    page
+}
+
+fn struct_page(structure: &Struct, definitions: &ParsedDefinitions) -> String {
+    let mut page = String::new();
+    writeln!(page, "# {}\n", structure.name).unwrap();
+    write_description(&mut page, &structure.flags);
+
+    let groups: Vec<String> = sorted_groups(definitions).iter()
+        .filter(|g| g.struct_name == structure.name)
+        .map(|g| format!("[{g}](../groups/{g}.md)", g = g.name))
+        .collect();
+    if !groups.is_empty() {
+        writeln!(page, "* Group: {}", groups.join(", ")).unwrap();
+    }
+    writeln!(page, "* Size: {} (0x{:X}) bytes\n", structure.size, structure.size).unwrap();
+
+    write_struct_fields(&mut page, structure, definitions);
+    page
+}
+
+fn write_struct_fields(page: &mut String, structure: &Struct, definitions: &ParsedDefinitions) {
+    const TABLE_HEADER: &str = "| Offset | Field | Type | Notes |\n| --- | --- | --- | --- |\n";
+
+    writeln!(page, "## Fields\n").unwrap();
+
+    let mut offset = 0;
+    let mut in_table = false;
+    for field in &structure.fields {
+        let size = field.size(definitions);
+        match &field.field_type {
+            StructFieldType::EditorSection(section) => {
+                if in_table {
+                    writeln!(page).unwrap();
+                    in_table = false;
+                }
+                writeln!(page, "### {}\n", section.name).unwrap();
+                if let Some(description) = &section.description {
+                    writeln!(page, "{description}\n").unwrap();
+                }
+            },
+            StructFieldType::Padding(_) => (),
+            StructFieldType::Object(object_type) => {
+                if !in_table {
+                    page.push_str(TABLE_HEADER);
+                    in_table = true;
+                }
+                let name = if field.flags.exclude { format!("~~{}~~", field.name) } else { format!("`{}`", matcher_name(&field.name)) };
+                let field_type = field_type_name(object_type, field.count, definitions);
+                let notes = field_notes(field, object_type, definitions).join("<br>");
+                writeln!(page, "| 0x{offset:X} | {name} | {field_type} | {notes} |").unwrap();
+            }
+        }
+        offset += size;
+    }
+
+    if !structure.fields.iter().any(|f| matches!(f.field_type, StructFieldType::Object(_))) {
+        writeln!(page, "This struct has no fields.").unwrap();
+    }
+}
+
+fn field_type_name(object_type: &ObjectType, count: FieldCount, definitions: &ParsedDefinitions) -> String {
+    let link = |name: &str| match definitions.objects.get(name) {
+        Some(object) => format!("[{name}](../{})", object_link(object)),
+        None => name.to_owned()
+    };
+
+    let base = match object_type {
+        ObjectType::NamedObject(name) => link(name),
+        ObjectType::Reflexive(name) => format!("Reflexive of {}", link(name)),
+        ObjectType::TagReference(_) => "TagReference".to_owned(),
+        ObjectType::TagGroup => "TagGroup".to_owned(),
+        ObjectType::Data => "Data".to_owned(),
+        ObjectType::FileData => "FileData".to_owned(),
+        ObjectType::BSPVertexData => "BSPVertexData".to_owned(),
+        ObjectType::UTF16String => "UTF16String".to_owned(),
+        ObjectType::Float => "float".to_owned(),
+        ObjectType::U8 => "uint8".to_owned(),
+        ObjectType::U16 => "uint16".to_owned(),
+        ObjectType::U32 => "uint32".to_owned(),
+        ObjectType::I8 => "int8".to_owned(),
+        ObjectType::I16 => "int16".to_owned(),
+        ObjectType::I32 => "int32".to_owned(),
+        ObjectType::TagID => "TagID".to_owned(),
+        ObjectType::ID => "ID".to_owned(),
+        ObjectType::Index => "Index".to_owned(),
+        ObjectType::Angle => "Angle".to_owned(),
+        ObjectType::Address => "Address".to_owned(),
+        ObjectType::Vector2D => "Vector2D".to_owned(),
+        ObjectType::Vector3D => "Vector3D".to_owned(),
+        ObjectType::CompressedVector2D => "CompressedVector2D".to_owned(),
+        ObjectType::CompressedVector3D => "CompressedVector3D".to_owned(),
+        ObjectType::CompressedFloat => "CompressedFloat".to_owned(),
+        ObjectType::Vector2DInt => "Vector2DInt".to_owned(),
+        ObjectType::Plane2D => "Plane2D".to_owned(),
+        ObjectType::Plane3D => "Plane3D".to_owned(),
+        ObjectType::Euler2D => "Euler2D".to_owned(),
+        ObjectType::Euler3D => "Euler3D".to_owned(),
+        ObjectType::Rectangle => "Rectangle".to_owned(),
+        ObjectType::Quaternion => "Quaternion".to_owned(),
+        ObjectType::Matrix3x3 => "Matrix3x3".to_owned(),
+        ObjectType::ColorRGBFloat => "ColorRGBFloat".to_owned(),
+        ObjectType::ColorARGBFloat => "ColorARGBFloat".to_owned(),
+        ObjectType::ColorARGBInt => "ColorARGBInt".to_owned(),
+        ObjectType::String32 => "String32".to_owned(),
+        ObjectType::ScenarioScriptNodeValue => "ScenarioScriptNodeValue".to_owned(),
+    };
+
+    match count {
+        FieldCount::One => base,
+        FieldCount::Bounds => format!("{base} bounds"),
+        FieldCount::Array(n) => format!("{base} \\[{n}\\]")
+    }
+}
+
+fn field_notes(field: &StructField, object_type: &ObjectType, definitions: &ParsedDefinitions) -> Vec<String> {
+    let flags = &field.flags;
+    let mut notes = Vec::new();
+
+    for text in [&flags.description, &flags.comment].into_iter().flatten() {
+        notes.push(escape_cell(text));
+    }
+    if let Some(note) = &flags.developer_note {
+        notes.push(format!("**Developer note:** {}", escape_cell(note)));
+    }
+
+    if let ObjectType::TagReference(reference) = object_type {
+        let groups: Vec<String> = reference.allowed_groups.iter().map(|g| format!("[{g}](../groups/{g}.md)")).collect();
+        notes.push(format!("Allowed groups: {}", groups.join(", ")));
+    }
+    if let Some(reference) = &field.index_reference {
+        let relation = if matches!(object_type, ObjectType::Index) { "Index of" } else { "Refers to" };
+        notes.push(format!("{relation} `{}` in [{s}](../structs/{s}.md)", matcher_name(&reference.reflexive), s = reference.struct_name));
+    }
+
+    if let Some(default) = &field.default_value {
+        let unit = if matches!(object_type, ObjectType::Angle) { "°" } else { "" };
+        let values: Vec<String> = default.iter().map(|v| display_static_value(v) + unit).collect();
+        notes.push(format!("Default: {}", values.join(", ")));
+    }
+    let minimum = field.minimum.as_ref().map(display_static_value);
+    let maximum = field.maximum.as_ref().map(display_static_value);
+    if minimum.is_some() || maximum.is_some() {
+        notes.push(format!("Range: [{}, {}]", minimum.unwrap_or_else(|| "-inf".to_owned()), maximum.unwrap_or_else(|| "inf".to_owned())));
+    }
+    if let Some(limits) = &field.limit {
+        let mut engine_limits: Vec<String> = limits.iter()
+            .filter_map(|(k, v)| match k {
+                LimitType::Engine(engine) => Some(format!("{} on {}", v, engine_display_name(engine, definitions))),
+                _ => None
+            })
+            .collect();
+        engine_limits.sort();
+        let mut limit = format!("Limit: {}", limits[&LimitType::Default]);
+        if !engine_limits.is_empty() {
+            write!(limit, " ({})", engine_limits.join(", ")).unwrap();
+        }
+        notes.push(limit);
+    }
+
+    let flag_notes = [
+        (flags.non_null, "Must be set"),
+        (flags.normalize, "Must be unit length"),
+        (flags.unordered_bounds, "Bounds may be unordered"),
+        (flags.cache_only, "Only present in cache files"),
+        (flags.non_cached, "Only present in tag files"),
+        (flags.uneditable_in_editor, "Read-only"),
+        (flags.hidden_in_editor, "Hidden"),
+        (flags.little_endian_in_tags, "Little endian in tag files"),
+        (flags.exclude, "Unused; not readable or writable"),
+    ];
+    notes.extend(flag_notes.into_iter().filter(|(set, _)| *set).map(|(_, note)| note.to_owned()));
+
+    if let Some(engines) = engines_note(&flags.supported_engines, definitions) {
+        notes.push(engines);
+    }
+
+    notes
+}
+
+fn enum_page(enumeration: &Enum) -> String {
+    let mut page = String::new();
+    writeln!(page, "# {}\n", enumeration.name).unwrap();
+    write_description(&mut page, &enumeration.flags);
+
+    writeln!(page, "| Value | Option | Notes |").unwrap();
+    writeln!(page, "| --- | --- | --- |").unwrap();
+    for option in &enumeration.options {
+        writeln!(page, "| {} | {} | {} |", option.value, option_name(option), option_notes(&option.flags)).unwrap();
+    }
+
+    page
+}
+
+fn bitfield_page(bitfield: &Bitfield) -> String {
+    let mut page = String::new();
+    writeln!(page, "# {}\n", bitfield.name).unwrap();
+    write_description(&mut page, &bitfield.flags);
+    writeln!(page, "* Width: {} bits\n", bitfield.width).unwrap();
+
+    let digits = (bitfield.width / 4) as usize;
+    writeln!(page, "| Mask | Flag | Notes |").unwrap();
+    writeln!(page, "| --- | --- | --- |").unwrap();
+    for field in &bitfield.fields {
+        writeln!(page, "| 0x{:0digits$X} | {} | {} |", field.value, option_name(field), option_notes(&field.flags)).unwrap();
+    }
+
+    page
+}
+
+fn option_name(field: &Field) -> String {
+    if field.flags.exclude {
+        format!("~~{}~~", field.name)
+    }
+    else {
+        format!("`{}`", matcher_name(&field.name))
+    }
+}
+
+fn option_notes(flags: &Flags) -> String {
+    let mut notes: Vec<String> = [&flags.description, &flags.comment].into_iter().flatten().map(|c| escape_cell(c)).collect();
+    if flags.cache_only {
+        notes.push("Only present in cache files".to_owned());
+    }
+    if flags.exclude {
+        notes.push("Unused".to_owned());
+    }
+    notes.join("<br>")
+}
+
+fn write_description(page: &mut String, flags: &Flags) {
+    for text in [&flags.description, &flags.comment].into_iter().flatten() {
+        writeln!(page, "{text}\n").unwrap();
+    }
+    if let Some(note) = &flags.developer_note {
+        writeln!(page, "**Developer note:** {note}\n").unwrap();
+    }
+}
+
+fn engines_note(supported_engines: &SupportedEngines, definitions: &ParsedDefinitions) -> Option<String> {
+    let engines: Vec<String> = supported_engines.supported_engines.as_ref()?.iter().map(|e| engine_display_name(e, definitions)).collect();
+    Some(format!("Supported engines: {}", engines.join(", ")))
+}
+
+fn engine_display_name(engine: &str, definitions: &ParsedDefinitions) -> String {
+    definitions.engines.get(engine).map(|e| e.display_name.clone()).unwrap_or_else(|| engine.to_owned())
+}
+
+fn display_static_value(value: &StaticValue) -> String {
+    match value {
+        StaticValue::F32(f) => f.to_string(),
+        StaticValue::Uint(u) => u.to_string(),
+        StaticValue::Int(i) => i.to_string(),
+        StaticValue::String(s) => format!("`{s}`")
+    }
+}
+
+/// Escape text so it fits in a single table cell.
+fn escape_cell(text: &str) -> String {
+    text.trim().replace('|', "\\|").replace("\r\n", "<br>").replace('\n', "<br>")
+}
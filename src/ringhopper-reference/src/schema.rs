@@ -0,0 +1,242 @@
+use ringhopper_definitions::*;
+use serde_json::{json, Map, Value};
+use crate::{sorted_groups};
+
+/// Generate a JSON Schema for tags of the group, as written by `ringhopper::tag::json::tag_to_json`.
+pub fn group_schema(group: &TagGroup, definitions: &ParsedDefinitions) -> Value {
+    let mut builder = SchemaBuilder {
+        definitions,
+        defs: Map::new(),
+        pending: vec![group.struct_name.clone()]
+    };
+
+    while let Some(name) = builder.pending.pop() {
+        if builder.defs.contains_key(&name) {
+            continue
+        }
+        let def = builder.named_object_schema(&name);
+        builder.defs.insert(name, def);
+    }
+
+    let mut defs: Vec<(String, Value)> = builder.defs.into_iter().collect();
+    defs.sort_by(|a, b| a.0.cmp(&b.0));
+    let mut defs: Map<String, Value> = defs.into_iter().collect();
+    defs.insert("float".to_owned(), json!({
+        "description": "Float; non-finite values are written as a string of their 32-bit representation",
+        "anyOf": [{ "type": "number" }, { "$ref": "#/$defs/float_bits" }]
+    }));
+    defs.insert("float_bits".to_owned(), json!({ "type": "string", "pattern": "^0x[0-9A-Fa-f]{8}$" }));
+    defs.insert("hex".to_owned(), json!({ "type": "string", "pattern": "^([0-9A-Fa-f]{2})*$" }));
+
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": group.name,
+        "type": "object",
+        "properties": {
+            "group": { "const": group.name },
+            "data": { "$ref": format!("#/$defs/{}", group.struct_name) }
+        },
+        "required": ["group", "data"],
+        "additionalProperties": false,
+        "$defs": defs
+    })
+}
+
+struct SchemaBuilder<'a> {
+    definitions: &'a ParsedDefinitions,
+    defs: Map<String, Value>,
+    pending: Vec<String>
+}
+
+impl<'a> SchemaBuilder<'a> {
+    fn named_object_schema(&mut self, name: &str) -> Value {
+        match &self.definitions.objects[name] {
+            NamedObject::Struct(s) => {
+                let mut properties = Map::new();
+                for field in &s.fields {
+                    let StructFieldType::Object(object_type) = &field.field_type else { continue };
+                    if field.flags.exclude {
+                        continue
+                    }
+                    properties.insert(matcher_name(&field.name), self.field_schema(field, object_type));
+                }
+
+                let mut schema = json!({ "type": "object", "properties": properties, "additionalProperties": false });
+                insert_description(&mut schema, &s.flags);
+                schema
+            },
+            NamedObject::Enum(e) => {
+                let options: Vec<String> = e.options.iter().filter(|o| !o.flags.exclude).map(|o| matcher_name(&o.name)).collect();
+                let mut schema = json!({ "type": "string", "enum": options });
+                insert_description(&mut schema, &e.flags);
+                schema
+            },
+            NamedObject::Bitfield(b) => {
+                let flags: Vec<String> = b.fields.iter().filter(|f| !f.flags.exclude).map(|f| matcher_name(&f.name)).collect();
+                let mut schema = json!({ "type": "array", "items": { "enum": flags }, "uniqueItems": true });
+                insert_description(&mut schema, &b.flags);
+                schema
+            }
+        }
+    }
+
+    fn field_schema(&mut self, field: &StructField, object_type: &ObjectType) -> Value {
+        let mut schema = self.object_type_schema(object_type);
+        apply_range(&mut schema, field, object_type);
+
+        if let (FieldCount::One, Some([default])) = (field.count, field.default_value.as_deref()) {
+            // Angle defaults are defined in degrees, but angles are stored in radians
+            let default = match (object_type, default) {
+                (ObjectType::Angle, StaticValue::F32(f)) => &StaticValue::F32(f.to_radians()),
+                _ => default
+            };
+            if let Some(default) = static_value_to_json(default) {
+                schema["default"] = default;
+            }
+        }
+
+        let mut schema = match field.count {
+            FieldCount::One => schema,
+            FieldCount::Bounds => json!({
+                "type": "object",
+                "properties": { "lower": schema.clone(), "upper": schema },
+                "additionalProperties": false
+            }),
+            FieldCount::Array(n) => json!({ "type": "array", "items": schema, "minItems": n, "maxItems": n })
+        };
+
+        insert_description(&mut schema, &field.flags);
+        schema
+    }
+
+    fn object_type_schema(&mut self, object_type: &ObjectType) -> Value {
+        let float = || json!({ "$ref": "#/$defs/float" });
+        let floats = |components: &[&str]| {
+            let properties: Map<String, Value> = components.iter().map(|c| ((*c).to_owned(), float())).collect();
+            json!({ "type": "object", "properties": properties, "required": components })
+        };
+        let integer = |minimum: i64, maximum: i64| json!({ "type": "integer", "minimum": minimum, "maximum": maximum });
+        let integers = |components: &[&str], minimum: i64, maximum: i64| {
+            let properties: Map<String, Value> = components.iter().map(|c| ((*c).to_owned(), integer(minimum, maximum))).collect();
+            json!({ "type": "object", "properties": properties, "required": components })
+        };
+
+        match object_type {
+            ObjectType::NamedObject(name) => {
+                self.pending.push(name.to_owned());
+                json!({ "$ref": format!("#/$defs/{name}") })
+            },
+            ObjectType::Reflexive(name) => {
+                self.pending.push(name.to_owned());
+                json!({ "type": "array", "items": { "$ref": format!("#/$defs/{name}") } })
+            },
+            ObjectType::TagReference(reference) => json!({
+                "anyOf": [
+                    { "type": "string", "description": "Internal tag path, including the extension" },
+                    {
+                        "type": "object",
+                        "description": "Null reference",
+                        "properties": { "group": { "enum": reference.allowed_groups } },
+                        "required": ["group"]
+                    }
+                ]
+            }),
+            ObjectType::TagGroup => {
+                let mut groups: Vec<Value> = sorted_groups(self.definitions).iter().map(|g| Value::String(g.name.clone())).collect();
+                groups.push(Value::Null);
+                json!({ "enum": groups })
+            },
+            ObjectType::Data | ObjectType::FileData | ObjectType::BSPVertexData => json!({ "$ref": "#/$defs/hex" }),
+            ObjectType::UTF16String => json!({
+                "anyOf": [
+                    { "type": "string" },
+                    { "type": "object", "properties": { "utf16": { "$ref": "#/$defs/hex" } }, "required": ["utf16"] }
+                ]
+            }),
+            ObjectType::Float | ObjectType::Angle => float(),
+            ObjectType::U8 => integer(u8::MIN.into(), u8::MAX.into()),
+            ObjectType::U16 => integer(u16::MIN.into(), u16::MAX.into()),
+            ObjectType::U32 | ObjectType::Address | ObjectType::ScenarioScriptNodeValue | ObjectType::CompressedVector2D | ObjectType::CompressedVector3D => integer(u32::MIN.into(), u32::MAX.into()),
+            ObjectType::CompressedFloat => integer(u16::MIN.into(), u16::MAX.into()),
+            ObjectType::I8 => integer(i8::MIN.into(), i8::MAX.into()),
+            ObjectType::I16 => integer(i16::MIN.into(), i16::MAX.into()),
+            ObjectType::I32 => integer(i32::MIN.into(), i32::MAX.into()),
+            ObjectType::TagID | ObjectType::ID => json!({ "type": ["integer", "null"], "minimum": u32::MIN, "maximum": u32::MAX }),
+            ObjectType::Index => json!({ "type": ["integer", "null"], "minimum": u16::MIN, "maximum": u16::MAX }),
+            ObjectType::Vector2D => floats(&["x", "y"]),
+            ObjectType::Vector3D => floats(&["x", "y", "z"]),
+            ObjectType::Plane2D => floats(&["x", "y", "d"]),
+            ObjectType::Plane3D => floats(&["x", "y", "z", "d"]),
+            ObjectType::Euler2D => floats(&["yaw", "pitch"]),
+            ObjectType::Euler3D => floats(&["yaw", "pitch", "roll"]),
+            ObjectType::Quaternion => floats(&["x", "y", "z", "w"]),
+            ObjectType::Matrix3x3 => json!({ "type": "array", "items": floats(&["x", "y", "z"]), "minItems": 3, "maxItems": 3 }),
+            ObjectType::ColorRGBFloat => floats(&["red", "green", "blue"]),
+            ObjectType::ColorARGBFloat => floats(&["alpha", "red", "green", "blue"]),
+            ObjectType::ColorARGBInt => integers(&["alpha", "red", "green", "blue"], u8::MIN.into(), u8::MAX.into()),
+            ObjectType::Vector2DInt => integers(&["x", "y"], i16::MIN.into(), i16::MAX.into()),
+            ObjectType::Rectangle => integers(&["top", "left", "bottom", "right"], i16::MIN.into(), i16::MAX.into()),
+            ObjectType::String32 => json!({ "type": "string", "maxLength": 31 }),
+        }
+    }
+}
+
+/// Apply the field's minimum, maximum, and limit (the largest limit of any engine) to the schema.
+fn apply_range(schema: &mut Value, field: &StructField, object_type: &ObjectType) {
+    let minimum = field.minimum.as_ref().and_then(static_value_to_json);
+    let maximum = field.maximum.as_ref().and_then(static_value_to_json);
+    let limit = field.limit.as_ref().and_then(|l| l.get(&LimitType::Editor)).copied();
+
+    match object_type {
+        ObjectType::Reflexive(_) => {
+            if let Some(minimum) = minimum {
+                schema["minItems"] = minimum;
+            }
+            let maximum = maximum.and_then(|m| m.as_u64()).map(|m| m as usize);
+            if let Some(maximum) = [maximum, limit].into_iter().flatten().min() {
+                schema["maxItems"] = maximum.into();
+            }
+        },
+        ObjectType::Data | ObjectType::FileData => {
+            if let Some(limit) = limit {
+                schema["maxLength"] = (limit * 2).into();
+            }
+        },
+        ObjectType::U8 | ObjectType::U16 | ObjectType::U32 | ObjectType::I8 | ObjectType::I16 | ObjectType::I32 => {
+            if let Some(minimum) = minimum {
+                schema["minimum"] = minimum;
+            }
+            if let Some(maximum) = maximum {
+                schema["maximum"] = maximum;
+            }
+        },
+        ObjectType::Float if minimum.is_some() || maximum.is_some() => {
+            let mut number = json!({ "type": "number" });
+            if let Some(minimum) = minimum {
+                number["minimum"] = minimum;
+            }
+            if let Some(maximum) = maximum {
+                number["maximum"] = maximum;
+            }
+            *schema = json!({ "anyOf": [number, { "$ref": "#/$defs/float_bits" }] });
+        },
+        _ => ()
+    }
+}
+
+fn static_value_to_json(value: &StaticValue) -> Option<Value> {
+    match value {
+        // Go through the shortest representation so 32-bit floats aren't written with extra digits
+        StaticValue::F32(f) => serde_json::Number::from_f64(f.to_string().parse().unwrap()).map(Value::Number),
+        StaticValue::Uint(u) => Some((*u).into()),
+        StaticValue::Int(i) => Some((*i).into()),
+        StaticValue::String(s) => Some(Value::String(s.clone()))
+    }
+}
+
+fn insert_description(schema: &mut Value, flags: &Flags) {
+    let description = [&flags.description, &flags.comment].into_iter().flatten().next();
+    if let Some(description) = description {
+        schema["description"] = Value::String(description.clone());
+    }
+}
@@ -0,0 +1,52 @@
+use ringhopper_definitions::load_all_definitions;
+use ringhopper_primitives::dynamic::{DynamicTagData, DynamicTagDataType};
+use ringhopper_primitives::primitive::TagGroup;
+use serde_json::Value;
+use crate::schema::group_schema;
+use crate::sorted_groups;
+
+fn resolve<'a>(schema: &'a Value, value: &'a Value) -> &'a Value {
+    match value.get("$ref").and_then(|r| r.as_str()) {
+        Some(r) => &schema["$defs"][r.strip_prefix("#/$defs/").unwrap()],
+        None => value
+    }
+}
+
+fn check_refs(schema: &Value, value: &Value) {
+    match value {
+        Value::Object(o) => {
+            if let Some(r) = o.get("$ref") {
+                assert!(!resolve(schema, value).is_null(), "unresolved reference {r}");
+            }
+            o.values().for_each(|v| check_refs(schema, v));
+        },
+        Value::Array(a) => a.iter().for_each(|v| check_refs(schema, v)),
+        _ => ()
+    }
+}
+
+fn check_fields(schema: &Value, block_schema: &Value, data: &dyn DynamicTagData, path: &str) {
+    let properties = block_schema["properties"].as_object().unwrap_or_else(|| panic!("{path} has no properties"));
+    let property_names: Vec<&str> = properties.keys().map(|k| k.as_str()).collect();
+    assert_eq!(data.fields(), property_names.as_slice(), "fields of {path} do not match");
+
+    for field in data.fields() {
+        let field_data = data.get_field(field).unwrap();
+        let field_schema = resolve(schema, &properties[*field]);
+        if field_data.data_type() == DynamicTagDataType::Block && field_schema["type"] == "object" {
+            check_fields(schema, field_schema, field_data, &format!("{path}.{field}"));
+        }
+    }
+}
+
+#[test]
+fn schema_matches_tag_fields() {
+    let definitions = load_all_definitions();
+    for group in sorted_groups(&definitions) {
+        let schema = group_schema(group, &definitions);
+        check_refs(&schema, &schema);
+
+        let tag = ringhopper_structs::new_any_tag(TagGroup::from_str(&group.name).expect(&group.name)).unwrap();
+        check_fields(&schema, resolve(&schema, &schema["properties"]["data"]), tag.as_dynamic(), &group.name);
+    }
+}
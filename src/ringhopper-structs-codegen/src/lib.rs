@@ -3,7 +3,7 @@ extern crate ringhopper_definitions;
 use std::fmt::Write;
 use std::borrow::Cow;
 
-use ringhopper_definitions::{load_all_definitions, SizeableObject, Struct, NamedObject, Enum, Bitfield, StructFieldType, ObjectType, ParsedDefinitions, FieldCount, TagGroup, StaticValue, Flags};
+use ringhopper_definitions::{load_all_definitions, SizeableObject, Struct, NamedObject, Enum, Bitfield, StructFieldType, ObjectType, ParsedDefinitions, FieldCount, TagGroup, StaticValue, Flags, StructField, LimitType, matcher_name};
 
 use proc_macro::TokenStream;
 use std::collections::HashSet;
@@ -18,7 +18,10 @@ pub fn generate_ringhopper_structs(_: TokenStream) -> TokenStream {
     }
 
     let mut read_any_tag_lines = String::new();
+    let mut scan_any_tag_lines = String::new();
+    let mut find_reset_values_lines = String::new();
     let mut read_any_map_lines = String::new();
+    let mut new_any_tag_lines = String::new();
     let mut referenceable_tag_groups_hint = String::new();
     let mut supported_groups_for_engines = String::new();
     let mut defaultable_tag_groups_hint = String::new();
@@ -64,8 +67,11 @@ pub fn generate_ringhopper_structs(_: TokenStream) -> TokenStream {
         }
 
         writeln!(referenceable_tag_groups_hint, "TagGroup::{group_name_fixed} => &[{list}],").unwrap();
-        writeln!(read_any_tag_lines, "TagGroup::{group_name_fixed} => b(TagFile::read_tag_from_file_buffer::<{struct_name}>(file, ParseStrictness::Relaxed)),").unwrap();
+        writeln!(read_any_tag_lines, "TagGroup::{group_name_fixed} => b(TagFile::read_tag_from_file_buffer::<{struct_name}>(file, strictness)),").unwrap();
+        writeln!(scan_any_tag_lines, "TagGroup::{group_name_fixed} => TagFile::scan_tag_references_from_file_buffer::<{struct_name}>(file),").unwrap();
+        writeln!(find_reset_values_lines, "TagGroup::{group_name_fixed} => TagFile::find_reset_values_in_file_buffer::<{struct_name}>(file, strictness),").unwrap();
         writeln!(read_any_map_lines, "TagGroup::{group_name_fixed} => b({struct_name}::read_from_map(map, tag_info.address, &tag_info.domain)),").unwrap();
+        writeln!(new_any_tag_lines, "TagGroup::{group_name_fixed} => b(Ok({struct_name}::default())),").unwrap();
     }
 
     stream.extend(format!("
@@ -77,7 +83,8 @@ pub fn generate_ringhopper_structs(_: TokenStream) -> TokenStream {
     ///
     /// Returns `Err` if the tag data is invalid, corrupt, or does not correspond to any known tag group.
     pub fn read_any_tag_from_file_buffer(file: &[u8], strictness: ParseStrictness) -> RinghopperResult<Box<dyn PrimaryTagStructDyn>> {{
-        let (header, _) = TagFile::load_header_and_data(file, strictness)?;
+        // The checksum is checked when reading the tag itself
+        let header = TagFile::load_header(file)?;
 
         match header.group {{
             {read_any_tag_lines}
@@ -85,6 +92,36 @@ pub fn generate_ringhopper_structs(_: TokenStream) -> TokenStream {
         }}
     }}
 
+    /// Get all tag references in the tag file buffer without reading the rest of the tag.
+    ///
+    /// This is faster than [`read_any_tag_from_file_buffer`] for getting the dependencies of a tag, especially for tags
+    /// with a lot of data such as models and BSPs.
+    ///
+    /// The CRC32 is not checked (see [`TagFile::scan_tag_references_from_file_buffer`]).
+    ///
+    /// Returns `Err` if the tag data is invalid or does not correspond to any known tag group.
+    pub fn scan_any_tag_references_from_file_buffer(file: &[u8]) -> RinghopperResult<Vec<TagReference>> {{
+        let header = TagFile::load_header(file)?;
+
+        match header.group {{
+            {scan_any_tag_lines}
+            _ => Err(Error::TagGroupUnimplemented)
+        }}
+    }}
+
+    /// Find all values in the tag file buffer that are reset when it is read, such as invalid enum values and unknown
+    /// bitfield bits.
+    ///
+    /// Returns `Err` if the tag data is invalid, corrupt, or does not correspond to any known tag group.
+    pub fn find_any_reset_values_in_file_buffer(file: &[u8], strictness: ParseStrictness) -> RinghopperResult<Vec<ResetValue>> {{
+        let header = TagFile::load_header(file)?;
+
+        match header.group {{
+            {find_reset_values_lines}
+            _ => Err(Error::TagGroupUnimplemented)
+        }}
+    }}
+
     /// Read the tag from the map.
     ///
     /// It is not recommended to call this directly, instead using extract_tag, as extract_tag will also fix any extraction
@@ -100,6 +137,16 @@ pub fn generate_ringhopper_structs(_: TokenStream) -> TokenStream {
         }}
     }}
 
+    /// Create a tag of the given group with all fields zeroed.
+    ///
+    /// Returns `Err` if the group does not correspond to any known tag group.
+    pub fn new_any_tag(group: TagGroup) -> RinghopperResult<Box<dyn PrimaryTagStructDyn>> {{
+        match group {{
+            {new_any_tag_lines}
+            _ => Err(Error::TagGroupUnimplemented)
+        }}
+    }}
+
     /// Get all tag groups this tag group can reference.
     pub fn get_all_referenceable_tag_groups_for_group(what: TagGroup) -> &'static [TagGroup] {{
         match what {{
@@ -408,6 +455,9 @@ impl ToTokenStream for Struct {
 
         let mut write_out = String::new();
         let mut read_tag_in = String::new();
+        let mut scan_tag_in = String::new();
+        let mut find_reset_in = String::new();
+        let mut read_field_in = String::new();
         let mut read_map_in = String::new();
 
         let mut field_list = String::new();
@@ -433,7 +483,7 @@ impl ToTokenStream for Struct {
                 "None".to_owned()
             };
 
-            let metadata = build_metadata(&field.flags, &allowed_references);
+            let metadata = build_metadata(&field.flags, &allowed_references, &build_field_constraints(field, definitions));
             let field_name = &fields_with_names[i];
             let field_matcher = &fields_with_matchers[i];
             writeln!(&mut metadata_matcher, "\"{field_matcher}\" => Some({metadata}),").unwrap();
@@ -460,8 +510,27 @@ impl ToTokenStream for Struct {
                 format!("<{field_type}>::read_from_tag_file(data, _pos, struct_end, extra_data_cursor)?")
             };
 
+            let field_matcher = &fields_with_matchers[i];
+            let scan_tag_code = |references: &str| format!("<{field_type}>::scan_tag_references_from_tag_file(data, _pos, struct_end, extra_data_cursor, {references})?;");
+
+            let find_reset_code = if little_endian {
+                format!("<{field_type}>::find_reset_values::<LittleEndian>(data, _pos, struct_end, path, values)")
+            }
+            else if simple_struct {
+                format!("<{field_type}>::find_reset_values::<B>(data, _pos, struct_end, path, values)")
+            }
+            else {
+                format!("<{field_type}>::find_reset_values_in_tag_file(data, _pos, struct_end, extra_data_cursor, path, values)")
+            };
+
             if fields_read_from_tags[i] {
                 writeln!(&mut read_tag_in, "output.{field_name} = {read_tag_code};").unwrap();
+                writeln!(&mut find_reset_in, "with_field_path(path, \"{field_matcher}\", |path| {find_reset_code})?;").unwrap();
+                writeln!(&mut read_field_in, "if field == \"{field_matcher}\" {{ return Ok(Some(f(&{read_tag_code}))) }}").unwrap();
+                if !little_endian {
+                    writeln!(&mut scan_tag_in, "{}", scan_tag_code("references")).unwrap();
+                    writeln!(&mut read_field_in, "{}", scan_tag_code("&mut ignored")).unwrap();
+                }
                 if little_endian {
                     writeln!(&mut write_out, "self.{field_name}.write::<LittleEndian>(data, _pos, struct_end)?;").unwrap();
                 }
@@ -483,8 +552,15 @@ impl ToTokenStream for Struct {
                     ObjectType::TagReference(_) => true,
                     _ => false
                 };
+                if !self.fields[i].flags.exclude {
+                    // Not stored in tag files, so this is the value it would be after reading the whole tag
+                    writeln!(&mut read_field_in, "if field == \"{field_matcher}\" {{ return Ok(Some(f(&Self::default().{field_name}))) }}").unwrap();
+                }
                 if should_output_code_anyway {
                     writeln!(&mut read_tag_in, "{read_tag_code};").unwrap();
+                    writeln!(&mut scan_tag_in, "{}", scan_tag_code("&mut Vec::new()")).unwrap();
+                    writeln!(&mut find_reset_in, "{}", scan_tag_code("&mut Vec::new()")).unwrap();
+                    writeln!(&mut read_field_in, "{}", scan_tag_code("&mut ignored")).unwrap();
                     match &self.fields[i].field_type {
                         StructFieldType::Object(ObjectType::TagReference(t)) => {
                             let best_group = camel_case(&t.allowed_groups[0]);
@@ -496,6 +572,9 @@ impl ToTokenStream for Struct {
             }
             writeln!(&mut write_out, "let _pos = _pos.add_overflow_checked({length})?;").unwrap();
             writeln!(&mut read_tag_in, "let _pos = _pos.add_overflow_checked({length})?;").unwrap();
+            writeln!(&mut scan_tag_in, "let _pos = _pos.add_overflow_checked({length})?;").unwrap();
+            writeln!(&mut find_reset_in, "let _pos = _pos.add_overflow_checked({length})?;").unwrap();
+            writeln!(&mut read_field_in, "let _pos = _pos.add_overflow_checked({length})?;").unwrap();
         }
 
         // Map I/O
@@ -614,6 +693,12 @@ impl ToTokenStream for Struct {
                     {write_out}
                     Ok(())
                 }}
+
+                fn find_reset_values<B: ByteOrder>(data: &[u8], at: usize, struct_end: usize, path: &mut String, values: &mut Vec<ResetValue>) -> RinghopperResult<()> {{
+                    let _pos = at;
+                    {find_reset_in}
+                    Ok(())
+                }}
             }}")
         }
         else {
@@ -635,6 +720,18 @@ impl ToTokenStream for Struct {
                     Ok(())
                 }}
 
+                fn scan_tag_references_from_tag_file(data: &[u8], at: usize, struct_end: usize, extra_data_cursor: &mut usize, references: &mut Vec<TagReference>) -> RinghopperResult<()> {{
+                    let _pos = at;
+                    {scan_tag_in}
+                    Ok(())
+                }}
+
+                fn find_reset_values_in_tag_file(data: &[u8], at: usize, struct_end: usize, extra_data_cursor: &mut usize, path: &mut String, values: &mut Vec<ResetValue>) -> RinghopperResult<()> {{
+                    let _pos = at;
+                    {find_reset_in}
+                    Ok(())
+                }}
+
                 fn read_from_map<M: Map>(map: &M, address: usize, domain_type: &DomainType) -> RinghopperResult<Self> {{
                     let _pos = address;
                     let mut output = Self::default();
@@ -644,6 +741,23 @@ impl ToTokenStream for Struct {
             }}")
         }.parse::<TokenStream>().unwrap();
 
+        let field_reader = if main_group_struct {
+            format!("impl {struct_name} {{
+                #[allow(unused_mut, unused_variables)]
+                fn read_top_level_field_from_tag_file<R, F: FnOnce(&dyn DynamicTagData) -> R>(data: &[u8], field: &str, f: F) -> RinghopperResult<Option<R>> {{
+                    let struct_end = <Self as TagData>::size();
+                    let extra_data_cursor = &mut struct_end.clone();
+                    let mut ignored = Vec::new();
+                    let _pos = 0usize;
+                    {read_field_in}
+                    Ok(None)
+                }}
+            }}")
+        }
+        else {
+            String::new()
+        }.parse::<TokenStream>().unwrap();
+
         let aux_functions = format!("impl Default for {struct_name} {{
             fn default() -> Self {{
                 Self {{
@@ -702,6 +816,7 @@ impl ToTokenStream for Struct {
 
         tokens.extend(structure);
         tokens.extend(tag_data_functions);
+        tokens.extend(field_reader);
         tokens.extend(aux_functions);
 
         tokens
@@ -763,6 +878,14 @@ impl ToTokenStream for Enum {
                 Ok(u16::read::<B>(data, at, struct_end)?.try_into().unwrap_or_else(|_| Default::default()))
             }}
 
+            fn find_reset_values<B: ByteOrder>(data: &[u8], at: usize, struct_end: usize, path: &mut String, values: &mut Vec<ResetValue>) -> RinghopperResult<()> {{
+                let value = u16::read::<B>(data, at, struct_end)?;
+                if Self::try_from(value).is_err() {{
+                    values.push(ResetValue {{ path: path.clone(), description: format!(\"reset invalid enum value {{value}} to {{}}\", Self::default()) }});
+                }}
+                Ok(())
+            }}
+
             fn write<B: ByteOrder>(&self, data: &mut [u8], at: usize, struct_end: usize) -> RinghopperResult<()> {{
                 (*self as u16).write::<B>(data, at, struct_end)
             }}
@@ -885,7 +1008,7 @@ impl ToTokenStream for Bitfield {
                 continue
             }
             let field_matcher = &field_names_matchers[i];
-            let metadata = build_metadata(&field.flags, "None");
+            let metadata = build_metadata(&field.flags, "None", "minimum: None, maximum: None, limit: None, index_reference: None");
             writeln!(&mut all_metadata, "\"{field_matcher}\" => Some({metadata}),").unwrap();
         }
 
@@ -902,6 +1025,15 @@ impl ToTokenStream for Bitfield {
             .unwrap();
         let not_tag_only = !tag_only_mask;
 
+        let known_mask = self.fields.iter()
+            .filter(|f| !f.flags.exclude)
+            .map(|f| f.value)
+            .fold(0, |a, b| a | b);
+        let find_reset_code = format!("if value & !{known_mask}u{width} != 0 {{
+            values.push(ResetValue {{ path: path.clone(), description: format!(\"cleared unknown bits 0x{{:X}}\", value & !{known_mask}u{width}) }});
+        }}
+        Ok(())");
+
         let functions = format!("
         impl From<u{width}> for {struct_name} {{
             fn from(value: u{width}) -> Self {{
@@ -973,6 +1105,11 @@ impl ToTokenStream for Bitfield {
                     Ok(read_in.into())
                 }}
 
+                fn find_reset_values<B: ByteOrder>(data: &[u8], at: usize, struct_end: usize, path: &mut String, values: &mut Vec<ResetValue>) -> RinghopperResult<()> {{
+                    let value = u{width}::read::<B>(data, at, struct_end)?;
+                    {find_reset_code}
+                }}
+
                 fn write<B: ByteOrder>(&self, data: &mut [u8], at: usize, struct_end: usize) -> RinghopperResult<()> {{
                     let write_out: u{width} = (*self).into();
                     write_out.write::<B>(data, at, struct_end)
@@ -990,6 +1127,12 @@ impl ToTokenStream for Bitfield {
                     Ok(read_in.into())
                 }}
 
+                fn find_reset_values_in_tag_file(data: &[u8], at: usize, struct_end: usize, extra_data_cursor: &mut usize, path: &mut String, values: &mut Vec<ResetValue>) -> RinghopperResult<()> {{
+                    // Cache-only bits are cleared on purpose, so only unknown bits are reported
+                    let value = u{width}::read_from_tag_file(data, at, struct_end, extra_data_cursor)?;
+                    {find_reset_code}
+                }}
+
                 fn write_to_tag_file(&self, data: &mut Vec<u8>, at: usize, struct_end: usize) -> RinghopperResult<()> {{
                     let output = u{width}::from(*self) & {not_cache_only};
                     output.write_to_tag_file(data, at, struct_end)
@@ -1031,6 +1174,9 @@ impl ToTokenStream for TagGroup {
             fn metadata_mut(&mut self) -> &mut PrimaryTagStructMetadata {{
                 &mut self.metadata
             }}
+            fn read_field_from_tag_file<R, F: FnOnce(&dyn DynamicTagData) -> R>(data: &[u8], field: &str, f: F) -> RinghopperResult<Option<R>> {{
+                Self::read_top_level_field_from_tag_file(data, field, f)
+            }}
         }}").parse().unwrap()
     }
 }
@@ -1105,26 +1251,12 @@ fn resolve_all_engines_for_parents<'a>(parents: &'a [String], definitions: &'a P
 }
 
 fn safe_str(string: &str, safety_level: SafetyLevel) -> Cow<str> {
-    let mut string = Cow::Borrowed(string);
+    let mut string: Cow<str> = Cow::Owned(matcher_name(string));
 
     if string.is_empty() {
         return string
     }
 
-    let underscored_characters = ['-', ' '];
-    if string.chars().any(|c| underscored_characters.contains(&c)) {
-        string = Cow::Owned(string.chars().map(|c| if underscored_characters.contains(&c) { '_' } else { c }).collect())
-    }
-
-    let forbidden_chars = ['\'', '"', '(', ')'];
-    if string.chars().any(|c| forbidden_chars.contains(&c)) {
-        string = Cow::Owned(string.chars().filter(|c| !forbidden_chars.contains(&c)).collect())
-    }
-
-    if string.chars().any(|c| c.is_ascii_uppercase()) {
-        string = Cow::Owned(string.to_ascii_lowercase());
-    }
-
     if safety_level == SafetyLevel::RustCompilation {
         if string.chars().next().unwrap().is_numeric() {
             string = Cow::Owned(format!("_{string}"));
@@ -1142,7 +1274,72 @@ fn safe_str(string: &str, safety_level: SafetyLevel) -> Cow<str> {
     string
 }
 
-fn build_metadata(flags: &Flags, allowed_references: &str) -> String {
+fn build_field_constraints(field: &StructField, definitions: &ParsedDefinitions) -> String {
+    let range_value = |value: &Option<StaticValue>| match value {
+        Some(StaticValue::String(_)) | None => "None".to_owned(),
+        Some(n) => format!("Some(({n}) as f64)")
+    };
+    let minimum = range_value(&field.minimum);
+    let maximum = range_value(&field.maximum);
+
+    let limit = match &field.limit {
+        Some(limits) => {
+            // Resolve engine-specific limits through inheritance so they can be looked up by name at runtime.
+            let mut engines: Vec<&String> = definitions.engines.keys().collect();
+            engines.sort();
+
+            let mut engine_limits = String::new();
+            for name in engines {
+                let mut engine = Some(name);
+                while let Some(e) = engine {
+                    if let Some(limit) = limits.get(&LimitType::Engine(e.to_owned())) {
+                        write!(&mut engine_limits, "(\"{name}\", {limit}),").unwrap();
+                        break
+                    }
+                    engine = definitions.engines[e].inherits.as_ref();
+                }
+            }
+
+            let default = limits[&LimitType::Default];
+            let editor = limits[&LimitType::Editor];
+            format!("Some(TagFieldLimit {{ default: {default}, editor: {editor}, engines: &[{engine_limits}] }})")
+        },
+        None => "None".to_owned()
+    };
+
+    let index_reference = match &field.index_reference {
+        // Only indices can be checked against the reflexive
+        Some(reference) if matches!(field.field_type, StructFieldType::Object(ObjectType::Index)) => {
+            let reflexive_struct = |s: &Struct| s.fields.iter().find_map(|f| match &f.field_type {
+                StructFieldType::Object(ObjectType::Reflexive(r)) if f.name == reference.reflexive => Some(r.to_owned()),
+                _ => None
+            });
+
+            // Include other structs with the same reflexive (e.g. shared between model and gbxmodel).
+            let indexed_struct = match &definitions.objects[&reference.struct_name] {
+                NamedObject::Struct(s) => reflexive_struct(s),
+                _ => None
+            };
+            let mut parents: Vec<&String> = definitions.objects.iter().filter_map(|(name, o)| match o {
+                NamedObject::Struct(s) if reflexive_struct(s) == indexed_struct => Some(name),
+                _ => None
+            }).collect();
+            parents.sort();
+
+            let mut parents_list = String::new();
+            for p in parents {
+                write!(&mut parents_list, "std::any::TypeId::of::<{p}>,").unwrap();
+            }
+            let reflexive = safe_str(&reference.reflexive, SafetyLevel::Matcher);
+            format!("Some(TagIndexReference {{ reflexive: \"{reflexive}\", parents: &[{parents_list}] }})")
+        },
+        _ => "None".to_owned()
+    };
+
+    format!("minimum: {minimum}, maximum: {maximum}, limit: {limit}, index_reference: {index_reference}")
+}
+
+fn build_metadata(flags: &Flags, allowed_references: &str, range_and_limit: &str) -> String {
     let comment = if let Some(n) = &flags.comment {
         let r = n.replace("\\", "\\\\").replace("\"", "\\\"").replace("\n", "\\n");
         format!("Some(\"{r}\")")
@@ -1153,12 +1350,17 @@ fn build_metadata(flags: &Flags, allowed_references: &str) -> String {
     let read_only = flags.uneditable_in_editor;
     let cache_only = flags.cache_only;
     let non_cached = flags.non_cached;
+    let normalize = flags.normalize;
+    let unordered_bounds = flags.unordered_bounds;
 
     format!("TagFieldMetadata {{
         comment: {comment},
         read_only: {read_only},
         cache_only: {cache_only},
         non_cached: {non_cached},
-        allowed_references: {allowed_references}
+        allowed_references: {allowed_references},
+        normalize: {normalize},
+        unordered_bounds: {unordered_bounds},
+        {range_and_limit}
     }}")
 }
\ No newline at end of file
@@ -1 +1,5 @@
 pub mod bitmap;
+pub mod jma;
+pub mod jms;
+pub mod obj;
+mod reader;
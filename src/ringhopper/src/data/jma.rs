@@ -0,0 +1,161 @@
+use std::fmt::Write;
+use primitives::error::RinghopperResult;
+use primitives::primitive::{Quaternion, Vector3D};
+use super::reader::LineReader;
+
+/// Version of the JMA format written by Ringhopper.
+///
+/// This version stores the first child and sibling of each node.
+pub const JMA_VERSION: u32 = 16392;
+
+/// Older version of the JMA format which only stores node names.
+pub const JMA_VERSION_NODE_NAMES_ONLY: u32 = 16390;
+
+/// Default frame rate of animations.
+pub const JMA_DEFAULT_FRAME_RATE: u32 = 30;
+
+/// Represents an animation source file (JMA, JMM, JMT, JMO, JMR, JMW, or JMZ).
+///
+/// All of these formats share the same layout; the extension determines how the animation is imported. Positions are
+/// stored in JMS units (see [`JMS_UNITS_PER_WORLD_UNIT`](crate::data::jms::JMS_UNITS_PER_WORLD_UNIT)).
+#[derive(Clone, Debug, PartialEq)]
+pub struct JMA {
+    pub node_list_checksum: i32,
+    pub frame_rate: u32,
+    pub actors: Vec<String>,
+    pub nodes: Vec<JMANode>,
+
+    /// Node transforms for each frame; each frame has one transform per node.
+    pub frames: Vec<Vec<JMANodeTransform>>
+}
+
+impl Default for JMA {
+    fn default() -> Self {
+        Self {
+            node_list_checksum: 0,
+            frame_rate: JMA_DEFAULT_FRAME_RATE,
+            actors: vec!["unnamedActor".to_owned()],
+            nodes: Vec::new(),
+            frames: Vec::new()
+        }
+    }
+}
+
+#[derive(Clone, Default, Debug, PartialEq)]
+pub struct JMANode {
+    pub name: String,
+    pub first_child: Option<u16>,
+    pub sibling_node: Option<u16>
+}
+
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+pub struct JMANodeTransform {
+    pub position: Vector3D,
+    pub rotation: Quaternion,
+    pub scale: f64
+}
+
+fn index_to_jma(index: Option<u16>) -> i32 {
+    index.map(|i| i as i32).unwrap_or(-1)
+}
+
+impl JMA {
+    /// Parse a JMA from its text representation.
+    ///
+    /// Versions 16390 and 16392 are supported.
+    pub fn from_text(text: &str) -> RinghopperResult<JMA> {
+        let mut reader = LineReader::new(text, "JMA");
+
+        let version: u32 = reader.next_value()?;
+        if version != JMA_VERSION && version != JMA_VERSION_NODE_NAMES_ONLY {
+            return Err(reader.error(&format!("unsupported version {version}")))
+        }
+
+        let frame_count = reader.next_count()?;
+        let frame_rate = reader.next_value()?;
+
+        let actor_count = reader.next_count()?;
+        let mut actors = Vec::new();
+        for _ in 0..actor_count {
+            actors.push(reader.next_line()?.to_owned());
+        }
+
+        let node_count = reader.next_count()?;
+        let node_list_checksum = reader.next_value()?;
+
+        let mut nodes = Vec::new();
+        for _ in 0..node_count {
+            let name = reader.next_line()?.to_owned();
+            let (first_child, sibling_node) = if version == JMA_VERSION {
+                (reader.next_index()?, reader.next_index()?)
+            }
+            else {
+                (None, None)
+            };
+            nodes.push(JMANode { name, first_child, sibling_node });
+        }
+
+        let mut frames = Vec::new();
+        for _ in 0..frame_count {
+            let mut frame = Vec::new();
+            for _ in 0..node_count {
+                let [x, y, z] = reader.next_floats()?;
+                let [i, j, k, w] = reader.next_floats()?;
+                let scale = reader.next_value()?;
+                frame.push(JMANodeTransform {
+                    position: Vector3D { x, y, z },
+                    rotation: Quaternion { x: i, y: j, z: k, w },
+                    scale
+                });
+            }
+            frames.push(frame);
+        }
+
+        if !reader.is_at_end() {
+            return Err(reader.error("unexpected data after the last frame"))
+        }
+
+        Ok(JMA { node_list_checksum, frame_rate, actors, nodes, frames })
+    }
+
+    /// Encode the JMA into its text representation.
+    ///
+    /// Lines are separated with CRLF line endings, and the latest version is always written.
+    pub fn to_text(&self) -> String {
+        let mut output = String::new();
+
+        macro_rules! line {
+            ($($fmt:tt)*) => {{
+                write!(&mut output, $($fmt)*).unwrap();
+                output += "\r\n";
+            }};
+        }
+
+        line!("{JMA_VERSION}");
+        line!("{}", self.frames.len());
+        line!("{}", self.frame_rate);
+
+        line!("{}", self.actors.len());
+        for actor in &self.actors {
+            line!("{actor}");
+        }
+
+        line!("{}", self.nodes.len());
+        line!("{}", self.node_list_checksum);
+        for node in &self.nodes {
+            line!("{}", node.name);
+            line!("{}", index_to_jma(node.first_child));
+            line!("{}", index_to_jma(node.sibling_node));
+        }
+
+        for frame in &self.frames {
+            for transform in frame {
+                line!("{:.6}\t{:.6}\t{:.6}", transform.position.x, transform.position.y, transform.position.z);
+                line!("{:.10}\t{:.10}\t{:.10}\t{:.10}", transform.rotation.x, transform.rotation.y, transform.rotation.z, transform.rotation.w);
+                line!("{:.6}", transform.scale);
+            }
+        }
+
+        output
+    }
+}
@@ -0,0 +1,284 @@
+use std::fmt::Write;
+use primitives::error::{Error, RinghopperResult};
+use primitives::primitive::{Quaternion, Vector, Vector2D, Vector3D};
+use super::reader::LineReader;
+
+/// Version of the JMS format written by Ringhopper.
+pub const JMS_VERSION: u32 = 8200;
+
+/// Represents a JMS file.
+///
+/// JMS files store positions in JMS units, where 1 world unit is equal to 100 JMS units.
+#[derive(Clone, Default, Debug, PartialEq)]
+pub struct JMS {
+    pub node_list_checksum: i32,
+    pub nodes: Vec<JMSNode>,
+    pub materials: Vec<JMSMaterial>,
+    pub markers: Vec<JMSMarker>,
+    pub regions: Vec<JMSRegion>,
+    pub vertices: Vec<JMSVertex>,
+    pub triangles: Vec<JMSTriangle>
+}
+
+#[derive(Clone, Default, Debug, PartialEq)]
+pub struct JMSNode {
+    pub name: String,
+    pub first_child: Option<u16>,
+    pub sibling_node: Option<u16>,
+    pub rotation: Quaternion,
+    pub position: Vector3D
+}
+
+#[derive(Clone, Default, Debug, PartialEq)]
+pub struct JMSMaterial {
+    pub name: String,
+    pub tif_path: String
+}
+
+#[derive(Clone, Default, Debug, PartialEq)]
+pub struct JMSMarker {
+    pub name: String,
+    pub region: Option<u16>,
+    pub node: u16,
+    pub rotation: Quaternion,
+    pub position: Vector3D,
+    pub radius: f64
+}
+
+#[derive(Clone, Default, Debug, PartialEq)]
+pub struct JMSRegion {
+    pub name: String
+}
+
+#[derive(Clone, Default, Debug, PartialEq)]
+pub struct JMSVertex {
+    pub node0: u16,
+    pub position: Vector3D,
+    pub normal: Vector3D,
+    pub node1: Option<u16>,
+    pub node1_weight: f64,
+    pub texture_coordinates: Vector2D
+}
+
+#[derive(Clone, Default, Debug, PartialEq)]
+pub struct JMSTriangle {
+    pub region: u16,
+    pub shader: u16,
+    pub vertices: [u32; 3]
+}
+
+/// Number of JMS units in one world unit.
+pub const JMS_UNITS_PER_WORLD_UNIT: f64 = 100.0;
+
+fn index_to_jms(index: Option<u16>) -> i32 {
+    index.map(|i| i as i32).unwrap_or(-1)
+}
+
+impl JMS {
+    /// Parse a JMS from its text representation.
+    pub fn from_text(text: &str) -> RinghopperResult<JMS> {
+        let mut reader = LineReader::new(text, "JMS");
+
+        let version: u32 = reader.next_value()?;
+        if version != JMS_VERSION {
+            return Err(reader.error(&format!("unsupported version {version}")))
+        }
+
+        let node_list_checksum = reader.next_value()?;
+        let mut jms = JMS { node_list_checksum, ..Default::default() };
+
+        let node_count = reader.next_count()?;
+        for _ in 0..node_count {
+            let name = reader.next_line()?.to_owned();
+            let first_child = reader.next_index()?;
+            let sibling_node = reader.next_index()?;
+            let [x, y, z, w] = reader.next_floats()?;
+            let [px, py, pz] = reader.next_floats()?;
+            jms.nodes.push(JMSNode {
+                name,
+                first_child,
+                sibling_node,
+                rotation: Quaternion { x, y, z, w },
+                position: Vector3D { x: px, y: py, z: pz }
+            });
+        }
+
+        let material_count = reader.next_count()?;
+        for _ in 0..material_count {
+            let name = reader.next_line()?.to_owned();
+            let tif_path = reader.next_line()?.to_owned();
+            jms.materials.push(JMSMaterial { name, tif_path });
+        }
+
+        let marker_count = reader.next_count()?;
+        for _ in 0..marker_count {
+            let name = reader.next_line()?.to_owned();
+            let region = reader.next_index()?;
+            let node = reader.next_index()?.ok_or_else(|| reader.error("marker has no node"))?;
+            let [x, y, z, w] = reader.next_floats()?;
+            let [px, py, pz] = reader.next_floats()?;
+            let radius = reader.next_value()?;
+            jms.markers.push(JMSMarker {
+                name,
+                region,
+                node,
+                rotation: Quaternion { x, y, z, w },
+                position: Vector3D { x: px, y: py, z: pz },
+                radius
+            });
+        }
+
+        let region_count = reader.next_count()?;
+        for _ in 0..region_count {
+            jms.regions.push(JMSRegion { name: reader.next_line()?.to_owned() });
+        }
+
+        let vertex_count = reader.next_count()?;
+        for _ in 0..vertex_count {
+            let node0 = reader.next_index()?.ok_or_else(|| reader.error("vertex has no node"))?;
+            let [px, py, pz] = reader.next_floats()?;
+            let [nx, ny, nz] = reader.next_floats()?;
+            let node1 = reader.next_index()?;
+            let node1_weight = reader.next_value()?;
+            let [u, v] = reader.next_floats()?;
+            jms.vertices.push(JMSVertex {
+                node0,
+                position: Vector3D { x: px, y: py, z: pz },
+                normal: Vector3D { x: nx, y: ny, z: nz },
+                node1,
+                node1_weight,
+                texture_coordinates: Vector2D { x: u, y: v }
+            });
+        }
+
+        let triangle_count = reader.next_count()?;
+        for _ in 0..triangle_count {
+            let region = reader.next_value()?;
+            let shader = reader.next_value()?;
+            let line = reader.next_line()?;
+            let mut indices = line.split_whitespace().map(|i| i.parse::<u32>());
+            let mut vertices = [0u32; 3];
+            for v in &mut vertices {
+                *v = match indices.next() {
+                    Some(Ok(n)) if (n as usize) < jms.vertices.len() => n,
+                    _ => return Err(reader.error(&format!("invalid triangle `{line}`")))
+                };
+            }
+            jms.triangles.push(JMSTriangle { region, shader, vertices });
+        }
+
+        if !reader.is_at_end() {
+            return Err(reader.error("unexpected data after the last triangle"))
+        }
+
+        Ok(jms)
+    }
+
+    /// Encode the JMS into its text representation.
+    ///
+    /// Lines are separated with CRLF line endings.
+    pub fn to_text(&self) -> String {
+        let mut output = String::new();
+
+        macro_rules! line {
+            ($($fmt:tt)*) => {{
+                write!(&mut output, $($fmt)*).unwrap();
+                output += "\r\n";
+            }};
+        }
+
+        line!("{JMS_VERSION}");
+        line!("{}", self.node_list_checksum);
+
+        line!("{}", self.nodes.len());
+        for node in &self.nodes {
+            line!("{}", node.name);
+            line!("{}", index_to_jms(node.first_child));
+            line!("{}", index_to_jms(node.sibling_node));
+            line!("{:.10}\t{:.10}\t{:.10}\t{:.10}", node.rotation.x, node.rotation.y, node.rotation.z, node.rotation.w);
+            line!("{:.6}\t{:.6}\t{:.6}", node.position.x, node.position.y, node.position.z);
+        }
+
+        line!("{}", self.materials.len());
+        for material in &self.materials {
+            line!("{}", material.name);
+            line!("{}", material.tif_path);
+        }
+
+        line!("{}", self.markers.len());
+        for marker in &self.markers {
+            line!("{}", marker.name);
+            line!("{}", index_to_jms(marker.region));
+            line!("{}", marker.node);
+            line!("{:.10}\t{:.10}\t{:.10}\t{:.10}", marker.rotation.x, marker.rotation.y, marker.rotation.z, marker.rotation.w);
+            line!("{:.6}\t{:.6}\t{:.6}", marker.position.x, marker.position.y, marker.position.z);
+            line!("{:.6}", marker.radius);
+        }
+
+        line!("{}", self.regions.len());
+        for region in &self.regions {
+            line!("{}", region.name);
+        }
+
+        line!("{}", self.vertices.len());
+        for vertex in &self.vertices {
+            line!("{}", vertex.node0);
+            line!("{:.6}\t{:.6}\t{:.6}", vertex.position.x, vertex.position.y, vertex.position.z);
+            line!("{:.10}\t{:.10}\t{:.10}", vertex.normal.x, vertex.normal.y, vertex.normal.z);
+            line!("{}", index_to_jms(vertex.node1));
+            line!("{:.10}", vertex.node1_weight);
+            line!("{:.10}\t{:.10}\t0", vertex.texture_coordinates.x, vertex.texture_coordinates.y);
+        }
+
+        line!("{}", self.triangles.len());
+        for triangle in &self.triangles {
+            line!("{}", triangle.region);
+            line!("{}", triangle.shader);
+            line!("{}\t{}\t{}", triangle.vertices[0], triangle.vertices[1], triangle.vertices[2]);
+        }
+
+        output
+    }
+
+    /// Get the parent of each node from the first child and sibling indices.
+    ///
+    /// Returns an error if a node's children do not come after it.
+    pub fn node_parents(&self) -> RinghopperResult<Vec<Option<u16>>> {
+        let mut parents = vec![None; self.nodes.len()];
+        for (index, node) in self.nodes.iter().enumerate() {
+            let mut child = node.first_child;
+            let mut visited = 0;
+            while let Some(c) = child {
+                let c = c as usize;
+                if c <= index || c >= self.nodes.len() || visited > self.nodes.len() {
+                    return Err(Error::Other(format!("node `{}` has an invalid child", node.name)))
+                }
+                parents[c] = Some(index as u16);
+                child = self.nodes[c].sibling_node;
+                visited += 1;
+            }
+        }
+        Ok(parents)
+    }
+
+    /// Compute the absolute rotation and position of each node, in world units.
+    pub fn absolute_node_transforms(&self) -> RinghopperResult<Vec<(Quaternion, Vector3D)>> {
+        let parents = self.node_parents()?;
+        let mut transforms: Vec<(Quaternion, Vector3D)> = Vec::with_capacity(self.nodes.len());
+
+        // Parents always come before their children.
+        for (index, node) in self.nodes.iter().enumerate() {
+            let position = node.position.scale(1.0 / JMS_UNITS_PER_WORLD_UNIT);
+            let transform = match parents[index] {
+                Some(parent) => {
+                    let (parent_rotation, parent_position) = transforms[parent as usize];
+                    (parent_rotation.multiply(&node.rotation), parent_position + parent_rotation.rotate_vector(position))
+                },
+                None => (node.rotation, position)
+            };
+            transforms.push(transform);
+        }
+
+        Ok(transforms)
+    }
+}
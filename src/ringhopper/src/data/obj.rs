@@ -0,0 +1,78 @@
+use std::fmt::Write;
+use primitives::primitive::{Vector2D, Vector3D};
+
+/// Represents a Wavefront OBJ file.
+///
+/// Unlike JMS files, positions are stored in world units.
+#[derive(Clone, Default, Debug, PartialEq)]
+pub struct OBJ {
+    pub vertices: Vec<OBJVertex>,
+    pub objects: Vec<OBJObject>
+}
+
+#[derive(Clone, Default, Debug, PartialEq)]
+pub struct OBJVertex {
+    pub position: Vector3D,
+    pub normal: Vector3D,
+    pub texture_coordinates: Vector2D
+}
+
+/// A named group of faces.
+#[derive(Clone, Default, Debug, PartialEq)]
+pub struct OBJObject {
+    pub name: String,
+    pub faces: Vec<OBJFace>
+}
+
+/// A polygon referencing vertices by index (starting at 0).
+#[derive(Clone, Default, Debug, PartialEq)]
+pub struct OBJFace {
+    pub material: String,
+    pub vertices: Vec<u32>
+}
+
+impl OBJ {
+    /// Encode the OBJ into its text representation.
+    ///
+    /// Every vertex has exactly one position, texture coordinate, and normal, so all three share the same index.
+    pub fn to_text(&self) -> String {
+        let mut output = String::new();
+
+        macro_rules! line {
+            ($($fmt:tt)*) => {{
+                writeln!(&mut output, $($fmt)*).unwrap();
+            }};
+        }
+
+        for vertex in &self.vertices {
+            line!("v {:.6} {:.6} {:.6}", vertex.position.x, vertex.position.y, vertex.position.z);
+        }
+        for vertex in &self.vertices {
+            line!("vt {:.10} {:.10}", vertex.texture_coordinates.x, vertex.texture_coordinates.y);
+        }
+        for vertex in &self.vertices {
+            line!("vn {:.10} {:.10} {:.10}", vertex.normal.x, vertex.normal.y, vertex.normal.z);
+        }
+
+        for object in &self.objects {
+            line!("o {}", object.name);
+
+            let mut material: Option<&str> = None;
+            for face in &object.faces {
+                if material != Some(face.material.as_str()) {
+                    line!("usemtl {}", face.material);
+                    material = Some(face.material.as_str());
+                }
+
+                output += "f";
+                for index in &face.vertices {
+                    let index = index + 1;
+                    write!(&mut output, " {index}/{index}/{index}").unwrap();
+                }
+                output += "\n";
+            }
+        }
+
+        output
+    }
+}
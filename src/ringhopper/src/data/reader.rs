@@ -0,0 +1,78 @@
+use std::str::FromStr;
+use primitives::error::{Error, RinghopperResult};
+
+/// Reads line-based text formats such as JMS and JMA files.
+///
+/// Blank lines are skipped, and both CRLF and LF line endings are accepted.
+pub(crate) struct LineReader<'a> {
+    lines: std::iter::Enumerate<std::str::Lines<'a>>,
+    format: &'static str,
+    line_number: usize
+}
+
+impl<'a> LineReader<'a> {
+    pub fn new(text: &'a str, format: &'static str) -> Self {
+        Self {
+            lines: text.lines().enumerate(),
+            format,
+            line_number: 0
+        }
+    }
+
+    /// Get the next non-empty line, trimmed of whitespace.
+    pub fn next_line(&mut self) -> RinghopperResult<&'a str> {
+        for (number, line) in self.lines.by_ref() {
+            let line = line.trim();
+            if !line.is_empty() {
+                self.line_number = number + 1;
+                return Ok(line)
+            }
+        }
+        Err(Error::Other(format!("unexpected end of {} file", self.format)))
+    }
+
+    /// Return `true` if there are no more non-empty lines.
+    pub fn is_at_end(&self) -> bool {
+        self.lines.clone().all(|(_, line)| line.trim().is_empty())
+    }
+
+    /// Create an error for the current line.
+    pub fn error(&self, what: &str) -> Error {
+        Error::Other(format!("{} error on line {}: {what}", self.format, self.line_number))
+    }
+
+    /// Parse the next line as a single value.
+    pub fn next_value<T: FromStr>(&mut self) -> RinghopperResult<T> {
+        let line = self.next_line()?;
+        line.parse().map_err(|_| self.error(&format!("cannot parse `{line}`")))
+    }
+
+    /// Parse the next line as a count.
+    pub fn next_count(&mut self) -> RinghopperResult<usize> {
+        self.next_value()
+    }
+
+    /// Parse the next line as an index, where -1 is null.
+    pub fn next_index(&mut self) -> RinghopperResult<Option<u16>> {
+        let value: i32 = self.next_value()?;
+        match value {
+            -1 => Ok(None),
+            0..=0xFFFE => Ok(Some(value as u16)),
+            _ => Err(self.error(&format!("index {value} is out of range")))
+        }
+    }
+
+    /// Parse the next line as `N` whitespace-separated floats.
+    ///
+    /// Any additional values on the line are ignored.
+    pub fn next_floats<const N: usize>(&mut self) -> RinghopperResult<[f64; N]> {
+        let line = self.next_line()?;
+        let mut values = [0.0; N];
+        let mut tokens = line.split_whitespace();
+        for v in &mut values {
+            let token = tokens.next().ok_or_else(|| self.error(&format!("expected {N} values")))?;
+            *v = token.parse().map_err(|_| self.error(&format!("cannot parse `{token}`")))?;
+        }
+        Ok(values)
+    }
+}
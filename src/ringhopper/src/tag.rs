@@ -10,11 +10,18 @@ pub mod dependency;
 pub mod tag_collection;
 pub mod nudge;
 pub mod compare;
+pub mod edit;
+pub mod json;
+pub mod query;
 pub mod convert;
 pub mod model;
 pub mod model_animations;
+pub mod model_collision_geometry;
+pub mod collision_bsp;
+pub mod physics;
 pub mod scenario;
 pub mod object;
+pub mod shader;
 pub mod scenario_structure_bsp;
 pub mod bitmap;
 pub mod archive;
@@ -1,4 +1,6 @@
-use primitives::{primitive::{TagGroup, TagPath}, tag::PrimaryTagStructDyn};
+use std::fmt::{Display, Formatter};
+use primitives::error::RinghopperResult;
+use primitives::{primitive::{TagGroup, TagPath}, tag::{ParseStrictness, PrimaryTagStructDyn}};
 
 mod sound;
 mod model;
@@ -6,14 +8,51 @@ mod scenario;
 mod unicode_string_list;
 mod scenario_structure_bsp;
 mod floats;
+mod indices;
+mod ranges;
+
+#[cfg(test)]
+mod test;
 
 pub enum BludgeonResult {
     Done,
     CannotRepair
 }
 
-pub fn bludgeon_tag(tag: &mut dyn PrimaryTagStructDyn, path: &TagPath) -> BludgeonResult {
+/// Describes a change made to a field by [`bludgeon_tag`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct BludgeonChange {
+    /// Matcher path to the field that was changed
+    pub path: String,
+
+    /// Description of the change
+    pub description: String
+}
+
+impl Display for BludgeonChange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.path, self.description)
+    }
+}
+
+/// Find all invalid enum values and unknown bitfield bits in the tag file, appending them to `changes`.
+///
+/// These are reset when the tag is parsed rather than by [`bludgeon_tag`], so writing the parsed tag back is enough to
+/// persist those fixes, but this has to be called on the original file to report them.
+pub fn find_values_reset_on_parse(file: &[u8], changes: &mut Vec<BludgeonChange>) -> RinghopperResult<()> {
+    let values = ringhopper_structs::find_any_reset_values_in_file_buffer(file, ParseStrictness::Relaxed)?;
+    changes.extend(values.into_iter().map(|v| BludgeonChange { path: v.path, description: v.description }));
+    Ok(())
+}
+
+/// Repair the tag, appending any per-field changes made to `changes`.
+///
+/// Invalid enum values and unknown bitfield bits are reset when the tag is parsed; use [`find_values_reset_on_parse`]
+/// to report those.
+pub fn bludgeon_tag(tag: &mut dyn PrimaryTagStructDyn, path: &TagPath, changes: &mut Vec<BludgeonChange>) -> BludgeonResult {
     floats::fix_bad_floats(tag);
+    ranges::fix_out_of_range_values(tag, changes);
+    indices::fix_invalid_indices(tag, changes);
 
     match tag.group() {
         TagGroup::Model | TagGroup::GBXModel => model::repair_model(tag),
@@ -22,9 +61,6 @@ pub fn bludgeon_tag(tag: &mut dyn PrimaryTagStructDyn, path: &TagPath) -> Bludge
         TagGroup::UnicodeStringList => unicode_string_list::repair_unicode_string_list(tag),
         TagGroup::ScenarioStructureBSP => scenario_structure_bsp::repair_scenario_structure_bsp(tag),
 
-        // TODO: indices
-        // TODO: out-of-range clamping
-
         // VERIFY THAT THIS IS NOT JUST AUTOMATICALLY FIXED:
         // - TODO: uppercase tag references??
 
@@ -0,0 +1,17 @@
+use primitives::primitive::Index;
+use primitives::tag::PrimaryTagStructDyn;
+use crate::tag::verify::indices::find_invalid_indices;
+use super::BludgeonChange;
+
+pub fn fix_invalid_indices(tag: &mut dyn PrimaryTagStructDyn, changes: &mut Vec<BludgeonChange>) {
+    for i in find_invalid_indices(tag.as_dynamic()) {
+        tag.as_mut_dynamic().foreach_mut(&i.path, |field| {
+            *field.unwrap().as_any_mut().downcast_mut::<Index>().unwrap() = None;
+            true
+        });
+        changes.push(BludgeonChange {
+            path: i.path,
+            description: format!("nulled index {} which is out of bounds for {} ({} entries)", i.index, i.reflexive, i.count)
+        });
+    }
+}
@@ -0,0 +1,120 @@
+use primitives::dynamic::{DynamicTagData, DynamicTagDataType, SimplePrimitiveType, TagFieldMetadata};
+use primitives::primitive::{Angle, Quaternion, Vector, Vector3D};
+use primitives::tag::PrimaryTagStructDyn;
+use super::BludgeonChange;
+
+/// Fix inverted bounds and normalize values which should be unit length.
+///
+/// Values outside of a field's minimum and maximum are left alone, as those are editor limits rather than limits the
+/// engine enforces, and clamping them would destroy the tag author's data.
+pub fn fix_out_of_range_values(tag: &mut dyn PrimaryTagStructDyn, changes: &mut Vec<BludgeonChange>) {
+    fn recursion(data: &mut dyn DynamicTagData, path: &mut String, changes: &mut Vec<BludgeonChange>) {
+        for field_name in data.fields() {
+            let metadata = data.get_metadata_for_field(field_name);
+            let field = data.get_field_mut(field_name).unwrap();
+            let len = path.len();
+            if !path.is_empty() {
+                path.push('.');
+            }
+            *path += field_name;
+
+            if let Some(metadata) = metadata.filter(|m| !m.cache_only) {
+                fix_field(field, &metadata, path, changes);
+            }
+
+            if let Some(array) = field.as_array_mut() {
+                for i in 0..array.len() {
+                    let len = path.len();
+                    *path += &format!("[{i}]");
+                    recursion(array.get_at_index_mut(i).unwrap(), path, changes);
+                    path.truncate(len);
+                }
+            }
+            else if !field.fields().is_empty() {
+                recursion(field, path, changes);
+            }
+
+            path.truncate(len);
+        }
+    }
+
+    recursion(tag.as_mut_dynamic(), &mut String::new(), changes);
+}
+
+fn fix_field(field: &mut dyn DynamicTagData, metadata: &TagFieldMetadata, path: &str, changes: &mut Vec<BludgeonChange>) {
+    let mut change = |description: String| changes.push(BludgeonChange { path: path.to_owned(), description });
+
+    if metadata.normalize {
+        if let Some(v) = field.as_any_mut().downcast_mut::<Vector3D>() {
+            // A zero vector has no direction to preserve.
+            if !v.is_unit_vector() && v.magnitude_squared() > 0.0 {
+                change(format!("normalized vector with a magnitude of {}", v.magnitude_squared().sqrt()));
+                *v = v.normalize();
+            }
+        }
+        else if let Some(q) = field.as_any_mut().downcast_mut::<Quaternion>() {
+            if !q.is_unit_vector() && q.magnitude_squared() > 0.0 {
+                change(format!("normalized vector with a magnitude of {}", q.magnitude_squared().sqrt()));
+                *q = q.normalize();
+            }
+        }
+    }
+
+    // Bounds
+    if field.data_type() == DynamicTagDataType::Block && field.fields() == ["lower", "upper"] {
+        let lower = get_number(field.get_field("lower").unwrap());
+        let upper = get_number(field.get_field("upper").unwrap());
+        if let (Some(lower), Some(upper)) = (lower, upper) {
+            if lower > upper && !metadata.unordered_bounds {
+                set_number(field.get_field_mut("lower").unwrap(), upper);
+                set_number(field.get_field_mut("upper").unwrap(), lower);
+                change(format!("swapped inverted bounds ({lower} > {upper})"));
+            }
+        }
+    }
+}
+
+fn get_number(field: &dyn DynamicTagData) -> Option<f64> {
+    let any = field.as_any();
+    macro_rules! get {
+        ($t:ty) => { *any.downcast_ref::<$t>().unwrap() as f64 };
+    }
+
+    let DynamicTagDataType::SimplePrimitive(t) = field.data_type() else {
+        return None
+    };
+    Some(match t {
+        SimplePrimitiveType::I8 => get!(i8),
+        SimplePrimitiveType::U8 => get!(u8),
+        SimplePrimitiveType::I16 => get!(i16),
+        SimplePrimitiveType::U16 => get!(u16),
+        SimplePrimitiveType::I32 => get!(i32),
+        SimplePrimitiveType::U32 => get!(u32),
+        SimplePrimitiveType::Float => get!(f64),
+        SimplePrimitiveType::Angle => any.downcast_ref::<Angle>().unwrap().angle as f64,
+        _ => return None
+    })
+}
+
+fn set_number(field: &mut dyn DynamicTagData, value: f64) {
+    let DynamicTagDataType::SimplePrimitive(t) = field.data_type() else {
+        unreachable!()
+    };
+
+    let any = field.as_any_mut();
+    macro_rules! set {
+        ($t:ty) => { *any.downcast_mut::<$t>().unwrap() = value as $t };
+    }
+
+    match t {
+        SimplePrimitiveType::I8 => set!(i8),
+        SimplePrimitiveType::U8 => set!(u8),
+        SimplePrimitiveType::I16 => set!(i16),
+        SimplePrimitiveType::U16 => set!(u16),
+        SimplePrimitiveType::I32 => set!(i32),
+        SimplePrimitiveType::U32 => set!(u32),
+        SimplePrimitiveType::Float => set!(f64),
+        SimplePrimitiveType::Angle => any.downcast_mut::<Angle>().unwrap().angle = value as f32,
+        _ => unreachable!()
+    }
+}
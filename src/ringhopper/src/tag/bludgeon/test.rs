@@ -0,0 +1,99 @@
+use definitions::{CameraTrack, CameraTrackControlPoint, Weapon, WeaponSecondaryTriggerMode, WeaponTrigger, WeaponTriggerFiringEffect};
+use primitives::primitive::{Angle, Bounds, Quaternion, TagPath, Vector};
+use primitives::tag::PrimaryTagStructDyn;
+use super::{bludgeon_tag, find_values_reset_on_parse, BludgeonChange};
+
+fn bludgeon<T: PrimaryTagStructDyn>(tag: &mut T, path: &str) -> Vec<String> {
+    let mut changes = Vec::new();
+    bludgeon_tag(tag, &TagPath::from_path(path).unwrap(), &mut changes);
+    changes.iter().map(BludgeonChange::to_string).collect()
+}
+
+#[test]
+fn out_of_range_values() {
+    let mut weapon = Weapon::default();
+    weapon.triggers.items.push(WeaponTrigger {
+        heat_generated_per_round: 2.0,
+        error: Bounds { lower: 0.5, upper: 0.25 },
+        error_angle: Bounds { lower: Angle::from_degrees(10.0), upper: Angle::from_degrees(5.0) },
+        maximum_rate_of_fire: Bounds { lower: 10.0, upper: 5.0 },
+        ..Default::default()
+    });
+    weapon.triggers.items[0].firing_effects.items.push(WeaponTriggerFiringEffect {
+        shot_count: Bounds { lower: 3, upper: 1 },
+        ..Default::default()
+    });
+    weapon.zoom_magnification_range = Bounds { lower: 8.0, upper: 2.0 };
+
+    let changes = bludgeon(&mut weapon, "weapons\\test\\test.weapon");
+    assert_eq!(vec![
+        "triggers[0].firing_effects[0].shot_count: swapped inverted bounds (3 > 1)"
+    ], changes);
+
+    // Editor minimums and maximums are not enforced by the engine, so they are left alone
+    let trigger = &weapon.triggers.items[0];
+    assert_eq!(2.0, trigger.heat_generated_per_round);
+    assert_eq!(Bounds { lower: 1, upper: 3 }, trigger.firing_effects.items[0].shot_count);
+
+    // Initial/final values, not minimum/maximum
+    assert_eq!(Bounds { lower: 0.5, upper: 0.25 }, trigger.error);
+    assert_eq!(Bounds { lower: Angle::from_degrees(10.0), upper: Angle::from_degrees(5.0) }, trigger.error_angle);
+    assert_eq!(Bounds { lower: 10.0, upper: 5.0 }, trigger.maximum_rate_of_fire);
+    assert_eq!(Bounds { lower: 8.0, upper: 2.0 }, weapon.zoom_magnification_range);
+    assert!(bludgeon(&mut weapon, "weapons\\test\\test.weapon").is_empty());
+
+    let mut camera_track = CameraTrack::default();
+    camera_track.control_points.items.push(CameraTrackControlPoint {
+        orientation: Quaternion { x: 0.0, y: 0.0, z: 0.0, w: 2.0 },
+        ..Default::default()
+    });
+    assert_eq!(
+        vec!["control_points[0].orientation: normalized vector with a magnitude of 2"],
+        bludgeon(&mut camera_track, "camera\\test.camera_track")
+    );
+    assert!(camera_track.control_points.items[0].orientation.is_unit_vector());
+
+    // Zero vectors have no direction to normalize to
+    camera_track.control_points.items[0].orientation = Quaternion { x: 0.0, y: 0.0, z: 0.0, w: 0.0 };
+    assert!(bludgeon(&mut camera_track, "camera\\test.camera_track").is_empty());
+    assert_eq!(Quaternion { x: 0.0, y: 0.0, z: 0.0, w: 0.0 }, camera_track.control_points.items[0].orientation);
+}
+
+/// Get the offset of the last byte that differs between two tag files.
+fn differing_offset(a: &Weapon, b: &Weapon) -> usize {
+    let (a, b) = (a.to_tag_file().unwrap(), b.to_tag_file().unwrap());
+    (0x40..a.len()).rev().find(|&i| a[i] != b[i]).unwrap()
+}
+
+#[test]
+fn values_reset_on_parse() {
+    let mut weapon = Weapon::default();
+    weapon.triggers.items.push(WeaponTrigger::default());
+
+    let mut changed = weapon.clone();
+    changed.secondary_trigger_mode = WeaponSecondaryTriggerMode::SlavedToPrimary;
+    let enum_offset = differing_offset(&weapon, &changed) - 1;
+
+    let mut changed = weapon.clone();
+    changed.weapon_flags.must_be_readied = true;
+    let weapon_flags_offset = differing_offset(&weapon, &changed) - 3;
+
+    let mut changed = weapon.clone();
+    changed.triggers.items[0].flags.tracks_fired_projectile = true;
+    let trigger_flags_offset = differing_offset(&weapon, &changed) - 3;
+
+    let mut file = weapon.to_tag_file().unwrap();
+    let mut changes = Vec::new();
+    find_values_reset_on_parse(&file, &mut changes).unwrap();
+    assert!(changes.is_empty());
+
+    file[enum_offset..enum_offset + 2].copy_from_slice(&[0x7F, 0xFF]);
+    file[weapon_flags_offset] = 0x80;
+    file[trigger_flags_offset] = 0x40;
+    find_values_reset_on_parse(&file, &mut changes).unwrap();
+    assert_eq!(vec![
+        "weapon_flags: cleared unknown bits 0x80000000",
+        "secondary_trigger_mode: reset invalid enum value 32767 to normal",
+        "triggers[0].flags: cleared unknown bits 0x40000000"
+    ], changes.iter().map(BludgeonChange::to_string).collect::<Vec<_>>());
+}
@@ -0,0 +1,1029 @@
+use std::collections::HashMap;
+use definitions::{ModelCollisionGeometryBSP, ModelCollisionGeometryBSP2DNode, ModelCollisionGeometryBSP2DReference, ModelCollisionGeometryBSP3DNode, ModelCollisionGeometryBSPEdge, ModelCollisionGeometryBSPLeaf, ModelCollisionGeometryBSPLeafFlags, ModelCollisionGeometryBSPPlane, ModelCollisionGeometryBSPSurface, ModelCollisionGeometryBSPSurfaceFlags, ModelCollisionGeometryBSPVertex};
+use primitives::error::{Error, RinghopperResult};
+use primitives::primitive::{Index, Plane2D, Plane3D, Vector, Vector2D, Vector3D};
+
+pub mod check;
+
+/// Flag set on BSP indices that refer to a leaf (for 3D nodes) or a surface (for 2D nodes) rather than a node.
+pub const BSP_LEAF_FLAG: u32 = 0x80000000;
+
+/// Null BSP index.
+///
+/// As a 3D node child, this refers to solid space. As a 2D node child, no surface is there.
+pub const BSP_NULL: u32 = 0xFFFFFFFF;
+
+/// Distance within which a point is considered to lie on a plane.
+pub const BSP_PLANE_EPSILON: f64 = 0.0001;
+
+/// Maximum number of planes to try when choosing a splitting plane.
+const MAX_SPLITTER_CANDIDATES: usize = 24;
+
+/// Triangle used as input for building a collision BSP.
+///
+/// Triangles face outward from solid space, using counterclockwise winding. Two-sided triangles (with the `two_sided`
+/// flag set) do not enclose solid space and are collideable from both sides.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CollisionTriangle {
+    pub vertices: [Vector3D; 3],
+    pub material: Index,
+    pub flags: ModelCollisionGeometryBSPSurfaceFlags,
+    pub breakable_surface: i8
+}
+
+#[derive(Clone)]
+struct Polygon {
+    points: Vec<Vector3D>,
+    plane: usize,
+
+    /// Index of the triangle (or splitter if `splitter` is set) this polygon came from.
+    source: usize,
+    splitter: bool
+}
+
+/// Face of a [`BSPCell`].
+#[derive(Clone, Debug, Default)]
+pub struct BSPCellFace {
+    pub points: Vec<Vector3D>,
+
+    /// 3D node whose plane created this face, and whether the cell is in front of it.
+    ///
+    /// This is `None` for faces of the bounding box surrounding the whole BSP.
+    pub node: Option<(u32, bool)>
+}
+
+/// Convex region of space partitioned by a BSP.
+#[derive(Clone, Debug, Default)]
+pub struct BSPCell {
+    pub faces: Vec<BSPCellFace>
+}
+
+/// Result of [`build_collision_bsp_with_splitters`].
+#[derive(Clone, Debug, Default)]
+pub struct CollisionBSPPartition {
+    pub bsp: ModelCollisionGeometryBSP,
+
+    /// Region of space enclosed by each leaf.
+    pub leaf_cells: Vec<BSPCell>,
+
+    /// Splitters that lie on the plane of each 3D node.
+    pub node_splitters: Vec<Vec<usize>>
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum Side {
+    Front,
+    Back,
+    On
+}
+
+fn classify(distance: f64) -> Side {
+    if distance > BSP_PLANE_EPSILON {
+        Side::Front
+    }
+    else if distance < -BSP_PLANE_EPSILON {
+        Side::Back
+    }
+    else {
+        Side::On
+    }
+}
+
+/// Split a convex polygon by a plane, returning the front and back parts (if any).
+fn split_polygon(points: &[Vector3D], plane: &Plane3D) -> (Vec<Vector3D>, Vec<Vector3D>) {
+    let distances: Vec<f64> = points.iter().map(|p| p.distance_from_plane(plane)).collect();
+    let mut front = Vec::new();
+    let mut back = Vec::new();
+
+    for i in 0..points.len() {
+        let j = (i + 1) % points.len();
+        let (a, b) = (points[i], points[j]);
+        let (da, db) = (distances[i], distances[j]);
+        let (sa, sb) = (classify(da), classify(db));
+
+        match sa {
+            Side::Front => front.push(a),
+            Side::Back => back.push(a),
+            Side::On => {
+                front.push(a);
+                back.push(a);
+            }
+        }
+
+        if (sa == Side::Front && sb == Side::Back) || (sa == Side::Back && sb == Side::Front) {
+            let t = da / (da - db);
+            let intersection = a + (b - a).scale(t);
+            front.push(intersection);
+            back.push(intersection);
+        }
+    }
+
+    (front, back)
+}
+
+fn polygon_area(points: &[Vector3D]) -> f64 {
+    let mut sum = Vector3D::zero();
+    for i in 1..points.len().saturating_sub(1) {
+        sum += (points[i] - points[0]).cross(&(points[i + 1] - points[0]));
+    }
+    sum.magnitude_squared().sqrt() / 2.0
+}
+
+fn is_degenerate(points: &[Vector3D]) -> bool {
+    points.len() < 3 || polygon_area(points) < BSP_PLANE_EPSILON * BSP_PLANE_EPSILON
+}
+
+/// Get the plane a triangle lies on, or `None` if the triangle is degenerate.
+pub fn plane_for_triangle(vertices: &[Vector3D; 3]) -> Option<Plane3D> {
+    let normal = (vertices[1] - vertices[0]).cross(&(vertices[2] - vertices[0]));
+    let magnitude = normal.magnitude_squared().sqrt();
+    if magnitude < BSP_PLANE_EPSILON * BSP_PLANE_EPSILON {
+        return None
+    }
+    let vector = normal.scale(1.0 / magnitude);
+    Some(Plane3D { vector, d: vector.dot(&vertices[0]) })
+}
+
+/// Axis dropped when projecting points on a plane into two dimensions.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ProjectionAxis {
+    X,
+    Y,
+    Z
+}
+
+impl ProjectionAxis {
+    /// Get the axis to drop for a plane, which is the largest component of its normal.
+    pub fn for_normal(normal: Vector3D) -> Self {
+        let (x, y, z) = (normal.x.abs(), normal.y.abs(), normal.z.abs());
+        if x >= y && x >= z {
+            Self::X
+        }
+        else if y >= z {
+            Self::Y
+        }
+        else {
+            Self::Z
+        }
+    }
+
+    /// Project a point into two dimensions.
+    pub fn project(self, point: Vector3D) -> Vector2D {
+        match self {
+            Self::X => Vector2D { x: point.y, y: point.z },
+            Self::Y => Vector2D { x: point.z, y: point.x },
+            Self::Z => Vector2D { x: point.x, y: point.y }
+        }
+    }
+}
+
+/// Welds nearby points together.
+#[derive(Default)]
+pub(crate) struct PointWelder {
+    points: Vec<Vector3D>,
+    lookup: HashMap<[i64; 3], u32>
+}
+
+impl PointWelder {
+    pub fn add(&mut self, point: Vector3D) -> u32 {
+        let quantize = |v: f64| (v / BSP_PLANE_EPSILON).round() as i64;
+        let key = [quantize(point.x), quantize(point.y), quantize(point.z)];
+        *self.lookup.entry(key).or_insert_with(|| {
+            self.points.push(point);
+            (self.points.len() - 1) as u32
+        })
+    }
+
+    pub fn into_points(self) -> Vec<Vector3D> {
+        self.points
+    }
+}
+
+/// Count the number of edges in a triangle mesh that are not shared by exactly one other triangle going the opposite
+/// direction.
+///
+/// A closed mesh has no open edges.
+pub fn count_open_edges(triangles: &[CollisionTriangle]) -> usize {
+    let mut welder = PointWelder::default();
+    let mut edges: HashMap<(u32, u32), isize> = HashMap::new();
+    for triangle in triangles {
+        let indices = triangle.vertices.map(|v| welder.add(v));
+        for i in 0..3 {
+            let (a, b) = (indices[i], indices[(i + 1) % 3]);
+            if a == b {
+                continue
+            }
+            let (key, delta) = if a < b { ((a, b), 1) } else { ((b, a), -1) };
+            *edges.entry(key).or_default() += delta;
+        }
+    }
+    edges.values().map(|v| v.unsigned_abs()).sum()
+}
+
+struct BSPBuilder<'a> {
+    triangles: &'a [CollisionTriangle],
+    planes: Vec<Plane3D>,
+    nodes: Vec<ModelCollisionGeometryBSP3DNode>,
+    node_references: Vec<ModelCollisionGeometryBSP2DReference>,
+    node_surfaces: Vec<std::ops::Range<usize>>,
+    node_splitters: Vec<Vec<usize>>,
+    leaves: Vec<ModelCollisionGeometryBSPLeaf>,
+    references: Vec<ModelCollisionGeometryBSP2DReference>,
+    nodes_2d: Vec<ModelCollisionGeometryBSP2DNode>,
+    surfaces: Vec<Polygon>,
+    leaf_cells: Vec<BSPCell>
+}
+
+/// Build a collision BSP from a closed triangle mesh.
+///
+/// Space in front of every triangle is empty, and space behind is solid. Leaves are only generated for empty space;
+/// solid space is referred to with [`BSP_NULL`]. Two-sided triangles are empty on both sides and do not need to be
+/// part of the closed mesh.
+///
+/// Returns an error if the mesh is not closed.
+pub fn build_collision_bsp(triangles: &[CollisionTriangle]) -> RinghopperResult<ModelCollisionGeometryBSP> {
+    build_collision_bsp_with_splitters(triangles, &[]).map(|p| p.bsp)
+}
+
+/// Build a collision BSP from a closed triangle mesh, additionally splitting space along the planes of the given
+/// splitter triangles.
+///
+/// Splitters, such as portals, are not collideable and do not need to form a closed mesh, but every splitter is
+/// guaranteed to lie on the plane of a 3D node. This also returns the cell enclosed by each leaf.
+pub fn build_collision_bsp_with_splitters(triangles: &[CollisionTriangle], splitters: &[[Vector3D; 3]]) -> RinghopperResult<CollisionBSPPartition> {
+    let one_sided: Vec<CollisionTriangle> = triangles.iter().filter(|t| !t.flags.two_sided).copied().collect();
+    let open_edges = count_open_edges(&one_sided);
+    if open_edges > 0 {
+        return Err(Error::Other(format!("collision mesh is not closed ({open_edges} open edge(s))")))
+    }
+
+    let mut builder = BSPBuilder {
+        triangles,
+        planes: Vec::new(),
+        nodes: Vec::new(),
+        node_references: Vec::new(),
+        node_surfaces: Vec::new(),
+        node_splitters: Vec::new(),
+        leaves: Vec::new(),
+        references: Vec::new(),
+        nodes_2d: Vec::new(),
+        surfaces: Vec::new(),
+        leaf_cells: Vec::new()
+    };
+
+    let mut plane_lookup = PlaneLookup::default();
+    let mut polygons = Vec::with_capacity(triangles.len());
+    let sources = triangles.iter().map(|t| (&t.vertices, false)).chain(splitters.iter().map(|s| (s, true)));
+    let mut counts = [0usize; 2];
+    for (vertices, splitter) in sources {
+        let source = counts[splitter as usize];
+        counts[splitter as usize] += 1;
+        let Some(plane) = plane_for_triangle(vertices) else {
+            continue
+        };
+        let plane = plane_lookup.add(&mut builder.planes, plane);
+        polygons.push(Polygon { points: vertices.to_vec(), plane, source, splitter });
+    }
+
+    if polygons.iter().all(|p| p.splitter) {
+        return Ok(CollisionBSPPartition::default())
+    }
+
+    // Anything outside of the mesh is solid, unless there are only two-sided surfaces which can't enclose anything.
+    let empty = polygons.iter().all(|p| p.splitter || builder.is_two_sided(p));
+    let cell = bounding_cell(polygons.iter().flat_map(|p| p.points.iter()));
+    builder.build_node(polygons, cell, empty)?;
+    builder.finish()
+}
+
+/// Deduplicates planes that are within [`BSP_PLANE_EPSILON`] of each other.
+#[derive(Default)]
+struct PlaneLookup {
+    /// Planes bucketed by their distance from the origin.
+    buckets: HashMap<i64, Vec<usize>>
+}
+
+impl PlaneLookup {
+    fn add(&mut self, planes: &mut Vec<Plane3D>, plane: Plane3D) -> usize {
+        let bucket = (plane.d / BSP_PLANE_EPSILON).floor() as i64;
+        let matches = |p: &Plane3D| (p.d - plane.d).abs() <= BSP_PLANE_EPSILON
+            && p.vector.distance_squared(&plane.vector) <= BSP_PLANE_EPSILON * BSP_PLANE_EPSILON;
+
+        // A matching plane may have been rounded into a neighboring bucket.
+        for b in bucket - 1..=bucket + 1 {
+            if let Some(existing) = self.buckets.get(&b).and_then(|b| b.iter().find(|i| matches(&planes[**i]))) {
+                return *existing
+            }
+        }
+
+        planes.push(plane);
+        let index = planes.len() - 1;
+        self.buckets.entry(bucket).or_default().push(index);
+        index
+    }
+}
+
+/// Get a box surrounding the points with some margin, which must not be empty.
+fn bounding_cell<'a, I: IntoIterator<Item = &'a Vector3D>>(points: I) -> BSPCell {
+    let mut points = points.into_iter().peekable();
+    let mut min = **points.peek().unwrap();
+    let mut max = min;
+    for p in points {
+        min = Vector3D { x: min.x.min(p.x), y: min.y.min(p.y), z: min.z.min(p.z) };
+        max = Vector3D { x: max.x.max(p.x), y: max.y.max(p.y), z: max.z.max(p.z) };
+    }
+
+    let margin = Vector3D { x: 1.0, y: 1.0, z: 1.0 };
+    let (l, h) = (min - margin, max + margin);
+    let corner = |x: bool, y: bool, z: bool| Vector3D {
+        x: if x { h.x } else { l.x },
+        y: if y { h.y } else { l.y },
+        z: if z { h.z } else { l.z }
+    };
+
+    let faces = [
+        [corner(false, false, false), corner(false, true, false), corner(true, true, false), corner(true, false, false)],
+        [corner(false, false, true), corner(true, false, true), corner(true, true, true), corner(false, true, true)],
+        [corner(false, false, false), corner(true, false, false), corner(true, false, true), corner(false, false, true)],
+        [corner(false, true, false), corner(false, true, true), corner(true, true, true), corner(true, true, false)],
+        [corner(false, false, false), corner(false, false, true), corner(false, true, true), corner(false, true, false)],
+        [corner(true, false, false), corner(true, true, false), corner(true, true, true), corner(true, false, true)]
+    ];
+
+    BSPCell { faces: faces.into_iter().map(|f| BSPCellFace { points: f.to_vec(), node: None }).collect() }
+}
+
+/// Split a cell by a plane, returning the front and back cells.
+fn split_cell(cell: BSPCell, plane: &Plane3D, node: u32) -> (BSPCell, BSPCell) {
+    let mut front = BSPCell { faces: Vec::new() };
+    let mut back = BSPCell { faces: Vec::new() };
+    let mut cap_points = Vec::new();
+
+    for face in cell.faces {
+        for p in &face.points {
+            if classify(p.distance_from_plane(plane)) == Side::On {
+                cap_points.push(*p);
+            }
+        }
+
+        let (f, b) = split_polygon(&face.points, plane);
+        for p in f.iter().chain(b.iter()) {
+            if classify(p.distance_from_plane(plane)) == Side::On {
+                cap_points.push(*p);
+            }
+        }
+        if !is_degenerate(&f) && f.iter().any(|p| classify(p.distance_from_plane(plane)) == Side::Front) {
+            front.faces.push(BSPCellFace { points: f, node: face.node });
+        }
+        if !is_degenerate(&b) && b.iter().any(|p| classify(p.distance_from_plane(plane)) == Side::Back) {
+            back.faces.push(BSPCellFace { points: b, node: face.node });
+        }
+    }
+
+    let cap = convex_polygon_on_plane(cap_points, plane);
+    if !is_degenerate(&cap) {
+        front.faces.push(BSPCellFace { points: cap.clone(), node: Some((node, true)) });
+        back.faces.push(BSPCellFace { points: cap, node: Some((node, false)) });
+    }
+
+    (front, back)
+}
+
+/// Order points lying on a plane into a convex polygon.
+fn convex_polygon_on_plane(mut points: Vec<Vector3D>, plane: &Plane3D) -> Vec<Vector3D> {
+    if points.len() < 3 {
+        return points
+    }
+
+    let center = points.iter().fold(Vector3D::zero(), |a, b| a + *b).scale(1.0 / points.len() as f64);
+    let axis = ProjectionAxis::for_normal(plane.vector);
+    let center_2d = axis.project(center);
+    let angle = |p: &Vector3D| {
+        let p = axis.project(*p);
+        (p.y - center_2d.y).atan2(p.x - center_2d.x)
+    };
+    points.sort_by(|a, b| angle(a).total_cmp(&angle(b)));
+    points.dedup_by(|a, b| a.distance_squared(b) < BSP_PLANE_EPSILON * BSP_PLANE_EPSILON);
+    points
+}
+
+struct Partition {
+    front: Vec<Polygon>,
+    back: Vec<Polygon>,
+    on: Vec<Polygon>
+}
+
+impl<'a> BSPBuilder<'a> {
+    fn is_two_sided(&self, polygon: &Polygon) -> bool {
+        !polygon.splitter && self.triangles[polygon.source].flags.two_sided
+    }
+
+    fn partition(&self, polygons: Vec<Polygon>, plane_index: usize) -> Partition {
+        let plane = self.planes[plane_index];
+        let mut partition = Partition { front: Vec::new(), back: Vec::new(), on: Vec::new() };
+
+        for polygon in polygons {
+            let sides: Vec<Side> = polygon.points.iter().map(|p| classify(p.distance_from_plane(&plane))).collect();
+            let has_front = sides.contains(&Side::Front);
+            let has_back = sides.contains(&Side::Back);
+
+            match (has_front, has_back) {
+                (false, false) => {
+                    // Coplanar polygons facing the other way are solid on the front side, unless they're two-sided, in
+                    // which case they can be flipped to face the same way.
+                    if polygon.splitter || polygon.plane == plane_index || self.planes[polygon.plane].vector.dot(&plane.vector) > 0.0 {
+                        partition.on.push(polygon);
+                    }
+                    else if self.is_two_sided(&polygon) {
+                        let mut points = polygon.points.clone();
+                        points.reverse();
+                        partition.on.push(Polygon { points, plane: plane_index, ..polygon });
+                    }
+                    else {
+                        partition.front.push(polygon);
+                    }
+                },
+                (true, false) => partition.front.push(polygon),
+                (false, true) => partition.back.push(polygon),
+                (true, true) => {
+                    let (front, back) = split_polygon(&polygon.points, &plane);
+                    if !is_degenerate(&front) {
+                        partition.front.push(Polygon { points: front, ..polygon.clone() });
+                    }
+                    if !is_degenerate(&back) {
+                        partition.back.push(Polygon { points: back, ..polygon });
+                    }
+                }
+            }
+        }
+
+        partition
+    }
+
+    fn choose_splitter(&self, polygons: &[Polygon]) -> usize {
+        let step = (polygons.len() / MAX_SPLITTER_CANDIDATES).max(1);
+        let mut best = polygons[0].plane;
+        let mut best_score = usize::MAX;
+        let mut tried = Vec::new();
+
+        for candidate in polygons.iter().step_by(step) {
+            if tried.contains(&candidate.plane) {
+                continue
+            }
+            tried.push(candidate.plane);
+
+            let plane = self.planes[candidate.plane];
+            let (mut front, mut back, mut split) = (0usize, 0usize, 0usize);
+            for polygon in polygons {
+                let mut has_front = false;
+                let mut has_back = false;
+                for p in &polygon.points {
+                    match classify(p.distance_from_plane(&plane)) {
+                        Side::Front => has_front = true,
+                        Side::Back => has_back = true,
+                        Side::On => ()
+                    }
+                }
+                match (has_front, has_back) {
+                    (true, true) => split += 1,
+                    (true, false) => front += 1,
+                    (false, true) => back += 1,
+                    (false, false) => ()
+                }
+            }
+
+            let score = split * 8 + front.abs_diff(back);
+            if score < best_score {
+                best_score = score;
+                best = candidate.plane;
+            }
+        }
+
+        best
+    }
+
+    /// Build a node or leaf for a region of space.
+    ///
+    /// `empty` is whether the region is empty space if there are no surfaces left in it.
+    fn build_child(&mut self, polygons: Vec<Polygon>, cell: BSPCell, empty: bool) -> RinghopperResult<u32> {
+        // Splitters and two-sided surfaces don't matter in solid space.
+        if polygons.is_empty() || (!empty && polygons.iter().all(|p| p.splitter || self.is_two_sided(p))) {
+            return if empty { self.make_leaf(cell) } else { Ok(BSP_NULL) }
+        }
+        self.build_node(polygons, cell, empty)
+    }
+
+    fn build_node(&mut self, polygons: Vec<Polygon>, cell: BSPCell, empty: bool) -> RinghopperResult<u32> {
+        let plane_index = self.choose_splitter(&polygons);
+        let plane = self.planes[plane_index];
+        let Partition { front, back, on } = self.partition(polygons, plane_index);
+        debug_assert!(!on.is_empty());
+
+        let node_index = self.nodes.len();
+        self.nodes.push(ModelCollisionGeometryBSP3DNode {
+            plane: plane_index as u32,
+            back_child: BSP_NULL,
+            front_child: BSP_NULL
+        });
+
+        let (splitters, surfaces): (Vec<Polygon>, Vec<Polygon>) = on.into_iter().partition(|p| p.splitter);
+        let mut splitters: Vec<usize> = splitters.into_iter().map(|p| p.source).collect();
+        splitters.sort_unstable();
+        splitters.dedup();
+        self.node_splitters.push(splitters);
+
+        // Space in front of a surface is empty and space behind it is solid, but splitters and two-sided surfaces don't
+        // change anything.
+        let one_sided = surfaces.iter().any(|p| !self.is_two_sided(p));
+        let (front_empty, back_empty) = if one_sided { (true, false) } else { (empty, empty) };
+
+        let first_surface = self.surfaces.len();
+        self.surfaces.extend(surfaces);
+        let bsp2d_node = self.build_2d_root(first_surface..self.surfaces.len(), &plane);
+        self.node_references.push(ModelCollisionGeometryBSP2DReference { plane: plane_index as u32, bsp2d_node });
+        self.node_surfaces.push(first_surface..self.surfaces.len());
+
+        let (front_cell, back_cell) = split_cell(cell, &plane, node_index as u32);
+        let front_child = self.build_child(front, front_cell, front_empty)?;
+        let back_child = self.build_child(back, back_cell, back_empty)?;
+
+        let node = &mut self.nodes[node_index];
+        node.front_child = front_child;
+        node.back_child = back_child;
+
+        Ok(node_index as u32)
+    }
+
+    fn make_leaf(&mut self, cell: BSPCell) -> RinghopperResult<u32> {
+        // The leaf can only touch surfaces on planes it is in front of, or two-sided surfaces behind it.
+        let mut nodes: Vec<usize> = cell.faces.iter().filter_map(|f| {
+            let (node, front) = f.node?;
+            let surfaces = &self.surfaces[self.node_surfaces[node as usize].clone()];
+            let touching = if front { !surfaces.is_empty() } else { surfaces.iter().any(|s| self.is_two_sided(s)) };
+            touching.then_some(node as usize)
+        }).collect();
+        nodes.sort_unstable();
+        nodes.dedup();
+
+        let reference_count = u16::try_from(nodes.len()).map_err(|_| Error::Other("BSP leaf has too many surfaces".to_owned()))?;
+        let first_bsp2d_reference = self.references.len() as u32;
+        let mut flags = ModelCollisionGeometryBSPLeafFlags::default();
+        for n in nodes {
+            self.references.push(self.node_references[n]);
+            flags.contains_double_sided_surfaces |= self.surfaces[self.node_surfaces[n].clone()]
+                .iter()
+                .any(|s| self.is_two_sided(s));
+        }
+
+        self.leaves.push(ModelCollisionGeometryBSPLeaf {
+            flags,
+            bsp2d_reference_count: reference_count,
+            first_bsp2d_reference
+        });
+        self.leaf_cells.push(cell);
+
+        Ok((self.leaves.len() - 1) as u32 | BSP_LEAF_FLAG)
+    }
+
+    fn build_2d_root(&mut self, surfaces: std::ops::Range<usize>, plane: &Plane3D) -> u32 {
+        let axis = ProjectionAxis::for_normal(plane.vector);
+        let items = surfaces.map(|s| Item2D::new(s as u32, &self.surfaces[s].points, axis)).collect();
+        self.build_2d(items)
+    }
+
+    fn build_2d(&mut self, items: Vec<Item2D>) -> u32 {
+        if items.is_empty() {
+            return BSP_NULL
+        }
+
+        let candidates: Vec<Plane2D> = items.iter().flat_map(|i| i.unused_lines()).collect();
+        if candidates.is_empty() {
+            // Every edge of every remaining surface encloses this region, so they all overlap here.
+            return items[0].surface | BSP_LEAF_FLAG
+        }
+
+        let step = (candidates.len() / MAX_SPLITTER_CANDIDATES).max(1);
+        let mut best = candidates[0];
+        let mut best_score = usize::MAX;
+        for line in candidates.iter().step_by(step) {
+            let (mut front, mut back, mut both) = (0usize, 0usize, 0usize);
+            for item in &items {
+                match item.classify(line) {
+                    (true, false) => front += 1,
+                    (false, true) => back += 1,
+                    _ => both += 1
+                }
+            }
+            let score = both * 2 + front.abs_diff(back);
+            if score < best_score {
+                best_score = score;
+                best = *line;
+            }
+        }
+
+        let mut front_items = Vec::new();
+        let mut back_items = Vec::new();
+        for mut item in items {
+            let (front, back) = item.classify(&best);
+            if front {
+                front_items.push(item.clone());
+            }
+            if back {
+                item.mark_used(&best);
+                back_items.push(item);
+            }
+        }
+
+        let node_index = self.nodes_2d.len();
+        self.nodes_2d.push(ModelCollisionGeometryBSP2DNode { plane: best, left_child: BSP_NULL, right_child: BSP_NULL });
+
+        let right_child = self.build_2d(front_items);
+        let left_child = self.build_2d(back_items);
+
+        let node = &mut self.nodes_2d[node_index];
+        node.right_child = right_child;
+        node.left_child = left_child;
+
+        node_index as u32
+    }
+
+    fn finish(self) -> RinghopperResult<CollisionBSPPartition> {
+        let mut bsp = ModelCollisionGeometryBSP::default();
+
+        bsp.planes.items = self.planes.into_iter().map(|plane| ModelCollisionGeometryBSPPlane { plane }).collect();
+        bsp.bsp3d_nodes.items = self.nodes;
+        bsp.leaves.items = self.leaves;
+        bsp.bsp2d_references.items = self.references;
+        bsp.bsp2d_nodes.items = self.nodes_2d;
+
+        let mut welder = PointWelder::default();
+        let mut edges: Vec<ModelCollisionGeometryBSPEdge> = Vec::new();
+        let mut edge_lookup: HashMap<(u32, u32), u32> = HashMap::new();
+
+        for (surface_index, surface) in self.surfaces.iter().enumerate() {
+            let mut indices: Vec<u32> = surface.points.iter().map(|p| welder.add(*p)).collect();
+            indices.dedup();
+            while indices.len() > 1 && indices.first() == indices.last() {
+                indices.pop();
+            }
+
+            // Find or create each edge going around the surface.
+            let surface_index = surface_index as u32;
+            let mut surface_edges = Vec::with_capacity(indices.len());
+            for i in 0..indices.len() {
+                let (a, b) = (indices[i], indices[(i + 1) % indices.len()]);
+                match edge_lookup.get(&(b, a)).copied() {
+                    Some(e) if edges[e as usize].right_surface == BSP_NULL => {
+                        edges[e as usize].right_surface = surface_index;
+                        surface_edges.push((e, false));
+                    },
+                    _ => {
+                        let e = edges.len() as u32;
+                        edges.push(ModelCollisionGeometryBSPEdge {
+                            start_vertex: a,
+                            end_vertex: b,
+                            forward_edge: BSP_NULL,
+                            reverse_edge: BSP_NULL,
+                            left_surface: surface_index,
+                            right_surface: BSP_NULL
+                        });
+                        edge_lookup.entry((a, b)).or_insert(e);
+                        surface_edges.push((e, true));
+                    }
+                }
+            }
+
+            for i in 0..surface_edges.len() {
+                let (edge, is_left) = surface_edges[i];
+                let (next, _) = surface_edges[(i + 1) % surface_edges.len()];
+                let edge = &mut edges[edge as usize];
+                if is_left {
+                    edge.forward_edge = next;
+                }
+                else {
+                    edge.reverse_edge = next;
+                }
+            }
+
+            let source = &self.triangles[surface.source];
+            bsp.surfaces.items.push(ModelCollisionGeometryBSPSurface {
+                plane: surface.plane as u32,
+                first_edge: surface_edges.first().map(|e| e.0).unwrap_or(BSP_NULL),
+                flags: source.flags,
+                breakable_surface: source.breakable_surface,
+                material: source.material
+            });
+        }
+
+        let mut vertices: Vec<ModelCollisionGeometryBSPVertex> = welder
+            .into_points()
+            .into_iter()
+            .map(|point| ModelCollisionGeometryBSPVertex { point, first_edge: BSP_NULL })
+            .collect();
+        for (index, edge) in edges.iter().enumerate() {
+            for v in [edge.start_vertex, edge.end_vertex] {
+                let vertex = &mut vertices[v as usize];
+                if vertex.first_edge == BSP_NULL {
+                    vertex.first_edge = index as u32;
+                }
+            }
+        }
+
+        bsp.edges.items = edges;
+        bsp.vertices.items = vertices;
+
+        Ok(CollisionBSPPartition { bsp, leaf_cells: self.leaf_cells, node_splitters: self.node_splitters })
+    }
+}
+
+/// Surface being partitioned by a 2D BSP.
+#[derive(Clone)]
+struct Item2D {
+    surface: u32,
+    points: Vec<Vector2D>,
+
+    /// Outward-facing lines along each edge, and whether the edge has been used to split yet.
+    lines: Vec<(Plane2D, bool)>
+}
+
+impl Item2D {
+    fn new(surface: u32, points: &[Vector3D], axis: ProjectionAxis) -> Self {
+        let points: Vec<Vector2D> = points.iter().map(|p| axis.project(*p)).collect();
+
+        let mut area = 0.0;
+        for i in 0..points.len() {
+            let (a, b) = (points[i], points[(i + 1) % points.len()]);
+            area += a.x * b.y - b.x * a.y;
+        }
+        let winding = if area >= 0.0 { 1.0 } else { -1.0 };
+
+        let mut lines = Vec::with_capacity(points.len());
+        for i in 0..points.len() {
+            let (a, b) = (points[i], points[(i + 1) % points.len()]);
+            let normal = Vector2D { x: (b.y - a.y) * winding, y: (a.x - b.x) * winding };
+            if normal.magnitude_squared() < BSP_PLANE_EPSILON * BSP_PLANE_EPSILON {
+                continue
+            }
+            let normal = normal.normalize();
+            lines.push((Plane2D { vector: normal, d: normal.dot(&a) }, false));
+        }
+
+        Self { surface, points, lines }
+    }
+
+    fn unused_lines(&self) -> impl Iterator<Item = Plane2D> + '_ {
+        self.lines.iter().filter(|l| !l.1).map(|l| l.0)
+    }
+
+    /// Return whether the surface is in front of and/or behind a line.
+    fn classify(&self, line: &Plane2D) -> (bool, bool) {
+        let mut front = false;
+        let mut back = false;
+        for p in &self.points {
+            match classify(p.distance_from_plane(line)) {
+                Side::Front => front = true,
+                Side::Back => back = true,
+                Side::On => ()
+            }
+        }
+        if !front && !back {
+            back = true;
+        }
+        (front, back)
+    }
+
+    fn mark_used(&mut self, line: &Plane2D) {
+        for (l, used) in &mut self.lines {
+            if l.vector.dot(&line.vector) > 1.0 - BSP_PLANE_EPSILON && (l.d - line.d).abs() < BSP_PLANE_EPSILON {
+                *used = true;
+            }
+        }
+    }
+}
+
+/// Find the leaf containing a point, or `None` if the point is in solid space (or the BSP has no nodes).
+pub fn bsp_leaf_for_point(bsp: &ModelCollisionGeometryBSP, point: Vector3D) -> Option<u32> {
+    let nodes = &bsp.bsp3d_nodes.items;
+    let mut node_index = 0u32;
+
+    // Guard against cycles in broken BSPs.
+    for _ in 0..nodes.len() {
+        let node = nodes.get(node_index as usize)?;
+        let plane = &bsp.planes.items.get((node.plane & !BSP_LEAF_FLAG) as usize)?.plane;
+        let mut distance = point.distance_from_plane(plane);
+        if node.plane & BSP_LEAF_FLAG != 0 {
+            distance = -distance;
+        }
+
+        let child = if distance >= 0.0 { node.front_child } else { node.back_child };
+        if child == BSP_NULL {
+            return None
+        }
+        if child & BSP_LEAF_FLAG != 0 {
+            return Some(child & !BSP_LEAF_FLAG)
+        }
+        node_index = child;
+    }
+
+    None
+}
+
+/// Return `true` if the point is in solid space.
+pub fn bsp_point_in_solid(bsp: &ModelCollisionGeometryBSP, point: Vector3D) -> bool {
+    !bsp.bsp3d_nodes.items.is_empty() && bsp_leaf_for_point(bsp, point).is_none()
+}
+
+/// Find the surface in a 2D BSP containing a projected point, if any.
+pub fn bsp2d_surface_for_point(bsp: &ModelCollisionGeometryBSP, root: u32, point: Vector2D) -> Option<u32> {
+    let nodes = &bsp.bsp2d_nodes.items;
+    let mut node_index = root;
+
+    for _ in 0..=nodes.len() {
+        if node_index == BSP_NULL {
+            return None
+        }
+        if node_index & BSP_LEAF_FLAG != 0 {
+            return Some(node_index & !BSP_LEAF_FLAG)
+        }
+        let node = nodes.get(node_index as usize)?;
+        node_index = if point.distance_from_plane(&node.plane) >= 0.0 { node.right_child } else { node.left_child };
+    }
+
+    None
+}
+
+/// Result of tracing a ray through a collision BSP.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BSPRayHit {
+    /// Fraction of the way from the start to the end of the ray where it hit solid space.
+    pub fraction: f64,
+
+    /// Point that was hit.
+    pub point: Vector3D,
+
+    /// Plane that was hit, or `None` if the ray started in solid space.
+    pub plane: Option<u32>,
+
+    /// Normal of the plane that was hit, facing the start of the ray.
+    pub normal: Vector3D,
+
+    /// Surface that was hit, if one could be found.
+    pub surface: Option<u32>
+}
+
+struct RayTrace<'a> {
+    bsp: &'a ModelCollisionGeometryBSP,
+    start: Vector3D,
+    end: Vector3D,
+    leaf: Option<u32>
+}
+
+impl RayTrace<'_> {
+    fn point_at(&self, fraction: f64) -> Vector3D {
+        self.start + (self.end - self.start).scale(fraction)
+    }
+
+    fn trace(&mut self, child: u32, t0: f64, t1: f64, plane: Option<(u32, Vector3D)>, depth: usize) -> Option<BSPRayHit> {
+        if child == BSP_NULL {
+            let point = self.point_at(t0);
+            let (plane, normal) = match plane {
+                Some((plane, normal)) => (Some(plane), normal),
+                None => (None, Vector3D::zero())
+            };
+            return Some(BSPRayHit { fraction: t0, point, plane, normal, surface: plane.and_then(|p| self.surface_at(p, point)) })
+        }
+        if child & BSP_LEAF_FLAG != 0 {
+            self.leaf = Some(child & !BSP_LEAF_FLAG);
+            return None
+        }
+
+        // Guard against cycles in broken BSPs.
+        if depth > self.bsp.bsp3d_nodes.items.len() {
+            return None
+        }
+
+        let node = self.bsp.bsp3d_nodes.items.get(child as usize)?;
+        let plane_index = node.plane & !BSP_LEAF_FLAG;
+        let mut node_plane = self.bsp.planes.items.get(plane_index as usize)?.plane;
+        if node.plane & BSP_LEAF_FLAG != 0 {
+            node_plane = Plane3D { vector: -node_plane.vector, d: -node_plane.d };
+        }
+
+        let d0 = self.point_at(t0).distance_from_plane(&node_plane);
+        let d1 = self.point_at(t1).distance_from_plane(&node_plane);
+        if d0 >= 0.0 && d1 >= 0.0 {
+            return self.trace(node.front_child, t0, t1, plane, depth + 1)
+        }
+        if d0 < 0.0 && d1 < 0.0 {
+            return self.trace(node.back_child, t0, t1, plane, depth + 1)
+        }
+
+        let middle = t0 + (t1 - t0) * d0 / (d0 - d1);
+        let (near, far, normal) = if d0 >= 0.0 {
+            (node.front_child, node.back_child, node_plane.vector)
+        }
+        else {
+            (node.back_child, node.front_child, -node_plane.vector)
+        };
+        self.trace(near, t0, middle, plane, depth + 1)
+            .or_else(|| self.trace(far, middle, t1, Some((plane_index, normal)), depth + 1))
+    }
+
+    fn surface_at(&self, plane: u32, point: Vector3D) -> Option<u32> {
+        let leaf = self.bsp.leaves.items.get(self.leaf? as usize)?;
+        let first = leaf.first_bsp2d_reference as usize;
+        let references = self.bsp.bsp2d_references.items.get(first..first + leaf.bsp2d_reference_count as usize)?;
+        let axis = ProjectionAxis::for_normal(self.bsp.planes.items.get(plane as usize)?.plane.vector);
+        let point = axis.project(point);
+        references
+            .iter()
+            .find(|r| r.plane & !BSP_LEAF_FLAG == plane)
+            .and_then(|r| bsp2d_surface_for_point(self.bsp, r.bsp2d_node, point))
+            .or_else(|| self.surface_containing(plane, axis, point))
+    }
+
+    // Points lying exactly on a 2D BSP split can fall into a null child, so check the polygons directly.
+    fn surface_containing(&self, plane: u32, axis: ProjectionAxis, point: Vector2D) -> Option<u32> {
+        const EPSILON: f64 = 0.0001;
+        (0..self.bsp.surfaces.items.len() as u32)
+            .filter(|s| self.bsp.surfaces.items[*s as usize].plane & !BSP_LEAF_FLAG == plane)
+            .find(|s| {
+                let Ok(points) = collision_surface_vertices(self.bsp, *s) else {
+                    return false
+                };
+                let points: Vec<Vector2D> = points.into_iter().map(|p| axis.project(p)).collect();
+                point_in_convex_polygon(&points, point, EPSILON)
+            })
+    }
+}
+
+/// Return `true` if a point is inside (or within `epsilon` of) a convex polygon of either winding.
+fn point_in_convex_polygon(points: &[Vector2D], point: Vector2D, epsilon: f64) -> bool {
+    let crosses = points.iter().zip(points.iter().cycle().skip(1)).map(|(a, b)| {
+        let edge = *b - *a;
+        let length = edge.magnitude_squared().sqrt();
+        let offset = point - *a;
+        if length > 0.0 { (edge.x * offset.y - edge.y * offset.x) / length } else { 0.0 }
+    });
+    let (mut positive, mut negative) = (false, false);
+    for cross in crosses {
+        positive |= cross > epsilon;
+        negative |= cross < -epsilon;
+    }
+    points.len() >= 3 && !(positive && negative)
+}
+
+/// Trace a ray from `start` to `end`, returning where it first enters solid space, if it does.
+pub fn bsp_trace_ray(bsp: &ModelCollisionGeometryBSP, start: Vector3D, end: Vector3D) -> Option<BSPRayHit> {
+    if bsp.bsp3d_nodes.items.is_empty() {
+        return None
+    }
+    RayTrace { bsp, start, end, leaf: None }.trace(0, 0.0, 1.0, None, 0)
+}
+
+/// Get the vertices of a surface in order by walking its edges.
+///
+/// Returns an error if the surface, its edges, or its vertices are out of bounds, or if the edges do not loop back.
+pub fn collision_surface_vertices(bsp: &ModelCollisionGeometryBSP, surface: u32) -> RinghopperResult<Vec<Vector3D>> {
+    let first_edge = bsp.surfaces
+        .items
+        .get(surface as usize)
+        .ok_or_else(|| Error::InvalidTagData(format!("surface #{surface} is out of bounds")))?
+        .first_edge;
+
+    let mut points = Vec::new();
+    let mut edge_index = first_edge;
+    for _ in 0..bsp.edges.items.len() {
+        let edge = bsp.edges
+            .items
+            .get(edge_index as usize)
+            .ok_or_else(|| Error::InvalidTagData(format!("surface #{surface} references out-of-bounds edge #{edge_index}")))?;
+
+        let (vertex, next) = if edge.left_surface == surface {
+            (edge.start_vertex, edge.forward_edge)
+        }
+        else if edge.right_surface == surface {
+            (edge.end_vertex, edge.reverse_edge)
+        }
+        else {
+            return Err(Error::InvalidTagData(format!("edge #{edge_index} does not border surface #{surface}")))
+        };
+
+        let point = bsp.vertices
+            .items
+            .get(vertex as usize)
+            .ok_or_else(|| Error::InvalidTagData(format!("edge #{edge_index} references out-of-bounds vertex #{vertex}")))?
+            .point;
+        points.push(point);
+
+        if next == first_edge {
+            return Ok(points)
+        }
+        edge_index = next;
+    }
+
+    Err(Error::InvalidTagData(format!("edges of surface #{surface} do not form a loop")))
+}
+
+#[cfg(test)]
+mod test;
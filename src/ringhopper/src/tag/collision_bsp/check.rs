@@ -0,0 +1,289 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+use definitions::ModelCollisionGeometryBSP;
+use primitives::primitive::{Plane3D, Vector, Vector2D, Vector3D};
+use super::{BSP_LEAF_FLAG, BSP_NULL, BSP_PLANE_EPSILON, BSPCell, ProjectionAxis, bounding_cell, bsp_point_in_solid, collision_surface_vertices, is_degenerate, point_in_convex_polygon, split_cell};
+
+/// Distance from a cell face to test whether the other side is open.
+const PHANTOM_PROBE_DISTANCE: f64 = 0.01;
+
+/// Number of subdivisions along each edge of a cell face triangle when sampling for phantom BSP.
+const PHANTOM_SAMPLE_SUBDIVISIONS: usize = 6;
+
+/// Maximum distance a surface's vertices can be from its plane.
+const SURFACE_PLANE_TOLERANCE: f64 = 0.001;
+
+/// Severity of a [`CollisionBSPIssue`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum CollisionBSPIssueSeverity {
+    /// The BSP is corrupt and may crash the game.
+    Error,
+
+    /// The BSP is usable, but it may not collide as expected.
+    Warning
+}
+
+/// Problem found in a collision BSP.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CollisionBSPIssue {
+    pub severity: CollisionBSPIssueSeverity,
+    pub description: String,
+
+    /// Location of the problem, in the BSP's coordinate space, if it has one.
+    pub position: Option<Vector3D>
+}
+
+impl Display for CollisionBSPIssue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.position {
+            Some(p) => write!(f, "{} at ({:.3}, {:.3}, {:.3})", self.description, p.x, p.y, p.z),
+            None => f.write_str(&self.description)
+        }
+    }
+}
+
+/// Check a collision BSP for problems.
+///
+/// This checks that every index is in bounds, that edges are closed (unless they border a two-sided surface) and not
+/// degenerate, that surfaces are flat and not degenerate, and that there is no phantom BSP (solid space with no surface
+/// in front of it). `material_count` is the number of collision materials the surfaces can reference.
+///
+/// Geometry is only checked if every index is in bounds.
+pub fn check_collision_bsp(bsp: &ModelCollisionGeometryBSP, material_count: usize) -> Vec<CollisionBSPIssue> {
+    let mut checker = Checker { bsp, issues: Vec::new() };
+    checker.check_bounds(material_count);
+    if checker.issues.is_empty() {
+        checker.check_geometry();
+        checker.check_phantom_bsp();
+    }
+    checker.issues
+}
+
+struct Checker<'a> {
+    bsp: &'a ModelCollisionGeometryBSP,
+    issues: Vec<CollisionBSPIssue>
+}
+
+impl Checker<'_> {
+    fn error(&mut self, description: String, position: Option<Vector3D>) {
+        self.issues.push(CollisionBSPIssue { severity: CollisionBSPIssueSeverity::Error, description, position });
+    }
+
+    fn warning(&mut self, description: String, position: Option<Vector3D>) {
+        self.issues.push(CollisionBSPIssue { severity: CollisionBSPIssueSeverity::Warning, description, position });
+    }
+
+    fn vertex_position(&self, vertex: u32) -> Option<Vector3D> {
+        self.bsp.vertices.items.get(vertex as usize).map(|v| v.point)
+    }
+
+    fn edge_midpoint(&self, edge: u32) -> Option<Vector3D> {
+        let edge = self.bsp.edges.items.get(edge as usize)?;
+        let (a, b) = (self.vertex_position(edge.start_vertex)?, self.vertex_position(edge.end_vertex)?);
+        Some((a + b).scale(0.5))
+    }
+
+    fn check_bounds(&mut self, material_count: usize) {
+        let bsp = self.bsp;
+        let plane_count = bsp.planes.items.len();
+        let node_count = bsp.bsp3d_nodes.items.len();
+        let leaf_count = bsp.leaves.items.len();
+        let reference_count = bsp.bsp2d_references.items.len();
+        let node2d_count = bsp.bsp2d_nodes.items.len();
+        let surface_count = bsp.surfaces.items.len();
+        let edge_count = bsp.edges.items.len();
+        let vertex_count = bsp.vertices.items.len();
+
+        let plane_in_bounds = |plane: u32| ((plane & !BSP_LEAF_FLAG) as usize) < plane_count;
+        let child_in_bounds = |child: u32, leaves: usize, nodes: usize| {
+            child == BSP_NULL || if child & BSP_LEAF_FLAG != 0 { ((child & !BSP_LEAF_FLAG) as usize) < leaves } else { (child as usize) < nodes }
+        };
+
+        for (i, node) in bsp.bsp3d_nodes.items.iter().enumerate() {
+            if !plane_in_bounds(node.plane) {
+                self.error(format!("3D node #{i} references an out-of-bounds plane"), None);
+            }
+            if !child_in_bounds(node.front_child, leaf_count, node_count) || !child_in_bounds(node.back_child, leaf_count, node_count) {
+                self.error(format!("3D node #{i} has an out-of-bounds child"), None);
+            }
+        }
+
+        for (i, leaf) in bsp.leaves.items.iter().enumerate() {
+            let end = leaf.first_bsp2d_reference as usize + leaf.bsp2d_reference_count as usize;
+            if end > reference_count {
+                self.error(format!("leaf #{i} references out-of-bounds 2D BSP references"), None);
+            }
+        }
+
+        for (i, reference) in bsp.bsp2d_references.items.iter().enumerate() {
+            if !plane_in_bounds(reference.plane) {
+                self.error(format!("2D BSP reference #{i} references an out-of-bounds plane"), None);
+            }
+            if !child_in_bounds(reference.bsp2d_node, surface_count, node2d_count) {
+                self.error(format!("2D BSP reference #{i} references an out-of-bounds 2D node or surface"), None);
+            }
+        }
+
+        for (i, node) in bsp.bsp2d_nodes.items.iter().enumerate() {
+            if !child_in_bounds(node.left_child, surface_count, node2d_count) || !child_in_bounds(node.right_child, surface_count, node2d_count) {
+                self.error(format!("2D node #{i} has an out-of-bounds child"), None);
+            }
+        }
+
+        for (i, surface) in bsp.surfaces.items.iter().enumerate() {
+            let position = self.edge_midpoint(surface.first_edge);
+            if !plane_in_bounds(surface.plane) {
+                self.error(format!("surface #{i} references an out-of-bounds plane"), position);
+            }
+            if surface.first_edge as usize >= edge_count {
+                self.error(format!("surface #{i} references an out-of-bounds edge"), position);
+            }
+            if surface.material.is_some_and(|m| m as usize >= material_count) {
+                self.error(format!("surface #{i} references an out-of-bounds material"), position);
+            }
+        }
+
+        for (i, edge) in bsp.edges.items.iter().enumerate() {
+            let position = self.edge_midpoint(i as u32);
+            if edge.start_vertex as usize >= vertex_count || edge.end_vertex as usize >= vertex_count {
+                self.error(format!("edge #{i} references an out-of-bounds vertex"), position);
+            }
+            if edge.forward_edge as usize >= edge_count || edge.reverse_edge as usize >= edge_count {
+                self.error(format!("edge #{i} references an out-of-bounds edge"), position);
+            }
+            if edge.left_surface as usize >= surface_count {
+                self.error(format!("edge #{i} references an out-of-bounds surface"), position);
+            }
+            if edge.right_surface != BSP_NULL && edge.right_surface as usize >= surface_count {
+                self.error(format!("edge #{i} references an out-of-bounds surface"), position);
+            }
+        }
+
+        for (i, vertex) in bsp.vertices.items.iter().enumerate() {
+            if vertex.first_edge as usize >= edge_count {
+                self.error(format!("vertex #{i} references an out-of-bounds edge"), Some(vertex.point));
+            }
+        }
+    }
+
+    fn check_geometry(&mut self) {
+        let bsp = self.bsp;
+
+        for (i, edge) in bsp.edges.items.iter().enumerate() {
+            let (a, b) = (bsp.vertices.items[edge.start_vertex as usize].point, bsp.vertices.items[edge.end_vertex as usize].point);
+            if a.distance_squared(&b) < BSP_PLANE_EPSILON * BSP_PLANE_EPSILON {
+                self.warning(format!("edge #{i} is degenerate (zero length)"), Some(a));
+            }
+
+            // Two-sided surfaces don't need to be part of a closed mesh.
+            if edge.right_surface == BSP_NULL && !bsp.surfaces.items[edge.left_surface as usize].flags.two_sided {
+                self.error(format!("edge #{i} is open (it only borders one surface)"), Some((a + b).scale(0.5)));
+            }
+        }
+
+        for (i, surface) in bsp.surfaces.items.iter().enumerate() {
+            let points = match collision_surface_vertices(bsp, i as u32) {
+                Ok(n) => n,
+                Err(e) => {
+                    self.error(e.to_string(), self.edge_midpoint(surface.first_edge));
+                    continue
+                }
+            };
+            let centroid = points.iter().fold(Vector3D::zero(), |a, b| a + *b).scale(1.0 / points.len().max(1) as f64);
+
+            if is_degenerate(&points) {
+                self.warning(format!("surface #{i} is degenerate (zero area)"), Some(centroid));
+                continue
+            }
+
+            let plane = bsp.planes.items[(surface.plane & !BSP_LEAF_FLAG) as usize].plane;
+            if points.iter().any(|p| p.distance_from_plane(&plane).abs() > SURFACE_PLANE_TOLERANCE) {
+                self.warning(format!("surface #{i} does not lie on its plane"), Some(centroid));
+            }
+        }
+    }
+
+    /// Find places where solid space borders open space without a surface in between, which blocks objects with an
+    /// invisible wall.
+    fn check_phantom_bsp(&mut self) {
+        let bsp = self.bsp;
+        if bsp.bsp3d_nodes.items.is_empty() || bsp.vertices.items.is_empty() {
+            return
+        }
+
+        // Group surfaces by the plane they lie on, regardless of its direction, in case planes are duplicated.
+        let plane_key = |plane: &Plane3D| {
+            let (n, d) = (plane.vector, plane.d);
+            let flip = n.x < -BSP_PLANE_EPSILON
+                || (n.x.abs() <= BSP_PLANE_EPSILON && (n.y < -BSP_PLANE_EPSILON || (n.y.abs() <= BSP_PLANE_EPSILON && n.z < 0.0)));
+            let (n, d) = if flip { (-n, -d) } else { (n, d) };
+            [n.x * 1000.0, n.y * 1000.0, n.z * 1000.0, d * 100.0].map(|c| c.round() as i64)
+        };
+        let mut surfaces_on_plane: HashMap<[i64; 4], Vec<Vec<Vector3D>>> = HashMap::new();
+        for (i, surface) in bsp.surfaces.items.iter().enumerate() {
+            let plane = bsp.planes.items[(surface.plane & !BSP_LEAF_FLAG) as usize].plane;
+            if let Ok(points) = collision_surface_vertices(bsp, i as u32) {
+                surfaces_on_plane.entry(plane_key(&plane)).or_default().push(points);
+            }
+        }
+
+        let mut visited = HashSet::new();
+        let mut stack = vec![(0u32, bounding_cell(bsp.vertices.items.iter().map(|v| &v.point)))];
+        while let Some((index, cell)) = stack.pop() {
+            if !visited.insert(index) {
+                self.error(format!("3D node #{index} is referenced more than once"), None);
+                continue
+            }
+
+            let node = &bsp.bsp3d_nodes.items[index as usize];
+            let mut plane = bsp.planes.items[(node.plane & !BSP_LEAF_FLAG) as usize].plane;
+            if node.plane & BSP_LEAF_FLAG != 0 {
+                plane = Plane3D { vector: -plane.vector, d: -plane.d };
+            }
+
+            let surfaces = surfaces_on_plane.get(&plane_key(&plane)).map(Vec::as_slice).unwrap_or_default();
+            let (front, back) = split_cell(cell, &plane, index);
+            for (child, cell, in_front) in [(node.front_child, front, true), (node.back_child, back, false)] {
+                if child == BSP_NULL {
+                    if let Some(position) = phantom_sample(bsp, &cell, index, in_front, &plane, surfaces) {
+                        self.warning(format!("possible phantom BSP on 3D node #{index}"), Some(position));
+                    }
+                }
+                else if child & BSP_LEAF_FLAG == 0 {
+                    stack.push((child, cell));
+                }
+            }
+        }
+    }
+}
+
+/// Find a point on the face a solid child shares with its sibling which is open on the other side and not covered by
+/// any surface.
+fn phantom_sample(bsp: &ModelCollisionGeometryBSP, cell: &BSPCell, node: u32, in_front: bool, plane: &Plane3D, surfaces: &[Vec<Vector3D>]) -> Option<Vector3D> {
+    let face = cell.faces.iter().find(|f| f.node == Some((node, in_front)))?;
+    let open_direction = if in_front { -plane.vector } else { plane.vector };
+
+    let axis = ProjectionAxis::for_normal(plane.vector);
+    let surfaces: Vec<Vec<Vector2D>> = surfaces.iter().map(|s| s.iter().map(|p| axis.project(*p)).collect()).collect();
+
+    let n = PHANTOM_SAMPLE_SUBDIVISIONS;
+    let points = &face.points;
+    for i in 1..points.len().saturating_sub(1) {
+        let (a, b, c) = (points[0], points[i], points[i + 1]);
+        for u in 1..n {
+            for v in 1..(n - u) {
+                let (wu, wv) = (u as f64 / n as f64, v as f64 / n as f64);
+                let sample = a.scale(1.0 - wu - wv) + b.scale(wu) + c.scale(wv);
+                if bsp_point_in_solid(bsp, sample + open_direction.scale(PHANTOM_PROBE_DISTANCE)) {
+                    continue
+                }
+                let projected = axis.project(sample);
+                if !surfaces.iter().any(|s| point_in_convex_polygon(s, projected, BSP_PLANE_EPSILON)) {
+                    return Some(sample)
+                }
+            }
+        }
+    }
+
+    None
+}
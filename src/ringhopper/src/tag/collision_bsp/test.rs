@@ -0,0 +1,255 @@
+use primitives::primitive::Vector3D;
+use super::*;
+
+fn v(x: f64, y: f64, z: f64) -> Vector3D {
+    Vector3D { x, y, z }
+}
+
+/// Make an outward-facing box out of triangles.
+fn make_box(min: Vector3D, max: Vector3D) -> Vec<CollisionTriangle> {
+    let c = |x: bool, y: bool, z: bool| v(
+        if x { max.x } else { min.x },
+        if y { max.y } else { min.y },
+        if z { max.z } else { min.z }
+    );
+
+    let quads = [
+        [c(false, false, false), c(false, true, false), c(true, true, false), c(true, false, false)],
+        [c(false, false, true), c(true, false, true), c(true, true, true), c(false, true, true)],
+        [c(false, false, false), c(true, false, false), c(true, false, true), c(false, false, true)],
+        [c(false, true, false), c(false, true, true), c(true, true, true), c(true, true, false)],
+        [c(false, false, false), c(false, false, true), c(false, true, true), c(false, true, false)],
+        [c(true, false, false), c(true, true, false), c(true, true, true), c(true, false, true)]
+    ];
+
+    quads.iter().flat_map(|q| [
+        CollisionTriangle { vertices: [q[0], q[1], q[2]], ..Default::default() },
+        CollisionTriangle { vertices: [q[0], q[2], q[3]], ..Default::default() }
+    ]).collect()
+}
+
+fn check_grid(bsp: &ModelCollisionGeometryBSP, inside: impl Fn(Vector3D) -> bool) {
+    // Offset the grid so no sample lies exactly on a surface.
+    for x in -4..=12 {
+        for y in -4..=12 {
+            for z in -4..=12 {
+                let point = v(x as f64 * 0.25 + 0.0137, y as f64 * 0.25 + 0.0219, z as f64 * 0.25 + 0.0071);
+                assert_eq!(inside(point), bsp_point_in_solid(bsp, point), "wrong result at {point:?}");
+            }
+        }
+    }
+}
+
+fn check_edges(bsp: &ModelCollisionGeometryBSP) {
+    for edge in &bsp.edges.items {
+        assert_ne!(BSP_NULL, edge.right_surface, "closed meshes should have no open edges");
+        assert!((edge.forward_edge as usize) < bsp.edges.items.len());
+        assert!((edge.reverse_edge as usize) < bsp.edges.items.len());
+    }
+    for surface in &bsp.surfaces.items {
+        assert!((surface.first_edge as usize) < bsp.edges.items.len());
+    }
+}
+
+#[test]
+fn cube() {
+    let triangles = make_box(v(0.0, 0.0, 0.0), v(2.0, 2.0, 2.0));
+    let bsp = build_collision_bsp(&triangles).unwrap();
+    check_grid(&bsp, |p| (0.0..2.0).contains(&p.x) && (0.0..2.0).contains(&p.y) && (0.0..2.0).contains(&p.z));
+    check_edges(&bsp);
+
+    assert_eq!(6, bsp.planes.items.len());
+    assert_eq!(8, bsp.vertices.items.len());
+}
+
+#[test]
+fn concave() {
+    // L-shaped solid extruded along y, made of three unit squares in the xz plane.
+    let mut triangles = Vec::new();
+    let p = |x, y, z| v(x, y, z);
+    let outline = [
+        p(0.0, 0.0, 0.0), p(1.0, 0.0, 0.0), p(2.0, 0.0, 0.0), p(2.0, 0.0, 1.0),
+        p(1.0, 0.0, 1.0), p(1.0, 0.0, 2.0), p(0.0, 0.0, 2.0), p(0.0, 0.0, 1.0)
+    ];
+    let back = |a: Vector3D| v(a.x, 1.0, a.z);
+
+    // Side walls
+    for i in 0..outline.len() {
+        let (a, b) = (outline[i], outline[(i + 1) % outline.len()]);
+        triangles.push(CollisionTriangle { vertices: [a, back(b), b], ..Default::default() });
+        triangles.push(CollisionTriangle { vertices: [a, back(a), back(b)], ..Default::default() });
+    }
+
+    // Front (y = 0) and back (y = 1) caps
+    let caps = [
+        [p(0.0, 0.0, 0.0), p(1.0, 0.0, 0.0), p(1.0, 0.0, 1.0), p(0.0, 0.0, 1.0)],
+        [p(1.0, 0.0, 0.0), p(2.0, 0.0, 0.0), p(2.0, 0.0, 1.0), p(1.0, 0.0, 1.0)],
+        [p(0.0, 0.0, 1.0), p(1.0, 0.0, 1.0), p(1.0, 0.0, 2.0), p(0.0, 0.0, 2.0)]
+    ];
+    for q in caps {
+        triangles.push(CollisionTriangle { vertices: [q[0], q[1], q[2]], ..Default::default() });
+        triangles.push(CollisionTriangle { vertices: [q[0], q[2], q[3]], ..Default::default() });
+        triangles.push(CollisionTriangle { vertices: [back(q[0]), back(q[2]), back(q[1])], ..Default::default() });
+        triangles.push(CollisionTriangle { vertices: [back(q[0]), back(q[3]), back(q[2])], ..Default::default() });
+    }
+
+    let bsp = build_collision_bsp(&triangles).unwrap();
+    check_grid(&bsp, |p| {
+        let in_y = (0.0..1.0).contains(&p.y);
+        let in_bottom = (0.0..2.0).contains(&p.x) && (0.0..1.0).contains(&p.z);
+        let in_top = (0.0..1.0).contains(&p.x) && (1.0..2.0).contains(&p.z);
+        in_y && (in_bottom || in_top)
+    });
+    assert_eq!(Vec::<check::CollisionBSPIssue>::new(), check::check_collision_bsp(&bsp, 0));
+}
+
+#[test]
+fn open_mesh() {
+    let mut triangles = make_box(v(0.0, 0.0, 0.0), v(1.0, 1.0, 1.0));
+    triangles.pop();
+    assert!(build_collision_bsp(&triangles).is_err());
+}
+
+/// Make a two-sided square on the plane x = `x`, spanning `min`..`max` on the other axes.
+fn make_two_sided_square(x: f64, min: f64, max: f64) -> Vec<CollisionTriangle> {
+    let mut flags = ModelCollisionGeometryBSPSurfaceFlags::default();
+    flags.two_sided = true;
+    let q = [v(x, min, min), v(x, max, min), v(x, max, max), v(x, min, max)];
+    vec![
+        CollisionTriangle { vertices: [q[0], q[1], q[2]], flags, ..Default::default() },
+        CollisionTriangle { vertices: [q[0], q[2], q[3]], flags, ..Default::default() }
+    ]
+}
+
+#[test]
+fn two_sided() {
+    let mut triangles = make_box(v(0.0, 0.0, 0.0), v(1.0, 1.0, 1.0));
+    triangles.extend(make_two_sided_square(2.0, -0.5, 2.5));
+    let bsp = build_collision_bsp(&triangles).unwrap();
+
+    // Only the box is solid; both sides of the two-sided surface are open.
+    check_grid(&bsp, |p| (0.0..1.0).contains(&p.x) && (0.0..1.0).contains(&p.y) && (0.0..1.0).contains(&p.z));
+
+    // Leaves on either side of it can touch it.
+    for x in [1.9, 2.1] {
+        let leaf = bsp_leaf_for_point(&bsp, v(x, 1.5, 1.5)).expect("should be in empty space");
+        assert!(bsp.leaves.items[leaf as usize].flags.contains_double_sided_surfaces, "leaf at x = {x} should touch the two-sided surface");
+    }
+
+    // The surface isn't duplicated with an opposite-facing copy.
+    let two_sided_area: f64 = (0..bsp.surfaces.items.len() as u32)
+        .filter(|s| bsp.surfaces.items[*s as usize].flags.two_sided)
+        .map(|s| polygon_area(&collision_surface_vertices(&bsp, s).unwrap()))
+        .sum();
+    assert!((two_sided_area - 9.0).abs() < 0.00001, "two-sided area is {two_sided_area}");
+
+    // Its edges are open, but that's fine for a two-sided surface.
+    assert!(bsp.edges.items.iter().any(|e| e.right_surface == BSP_NULL));
+    assert!(!check::check_collision_bsp(&bsp, 0).iter().any(|i| i.description.contains("is open")));
+
+    // Nothing is solid without one-sided surfaces.
+    let bsp = build_collision_bsp(&make_two_sided_square(2.0, -0.5, 2.5)).unwrap();
+    check_grid(&bsp, |_| false);
+}
+
+#[test]
+fn near_coplanar_planes() {
+    let mut triangles = make_box(v(0.0, 0.0, 0.0), v(2.0, 2.0, 2.0));
+    for triangle in &mut triangles {
+        for vertex in &mut triangle.vertices {
+            if vertex.x == 2.0 && vertex.y == 2.0 && vertex.z == 2.0 {
+                vertex.x += 0.00000001;
+            }
+        }
+    }
+    let bsp = build_collision_bsp(&triangles).unwrap();
+    assert_eq!(6, bsp.planes.items.len());
+}
+
+#[test]
+fn leaf_lookup() {
+    let triangles = make_box(v(0.0, 0.0, 0.0), v(1.0, 1.0, 1.0));
+    let bsp = build_collision_bsp(&triangles).unwrap();
+
+    let leaf = bsp_leaf_for_point(&bsp, v(1.5, 0.5, 0.5)).expect("should be in empty space");
+    let leaf = &bsp.leaves.items[leaf as usize];
+
+    // The point is only in front of the +x face, so it can only touch that surface.
+    assert_eq!(1, leaf.bsp2d_reference_count);
+    let reference = &bsp.bsp2d_references.items[leaf.first_bsp2d_reference as usize];
+    let plane = bsp.planes.items[reference.plane as usize].plane;
+    assert_eq!(v(1.0, 0.0, 0.0), plane.vector);
+
+    let axis = ProjectionAxis::for_normal(plane.vector);
+    let surface = bsp2d_surface_for_point(&bsp, reference.bsp2d_node, axis.project(v(1.0, 0.25, 0.75))).expect("should hit a surface");
+    assert_eq!(reference.plane, bsp.surfaces.items[surface as usize].plane);
+    assert_eq!(None, bsp2d_surface_for_point(&bsp, reference.bsp2d_node, axis.project(v(1.0, 1.5, 0.5))));
+}
+
+#[test]
+fn trace_ray() {
+    let triangles = make_box(v(0.0, 0.0, 0.0), v(2.0, 2.0, 2.0));
+    let bsp = build_collision_bsp(&triangles).unwrap();
+
+    // Hit the -x face from outside.
+    let hit = bsp_trace_ray(&bsp, v(-1.0, 1.0, 0.5), v(3.0, 1.0, 0.5)).unwrap();
+    assert!((hit.fraction - 0.25).abs() < 0.00001);
+    assert!(hit.point.distance_squared(&v(0.0, 1.0, 0.5)) < 0.00001);
+    assert!(hit.normal.distance_squared(&v(-1.0, 0.0, 0.0)) < 0.00001);
+
+    let surface = hit.surface.expect("should find the surface that was hit");
+    let points = collision_surface_vertices(&bsp, surface).unwrap();
+    assert!(points.iter().all(|p| p.x.abs() < 0.00001));
+
+    // Miss entirely, or start inside.
+    assert!(bsp_trace_ray(&bsp, v(-1.0, 3.0, 0.5), v(3.0, 3.0, 0.5)).is_none());
+    let inside = bsp_trace_ray(&bsp, v(1.0, 1.0, 1.0), v(3.0, 1.0, 1.0)).unwrap();
+    assert_eq!(0.0, inside.fraction);
+    assert_eq!(None, inside.plane);
+}
+
+#[test]
+fn check_clean() {
+    let bsp = build_collision_bsp(&make_box(v(0.0, 0.0, 0.0), v(2.0, 2.0, 2.0))).unwrap();
+    assert_eq!(Vec::<check::CollisionBSPIssue>::new(), check::check_collision_bsp(&bsp, 0));
+}
+
+#[test]
+fn check_corrupt() {
+    let bsp = build_collision_bsp(&make_box(v(0.0, 0.0, 0.0), v(2.0, 2.0, 2.0))).unwrap();
+
+    let mut open = bsp.clone();
+    open.edges.items[0].right_surface = BSP_NULL;
+    let issues = check::check_collision_bsp(&open, 0);
+    let open_edge = issues.iter().find(|i| i.description.starts_with("edge #0 is open")).expect("open edge should be reported");
+    assert_eq!(check::CollisionBSPIssueSeverity::Error, open_edge.severity);
+    assert!(open_edge.position.is_some());
+
+    // An open edge isn't a bounds error, so the rest of the geometry is still checked.
+    assert!(issues.iter().any(|i| i.description.contains("does not border surface")));
+
+    let mut out_of_bounds = bsp.clone();
+    out_of_bounds.bsp3d_nodes.items[0].plane = 1000;
+    out_of_bounds.surfaces.items[0].material = Some(0);
+    let issues = check::check_collision_bsp(&out_of_bounds, 0);
+    assert_eq!(2, issues.len());
+    assert!(issues.iter().all(|i| i.severity == check::CollisionBSPIssueSeverity::Error));
+}
+
+#[test]
+fn check_phantom() {
+    let mut bsp = build_collision_bsp(&make_box(v(0.0, 0.0, 0.0), v(2.0, 2.0, 2.0))).unwrap();
+
+    // Fill an empty leaf outside the box with solid space.
+    let (index, node) = bsp.bsp3d_nodes.items.iter_mut().enumerate().find(|(_, n)| n.front_child & BSP_LEAF_FLAG != 0 && n.front_child != BSP_NULL).unwrap();
+    node.front_child = BSP_NULL;
+    let plane = bsp.planes.items[(node.plane & !BSP_LEAF_FLAG) as usize].plane;
+
+    let issues = check::check_collision_bsp(&bsp, 0);
+    assert!(!issues.is_empty());
+    for issue in issues {
+        assert_eq!(check::CollisionBSPIssueSeverity::Warning, issue.severity);
+        assert_eq!(format!("possible phantom BSP on 3D node #{index}"), issue.description);
+        assert!(issue.position.unwrap().distance_from_plane(&plane).abs() < 0.001);
+    }
+}
@@ -2,15 +2,18 @@ use std::collections::{HashMap, HashSet};
 use std::num::NonZeroUsize;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
-use definitions::{get_all_referenceable_tag_groups_for_group, Scenario, ScenarioType};
+use definitions::{get_all_referenceable_tag_groups_for_group, Scenario};
 use primitives::dynamic::DynamicTagData;
 use primitives::engine::Engine;
 use primitives::error::{Error, RinghopperResult};
 use primitives::primitive::{HALO_PATH_SEPARATOR_STR, TagGroup, TagPath, TagReference};
 use primitives::tag::{for_each_field, for_each_field_mut};
+use crate::tag::scenario::convert_type::required_tags_for_scenario_type;
 use crate::tag::result::TagResult;
 use crate::tag::tree::{CachingTagTree, CachingTagTreeWriteStrategy, iterate_through_all_tags, TagFilter, TagTree, VirtualTagsDirectory};
 
+pub mod index;
+
 /// Iterate through each [`TagReference`] of a block.
 pub fn for_each_dependency<P: FnMut(&TagReference)>(data: &dyn DynamicTagData, mut predicate: P) {
     for_each_field(data, |_, b| {
@@ -42,6 +45,17 @@ pub fn get_tag_dependencies_for_block(data: &dyn DynamicTagData) -> HashSet<TagP
     result
 }
 
+/// Get all dependencies from a list of tag references, such as from [`TagTree::open_tag_references`].
+pub fn get_tag_dependencies_for_references(references: Vec<TagReference>) -> HashSet<TagPath> {
+    references
+        .into_iter()
+        .filter_map(|r| match r {
+            TagReference::Set(p) => Some(p),
+            TagReference::Null(_) => None
+        })
+        .collect()
+}
+
 pub fn refactor_groups_for_block<T: TagTree>(data: &mut dyn DynamicTagData, from: TagGroup, to: TagGroup, tag_tree: &T, access_read_only_fields: bool) -> bool {
     let mut anything_done = false;
 
@@ -93,12 +107,12 @@ pub fn recursively_get_dependencies_for_tag<T: TagTree>(tag: &TagPath, tag_tree:
         }
 
         // Try to open it now, converting the TagNotFound error to one that has the tag that depends on it
-        let tag = match tag_tree.open_tag_shared(&p.0) {
+        let references = match tag_tree.open_tag_references(&p.0) {
             Ok(n) => Ok(n),
             Err(Error::TagNotFound(a)) if p.1.is_some() => Err(Error::BrokenDependency(p.1.unwrap(), a)),
             Err(e) => Err(e)
         };
-        let tag = match tag {
+        let references = match references {
             Ok(n) => n,
             Err(e) => {
                 if allow_broken {
@@ -108,7 +122,7 @@ pub fn recursively_get_dependencies_for_tag<T: TagTree>(tag: &TagPath, tag_tree:
             }
         };
 
-        let dependencies = get_tag_dependencies_for_block(tag.lock().unwrap().as_ref().as_dynamic());
+        let dependencies = get_tag_dependencies_for_references(references);
         for i in &dependencies {
             if result.contains_key(i) || pending.iter().find(|p| &p.0 == i).is_some() {
                 continue
@@ -122,6 +136,8 @@ pub fn recursively_get_dependencies_for_tag<T: TagTree>(tag: &TagPath, tag_tree:
 }
 
 /// Get all tags that depend on a tag.
+///
+/// Returns `Err` if any tag that could reference `tag` cannot be read, since the result would otherwise silently miss it.
 pub fn get_reverse_dependencies_for_tag<T: TagTree>(tag: &TagPath, tag_tree: &T) -> RinghopperResult<HashSet<TagPath>> {
     let mut result = HashSet::new();
 
@@ -130,9 +146,8 @@ pub fn get_reverse_dependencies_for_tag<T: TagTree>(tag: &TagPath, tag_tree: &T)
             continue
         }
 
-        let t = tag_tree.open_tag_shared(&i).unwrap();
-        let t = t.lock().unwrap();
-        if get_tag_dependencies_for_block(t.as_ref().as_dynamic()).contains(tag) {
+        let references = tag_tree.open_tag_references(&i)?;
+        if references.iter().any(|r| r.path() == Some(tag)) {
             result.insert(i);
         }
     }
@@ -149,18 +164,14 @@ pub fn recursively_get_dependencies_for_map<T: TagTree>(scenario: &TagPath, tag_
     let lock = mutex.lock().unwrap();
     let scenario_tag: &Scenario = lock.as_any().downcast_ref().unwrap();
 
-    let other: &'static [&'static str] = match scenario_tag._type {
-        ScenarioType::Singleplayer => engine.required_tags.singleplayer,
-        ScenarioType::Multiplayer => engine.required_tags.multiplayer,
-        ScenarioType::UserInterface => engine.required_tags.user_interface,
-    };
+    let scenario_type = scenario_tag._type;
 
     // prevent deadlocking for caching tag trees
     drop(lock);
 
     all_dependencies.extend(recursively_get_dependencies_for_tag(scenario, tag_tree, false)?.into_values().flatten());
 
-    for i in [engine.required_tags.all, other].into_iter().flatten() {
+    for i in required_tags_for_scenario_type(engine, scenario_type) {
         let tag = TagPath::from_path(i).unwrap();
         all_dependencies.extend(recursively_get_dependencies_for_tag(&tag, tag_tree, false)?.into_values().flatten());
         all_dependencies.insert(tag);
@@ -174,6 +185,10 @@ pub enum ReplaceType {
     All
 }
 
+/// Rename all tags matching `from` to `to`, and fix all references to them.
+///
+/// If `dir` has a dependency index, it must be up to date, as only tags it lists as depending on renamed tags (and tags it
+/// could not index) are fixed.
 pub fn refactor_paths_for_tag_tree(
     from: &str,
     to: &str,
@@ -297,8 +312,20 @@ pub fn refactor_paths_for_tag_tree(
         }
     }
 
+    // With an index, only tags that depend on a renamed tag have to be opened
+    let tags_to_fix = dir.get_dependency_index().map(|index| {
+        tags_to_rename
+            .iter()
+            .filter_map(|(old_path, _)| index.get_reverse_dependencies(old_path))
+            .flatten()
+            .chain(index.get_unindexed_tags().keys())
+            .map(|depender| tags_to_rename.iter().find(|r| &r.0 == depender).map(|r| &r.1).unwrap_or(depender).to_owned())
+            .collect::<HashSet<TagPath>>()
+    });
+
     struct Context {
         tags_to_rename: Vec<(TagPath, TagPath)>,
+        tags_to_fix: Option<HashSet<TagPath>>,
         results: Mutex<HashMap<TagPath, TagResult>>,
         cached_dir: CachingTagTree<VirtualTagsDirectory>,
         all_tags_post_rename: Vec<TagPath>,
@@ -308,6 +335,7 @@ pub fn refactor_paths_for_tag_tree(
 
     let context = Arc::new(Context {
         tags_to_rename,
+        tags_to_fix,
         results: Mutex::new(results),
         cached_dir: CachingTagTree::new(dir.to_owned(), CachingTagTreeWriteStrategy::Manual),
         all_tags_post_rename,
@@ -324,6 +352,10 @@ pub fn refactor_paths_for_tag_tree(
             }
 
             let tag = &context.all_tags_post_rename[tag];
+            if context.tags_to_fix.as_ref().is_some_and(|t| !t.contains(tag)) {
+                continue
+            }
+
             let referenceable = get_all_referenceable_tag_groups_for_group(tag.group());
             let mut allowed = false;
             for i in &context.tags_to_rename {
@@ -0,0 +1,255 @@
+use std::collections::{HashMap, HashSet};
+use std::collections::hash_map::Entry;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use serde_json::{json, Map, Value};
+use primitives::error::{Error, RinghopperResult};
+use primitives::primitive::TagPath;
+use crate::tag::dependency::get_tag_dependencies_for_references;
+use crate::tag::tree::{TagTree, VirtualTagsDirectory};
+
+const DEPENDENCY_INDEX_VERSION: u64 = 1;
+
+/// Persistent index of the dependencies and reverse dependencies of every tag in a [`VirtualTagsDirectory`].
+///
+/// Dependencies are stored by the CRC64 hash of the tag file. When updating, tags whose modification time and size
+/// are unchanged are not read again, and tags whose hash is unchanged (e.g. moved or copied tags) are not scanned again.
+///
+/// Tags that could not be scanned are left unindexed, so anything looking up their dependencies has to read them itself.
+#[derive(Default)]
+pub struct DependencyIndex {
+    directories: Vec<PathBuf>,
+    tags: HashMap<TagPath, IndexedTag>,
+    unindexed: HashMap<TagPath, Error>,
+    dependencies: HashMap<u64, HashSet<TagPath>>,
+    reverse_dependencies: HashMap<TagPath, HashSet<TagPath>>
+}
+
+#[derive(Clone)]
+struct IndexedTag {
+    stamp: FileStamp,
+    hash: u64
+}
+
+#[derive(Clone, PartialEq)]
+struct FileStamp {
+    directory: usize,
+    modified: Duration,
+    size: u64
+}
+
+impl FileStamp {
+    fn read(directory: usize, file_path: &Path) -> RinghopperResult<Self> {
+        let metadata = std::fs::metadata(file_path).map_err(|e| Error::FailedToReadFile(file_path.to_owned(), e))?;
+        let modified = metadata
+            .modified()
+            .map_err(|e| Error::FailedToReadFile(file_path.to_owned(), e))?
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default();
+        Ok(Self { directory, modified, size: metadata.len() })
+    }
+}
+
+impl DependencyIndex {
+    /// Create an empty index for the tags directory.
+    ///
+    /// Call [`DependencyIndex::update`] to fill it.
+    pub fn new(tags: &VirtualTagsDirectory) -> Self {
+        Self {
+            directories: all_directories(tags),
+            ..Default::default()
+        }
+    }
+
+    /// Load the index from a file.
+    ///
+    /// If the file does not exist, is not a valid index, or was made for different tags directories, an empty index
+    /// is returned instead, as with [`DependencyIndex::new`].
+    ///
+    /// Returns `Err` if the file exists but cannot be read.
+    pub fn load<P: AsRef<Path>>(path: P, tags: &VirtualTagsDirectory) -> RinghopperResult<Self> {
+        let path = path.as_ref();
+        if !path.is_file() {
+            return Ok(Self::new(tags))
+        }
+
+        let file = std::fs::read(path).map_err(|e| Error::FailedToReadFile(path.to_owned(), e))?;
+        let index = serde_json::from_slice::<Value>(&file)
+            .ok()
+            .and_then(|v| Self::from_json(&v))
+            .filter(|i| i.directories == all_directories(tags));
+
+        Ok(index.unwrap_or_else(|| Self::new(tags)))
+    }
+
+    /// Save the index to a file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> RinghopperResult<()> {
+        let path = path.as_ref();
+        let json = serde_json::to_vec(&self.to_json()).unwrap();
+        std::fs::write(path, json).map_err(|e| Error::FailedToWriteFile(path.to_owned(), e))
+    }
+
+    /// Update the index with all changes made to the tags directory since it was last updated.
+    ///
+    /// Tags that cannot be read or scanned are left unindexed rather than failing the whole update; see
+    /// [`DependencyIndex::get_unindexed_tags`]. They are tried again on the next update.
+    ///
+    /// Returns the number of tags that had to be scanned.
+    pub fn update(&mut self, tags: &VirtualTagsDirectory) -> usize {
+        if self.directories != all_directories(tags) {
+            *self = Self::new(tags);
+        }
+
+        let all_tags = tags.get_all_tags_with_filter(None);
+        let mut updated_tags = HashMap::with_capacity(all_tags.len());
+        let mut unindexed = HashMap::new();
+        let mut scanned = 0;
+
+        for path in all_tags {
+            match self.update_tag(tags, &path, &mut scanned) {
+                Ok(indexed) => { updated_tags.insert(path, indexed); },
+                Err(e) => { unindexed.insert(path, e); }
+            }
+        }
+
+        self.tags = updated_tags;
+        self.unindexed = unindexed;
+
+        // Forget dependencies of tags that were deleted or changed
+        let hashes_in_use: HashSet<u64> = self.tags.values().map(|t| t.hash).collect();
+        self.dependencies.retain(|hash, _| hashes_in_use.contains(hash));
+
+        self.build_reverse_dependencies();
+        scanned
+    }
+
+    fn update_tag(&mut self, tags: &VirtualTagsDirectory, path: &TagPath, scanned: &mut usize) -> RinghopperResult<IndexedTag> {
+        let (directory, file_path) = tags.path_for_tag(path).ok_or_else(|| Error::TagNotFound(path.clone()))?;
+        let stamp = FileStamp::read(directory, &file_path)?;
+
+        if let Some(indexed) = self.tags.get(path).filter(|t| t.stamp == stamp) {
+            return Ok(indexed.clone())
+        }
+
+        let file = std::fs::read(&file_path).map_err(|e| Error::FailedToReadFile(file_path, e))?;
+        let hash = VirtualTagsDirectory::hash_file(&file);
+        if let Entry::Vacant(entry) = self.dependencies.entry(hash) {
+            let references = ringhopper_structs::scan_any_tag_references_from_file_buffer(&file)
+                .map_err(|e| Error::FailedToReadTag(path.clone(), vec![e]))?;
+            entry.insert(get_tag_dependencies_for_references(references));
+            *scanned += 1;
+        }
+        Ok(IndexedTag { stamp, hash })
+    }
+
+    /// Get all tags that could not be indexed on the last update, along with why.
+    ///
+    /// Their dependencies are unknown, so they may depend on any tag.
+    pub fn get_unindexed_tags(&self) -> &HashMap<TagPath, Error> {
+        &self.unindexed
+    }
+
+    /// Get all tags the tag depends on.
+    ///
+    /// Returns `None` if the tag is not indexed.
+    pub fn get_dependencies(&self, tag: &TagPath) -> Option<&HashSet<TagPath>> {
+        self.tags.get(tag).map(|t| &self.dependencies[&t.hash])
+    }
+
+    /// Get all tags that depend on the tag.
+    ///
+    /// Returns `None` if no indexed tag depends on it. Unindexed tags are not included; see
+    /// [`DependencyIndex::get_unindexed_tags`].
+    pub fn get_reverse_dependencies(&self, tag: &TagPath) -> Option<&HashSet<TagPath>> {
+        self.reverse_dependencies.get(tag)
+    }
+
+    /// Get all tags the tag depends on if the indexed file is the same one located at `directory` and `file_path`.
+    pub(crate) fn get_current_dependencies(&self, tag: &TagPath, directory: usize, file_path: &Path) -> Option<&HashSet<TagPath>> {
+        let indexed = self.tags.get(tag)?;
+        let stamp = FileStamp::read(directory, file_path).ok()?;
+        (indexed.stamp == stamp).then(|| &self.dependencies[&indexed.hash])
+    }
+
+    fn build_reverse_dependencies(&mut self) {
+        self.reverse_dependencies.clear();
+        for (path, indexed) in &self.tags {
+            for dependency in &self.dependencies[&indexed.hash] {
+                self.reverse_dependencies.entry(dependency.clone()).or_default().insert(path.clone());
+            }
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        let tags: Map<String, Value> = self.tags.iter().map(|(path, indexed)| {
+            (path.to_internal_path(), json!({
+                "directory": indexed.stamp.directory,
+                "modified": [indexed.stamp.modified.as_secs(), indexed.stamp.modified.subsec_nanos()],
+                "size": indexed.stamp.size,
+                "hash": format!("{:016X}", indexed.hash)
+            }))
+        }).collect();
+
+        let dependencies: Map<String, Value> = self.dependencies.iter().map(|(hash, dependencies)| {
+            let mut dependencies: Vec<String> = dependencies.iter().map(TagPath::to_internal_path).collect();
+            dependencies.sort();
+            (format!("{hash:016X}"), json!(dependencies))
+        }).collect();
+
+        json!({
+            "version": DEPENDENCY_INDEX_VERSION,
+            "directories": self.directories.iter().map(|d| d.to_string_lossy()).collect::<Vec<_>>(),
+            "tags": tags,
+            "dependencies": dependencies
+        })
+    }
+
+    fn from_json(value: &Value) -> Option<Self> {
+        if value.get("version")?.as_u64()? != DEPENDENCY_INDEX_VERSION {
+            return None
+        }
+
+        let parse_hash = |hash: &str| u64::from_str_radix(hash, 16).ok();
+
+        let mut index = Self::default();
+        for directory in value.get("directories")?.as_array()? {
+            index.directories.push(PathBuf::from(directory.as_str()?));
+        }
+
+        for (hash, dependencies) in value.get("dependencies")?.as_object()? {
+            let dependencies = dependencies
+                .as_array()?
+                .iter()
+                .map(|d| TagPath::from_path(d.as_str()?).ok())
+                .collect::<Option<HashSet<TagPath>>>()?;
+            index.dependencies.insert(parse_hash(hash)?, dependencies);
+        }
+
+        for (path, indexed) in value.get("tags")?.as_object()? {
+            let modified = indexed.get("modified")?.as_array()?;
+            let [secs, nanos] = modified.as_slice() else { return None };
+            let indexed = IndexedTag {
+                stamp: FileStamp {
+                    directory: indexed.get("directory")?.as_u64()? as usize,
+                    modified: Duration::new(secs.as_u64()?, nanos.as_u64()?.try_into().ok()?),
+                    size: indexed.get("size")?.as_u64()?
+                },
+                hash: parse_hash(indexed.get("hash")?.as_str()?)?
+            };
+            if !index.dependencies.contains_key(&indexed.hash) {
+                return None
+            }
+            index.tags.insert(TagPath::from_path(path).ok()?, indexed);
+        }
+
+        index.build_reverse_dependencies();
+        Some(index)
+    }
+}
+
+fn all_directories(tags: &VirtualTagsDirectory) -> Vec<PathBuf> {
+    (0..).map_while(|i| tags.get_directory(i)).map(Path::to_path_buf).collect()
+}
+
+#[cfg(test)]
+mod test;
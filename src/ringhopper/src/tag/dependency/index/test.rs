@@ -0,0 +1,95 @@
+use std::collections::HashSet;
+use std::path::PathBuf;
+use definitions::{Model, ModelShaderReference, Weapon};
+use primitives::primitive::{TagPath, TagReference};
+use crate::tag::dependency::index::DependencyIndex;
+use crate::tag::tree::VirtualTagsDirectory;
+
+struct TemporaryDirectory(PathBuf);
+
+impl Drop for TemporaryDirectory {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+fn path(path: &str) -> TagPath {
+    TagPath::from_path(path).unwrap()
+}
+
+fn model_with_shader(shader: &str) -> Model {
+    let mut model = Model::default();
+    model.shaders.items = vec![
+        ModelShaderReference {
+            shader: TagReference::Set(path(shader)),
+            ..Default::default()
+        }
+    ];
+    model
+}
+
+#[test]
+fn update_incrementally() {
+    let directory = TemporaryDirectory(std::env::temp_dir().join(format!("ringhopper-dependency-index-{}", std::process::id())));
+    std::fs::create_dir_all(&directory.0).unwrap();
+    let tags = VirtualTagsDirectory::new(&[&directory.0], None).unwrap();
+
+    let mut weapon = Weapon::default();
+    weapon.item.object.model = TagReference::Set(path("weapons\\gun\\gun.model"));
+    weapon.first_person_model = TagReference::Set(path("weapons\\gun\\fp\\fp.model"));
+    tags.write_tag_to_directory(&path("weapons\\gun\\gun.weapon"), &weapon, 0).unwrap();
+
+    // Both models have the same contents, so only one of them has to be scanned.
+    let model = model_with_shader("weapons\\gun\\shaders\\gun.shader_model");
+    tags.write_tag_to_directory(&path("weapons\\gun\\gun.model"), &model, 0).unwrap();
+    tags.write_tag_to_directory(&path("weapons\\gun\\fp\\fp.model"), &model, 0).unwrap();
+
+    let mut index = DependencyIndex::new(&tags);
+    assert_eq!(2, index.update(&tags));
+    assert_eq!(Some(&HashSet::from([path("weapons\\gun\\gun.model"), path("weapons\\gun\\fp\\fp.model")])), index.get_dependencies(&path("weapons\\gun\\gun.weapon")));
+    assert_eq!(Some(&HashSet::from([path("weapons\\gun\\gun.weapon")])), index.get_reverse_dependencies(&path("weapons\\gun\\fp\\fp.model")));
+    assert_eq!(Some(&HashSet::from([path("weapons\\gun\\gun.model"), path("weapons\\gun\\fp\\fp.model")])), index.get_reverse_dependencies(&path("weapons\\gun\\shaders\\gun.shader_model")));
+    assert_eq!(0, index.update(&tags));
+
+    // Nothing changed, so a saved index does not need to scan anything.
+    let index_path = directory.0.join("dependencies.json");
+    index.save(&index_path).unwrap();
+    let mut index = DependencyIndex::load(&index_path, &tags).unwrap();
+    assert_eq!(0, index.update(&tags));
+    assert_eq!(Some(&HashSet::from([path("weapons\\gun\\gun.weapon")])), index.get_reverse_dependencies(&path("weapons\\gun\\gun.model")));
+
+    // Only the changed tag is scanned.
+    let model = model_with_shader("weapons\\gun\\shaders\\fp.shader_model");
+    tags.write_tag_to_directory(&path("weapons\\gun\\fp\\fp.model"), &model, 0).unwrap();
+    assert_eq!(1, index.update(&tags));
+    assert_eq!(Some(&HashSet::from([path("weapons\\gun\\gun.model")])), index.get_reverse_dependencies(&path("weapons\\gun\\shaders\\gun.shader_model")));
+    assert_eq!(Some(&HashSet::from([path("weapons\\gun\\fp\\fp.model")])), index.get_reverse_dependencies(&path("weapons\\gun\\shaders\\fp.shader_model")));
+
+    // Deleted tags are removed.
+    std::fs::remove_file(tags.path_for_tag(&path("weapons\\gun\\gun.weapon")).unwrap().1).unwrap();
+    assert_eq!(0, index.update(&tags));
+    assert_eq!(None, index.get_dependencies(&path("weapons\\gun\\gun.weapon")));
+    assert_eq!(None, index.get_reverse_dependencies(&path("weapons\\gun\\gun.model")));
+
+    // Tags that can't be scanned are left out without failing the whole update.
+    let broken = path("weapons\\gun\\broken.weapon");
+    let broken_file = directory.0.join(broken.to_native_path());
+    std::fs::write(&broken_file, b"not a tag").unwrap();
+    assert_eq!(0, index.update(&tags));
+    assert_eq!(None, index.get_dependencies(&broken));
+    assert!(index.get_unindexed_tags().contains_key(&broken));
+    assert_eq!(Some(&HashSet::from([path("weapons\\gun\\fp\\fp.model")])), index.get_reverse_dependencies(&path("weapons\\gun\\shaders\\fp.shader_model")));
+
+    // ...and are indexed once they are fixed.
+    tags.write_tag_to_directory(&broken, &Weapon::default(), 0).unwrap();
+    assert_eq!(1, index.update(&tags));
+    assert!(index.get_unindexed_tags().is_empty());
+    assert_eq!(Some(&HashSet::new()), index.get_dependencies(&broken));
+}
+
+#[test]
+fn load_missing_index() {
+    let tags = VirtualTagsDirectory::new(&[std::env::temp_dir()], None).unwrap();
+    let index = DependencyIndex::load(std::env::temp_dir().join("ringhopper-dependency-index-does-not-exist.json"), &tags).unwrap();
+    assert_eq!(None, index.get_dependencies(&path("weapons\\gun\\gun.weapon")));
+}
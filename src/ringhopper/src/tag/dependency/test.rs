@@ -1,8 +1,9 @@
 use std::collections::HashMap;
 use definitions::*;
+use primitives::error::Error;
 use primitives::primitive::{TagPath, TagReference};
-use primitives::tag::PrimaryTagStructDyn;
-use crate::tag::dependency::{get_tag_dependencies_for_block, recursively_get_dependencies_for_tag};
+use primitives::tag::{ParseStrictness, PrimaryTagStructDyn, TagFile};
+use crate::tag::dependency::{get_tag_dependencies_for_block, get_tag_dependencies_for_references, recursively_get_dependencies_for_tag};
 use crate::tag::tree::TagTree;
 use crate::tag::tree::MockTagTree;
 
@@ -60,3 +61,39 @@ fn dependencies_recursive() {
 
     assert_eq!(8, dependencies.len());
 }
+
+#[test]
+fn scan_references_matches_full_read() {
+    let test_tree = generate_test_tag_tree();
+    for tag in test_tree.items.values() {
+        let tag = tag.as_ref().unwrap();
+        let file = tag.to_tag_file().unwrap();
+        let references = scan_any_tag_references_from_file_buffer(&file).unwrap();
+        assert_eq!(get_tag_dependencies_for_block(tag.as_dynamic()), get_tag_dependencies_for_references(references.clone()));
+
+        // Scanning references does not check the checksum, but reading the tag does
+        let mut corrupted = file.clone();
+        corrupted[0x28] ^= 1;
+        assert_eq!(references, scan_any_tag_references_from_file_buffer(&corrupted).unwrap());
+        assert!(matches!(read_any_tag_from_file_buffer(&corrupted, ParseStrictness::Strict), Err(Error::ChecksumMismatch)));
+    }
+}
+
+#[test]
+fn read_single_field() {
+    let test_tree = generate_test_tag_tree();
+    let weapon = test_tree.open_tag_copy(&TagPath::from_path("weapons\\myweapon\\myweapons.weapon").unwrap()).unwrap();
+    let file = weapon.to_tag_file().unwrap();
+
+    let first_person_model = TagFile::read_field_from_file_buffer::<Weapon, _, _>(&file, "first_person_model", ParseStrictness::Strict, |f| {
+        f.as_any().downcast_ref::<TagReference>().cloned()
+    }).unwrap();
+    assert_eq!(Some(Some(TagReference::Set(TagPath::from_path("weapons\\myweapon\\fp\\fp.model").unwrap()))), first_person_model);
+
+    let trigger_count = TagFile::read_field_from_file_buffer::<Weapon, _, _>(&file, "triggers", ParseStrictness::Strict, |f| f.as_array().unwrap().len()).unwrap();
+    assert_eq!(Some(1), trigger_count);
+
+    assert_eq!(None, TagFile::read_field_from_file_buffer::<Weapon, _, _>(&file, "not a field", ParseStrictness::Strict, |_| ()).unwrap());
+}
+
+// Run with `cargo test --release -- --ignored --nocapture scan_references_timing` to see the timings.
@@ -0,0 +1,275 @@
+use std::fmt::Write;
+use primitives::dynamic::{DynamicReflexive, DynamicTagData, DynamicTagDataType, TagFieldMetadata};
+use primitives::error::{Error, RinghopperResult};
+use primitives::primitive::parse_range;
+use serde_json::Value;
+use crate::tag::json::{dynamic_to_value, is_bitfield, value_to_dynamic, write_value};
+
+fn validate(data: &dyn DynamicTagData, matcher: &str) -> RinghopperResult<()> {
+    data.validate_matcher(matcher).map_err(|e| Error::Other(format!("Invalid matcher `{matcher}`: {e}")))
+}
+
+fn to_json_text(value: &Value) -> String {
+    let mut output = String::new();
+    write_value(&mut output, value, 0);
+    output
+}
+
+fn describe(data: &dyn DynamicTagData) -> String {
+    match data.data_type() {
+        DynamicTagDataType::Block if is_bitfield(data) => format!("= {} (flags: {})", to_json_text(&dynamic_to_value(data)), data.fields().join(", ")),
+        DynamicTagDataType::Block => "block".to_owned(),
+        DynamicTagDataType::Reflexive => format!("reflexive with {} entries", data.as_array().unwrap().len()),
+        DynamicTagDataType::Array => format!("array of {}", data.as_array().unwrap().len()),
+        DynamicTagDataType::Enum => {
+            let value = data.as_enum().unwrap();
+            format!("= \"{}\" (one of: {})", value.get_enum_string_value(), value.get_possible_enum_string_values().join(", "))
+        },
+        _ => format!("= {}", to_json_text(&dynamic_to_value(data)))
+    }
+}
+
+/// Describe everything matched by `matcher`, one line per field or entry.
+///
+/// Blocks list their fields, reflexives and arrays list their entries, and everything else is described on its own.
+pub fn list_fields(data: &dyn DynamicTagData, matcher: &str) -> RinghopperResult<Vec<String>> {
+    validate(data, matcher)?;
+
+    let mut lines = Vec::new();
+    data.foreach(matcher, |matched| {
+        let matched = matched.unwrap();
+        match matched.data_type() {
+            DynamicTagDataType::Block if !is_bitfield(matched) => {
+                for field in matched.fields() {
+                    lines.push(format!("{field} {}", describe(matched.get_field(field).unwrap())));
+                }
+            },
+            DynamicTagDataType::Reflexive | DynamicTagDataType::Array => {
+                let array = matched.as_array().unwrap();
+                for i in 0..array.len() {
+                    lines.push(format!("[{i}] {}", describe(array.get_at_index(i).unwrap())));
+                }
+            },
+            _ => lines.push(describe(matched))
+        }
+        true
+    });
+    Ok(lines)
+}
+
+/// Get everything matched by `matcher` as JSON text.
+pub fn get_fields(data: &dyn DynamicTagData, matcher: &str) -> RinghopperResult<Vec<String>> {
+    validate(data, matcher)?;
+
+    let mut values = Vec::new();
+    data.foreach(matcher, |matched| {
+        values.push(to_json_text(&dynamic_to_value(matched.unwrap())));
+        true
+    });
+    Ok(values)
+}
+
+fn check_writable(metadata: Option<TagFieldMetadata>, path: &str) -> RinghopperResult<()> {
+    match metadata {
+        Some(m) if m.cache_only => Err(Error::Other(format!("`{path}` is only used in cache files and cannot be set"))),
+        Some(m) if m.read_only => Err(Error::Other(format!("`{path}` is read-only and cannot be set"))),
+        _ => Ok(())
+    }
+}
+
+/// Check that none of the fields named in `matcher` are read-only or cache-only.
+fn check_matcher_writable(data: &dyn DynamicTagData, matcher: &str) -> RinghopperResult<()> {
+    let check_name = |name_start: usize, name_end: usize| -> RinghopperResult<()> {
+        let name = &matcher[name_start..name_end];
+        if name.is_empty() {
+            return Ok(())
+        }
+        let parent = &matcher[..name_start];
+        let mut result = Ok(());
+        data.foreach(parent.strip_suffix('.').unwrap_or(parent), |parent| {
+            result = check_writable(parent.ok().and_then(|p| p.get_metadata_for_field(name)), &matcher[..name_end]);
+            result.is_ok()
+        });
+        result
+    };
+
+    let mut name_start = 0;
+    let mut in_brackets = false;
+    for (index, character) in matcher.char_indices().chain(std::iter::once((matcher.len(), '.'))) {
+        if in_brackets {
+            if character == ']' {
+                in_brackets = false;
+                name_start = index + 1;
+            }
+            continue
+        }
+        match character {
+            '[' => {
+                check_name(name_start, index)?;
+                in_brackets = true;
+            },
+            '.' => {
+                check_name(name_start, index)?;
+                name_start = index + 1;
+            },
+            _ => ()
+        }
+    }
+    Ok(())
+}
+
+/// Check that `value` does not set any read-only or cache-only fields within `data`.
+fn check_value_writable(data: &mut dyn DynamicTagData, value: &Value, path: &mut String) -> RinghopperResult<()> {
+    match (data.data_type(), value) {
+        // Read-only flags can be listed as long as they aren't changed.
+        (DynamicTagDataType::Block, Value::Array(flags)) if is_bitfield(data) => {
+            for flag in data.fields() {
+                let set = flags.iter().any(|f| f.as_str() == Some(flag));
+                let current = data.get_field(flag).and_then(|f| f.as_any().downcast_ref::<bool>()).copied().unwrap_or(false);
+                if set != current {
+                    check_writable(data.get_metadata_for_field(flag), &format!("{path}.{flag}"))?;
+                }
+            }
+        },
+        (DynamicTagDataType::Block, Value::Object(object)) => {
+            for (field, field_value) in object {
+                let length = path.len();
+                write!(path, ".{field}").unwrap();
+                check_writable(data.get_metadata_for_field(field), path)?;
+                if let Some(field_data) = data.get_field_mut(field) {
+                    check_value_writable(field_data, field_value, path)?;
+                }
+                path.truncate(length);
+            }
+        },
+        (DynamicTagDataType::Reflexive | DynamicTagDataType::Array, Value::Array(items)) => {
+            let existing = data.as_array().unwrap().len();
+            for (index, item) in items.iter().enumerate() {
+                let length = path.len();
+                write!(path, "[{index}]").unwrap();
+                let result = if index < existing {
+                    check_value_writable(data.as_array_mut().unwrap().get_at_index_mut(index).unwrap(), item, path)
+                }
+                else if let Some(reflexive) = data.as_reflexive_mut() {
+                    // Check new entries against a temporary default entry.
+                    reflexive.insert_default(existing);
+                    let result = check_value_writable(data.as_array_mut().unwrap().get_at_index_mut(existing).unwrap(), item, path);
+                    data.as_reflexive_mut().unwrap().remove(existing);
+                    result
+                }
+                else {
+                    Ok(())
+                };
+                result?;
+                path.truncate(length);
+            }
+        },
+        _ => ()
+    }
+    Ok(())
+}
+
+/// Set everything matched by `matcher` to `value`, returning the number of fields set.
+///
+/// The value is parsed as JSON in the same format as [`tag_to_json`](crate::tag::json::tag_to_json). If it is not
+/// valid JSON, or if it is the wrong type and the field takes a string, it is used as a string as-is.
+///
+/// Read-only and cache-only fields cannot be set, whether they are matched directly or set as part of a larger value.
+pub fn set_fields(data: &mut dyn DynamicTagData, matcher: &str, value: &str) -> RinghopperResult<usize> {
+    validate(data, matcher)?;
+    check_matcher_writable(data, matcher)?;
+
+    let parsed = serde_json::from_str(value).unwrap_or_else(|_| Value::String(value.to_owned()));
+    let as_string = Value::String(value.to_owned());
+
+    let mut count = 0;
+    let mut result = Ok(());
+    data.foreach_mut(matcher, |matched| {
+        let matched = matched.unwrap();
+        let mut path = matcher.to_owned();
+        result = check_value_writable(matched, &parsed, &mut path);
+        if result.is_err() {
+            return false
+        }
+        result = value_to_dynamic(matched, &parsed, &mut path);
+        if result.is_err() && !parsed.is_string() {
+            let mut path = matcher.to_owned();
+            if value_to_dynamic(matched, &as_string, &mut path).is_ok() {
+                result = Ok(());
+            }
+        }
+        count += 1;
+        result.is_ok()
+    });
+    result.map(|_| count)
+}
+
+/// Split a matcher into the reflexive being matched and the `[...]` range after it, if any.
+fn split_range(matcher: &str) -> (&str, Option<&str>) {
+    if let Some(without_end) = matcher.strip_suffix(']') {
+        if let Some(start) = without_end.rfind('[') {
+            let reflexive = &matcher[..start];
+            return (reflexive.strip_suffix('.').unwrap_or(reflexive), Some(&without_end[start + 1..]))
+        }
+    }
+    (matcher, None)
+}
+
+fn foreach_reflexive<F: FnMut(&mut dyn DynamicReflexive) -> RinghopperResult<()>>(data: &mut dyn DynamicTagData, matcher: &str, mut function: F) -> RinghopperResult<usize> {
+    validate(data, matcher)?;
+
+    let mut count = 0;
+    let mut result = Ok(());
+    data.foreach_mut(matcher, |matched| {
+        result = match matched.unwrap().as_reflexive_mut() {
+            Some(reflexive) => function(reflexive),
+            None => Err(Error::Other(format!("`{matcher}` does not refer to a reflexive")))
+        };
+        count += 1;
+        result.is_ok()
+    });
+    result.map(|_| count)
+}
+
+/// Insert a default entry into every reflexive matched by `matcher`, returning the number of entries inserted.
+///
+/// The matcher can end with an index (e.g. `triggers[2]`) to insert at that index. Otherwise, the entry is appended.
+pub fn insert_entries(data: &mut dyn DynamicTagData, matcher: &str) -> RinghopperResult<usize> {
+    let (reflexive_matcher, index) = split_range(matcher);
+    let index: Option<usize> = index
+        .map(|i| i.parse().map_err(|_| Error::Other(format!("Invalid matcher `{matcher}`: can only insert at a single index"))))
+        .transpose()?;
+
+    foreach_reflexive(data, reflexive_matcher, |reflexive| {
+        let index = index.unwrap_or(reflexive.len());
+        if index > reflexive.len() {
+            return Err(Error::Other(format!("Cannot insert at index {index} of `{reflexive_matcher}`; it only has {} entries", reflexive.len())))
+        }
+        reflexive.insert_default(index);
+        Ok(())
+    })
+}
+
+/// Delete the entries matched by `matcher`, returning the number of entries deleted.
+///
+/// The matcher must end with the range of entries to delete (e.g. `triggers[1-e]`).
+pub fn delete_entries(data: &mut dyn DynamicTagData, matcher: &str) -> RinghopperResult<usize> {
+    let (reflexive_matcher, range) = split_range(matcher);
+    let range = range.ok_or_else(|| Error::Other(format!("Invalid matcher `{matcher}`: expected a range of entries to delete")))?;
+
+    let mut deleted = 0;
+    foreach_reflexive(data, reflexive_matcher, |reflexive| {
+        let ranges = parse_range(range, reflexive.len()).map_err(|e| Error::Other(format!("Invalid matcher `{matcher}`: {e}")))?;
+        for (start, end) in ranges.into_iter().rev() {
+            for i in (start..=end).rev() {
+                reflexive.remove(i);
+                deleted += 1;
+            }
+        }
+        Ok(())
+    })?;
+    Ok(deleted)
+}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,73 @@
+use definitions::{Bitmap, Weapon, WeaponSecondaryTriggerMode, WeaponTrigger};
+use super::{delete_entries, get_fields, insert_entries, list_fields, set_fields};
+
+fn weapon() -> Weapon {
+    let mut weapon = Weapon::default();
+    for rounds in [1, 2, 3] {
+        weapon.triggers.items.push(WeaponTrigger { rounds_per_shot: rounds, ..Default::default() });
+    }
+    weapon
+}
+
+#[test]
+fn get_and_set_fields() {
+    let mut weapon = weapon();
+
+    assert_eq!(3, set_fields(&mut weapon, "triggers[*].rounds_per_shot", "7").unwrap());
+    assert!(weapon.triggers.items.iter().all(|t| t.rounds_per_shot == 7));
+    assert_eq!(vec!["7", "7"], get_fields(&weapon, "triggers[0-1].rounds_per_shot").unwrap());
+
+    set_fields(&mut weapon, "secondary_trigger_mode", "slaved_to_primary").unwrap();
+    assert_eq!(WeaponSecondaryTriggerMode::SlavedToPrimary, weapon.secondary_trigger_mode);
+    set_fields(&mut weapon, "label", "1234").unwrap();
+    assert_eq!("1234", weapon.label.as_str());
+    set_fields(&mut weapon, "triggers[1].flags.analog_rate_of_fire", "true").unwrap();
+    assert!(weapon.triggers.items[1].flags.analog_rate_of_fire);
+    set_fields(&mut weapon, "triggers[2].flags", r#"["sticks_when_dropped"]"#).unwrap();
+    assert!(weapon.triggers.items[2].flags.sticks_when_dropped);
+
+    set_fields(&mut weapon, "triggers[0].maximum_rate_of_fire.upper", "2.5").unwrap();
+    assert_eq!(2.5, weapon.triggers.items[0].maximum_rate_of_fire.upper);
+
+    assert!(set_fields(&mut weapon, "triggers[*].rounds_per_shot", "7.5").is_err());
+    assert!(set_fields(&mut weapon, "secondary_trigger_mode", "sideways").is_err());
+    assert!(set_fields(&mut weapon, "triggers[5].rounds_per_shot", "1").is_err());
+    assert!(set_fields(&mut weapon, "not_a_field", "1").is_err());
+
+    // Cache-only and read-only fields can't be set, either directly or within another value.
+    let error = set_fields(&mut weapon, "triggers[0].illumination_recovery_rate", "1").unwrap_err().to_string();
+    assert_eq!("`triggers[0].illumination_recovery_rate` is only used in cache files and cannot be set", error);
+    assert!(set_fields(&mut weapon, "triggers[0]", r#"{"illumination_recovery_rate": 1}"#).is_err());
+    assert!(set_fields(&mut weapon, "triggers", r#"[{}, {}, {}, {"illumination_recovery_rate": 1}]"#).is_err());
+    assert_eq!(0.0, weapon.triggers.items[0].illumination_recovery_rate);
+
+    let mut bitmap = Bitmap::default();
+    assert!(set_fields(&mut bitmap, "flags.uniform_sprite_sequences", "true").is_err());
+    assert!(set_fields(&mut bitmap, "flags", r#"["uniform_sprite_sequences"]"#).is_err());
+    assert!(!bitmap.flags.uniform_sprite_sequences);
+    set_fields(&mut bitmap, "flags", r#"["disable_height_map_compression"]"#).unwrap();
+    assert!(bitmap.flags.disable_height_map_compression);
+
+    let listed = list_fields(&weapon, "triggers[1]").unwrap();
+    assert!(listed.contains(&"rounds_per_shot = 7".to_owned()));
+    assert!(listed.iter().any(|l| l.starts_with("flags = [\n    \"analog_rate_of_fire\"\n] (flags: ")));
+    assert_eq!(vec!["[0] block", "[1] block", "[2] block"], list_fields(&weapon, "triggers").unwrap());
+}
+
+#[test]
+fn insert_and_delete_entries() {
+    let mut weapon = weapon();
+
+    assert_eq!(1, insert_entries(&mut weapon, "triggers[1]").unwrap());
+    assert_eq!(1, insert_entries(&mut weapon, "triggers").unwrap());
+    let rounds: Vec<i16> = weapon.triggers.items.iter().map(|t| t.rounds_per_shot).collect();
+    assert_eq!(vec![1, 0, 2, 3, 0], rounds);
+    assert!(insert_entries(&mut weapon, "triggers[9]").is_err());
+    assert!(insert_entries(&mut weapon, "label").is_err());
+
+    assert_eq!(3, delete_entries(&mut weapon, "triggers[1,3-e]").unwrap());
+    let rounds: Vec<i16> = weapon.triggers.items.iter().map(|t| t.rounds_per_shot).collect();
+    assert_eq!(vec![1, 2], rounds);
+    assert!(delete_entries(&mut weapon, "triggers").is_err());
+    assert!(delete_entries(&mut weapon, "triggers[5]").is_err());
+}
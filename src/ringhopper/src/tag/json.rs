@@ -0,0 +1,487 @@
+//! Lossless conversion between tags and JSON.
+//!
+//! Tags are written as an object containing the tag `group` and the tag `data`. Blocks are objects keyed by field
+//! name, reflexives and arrays are arrays, enums are their string values, and bitfields are arrays of the names of the
+//! set flags.
+//!
+//! Floats are written with their shortest 32-bit representation, and non-finite floats are written as strings of their
+//! bits (e.g. `"0x7FC00000"`) so they survive the round trip. Tag references are written as internal tag paths, or as
+//! `{"group": ...}` if null. Data is written as a hexadecimal string.
+
+use std::fmt::Write;
+use definitions::new_any_tag;
+use primitives::dynamic::{DynamicTagData, DynamicTagDataType, SimplePrimitiveType};
+use primitives::error::{Error, RinghopperResult};
+use primitives::primitive::*;
+use primitives::tag::PrimaryTagStructDyn;
+use serde_json::{Map, Number, Value};
+
+/// Convert the tag to JSON text.
+pub fn tag_to_json(tag: &dyn PrimaryTagStructDyn) -> String {
+    let mut root = Map::new();
+    root.insert("group".to_owned(), Value::String(tag.group().as_str().to_owned()));
+    root.insert("data".to_owned(), dynamic_to_value(tag.as_dynamic()));
+
+    let mut output = String::new();
+    write_value(&mut output, &Value::Object(root), 0);
+    output.push('\n');
+    output
+}
+
+/// Convert JSON text written by [`tag_to_json`] back into a tag.
+///
+/// Fields missing from the JSON are left zeroed. Unknown fields are an error.
+pub fn tag_from_json(json: &str) -> RinghopperResult<Box<dyn PrimaryTagStructDyn>> {
+    let root: Value = serde_json::from_str(json).map_err(|e| Error::Other(format!("Failed to parse JSON: {e}")))?;
+    let root = root.as_object().ok_or_else(|| Error::Other("Expected the JSON root to be an object".to_owned()))?;
+
+    let group = root.get("group")
+        .and_then(|g| g.as_str())
+        .ok_or_else(|| Error::Other("Expected a `group` string in the JSON root".to_owned()))?;
+    let group = TagGroup::from_str(group).map_err(|_| Error::Other(format!("`{group}` is not a valid tag group")))?;
+    let data = root.get("data").ok_or_else(|| Error::Other("Expected a `data` object in the JSON root".to_owned()))?;
+
+    let mut tag = new_any_tag(group)?;
+    let mut path = "data".to_owned();
+    value_to_dynamic(tag.as_mut_dynamic(), data, &mut path)?;
+    Ok(tag)
+}
+
+pub(crate) fn is_bitfield(data: &dyn DynamicTagData) -> bool {
+    let fields = data.fields();
+    !fields.is_empty() && fields.iter().all(|f| {
+        data.get_field(f).unwrap().data_type() == DynamicTagDataType::SimplePrimitive(SimplePrimitiveType::Bool)
+    })
+}
+
+fn float_to_value(value: f64) -> Value {
+    let value = value as f32;
+    match Number::from_f64(value as f64) {
+        Some(n) if value.is_finite() => Value::Number(n),
+        _ => Value::String(format!("0x{:08X}", value.to_bits()))
+    }
+}
+
+fn bytes_to_value(bytes: &[u8]) -> Value {
+    let mut string = String::with_capacity(bytes.len() * 2);
+    for b in bytes {
+        write!(string, "{b:02x}").unwrap();
+    }
+    Value::String(string)
+}
+
+macro_rules! object {
+    ($($name:expr => $value:expr),*) => {{
+        let mut map = Map::new();
+        $(map.insert($name.to_owned(), $value);)*
+        Value::Object(map)
+    }};
+}
+
+fn vector3d_to_value(vector: &Vector3D) -> Value {
+    object!("x" => float_to_value(vector.x), "y" => float_to_value(vector.y), "z" => float_to_value(vector.z))
+}
+
+pub(crate) fn dynamic_to_value(data: &dyn DynamicTagData) -> Value {
+    match data.data_type() {
+        DynamicTagDataType::Block if is_bitfield(data) => {
+            Value::Array(data.fields()
+                .iter()
+                .filter(|f| *data.get_field(f).unwrap().as_any().downcast_ref::<bool>().unwrap())
+                .map(|f| Value::String((*f).to_owned()))
+                .collect())
+        },
+        DynamicTagDataType::Block => {
+            let mut map = Map::new();
+            for field in data.fields() {
+                map.insert((*field).to_owned(), dynamic_to_value(data.get_field(field).unwrap()));
+            }
+            Value::Object(map)
+        },
+        DynamicTagDataType::Reflexive | DynamicTagDataType::Array => {
+            let array = data.as_array().unwrap();
+            Value::Array((0..array.len()).map(|i| dynamic_to_value(array.get_at_index(i).unwrap())).collect())
+        },
+        DynamicTagDataType::Enum => Value::String(data.as_enum().unwrap().get_enum_string_value().to_owned()),
+        DynamicTagDataType::TagReference => match data.as_any().downcast_ref::<TagReference>().unwrap() {
+            TagReference::Set(path) => Value::String(path.to_internal_path()),
+            TagReference::Null(group) => object!("group" => Value::String(group.as_str().to_owned()))
+        },
+        DynamicTagDataType::Data => bytes_to_value(&data.as_any().downcast_ref::<Data>().unwrap().bytes),
+        DynamicTagDataType::FileData => bytes_to_value(&data.as_any().downcast_ref::<FileData>().unwrap().bytes),
+        DynamicTagDataType::BSPVertexData => bytes_to_value(&data.as_any().downcast_ref::<BSPVertexData>().unwrap().bytes),
+        DynamicTagDataType::UTF16String => {
+            let string = data.as_any().downcast_ref::<UTF16String>().unwrap();
+            match string.get_string() {
+                Ok(s) if UTF16String::from_str(&s) == *string => Value::String(s.to_string()),
+                _ => object!("utf16" => bytes_to_value(string.get_utf16_bytes()))
+            }
+        },
+        DynamicTagDataType::SimplePrimitive(primitive_type) => {
+            let any = data.as_any();
+            macro_rules! get {
+                ($t:ty) => { any.downcast_ref::<$t>().unwrap() };
+            }
+            match primitive_type {
+                SimplePrimitiveType::Bool => Value::Bool(*get!(bool)),
+                SimplePrimitiveType::String32 => Value::String(get!(String32).as_str().to_owned()),
+                SimplePrimitiveType::I8 => Value::from(*get!(i8)),
+                SimplePrimitiveType::U8 => Value::from(*get!(u8)),
+                SimplePrimitiveType::I16 => Value::from(*get!(i16)),
+                SimplePrimitiveType::U16 => Value::from(*get!(u16)),
+                SimplePrimitiveType::I32 => Value::from(*get!(i32)),
+                SimplePrimitiveType::U32 => Value::from(*get!(u32)),
+                SimplePrimitiveType::Size => Value::from(*get!(usize)),
+                SimplePrimitiveType::Float => float_to_value(*get!(f64)),
+                SimplePrimitiveType::Angle => float_to_value(get!(Angle).angle as f64),
+                SimplePrimitiveType::Vector2D => {
+                    let v = get!(Vector2D);
+                    object!("x" => float_to_value(v.x), "y" => float_to_value(v.y))
+                },
+                SimplePrimitiveType::Vector3D => vector3d_to_value(get!(Vector3D)),
+                SimplePrimitiveType::Plane2D => {
+                    let p = get!(Plane2D);
+                    object!("x" => float_to_value(p.vector.x), "y" => float_to_value(p.vector.y), "d" => float_to_value(p.d))
+                },
+                SimplePrimitiveType::Plane3D => {
+                    let p = get!(Plane3D);
+                    object!("x" => float_to_value(p.vector.x), "y" => float_to_value(p.vector.y), "z" => float_to_value(p.vector.z), "d" => float_to_value(p.d))
+                },
+                SimplePrimitiveType::Euler2D => {
+                    let e = get!(Euler2D);
+                    object!("yaw" => float_to_value(e.yaw.angle as f64), "pitch" => float_to_value(e.pitch.angle as f64))
+                },
+                SimplePrimitiveType::Euler3D => {
+                    let e = get!(Euler3D);
+                    object!("yaw" => float_to_value(e.yaw.angle as f64), "pitch" => float_to_value(e.pitch.angle as f64), "roll" => float_to_value(e.roll.angle as f64))
+                },
+                SimplePrimitiveType::Quaternion => {
+                    let q = get!(Quaternion);
+                    object!("x" => float_to_value(q.x), "y" => float_to_value(q.y), "z" => float_to_value(q.z), "w" => float_to_value(q.w))
+                },
+                SimplePrimitiveType::Matrix3x3 => Value::Array(get!(Matrix3x3).vectors.iter().map(vector3d_to_value).collect()),
+                SimplePrimitiveType::Vector2DInt => {
+                    let v = get!(Vector2DInt);
+                    object!("x" => Value::from(v.x), "y" => Value::from(v.y))
+                },
+                SimplePrimitiveType::Rectangle => {
+                    let r = get!(Rectangle);
+                    object!("top" => Value::from(r.top), "left" => Value::from(r.left), "bottom" => Value::from(r.bottom), "right" => Value::from(r.right))
+                },
+                SimplePrimitiveType::CompressedVector3D => Value::from(get!(CompressedVector3D).data),
+                SimplePrimitiveType::CompressedVector2D => Value::from(get!(CompressedVector2D).data),
+                SimplePrimitiveType::CompressedFloat => Value::from(get!(CompressedFloat).data),
+                SimplePrimitiveType::ColorRGBFloat => {
+                    let c = get!(ColorRGBFloat);
+                    object!("red" => float_to_value(c.red), "green" => float_to_value(c.green), "blue" => float_to_value(c.blue))
+                },
+                SimplePrimitiveType::ColorARGBFloat => {
+                    let c = get!(ColorARGBFloat);
+                    object!("alpha" => float_to_value(c.alpha), "red" => float_to_value(c.red), "green" => float_to_value(c.green), "blue" => float_to_value(c.blue))
+                },
+                SimplePrimitiveType::ColorARGBInt | SimplePrimitiveType::ColorARGBIntBytes => {
+                    let c = match primitive_type {
+                        SimplePrimitiveType::ColorARGBInt => ColorARGBIntBytes::from(*get!(ColorARGBInt)),
+                        _ => *get!(ColorARGBIntBytes)
+                    };
+                    object!("alpha" => Value::from(c.alpha), "red" => Value::from(c.red), "green" => Value::from(c.green), "blue" => Value::from(c.blue))
+                },
+                SimplePrimitiveType::Index => get!(Index).map(Value::from).unwrap_or(Value::Null),
+                SimplePrimitiveType::ID => {
+                    let id = get!(ID);
+                    if id.is_null() { Value::Null } else { Value::from(id.as_u32()) }
+                },
+                SimplePrimitiveType::TagGroup => match get!(TagGroup) {
+                    TagGroup::_Unset => Value::Null,
+                    group => Value::String(group.as_str().to_owned())
+                },
+                SimplePrimitiveType::Address => Value::from(get!(Address).address),
+                SimplePrimitiveType::ScenarioScriptNodeValue => Value::from(get!(ScenarioScriptNodeValue).data),
+            }
+        }
+    }
+}
+
+fn expected<T>(path: &str, what: &str, value: &Value) -> RinghopperResult<T> {
+    Err(Error::Other(format!("{path}: expected {what}, got {value}")))
+}
+
+fn value_to_float(value: &Value, path: &str) -> RinghopperResult<f64> {
+    match value {
+        Value::Number(n) => Ok(n.as_f64().unwrap() as f32 as f64),
+        Value::String(s) => match s.strip_prefix("0x").and_then(|bits| u32::from_str_radix(bits, 16).ok()) {
+            Some(bits) => Ok(f32::from_bits(bits) as f64),
+            None => expected(path, "a float", value)
+        },
+        _ => expected(path, "a float", value)
+    }
+}
+
+fn value_to_int<T: TryFrom<i64> + TryFrom<u64>>(value: &Value, path: &str) -> RinghopperResult<T> {
+    let converted = match value {
+        Value::Number(n) => n.as_u64()
+            .and_then(|n| T::try_from(n).ok())
+            .or_else(|| n.as_i64().and_then(|n| T::try_from(n).ok())),
+        _ => None
+    };
+    converted.map_or_else(|| expected(path, &format!("an integer in the range of {}", std::any::type_name::<T>()), value), Ok)
+}
+
+fn value_to_bytes(value: &Value, path: &str) -> RinghopperResult<Vec<u8>> {
+    let string = match value.as_str() {
+        Some(s) if s.len() % 2 == 0 => s,
+        _ => return expected(path, "a hexadecimal string", value)
+    };
+    (0..string.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&string[i..i + 2], 16))
+        .collect::<Result<Vec<u8>, _>>()
+        .or_else(|_| expected(path, "a hexadecimal string", value))
+}
+
+fn get_object_field<'a>(value: &'a Value, field: &str, path: &str) -> RinghopperResult<&'a Value> {
+    value.get(field).map_or_else(|| expected(path, &format!("an object with `{field}`"), value), Ok)
+}
+
+fn object_float(value: &Value, field: &str, path: &str) -> RinghopperResult<f64> {
+    value_to_float(get_object_field(value, field, path)?, &format!("{path}.{field}"))
+}
+
+fn object_int<T: TryFrom<i64> + TryFrom<u64>>(value: &Value, field: &str, path: &str) -> RinghopperResult<T> {
+    value_to_int(get_object_field(value, field, path)?, &format!("{path}.{field}"))
+}
+
+fn object_angle(value: &Value, field: &str, path: &str) -> RinghopperResult<Angle> {
+    object_float(value, field, path).map(|angle| Angle { angle: angle as f32 })
+}
+
+fn value_to_vector3d(value: &Value, path: &str) -> RinghopperResult<Vector3D> {
+    Ok(Vector3D { x: object_float(value, "x", path)?, y: object_float(value, "y", path)?, z: object_float(value, "z", path)? })
+}
+
+pub(crate) fn value_to_dynamic(data: &mut dyn DynamicTagData, value: &Value, path: &mut String) -> RinghopperResult<()> {
+    match data.data_type() {
+        DynamicTagDataType::Block if value.is_array() && is_bitfield(data) => {
+            for field in data.fields() {
+                *data.get_field_mut(field).unwrap().as_any_mut().downcast_mut::<bool>().unwrap() = false;
+            }
+            for flag in value.as_array().unwrap() {
+                let name = flag.as_str().map_or_else(|| expected(path, "an array of flag names", value), Ok)?;
+                let flag = data.get_field_mut(name).ok_or_else(|| Error::Other(format!("{path}: no such flag `{name}`")))?;
+                *flag.as_any_mut().downcast_mut::<bool>().unwrap() = true;
+            }
+        },
+        DynamicTagDataType::Block => {
+            let object = value.as_object().map_or_else(|| expected(path, "an object", value), Ok)?;
+            for (field, field_value) in object {
+                let field_data = data.get_field_mut(field).ok_or_else(|| Error::Other(format!("{path}: no such field `{field}`")))?;
+                let length = path.len();
+                write!(path, ".{field}").unwrap();
+                value_to_dynamic(field_data, field_value, path)?;
+                path.truncate(length);
+            }
+        },
+        DynamicTagDataType::Reflexive | DynamicTagDataType::Array => {
+            let items = value.as_array().map_or_else(|| expected(path, "an array", value), Ok)?;
+            if data.data_type() == DynamicTagDataType::Reflexive {
+                let reflexive = data.as_reflexive_mut().unwrap();
+                while reflexive.len() < items.len() {
+                    reflexive.insert_default(reflexive.len());
+                }
+            }
+            let array = data.as_array_mut().unwrap();
+            if array.len() != items.len() {
+                return Err(Error::Other(format!("{path}: expected {} item(s), got {}", array.len(), items.len())))
+            }
+            for (index, item) in items.iter().enumerate() {
+                let length = path.len();
+                write!(path, "[{index}]").unwrap();
+                value_to_dynamic(array.get_at_index_mut(index).unwrap(), item, path)?;
+                path.truncate(length);
+            }
+        },
+        DynamicTagDataType::Enum => {
+            let string = value.as_str().map_or_else(|| expected(path, "a string", value), Ok)?;
+            let enum_value = data.as_enum_mut().unwrap();
+            if enum_value.set_enum_string_value(string).is_err() {
+                return Err(Error::Other(format!("{path}: `{string}` is not one of: {}", enum_value.get_possible_enum_string_values().join(", "))))
+            }
+        },
+        DynamicTagDataType::TagReference => {
+            let reference = data.as_any_mut().downcast_mut::<TagReference>().unwrap();
+            *reference = match value {
+                Value::String(s) => TagReference::Set(TagPath::from_path(s).map_err(|_| Error::Other(format!("{path}: `{s}` is not a valid tag path")))?),
+                Value::Object(o) => match o.get("group").and_then(|g| g.as_str()).map(TagGroup::from_str) {
+                    Some(Ok(group)) => TagReference::Null(group),
+                    _ => return expected(path, "a null reference with a valid `group`", value)
+                },
+                _ => return expected(path, "a tag path", value)
+            };
+        },
+        DynamicTagDataType::Data => data.as_any_mut().downcast_mut::<Data>().unwrap().bytes = value_to_bytes(value, path)?,
+        DynamicTagDataType::FileData => data.as_any_mut().downcast_mut::<FileData>().unwrap().bytes = value_to_bytes(value, path)?,
+        DynamicTagDataType::BSPVertexData => data.as_any_mut().downcast_mut::<BSPVertexData>().unwrap().bytes = value_to_bytes(value, path)?,
+        DynamicTagDataType::UTF16String => {
+            let string = data.as_any_mut().downcast_mut::<UTF16String>().unwrap();
+            *string = match value {
+                Value::String(s) => UTF16String::from_str(s),
+                Value::Object(_) => UTF16String::from_utf16_bytes(value_to_bytes(get_object_field(value, "utf16", path)?, path)?),
+                _ => return expected(path, "a string", value)
+            };
+        },
+        DynamicTagDataType::SimplePrimitive(primitive_type) => {
+            let path = path.as_str();
+            let any = data.as_any_mut();
+            macro_rules! set {
+                ($t:ty, $value:expr) => { *any.downcast_mut::<$t>().unwrap() = $value };
+            }
+            match primitive_type {
+                SimplePrimitiveType::Bool => set!(bool, value.as_bool().map_or_else(|| expected(path, "a boolean", value), Ok)?),
+                SimplePrimitiveType::String32 => {
+                    let string = value.as_str().map_or_else(|| expected(path, "a string", value), Ok)?;
+                    set!(String32, String32::from_str(string).map_err(|e| Error::Other(format!("{path}: {e}")))?)
+                },
+                SimplePrimitiveType::I8 => set!(i8, value_to_int(value, path)?),
+                SimplePrimitiveType::U8 => set!(u8, value_to_int(value, path)?),
+                SimplePrimitiveType::I16 => set!(i16, value_to_int(value, path)?),
+                SimplePrimitiveType::U16 => set!(u16, value_to_int(value, path)?),
+                SimplePrimitiveType::I32 => set!(i32, value_to_int(value, path)?),
+                SimplePrimitiveType::U32 => set!(u32, value_to_int(value, path)?),
+                SimplePrimitiveType::Size => set!(usize, value_to_int(value, path)?),
+                SimplePrimitiveType::Float => set!(f64, value_to_float(value, path)?),
+                SimplePrimitiveType::Angle => set!(Angle, Angle { angle: value_to_float(value, path)? as f32 }),
+                SimplePrimitiveType::Vector2D => set!(Vector2D, Vector2D { x: object_float(value, "x", path)?, y: object_float(value, "y", path)? }),
+                SimplePrimitiveType::Vector3D => set!(Vector3D, value_to_vector3d(value, path)?),
+                SimplePrimitiveType::Plane2D => set!(Plane2D, Plane2D {
+                    vector: Vector2D { x: object_float(value, "x", path)?, y: object_float(value, "y", path)? },
+                    d: object_float(value, "d", path)?
+                }),
+                SimplePrimitiveType::Plane3D => set!(Plane3D, Plane3D {
+                    vector: value_to_vector3d(value, path)?,
+                    d: object_float(value, "d", path)?
+                }),
+                SimplePrimitiveType::Euler2D => set!(Euler2D, Euler2D {
+                    yaw: object_angle(value, "yaw", path)?,
+                    pitch: object_angle(value, "pitch", path)?
+                }),
+                SimplePrimitiveType::Euler3D => set!(Euler3D, Euler3D {
+                    yaw: object_angle(value, "yaw", path)?,
+                    pitch: object_angle(value, "pitch", path)?,
+                    roll: object_angle(value, "roll", path)?
+                }),
+                SimplePrimitiveType::Quaternion => set!(Quaternion, Quaternion {
+                    x: object_float(value, "x", path)?,
+                    y: object_float(value, "y", path)?,
+                    z: object_float(value, "z", path)?,
+                    w: object_float(value, "w", path)?
+                }),
+                SimplePrimitiveType::Matrix3x3 => {
+                    let vectors = match value.as_array() {
+                        Some(v) if v.len() == 3 => v,
+                        _ => return expected(path, "an array of 3 vectors", value)
+                    };
+                    set!(Matrix3x3, Matrix3x3 { vectors: [
+                        value_to_vector3d(&vectors[0], &format!("{path}[0]"))?,
+                        value_to_vector3d(&vectors[1], &format!("{path}[1]"))?,
+                        value_to_vector3d(&vectors[2], &format!("{path}[2]"))?
+                    ]})
+                },
+                SimplePrimitiveType::Vector2DInt => set!(Vector2DInt, Vector2DInt { x: object_int(value, "x", path)?, y: object_int(value, "y", path)? }),
+                SimplePrimitiveType::Rectangle => set!(Rectangle, Rectangle {
+                    top: object_int(value, "top", path)?,
+                    left: object_int(value, "left", path)?,
+                    bottom: object_int(value, "bottom", path)?,
+                    right: object_int(value, "right", path)?
+                }),
+                SimplePrimitiveType::CompressedVector3D => set!(CompressedVector3D, CompressedVector3D { data: value_to_int(value, path)? }),
+                SimplePrimitiveType::CompressedVector2D => set!(CompressedVector2D, CompressedVector2D { data: value_to_int(value, path)? }),
+                SimplePrimitiveType::CompressedFloat => set!(CompressedFloat, CompressedFloat { data: value_to_int(value, path)? }),
+                SimplePrimitiveType::ColorRGBFloat => set!(ColorRGBFloat, ColorRGBFloat {
+                    red: object_float(value, "red", path)?,
+                    green: object_float(value, "green", path)?,
+                    blue: object_float(value, "blue", path)?
+                }),
+                SimplePrimitiveType::ColorARGBFloat => set!(ColorARGBFloat, ColorARGBFloat {
+                    alpha: object_float(value, "alpha", path)?,
+                    red: object_float(value, "red", path)?,
+                    green: object_float(value, "green", path)?,
+                    blue: object_float(value, "blue", path)?
+                }),
+                SimplePrimitiveType::ColorARGBInt | SimplePrimitiveType::ColorARGBIntBytes => {
+                    let color = ColorARGBIntBytes {
+                        alpha: object_int(value, "alpha", path)?,
+                        red: object_int(value, "red", path)?,
+                        green: object_int(value, "green", path)?,
+                        blue: object_int(value, "blue", path)?
+                    };
+                    match primitive_type {
+                        SimplePrimitiveType::ColorARGBInt => set!(ColorARGBInt, color.into()),
+                        _ => set!(ColorARGBIntBytes, color)
+                    }
+                },
+                SimplePrimitiveType::Index => set!(Index, if value.is_null() { None } else { Some(value_to_int(value, path)?) }),
+                SimplePrimitiveType::ID => set!(ID, if value.is_null() {
+                    ID::null()
+                }
+                else {
+                    ID::from_u32(value_to_int(value, path)?)
+                }),
+                SimplePrimitiveType::TagGroup => set!(TagGroup, match value {
+                    Value::Null => TagGroup::_Unset,
+                    Value::String(s) => TagGroup::from_str(s).map_err(|_| Error::Other(format!("{path}: `{s}` is not a valid tag group")))?,
+                    _ => return expected(path, "a tag group", value)
+                }),
+                SimplePrimitiveType::Address => set!(Address, Address { address: value_to_int(value, path)? }),
+                SimplePrimitiveType::ScenarioScriptNodeValue => set!(ScenarioScriptNodeValue, ScenarioScriptNodeValue { data: value_to_int(value, path)? }),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Write the value with four-space indentation, keeping floats in their shortest 32-bit form.
+pub(crate) fn write_value(output: &mut String, value: &Value, indent: usize) {
+    fn write_indent(output: &mut String, indent: usize) {
+        output.extend(std::iter::repeat_n(' ', indent * 4));
+    }
+
+    match value {
+        Value::Number(n) if n.is_f64() => {
+            let float = n.as_f64().unwrap();
+            if float as f32 as f64 == float {
+                write!(output, "{:?}", float as f32).unwrap();
+            }
+            else {
+                write!(output, "{n}").unwrap();
+            }
+        },
+        Value::Array(array) if array.is_empty() => output.push_str("[]"),
+        Value::Array(array) => {
+            output.push_str("[\n");
+            for (index, item) in array.iter().enumerate() {
+                write_indent(output, indent + 1);
+                write_value(output, item, indent + 1);
+                output.push_str(if index + 1 == array.len() { "\n" } else { ",\n" });
+            }
+            write_indent(output, indent);
+            output.push(']');
+        },
+        Value::Object(object) if object.is_empty() => output.push_str("{}"),
+        Value::Object(object) => {
+            output.push_str("{\n");
+            for (index, (key, item)) in object.iter().enumerate() {
+                write_indent(output, indent + 1);
+                output.push_str(&Value::String(key.to_owned()).to_string());
+                output.push_str(": ");
+                write_value(output, item, indent + 1);
+                output.push_str(if index + 1 == object.len() { "\n" } else { ",\n" });
+            }
+            write_indent(output, indent);
+            output.push('}');
+        },
+        other => output.push_str(&other.to_string())
+    }
+}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,77 @@
+use definitions::{Bitmap, BitmapData, BitmapDataFormat, BitmapType, Scenario, ScenarioScenery, ScenarioSceneryPalette, ScenarioType, UnicodeStringList, UnicodeStringListString};
+use primitives::primitive::{Angle, ID, String32, TagGroup, TagPath, TagReference, UTF16String, Vector2DInt, Vector3D};
+use primitives::tag::PrimaryTagStructDyn;
+use super::{tag_from_json, tag_to_json};
+
+fn assert_round_trip(tag: &dyn PrimaryTagStructDyn) -> String {
+    let json = tag_to_json(tag);
+    let back = tag_from_json(&json).unwrap();
+    assert_eq!(tag.group(), back.group());
+    assert_eq!(tag.to_tag_file().unwrap(), back.to_tag_file().unwrap(), "tag data changed after round trip:\n{json}");
+    assert_eq!(json, tag_to_json(back.as_ref()));
+    json
+}
+
+#[test]
+fn round_trip_default_tags() {
+    for group in [TagGroup::Bitmap, TagGroup::Scenario, TagGroup::ScenarioStructureBSP, TagGroup::Sound, TagGroup::Weapon, TagGroup::Biped] {
+        assert_round_trip(definitions::new_any_tag(group).unwrap().as_ref());
+    }
+}
+
+#[test]
+fn round_trip_tag_data() {
+    let mut bitmap = Bitmap::default();
+    bitmap._type = BitmapType::CubeMaps;
+    bitmap.flags.disable_height_map_compression = true;
+    bitmap.processing.bump_height = 0.1;
+    bitmap.processing.detail_fade_factor = f32::from_bits(0x7FC00123) as f64;
+    bitmap.processing.sharpen_amount = f64::INFINITY;
+    bitmap.processed_pixel_data.bytes = vec![0x00, 0x7F, 0xFF, 0x12];
+    bitmap.bitmap_data.items.push(BitmapData {
+        signature: TagGroup::Bitmap,
+        width: 64,
+        format: BitmapDataFormat::DXT5,
+        registration_point: Vector2DInt { x: -3, y: 5 },
+        bitmap_tag_id: ID::null(),
+        ..Default::default()
+    });
+    let json = assert_round_trip(&bitmap);
+    assert!(json.contains("\"bump_height\": 0.1\n"));
+    assert!(json.contains("\"detail_fade_factor\": \"0x7FC00123\","));
+    assert!(json.contains("\"processed_pixel_data\": \"007fff12\","));
+
+    let mut scenario = Scenario::default();
+    scenario._type = ScenarioType::Multiplayer;
+    scenario.scenery_palette.items.push(ScenarioSceneryPalette { name: TagReference::Set(TagPath::from_path("scenery\\rock\\rock.scenery").unwrap()), ..Default::default() });
+    scenario.scenery_palette.items.push(ScenarioSceneryPalette { name: TagReference::Null(TagGroup::Scenery), ..Default::default() });
+    let mut scenery = ScenarioScenery { _type: Some(0), name: None, ..Default::default() };
+    scenery.placement.position = Vector3D { x: 1.5, y: -2.25, z: 0.1f32 as f64 };
+    scenery.placement.rotation.yaw = Angle { angle: 1.25 };
+    scenery.placement.not_placed.automatically = true;
+    scenario.scenery.items.push(scenery);
+    scenario.object_names.items.push(definitions::ScenarioObjectName { name: String32::from_str("rock").unwrap(), ..Default::default() });
+    let json = assert_round_trip(&scenario);
+    assert!(json.contains("\"type\": \"multiplayer\""));
+    assert!(json.contains("\"name\": \"scenery\\\\rock\\\\rock.scenery\""));
+    assert!(json.contains("\"not_placed\": [\n"));
+
+    let mut strings = UnicodeStringList::default();
+    strings.strings.items.push(UnicodeStringListString { string: UTF16String::from_str("Hello\r\nworld") });
+    strings.strings.items.push(UnicodeStringListString { string: UTF16String::from_utf16_bytes(vec![0x00, 0xD8, 0x41]) });
+    let json = assert_round_trip(&strings);
+    assert!(json.contains("\"utf16\": \"00d841\""));
+}
+
+#[test]
+fn json_errors() {
+    assert!(tag_from_json("{}").is_err());
+    assert!(tag_from_json(r#"{"group": "spaceship", "data": {}}"#).is_err());
+    assert!(tag_from_json(r#"{"group": "scenario", "data": {"not_a_field": 1}}"#).is_err());
+    assert!(tag_from_json(r#"{"group": "scenario", "data": {"type": "campaign"}}"#).is_err());
+    assert!(tag_from_json(r#"{"group": "bitmap", "data": {"flags": ["not_a_flag"]}}"#).is_err());
+    assert!(tag_from_json(r#"{"group": "bitmap", "data": {"bitmap_data": [{"width": 70000}]}}"#).is_err());
+
+    let tag = tag_from_json(r#"{"group": "scenario", "data": {"type": "user_interface"}}"#).unwrap();
+    assert_eq!(ScenarioType::UserInterface, tag.get_ref::<Scenario>().unwrap()._type);
+}
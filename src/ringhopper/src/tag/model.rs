@@ -4,6 +4,8 @@ use primitives::error::{Error, RinghopperResult};
 use primitives::primitive::{Index, Reflexive, TagGroup, Vector2D};
 use primitives::tag::PrimaryTagStructDyn;
 
+pub mod jms;
+
 pub trait ModelFunctions {
     /// Convert into a model tag.
     ///
@@ -27,6 +29,9 @@ pub trait ModelFunctions {
     /// Get a mutable reference to the nodes of the model.
     fn nodes_mut(&mut self) -> &mut [ModelNode];
 
+    /// Get the node list checksum of the model.
+    fn node_list_checksum(&self) -> i32;
+
     /// Get the regions of the model.
     fn regions(&self) -> &[ModelRegion];
 
@@ -229,6 +234,9 @@ impl ModelFunctions for Model {
     fn shaders(&self) -> &[ModelShaderReference] {
         self.shaders.items.as_ref()
     }
+    fn node_list_checksum(&self) -> i32 {
+        self.node_list_checksum
+    }
     fn nodes(&self) -> &[ModelNode] {
         self.nodes.items.as_ref()
     }
@@ -285,6 +293,7 @@ impl ModelFunctions for GBXModel {
         debug_assert!(self.check_indices().is_ok());
 
         let uses_local_nodes = self.flags.parts_have_local_nodes;
+        let supports_compressed_vertices = self.nodes.len() <= MAX_NODES_FOR_COMPRESSED_VERTICES;
         let geometries = self.geometries.items.into_iter().map(|g| {
             if uses_local_nodes {
                 let parts = g.parts.items.into_iter().map(|p| {
@@ -292,11 +301,19 @@ impl ModelFunctions for GBXModel {
                     let mut part = p.model_geometry_part;
                     part.flags.zoner = false;
 
+                    // Compressed vertices also use local node indices, so decompress them if they're all we have.
+                    restore_missing_uncompressed_vertices(&mut part);
                     for v in &mut part.uncompressed_vertices {
                         v.node0_index = v.node0_index.map(|m| indices[m as usize] as u16);
                         v.node1_index = v.node1_index.map(|m| indices[m as usize] as u16);
                     }
 
+                    // ...and then recompress them with the remapped node indices.
+                    part.compressed_vertices.items.clear();
+                    if supports_compressed_vertices {
+                        restore_missing_compressed_vertices(&mut part);
+                    }
+
                     part
                 }).collect();
 
@@ -358,6 +375,9 @@ impl ModelFunctions for GBXModel {
         self.shaders.items.as_ref()
     }
 
+    fn node_list_checksum(&self) -> i32 {
+        self.node_list_checksum
+    }
     fn nodes(&self) -> &[ModelNode] {
         self.nodes.items.as_ref()
     }
@@ -567,7 +587,7 @@ fn check_indices_for_gbxpart(gbxmodel: &GBXModel, gbxpart: &GBXModelGeometryPart
             .ok_or_else(|| Error::InvalidTagData("corrupted model: invalid local node count".to_owned()))?;
 
         check_vertex_buffer(gbxpart.model_geometry_part.uncompressed_vertices.items.iter().map(ToOwned::to_owned), nodes, Some(local_nodes))?;
-        check_vertex_buffer(gbxpart.model_geometry_part.compressed_vertices.items.iter().map(decompress_model_vertex), nodes, None)?;
+        check_vertex_buffer(gbxpart.model_geometry_part.compressed_vertices.items.iter().map(decompress_model_vertex), nodes, Some(local_nodes))?;
     }
     else {
         check_indices_for_part(gbxmodel, &gbxpart.model_geometry_part)?;
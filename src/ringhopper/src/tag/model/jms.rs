@@ -0,0 +1,202 @@
+use definitions::{Model, ModelGeometryPart, ModelRegionPermutation, ModelVertexUncompressed, TriangleBufferType};
+use primitives::error::{Error, RinghopperResult};
+use primitives::primitive::{Index, TagGroup, Vector, Vector2D};
+use primitives::tag::PrimaryTagStructDyn;
+use crate::data::jms::*;
+use super::ModelFunctions;
+
+/// Names of each level of detail, from highest to lowest.
+pub const LOD_NAMES: [&str; 5] = ["superhigh", "high", "medium", "low", "superlow"];
+
+fn geometry_index_for_lod(permutation: &ModelRegionPermutation, lod: usize) -> Index {
+    match lod {
+        0 => permutation.super_high,
+        1 => permutation.high,
+        2 => permutation.medium,
+        3 => permutation.low,
+        4 => permutation.super_low,
+        _ => unreachable!()
+    }
+}
+
+/// Convert a triangle strip (or list) into a list of triangles.
+///
+/// Degenerate triangles, which are used to stitch strips together, are discarded.
+pub fn triangles_from_part(part: &ModelGeometryPart) -> Vec<[u16; 3]> {
+    let indices: Vec<u16> = part.triangle_data
+        .items
+        .iter()
+        .flat_map(|t| t.indices)
+        .map_while(|i| i)
+        .collect();
+
+    let mut triangles = Vec::with_capacity(indices.len());
+
+    match part.triangle_buffer_type {
+        TriangleBufferType::TriangleList => {
+            for t in indices.chunks_exact(3) {
+                triangles.push([t[0], t[1], t[2]]);
+            }
+        },
+        TriangleBufferType::TriangleStrip => {
+            for (i, t) in indices.windows(3).enumerate() {
+                let (a, b, c) = (t[0], t[1], t[2]);
+                if a == b || b == c || a == c {
+                    continue
+                }
+                triangles.push(if i % 2 == 0 { [a, b, c] } else { [b, a, c] });
+            }
+        }
+    }
+
+    triangles
+}
+
+/// Generate JMS files for each permutation and level of detail of a model or gbxmodel tag.
+///
+/// Each output is named `<permutation> <lod>`. Lower levels of detail are only output if they use different geometry
+/// than the next highest level of detail.
+///
+/// Compressed vertices are decompressed if uncompressed vertices are not present.
+pub fn extract_jms_from_model(tag: &dyn PrimaryTagStructDyn) -> RinghopperResult<Vec<(String, JMS)>> {
+    let mut model = match tag.group() {
+        TagGroup::Model => tag.get_ref::<Model>().unwrap().clone(),
+        TagGroup::GBXModel => {
+            let gbxmodel = tag.get_ref::<definitions::GBXModel>().unwrap();
+            gbxmodel.check_indices()?;
+            gbxmodel.clone().convert_to_model()
+        },
+        _ => return Err(Error::InvalidTagData(format!("{} is not a model", tag.group())))
+    };
+
+    model.check_indices()?;
+    model.fix_runtime_markers()?;
+    model.fix_uncompressed_vertices();
+
+    let mut permutation_names: Vec<&str> = Vec::new();
+    for region in &model.regions {
+        for permutation in &region.permutations {
+            let name = permutation.name.as_str();
+            if !permutation_names.contains(&name) {
+                permutation_names.push(name);
+            }
+        }
+    }
+
+    let mut output = Vec::new();
+    for permutation_name in permutation_names {
+        let mut previous_lod: Option<Vec<Index>> = None;
+        for (lod, lod_name) in LOD_NAMES.iter().enumerate() {
+            let geometries: Vec<Index> = model.regions
+                .items
+                .iter()
+                .map(|r| r.permutations.items.iter().find(|p| p.name.as_str() == permutation_name).and_then(|p| geometry_index_for_lod(p, lod)))
+                .collect();
+
+            if previous_lod.as_ref() == Some(&geometries) {
+                continue
+            }
+
+            output.push((format!("{permutation_name} {lod_name}"), build_jms(&model, permutation_name, lod)));
+            previous_lod = Some(geometries);
+        }
+    }
+
+    Ok(output)
+}
+
+fn build_jms(model: &Model, permutation_name: &str, lod: usize) -> JMS {
+    let mut jms = JMS {
+        node_list_checksum: model.node_list_checksum,
+        ..Default::default()
+    };
+
+    for node in &model.nodes {
+        jms.nodes.push(JMSNode {
+            name: node.name.to_string(),
+            first_child: node.first_child_node_index,
+            sibling_node: node.next_sibling_node_index,
+            rotation: node.default_rotation,
+            position: node.default_translation.scale(JMS_UNITS_PER_WORLD_UNIT)
+        });
+    }
+
+    for shader in &model.shaders {
+        let name = shader.shader.path().map(|p| p.base_name()).unwrap_or("default");
+        let name = match shader.permutation {
+            Some(n) if n != 0 => format!("{name}{n}"),
+            _ => name.to_owned()
+        };
+        jms.materials.push(JMSMaterial { name, tif_path: "<none>".to_owned() });
+    }
+
+    let u_scale = if model.base_map_u_scale == 0.0 { 1.0 } else { model.base_map_u_scale };
+    let v_scale = if model.base_map_v_scale == 0.0 { 1.0 } else { model.base_map_v_scale };
+
+    for region in &model.regions {
+        let Some(permutation) = region.permutations.items.iter().find(|p| p.name.as_str() == permutation_name) else {
+            continue
+        };
+
+        let region_index = jms.regions.len() as u16;
+        jms.regions.push(JMSRegion { name: region.name.to_string() });
+
+        for marker in &permutation.markers {
+            jms.markers.push(JMSMarker {
+                name: marker.name.to_string(),
+                region: Some(region_index),
+                node: marker.node_index.unwrap_or(0),
+                rotation: marker.rotation,
+                position: marker.translation.scale(JMS_UNITS_PER_WORLD_UNIT),
+                radius: 1.0
+            });
+        }
+
+        let Some(geometry) = geometry_index_for_lod(permutation, lod) else {
+            continue
+        };
+
+        for part in &model.geometries.items[geometry as usize].parts {
+            let vertex_offset = jms.vertices.len() as u32;
+            let shader = part.shader_index.unwrap_or(0);
+
+            for vertex in &part.uncompressed_vertices {
+                jms.vertices.push(convert_vertex(vertex, u_scale, v_scale));
+            }
+
+            let vertex_count = part.uncompressed_vertices.items.len();
+            for triangle in triangles_from_part(part) {
+                if triangle.iter().any(|i| *i as usize >= vertex_count) {
+                    continue
+                }
+                jms.triangles.push(JMSTriangle {
+                    region: region_index,
+                    shader,
+                    vertices: triangle.map(|i| i as u32 + vertex_offset)
+                });
+            }
+        }
+    }
+
+    jms
+}
+
+fn convert_vertex(vertex: &ModelVertexUncompressed, u_scale: f64, v_scale: f64) -> JMSVertex {
+    let node1 = if vertex.node1_weight > 0.0 { vertex.node1_index } else { None };
+    JMSVertex {
+        node0: vertex.node0_index.unwrap_or(0),
+        position: vertex.position.scale(JMS_UNITS_PER_WORLD_UNIT),
+        normal: vertex.normal,
+        node1,
+        node1_weight: if node1.is_some() { vertex.node1_weight } else { 0.0 },
+
+        // Texture coordinates are stored with the V axis flipped and divided by the base map scale.
+        texture_coordinates: Vector2D {
+            x: vertex.texture_coords.x * u_scale,
+            y: 1.0 - vertex.texture_coords.y * v_scale
+        }
+    }
+}
+
+#[cfg(test)]
+mod test;
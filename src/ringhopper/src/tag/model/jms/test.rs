@@ -0,0 +1,144 @@
+use definitions::{GBXModel, GBXModelGeometry, GBXModelGeometryPart, Model, ModelGeometry, ModelGeometryPart, ModelGeometryPartFlags, ModelNode, ModelRegion, ModelRegionPermutation, ModelRegionPermutationMarker, ModelShaderReference, ModelTriangleStripData, ModelVertexCompressed, ModelVertexUncompressed, TriangleBufferType};
+use primitives::primitive::{Reflexive, String32, TagGroup, TagPath, TagReference, Vector2D, Vector3D};
+use crate::data::jms::JMS;
+use super::extract_jms_from_model;
+
+fn name(name: &str) -> String32 {
+    String32::from_str(name).unwrap()
+}
+
+fn nodes() -> Vec<ModelNode> {
+    vec![
+        ModelNode { name: name("frame root"), first_child_node_index: Some(1), ..Default::default() },
+        ModelNode { name: name("frame gun"), next_sibling_node_index: Some(2), parent_node_index: Some(0), ..Default::default() },
+        ModelNode { name: name("frame trigger"), parent_node_index: Some(0), ..Default::default() }
+    ]
+}
+
+fn region() -> ModelRegion {
+    let permutation = ModelRegionPermutation {
+        name: name("base"),
+        super_low: Some(0),
+        low: Some(0),
+        medium: Some(0),
+        high: Some(0),
+        super_high: Some(0),
+        markers: Reflexive::new(vec![
+            ModelRegionPermutationMarker { name: name("muzzle"), node_index: Some(1), translation: Vector3D { x: 0.5, y: 0.0, z: 0.25 }, ..Default::default() }
+        ]),
+        ..Default::default()
+    };
+    ModelRegion { name: name("body"), permutations: Reflexive::new(vec![permutation]) }
+}
+
+fn strip(indices: &[u16]) -> Reflexive<ModelTriangleStripData> {
+    let mut indices = indices.iter().map(|i| Some(*i)).collect::<Vec<_>>();
+    indices.resize(indices.len().next_multiple_of(3), None);
+    Reflexive::new(indices.chunks(3).map(|i| ModelTriangleStripData { indices: [i[0], i[1], i[2]] }).collect())
+}
+
+fn vertex(x: f64, y: f64, node0: u16) -> ModelVertexUncompressed {
+    ModelVertexUncompressed {
+        position: Vector3D { x, y, z: 0.0 },
+        normal: Vector3D { x: 0.0, y: 0.0, z: 1.0 },
+        texture_coords: Vector2D { x, y },
+        node0_index: Some(node0),
+        node0_weight: 1.0,
+        ..Default::default()
+    }
+}
+
+fn shaders() -> Vec<ModelShaderReference> {
+    let shader = TagReference::Set(TagPath::new("weapons\\gun\\shaders\\metal", TagGroup::ShaderModel).unwrap());
+    vec![
+        ModelShaderReference { shader: shader.clone(), permutation: None },
+        ModelShaderReference { shader, permutation: Some(1) }
+    ]
+}
+
+#[test]
+fn extract_model() {
+    let mut model = Model::default();
+    model.node_list_checksum = 1234;
+    model.nodes.items = nodes();
+    model.regions.items = vec![region()];
+    model.shaders.items = shaders();
+    model.geometries.items = vec![ModelGeometry {
+        parts: Reflexive::new(vec![ModelGeometryPart {
+            shader_index: Some(1),
+            uncompressed_vertices: Reflexive::new(vec![vertex(0.0, 0.0, 0), vertex(1.0, 0.0, 1), vertex(0.0, 1.0, 1), vertex(1.0, 1.0, 2)]),
+
+            // The repeated index stitches a degenerate triangle which should be discarded.
+            triangle_data: strip(&[0, 1, 2, 3, 3]),
+            triangle_buffer_type: TriangleBufferType::TriangleStrip,
+            ..Default::default()
+        }]),
+        ..Default::default()
+    }];
+
+    let jms_files = extract_jms_from_model(&model).unwrap();
+
+    // Every level of detail uses the same geometry, so only the highest one is output.
+    assert_eq!(1, jms_files.len());
+    let (jms_name, jms) = &jms_files[0];
+    assert_eq!("base superhigh", jms_name);
+    assert_eq!(1234, jms.node_list_checksum);
+
+    // Strips are split into triangles, alternating the winding order.
+    let triangles: Vec<[u32; 3]> = jms.triangles.iter().map(|t| t.vertices).collect();
+    assert_eq!(vec![[0, 1, 2], [2, 1, 3]], triangles);
+    assert!(jms.triangles.iter().all(|t| t.shader == 1 && t.region == 0));
+
+    let materials: Vec<&str> = jms.materials.iter().map(|m| m.name.as_str()).collect();
+    assert_eq!(vec!["metal", "metal1"], materials);
+
+    assert_eq!(1, jms.markers.len());
+    assert_eq!("muzzle", jms.markers[0].name);
+    assert_eq!(Some(0), jms.markers[0].region);
+    assert_eq!(1, jms.markers[0].node);
+    assert_eq!(Vector3D { x: 50.0, y: 0.0, z: 25.0 }, jms.markers[0].position);
+
+    assert_eq!(&JMS::from_text(&jms.to_text()).unwrap(), jms);
+}
+
+#[test]
+fn extract_gbxmodel_with_local_nodes() {
+    let mut model = GBXModel::default();
+    model.flags.parts_have_local_nodes = true;
+    model.nodes.items = nodes();
+    model.regions.items = vec![region()];
+    model.shaders.items = shaders();
+
+    // Only compressed vertices, whose node indices (multiplied by 3) refer to the part's local nodes.
+    let compressed = |node0: u8, node1: u8| ModelVertexCompressed {
+        node0_index: node0 * 3,
+        node1_index: node1 * 3,
+        node0_weight: 0.5.into(),
+        ..Default::default()
+    };
+    let mut local_node_indices = [0u8; 22];
+    local_node_indices[..2].copy_from_slice(&[2, 1]);
+    model.geometries.items = vec![GBXModelGeometry {
+        parts: Reflexive::new(vec![GBXModelGeometryPart {
+            model_geometry_part: ModelGeometryPart {
+                flags: ModelGeometryPartFlags { zoner: true, ..Default::default() },
+                compressed_vertices: Reflexive::new(vec![compressed(0, 1), compressed(1, 0), compressed(0, 1)]),
+                triangle_data: strip(&[0, 1, 2]),
+                triangle_buffer_type: TriangleBufferType::TriangleList,
+                ..Default::default()
+            },
+            local_node_count: 2,
+            local_node_indices
+        }]),
+        ..Default::default()
+    }];
+
+    let jms_files = extract_jms_from_model(&model).unwrap();
+    let (_, jms) = &jms_files[0];
+    let nodes: Vec<(u16, Option<u16>)> = jms.vertices.iter().map(|v| (v.node0, v.node1)).collect();
+    assert_eq!(vec![(2, Some(1)), (1, Some(2)), (2, Some(1))], nodes);
+    assert_eq!(1, jms.triangles.len());
+
+    // Decompressed weights have more precision than JMS files store.
+    assert_eq!(JMS::from_text(&jms.to_text()).unwrap().to_text(), jms.to_text());
+}
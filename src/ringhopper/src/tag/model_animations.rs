@@ -3,6 +3,8 @@ use primitives::byteorder::ByteOrder;
 use primitives::error::{Error, OverflowCheck, RinghopperResult};
 use primitives::parse::SimpleTagData;
 
+pub mod jma;
+
 #[derive(Default, Clone, Copy, Debug)]
 pub enum FrameDataType {
     #[default]
@@ -80,6 +82,21 @@ impl FrameDataIterator {
             remaining_nodes: animation.node_count as usize
         }
     }
+    /// Iterate through the frame data types along with the index of the node each one belongs to.
+    pub fn with_node_indices(self) -> impl Iterator<Item = (usize, FrameDataType)> {
+        let node_count = self.remaining_nodes;
+        let mut iterator = self;
+        std::iter::from_fn(move || {
+            let data_type = iterator.next()?;
+
+            // The node is only finished after its scale is checked.
+            let remaining_nodes = match data_type {
+                FrameDataType::Scale => iterator.remaining_nodes + 1,
+                _ => iterator.remaining_nodes
+            };
+            Some((node_count - remaining_nodes, data_type))
+        })
+    }
     pub fn to_size(self) -> usize {
         let mut size = 0;
         for i in self {
@@ -197,3 +214,6 @@ fn flip_endianness_for_default_data<From: ByteOrder, To: ByteOrder>(animation: &
 
     Ok(())
 }
+
+#[cfg(test)]
+mod test;
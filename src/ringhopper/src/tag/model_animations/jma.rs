@@ -0,0 +1,542 @@
+use definitions::{AnimationFrameInfoType, AnimationType, ModelAnimations, ModelAnimationsAnimation, ModelAnimationsAnimationGraphNode, ModelAnimationsFrameInfoDxDy, ModelAnimationsFrameInfoDxDyDyaw, ModelAnimationsFrameInfoDxDyDzDyaw, ModelAnimationsRotation, ModelAnimationsScale, ModelAnimationsTransform};
+use primitives::byteorder::BigEndian;
+use primitives::error::{Error, RinghopperResult};
+use primitives::parse::SimpleTagData;
+use std::f64::consts::{PI, TAU};
+use primitives::primitive::{Data, Quaternion, String32, Vector, Vector3D};
+use crate::data::jma::*;
+use crate::data::jms::JMS_UNITS_PER_WORLD_UNIT;
+use crate::tag::model::ModelFunctions;
+use super::{FrameDataIterator, FrameDataType};
+
+/// Maximum number of nodes an animation can have.
+pub const MAX_ANIMATION_NODES: usize = 64;
+
+/// Animation source file type, determined by the file extension.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AnimationSourceType {
+    /// Base animation with no root movement
+    JMM,
+
+    /// Base animation with horizontal root movement
+    JMA,
+
+    /// Base animation with horizontal root movement and yaw
+    JMT,
+
+    /// Base animation with horizontal and vertical root movement and yaw
+    JMZ,
+
+    /// Base animation relative to the world
+    JMW,
+
+    /// Overlay animation
+    JMO,
+
+    /// Replacement animation
+    JMR
+}
+
+impl AnimationSourceType {
+    /// All source types.
+    pub const ALL: [AnimationSourceType; 7] = [Self::JMM, Self::JMA, Self::JMT, Self::JMZ, Self::JMW, Self::JMO, Self::JMR];
+
+    /// Get the source type for the given extension (without the dot), if any.
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|t| t.extension().eq_ignore_ascii_case(extension))
+    }
+
+    /// Get the file extension (without the dot).
+    pub const fn extension(self) -> &'static str {
+        match self {
+            Self::JMM => "jmm",
+            Self::JMA => "jma",
+            Self::JMT => "jmt",
+            Self::JMZ => "jmz",
+            Self::JMW => "jmw",
+            Self::JMO => "jmo",
+            Self::JMR => "jmr"
+        }
+    }
+
+    /// Get the animation type this source type imports as.
+    pub const fn animation_type(self) -> AnimationType {
+        match self {
+            Self::JMO => AnimationType::Overlay,
+            Self::JMR => AnimationType::Replacement,
+            _ => AnimationType::Base
+        }
+    }
+
+    /// Get the frame info type this source type imports as.
+    pub const fn frame_info_type(self) -> AnimationFrameInfoType {
+        match self {
+            Self::JMA => AnimationFrameInfoType::DxDy,
+            Self::JMT => AnimationFrameInfoType::DxDyDyaw,
+            Self::JMZ => AnimationFrameInfoType::DxDyDzDyaw,
+            _ => AnimationFrameInfoType::None
+        }
+    }
+
+    /// Return `true` if the animation is world relative.
+    pub const fn is_world_relative(self) -> bool {
+        matches!(self, Self::JMW)
+    }
+}
+
+/// Get a quaternion that rotates around the Z axis.
+pub(crate) fn yaw_quaternion(yaw: f64) -> Quaternion {
+    let half = yaw / 2.0;
+    Quaternion { x: 0.0, y: 0.0, z: half.sin(), w: half.cos() }
+}
+
+/// Get the rotation around the Z axis of a quaternion.
+fn quaternion_yaw(q: Quaternion) -> f64 {
+    (2.0 * (q.w * q.z + q.x * q.y)).atan2(1.0 - 2.0 * (q.y * q.y + q.z * q.z))
+}
+
+/// Wrap an angle to be between -pi and pi.
+fn wrap_angle(angle: f64) -> f64 {
+    (angle + PI).rem_euclid(TAU) - PI
+}
+
+/// Rotate a vector around the Z axis.
+pub(crate) fn rotate_yaw(vector: Vector3D, yaw: f64) -> Vector3D {
+    let (sin, cos) = yaw.sin_cos();
+    Vector3D {
+        x: vector.x * cos - vector.y * sin,
+        y: vector.x * sin + vector.y * cos,
+        z: vector.z
+    }
+}
+
+pub(crate) fn compress_rotation(rotation: Quaternion) -> ModelAnimationsRotation {
+    let compress = |v: f64| (v * i16::MAX as f64).round().clamp(-(i16::MAX as f64), i16::MAX as f64) as i16;
+    ModelAnimationsRotation {
+        x: compress(rotation.x),
+        y: compress(rotation.y),
+        z: compress(rotation.z),
+        w: compress(rotation.w)
+    }
+}
+
+/// Generate an animation from a JMA.
+///
+/// Root node movement is moved into frame info depending on `source_type`, and each node's rotation, transform, and
+/// scale is only stored per-frame if it changes throughout the animation; otherwise, it is stored in default data.
+pub fn animation_from_jma(name: &str, source_type: AnimationSourceType, jma: &JMA, node_list_checksum: i32) -> RinghopperResult<ModelAnimationsAnimation> {
+    let node_count = jma.nodes.len();
+    if node_count == 0 || node_count > MAX_ANIMATION_NODES {
+        return Err(Error::InvalidTagData(format!("animation `{name}` has {node_count} node(s), but between 1 and {MAX_ANIMATION_NODES} are required")))
+    }
+
+    let frame_count = jma.frames.len();
+    if frame_count == 0 || frame_count > u16::MAX as usize {
+        return Err(Error::InvalidTagData(format!("animation `{name}` has {frame_count} frame(s), but between 1 and {} are required", u16::MAX)))
+    }
+
+    if let Some(f) = jma.frames.iter().position(|f| f.len() != node_count) {
+        return Err(Error::InvalidTagData(format!("frame #{f} of animation `{name}` does not have {node_count} node(s)")))
+    }
+
+    let mut frames = jma.frames.clone();
+    let frame_info_type = source_type.frame_info_type();
+    let frame_info = extract_frame_info(&mut frames, frame_info_type);
+
+    let mut animation = ModelAnimationsAnimation {
+        name: String32::from_str(name)?,
+        _type: source_type.animation_type(),
+        frame_count: frame_count as u16,
+        frame_info_type,
+        node_list_checksum,
+        node_count: node_count as u16,
+        weight: 1.0,
+        ..Default::default()
+    };
+    animation.flags.world_relative = source_type.is_world_relative();
+    animation.frame_info = Data::new(frame_info);
+
+    // Determine which nodes are animated, comparing values after they are converted into their tag representation.
+    let mut rotation_flags = 0u64;
+    let mut transform_flags = 0u64;
+    let mut scale_flags = 0u64;
+    for node in 0..node_count {
+        let first = &frames[0][node];
+        let first_rotation = compress_rotation(first.rotation);
+        let first_position = first.position.scale(1.0 / JMS_UNITS_PER_WORLD_UNIT);
+        let bit = 1u64 << node;
+
+        for frame in &frames[1..] {
+            let transform = &frame[node];
+            if compress_rotation(transform.rotation) != first_rotation {
+                rotation_flags |= bit;
+            }
+            if !vectors_equal_f32(transform.position.scale(1.0 / JMS_UNITS_PER_WORLD_UNIT), first_position) {
+                transform_flags |= bit;
+            }
+            if transform.scale as f32 != first.scale as f32 {
+                scale_flags |= bit;
+            }
+        }
+    }
+
+    let u64_to_flag_data = |v: u64| [v as u32, (v >> 32) as u32];
+    animation.node_rotation_flag_data = u64_to_flag_data(rotation_flags);
+    animation.node_transform_flag_data = u64_to_flag_data(transform_flags);
+    animation.node_scale_flag_data = u64_to_flag_data(scale_flags);
+
+    let animated = FrameDataIterator::for_animation(&animation);
+    let frame_size = animated.to_size();
+    animation.frame_size = u16::try_from(frame_size).map_err(|_| Error::InvalidTagData(format!("animation `{name}` frame size is too large")))?;
+
+    let mut frame_data = vec![0u8; frame_size * frame_count];
+    let mut offset = 0;
+    for frame in &frames {
+        write_frame_data(animated, frame, &mut frame_data, &mut offset)?;
+    }
+    animation.frame_data = Data::new(frame_data);
+
+    let not_animated = FrameDataIterator::for_animation_inverted(&animation);
+    let mut default_data = vec![0u8; not_animated.to_size()];
+    write_frame_data(not_animated, &frames[0], &mut default_data, &mut 0)?;
+    animation.default_data = Data::new(default_data);
+
+    Ok(animation)
+}
+
+/// Move root node movement into frame info, returning the frame info data.
+///
+/// Movement is stored relative to the root node's yaw on each frame, and the yaw and movement are then removed from
+/// the root node. The last frame has no movement.
+fn extract_frame_info(frames: &mut [Vec<JMANodeTransform>], frame_info_type: AnimationFrameInfoType) -> Vec<u8> {
+    let (has_dz, has_dyaw, frame_info_size) = match frame_info_type {
+        AnimationFrameInfoType::None => return Vec::new(),
+        AnimationFrameInfoType::DxDy => (false, false, ModelAnimationsFrameInfoDxDy::simple_size()),
+        AnimationFrameInfoType::DxDyDyaw => (false, true, ModelAnimationsFrameInfoDxDyDyaw::simple_size()),
+        AnimationFrameInfoType::DxDyDzDyaw => (true, true, ModelAnimationsFrameInfoDxDyDzDyaw::simple_size())
+    };
+
+    let frame_count = frames.len();
+    let roots: Vec<JMANodeTransform> = frames.iter().map(|f| f[0]).collect();
+    let yaws: Vec<f64> = roots.iter().map(|r| if has_dyaw { quaternion_yaw(r.rotation) } else { 0.0 }).collect();
+
+    let mut data = vec![0u8; frame_info_size * frame_count];
+    for f in 0..frame_count {
+        let relative_yaw = yaws[f] - yaws[0];
+        let (delta, dyaw) = match roots.get(f + 1) {
+            Some(next) => {
+                let delta = (next.position - roots[f].position).scale(1.0 / JMS_UNITS_PER_WORLD_UNIT);
+                (rotate_yaw(delta, -relative_yaw), wrap_angle(yaws[f + 1] - yaws[f]))
+            },
+            None => (Vector3D::zero(), 0.0)
+        };
+
+        let at = f * frame_info_size;
+        let end = data.len();
+        let result = match frame_info_type {
+            AnimationFrameInfoType::DxDy => ModelAnimationsFrameInfoDxDy { dx: delta.x, dy: delta.y }.write::<BigEndian>(&mut data, at, end),
+            AnimationFrameInfoType::DxDyDyaw => ModelAnimationsFrameInfoDxDyDyaw { dx: delta.x, dy: delta.y, dyaw }.write::<BigEndian>(&mut data, at, end),
+            AnimationFrameInfoType::DxDyDzDyaw => ModelAnimationsFrameInfoDxDyDzDyaw { dx: delta.x, dy: delta.y, dz: delta.z, dyaw }.write::<BigEndian>(&mut data, at, end),
+            AnimationFrameInfoType::None => unreachable!()
+        };
+        result.expect("frame info should fit");
+
+        let root = &mut frames[f][0];
+        root.position.x = roots[0].position.x;
+        root.position.y = roots[0].position.y;
+        if has_dz {
+            root.position.z = roots[0].position.z;
+        }
+        if has_dyaw {
+            root.rotation = yaw_quaternion(-relative_yaw).multiply(&root.rotation);
+        }
+    }
+
+    data
+}
+
+/// Check that the nodes of a JMA match a model, returning the model's node list checksum.
+pub fn check_jma_nodes_against_model<M: ModelFunctions>(jma: &JMA, model: &M) -> RinghopperResult<i32> {
+    let model_nodes = model.nodes();
+    if model_nodes.len() != jma.nodes.len() {
+        return Err(Error::InvalidTagData(format!("animation has {} node(s), but the model has {} node(s)", jma.nodes.len(), model_nodes.len())))
+    }
+    for (index, (jma_node, model_node)) in jma.nodes.iter().zip(model_nodes).enumerate() {
+        if !jma_node.name.eq_ignore_ascii_case(model_node.name.as_str()) {
+            return Err(Error::InvalidTagData(format!("animation node #{index} is `{}`, but the model's node is `{}`", jma_node.name, model_node.name)))
+        }
+    }
+    Ok(model.node_list_checksum())
+}
+
+/// Import a JMA into a model_animations tag, replacing any existing animation with the same name.
+///
+/// If the tag has no nodes, they are generated from the JMA. Otherwise, the JMA's nodes must match the tag's nodes.
+///
+/// Returns the index of the animation.
+pub fn import_jma_into_tag(tag: &mut ModelAnimations, name: &str, source_type: AnimationSourceType, jma: &JMA, node_list_checksum: i32) -> RinghopperResult<usize> {
+    if tag.nodes.items.is_empty() {
+        tag.nodes.items = nodes_from_jma(jma)?;
+    }
+    else {
+        if tag.nodes.items.len() != jma.nodes.len() {
+            return Err(Error::InvalidTagData(format!("animation `{name}` has {} node(s), but the tag has {} node(s)", jma.nodes.len(), tag.nodes.items.len())))
+        }
+        for (index, (jma_node, tag_node)) in jma.nodes.iter().zip(tag.nodes.items.iter()).enumerate() {
+            if !jma_node.name.eq_ignore_ascii_case(tag_node.name.as_str()) {
+                return Err(Error::InvalidTagData(format!("node #{index} of animation `{name}` is `{}`, but the tag's node is `{}`", jma_node.name, tag_node.name)))
+            }
+        }
+    }
+
+    let animation = animation_from_jma(name, source_type, jma, node_list_checksum)?;
+    match tag.animations.items.iter().position(|a| a.name.as_str() == name) {
+        Some(index) => {
+            tag.animations.items[index] = animation;
+            Ok(index)
+        },
+        None => {
+            tag.animations.items.push(animation);
+            Ok(tag.animations.items.len() - 1)
+        }
+    }
+}
+
+fn nodes_from_jma(jma: &JMA) -> RinghopperResult<Vec<ModelAnimationsAnimationGraphNode>> {
+    let node_count = jma.nodes.len();
+    let check_index = |index: Option<u16>| match index {
+        Some(i) if i as usize >= node_count => Err(Error::InvalidTagData(format!("node index {i} is out of bounds"))),
+        _ => Ok(index)
+    };
+
+    let mut nodes = Vec::with_capacity(node_count);
+    for node in &jma.nodes {
+        nodes.push(ModelAnimationsAnimationGraphNode {
+            name: String32::from_str(&node.name)?,
+            first_child_node_index: check_index(node.first_child)?,
+            next_sibling_node_index: check_index(node.sibling_node)?,
+            ..Default::default()
+        });
+    }
+
+    for parent in 0..node_count {
+        let mut child = nodes[parent].first_child_node_index;
+        let mut iterations = 0;
+        while let Some(c) = child {
+            iterations += 1;
+            if iterations > node_count {
+                return Err(Error::InvalidTagData("node hierarchy has a cycle".to_owned()))
+            }
+            nodes[c as usize].parent_node_index = Some(parent as u16);
+            child = nodes[c as usize].next_sibling_node_index;
+        }
+    }
+
+    Ok(nodes)
+}
+
+fn vectors_equal_f32(a: Vector3D, b: Vector3D) -> bool {
+    a.x as f32 == b.x as f32 && a.y as f32 == b.y as f32 && a.z as f32 == b.z as f32
+}
+
+fn write_frame_data(iterator: FrameDataIterator, frame: &[JMANodeTransform], data: &mut [u8], offset: &mut usize) -> RinghopperResult<()> {
+    let end = data.len();
+    for (node, data_type) in iterator.with_node_indices() {
+        let transform = &frame[node];
+        match data_type {
+            FrameDataType::Rotate => {
+                compress_rotation(transform.rotation).write::<BigEndian>(data, *offset, end)?;
+                *offset += ModelAnimationsRotation::simple_size();
+            },
+            FrameDataType::Transform => {
+                let transform = ModelAnimationsTransform { transform: transform.position.scale(1.0 / JMS_UNITS_PER_WORLD_UNIT) };
+                transform.write::<BigEndian>(data, *offset, end)?;
+                *offset += ModelAnimationsTransform::simple_size();
+            },
+            FrameDataType::Scale => {
+                ModelAnimationsScale { scale: transform.scale }.write::<BigEndian>(data, *offset, end)?;
+                *offset += ModelAnimationsScale::simple_size();
+            }
+        }
+    }
+    Ok(())
+}
+
+impl AnimationSourceType {
+    /// Get the source type an animation would have been imported from.
+    pub fn from_animation(animation: &ModelAnimationsAnimation) -> Self {
+        match animation._type {
+            AnimationType::Overlay => Self::JMO,
+            AnimationType::Replacement => Self::JMR,
+            AnimationType::Base if animation.flags.world_relative => Self::JMW,
+            AnimationType::Base => match animation.frame_info_type {
+                AnimationFrameInfoType::None => Self::JMM,
+                AnimationFrameInfoType::DxDy => Self::JMA,
+                AnimationFrameInfoType::DxDyDyaw => Self::JMT,
+                AnimationFrameInfoType::DxDyDzDyaw => Self::JMZ
+            }
+        }
+    }
+}
+
+pub(crate) fn decompress_rotation(rotation: ModelAnimationsRotation) -> Quaternion {
+    let decompress = |v: i16| v as f64 / i16::MAX as f64;
+    Quaternion {
+        x: decompress(rotation.x),
+        y: decompress(rotation.y),
+        z: decompress(rotation.z),
+        w: decompress(rotation.w)
+    }
+}
+
+fn read_frame_data(iterator: FrameDataIterator, frame: &mut [JMANodeTransform], data: &[u8], offset: &mut usize) -> RinghopperResult<()> {
+    let end = data.len();
+    for (node, data_type) in iterator.with_node_indices() {
+        let transform = &mut frame[node];
+        match data_type {
+            FrameDataType::Rotate => {
+                transform.rotation = decompress_rotation(ModelAnimationsRotation::read::<BigEndian>(data, *offset, end)?);
+                *offset += ModelAnimationsRotation::simple_size();
+            },
+            FrameDataType::Transform => {
+                let transform_data = ModelAnimationsTransform::read::<BigEndian>(data, *offset, end)?;
+                transform.position = transform_data.transform.scale(JMS_UNITS_PER_WORLD_UNIT);
+                *offset += ModelAnimationsTransform::simple_size();
+            },
+            FrameDataType::Scale => {
+                transform.scale = ModelAnimationsScale::read::<BigEndian>(data, *offset, end)?.scale;
+                *offset += ModelAnimationsScale::simple_size();
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Move frame info back into the root node, reversing [`extract_frame_info`].
+fn apply_frame_info(frames: &mut [Vec<JMANodeTransform>], animation: &ModelAnimationsAnimation) -> RinghopperResult<()> {
+    let frame_info_size = match animation.frame_info_type {
+        AnimationFrameInfoType::None => return Ok(()),
+        AnimationFrameInfoType::DxDy => ModelAnimationsFrameInfoDxDy::simple_size(),
+        AnimationFrameInfoType::DxDyDyaw => ModelAnimationsFrameInfoDxDyDyaw::simple_size(),
+        AnimationFrameInfoType::DxDyDzDyaw => ModelAnimationsFrameInfoDxDyDzDyaw::simple_size()
+    };
+
+    let data = animation.frame_info.bytes.as_slice();
+    let expected_size = frame_info_size * frames.len();
+    if data.len() != expected_size {
+        return Err(Error::InvalidTagData(format!("animation `{}` has {} byte(s) of frame info when it should be {expected_size}", animation.name, data.len())))
+    }
+
+    let mut position = Vector3D::zero();
+    let mut yaw = 0.0;
+    for (f, frame) in frames.iter_mut().enumerate() {
+        let root = &mut frame[0];
+        root.position += position;
+        root.rotation = yaw_quaternion(yaw).multiply(&root.rotation);
+
+        let at = f * frame_info_size;
+        let (delta, dyaw) = match animation.frame_info_type {
+            AnimationFrameInfoType::DxDy => {
+                let info = ModelAnimationsFrameInfoDxDy::read::<BigEndian>(data, at, data.len())?;
+                (Vector3D { x: info.dx, y: info.dy, z: 0.0 }, 0.0)
+            },
+            AnimationFrameInfoType::DxDyDyaw => {
+                let info = ModelAnimationsFrameInfoDxDyDyaw::read::<BigEndian>(data, at, data.len())?;
+                (Vector3D { x: info.dx, y: info.dy, z: 0.0 }, info.dyaw)
+            },
+            AnimationFrameInfoType::DxDyDzDyaw => {
+                let info = ModelAnimationsFrameInfoDxDyDzDyaw::read::<BigEndian>(data, at, data.len())?;
+                (Vector3D { x: info.dx, y: info.dy, z: info.dz }, info.dyaw)
+            },
+            AnimationFrameInfoType::None => unreachable!()
+        };
+
+        position += rotate_yaw(delta, yaw).scale(JMS_UNITS_PER_WORLD_UNIT);
+        yaw += dyaw;
+    }
+
+    Ok(())
+}
+
+/// Generate a JMA from an animation.
+///
+/// Nodes that are not animated use the animation's default data on every frame.
+///
+/// Compressed animations are not supported.
+pub fn animation_to_jma(animation: &ModelAnimationsAnimation, nodes: &[ModelAnimationsAnimationGraphNode]) -> RinghopperResult<JMA> {
+    let name = &animation.name;
+    if animation.flags.compressed_data {
+        return Err(Error::InvalidTagData(format!("animation `{name}` is compressed, and compressed animations are not supported")))
+    }
+
+    let node_count = animation.node_count as usize;
+    if node_count > MAX_ANIMATION_NODES || node_count != nodes.len() {
+        return Err(Error::InvalidTagData(format!("animation `{name}` has {node_count} node(s), but the tag has {} node(s)", nodes.len())))
+    }
+
+    let frame_count = animation.frame_count as usize;
+    let animated = FrameDataIterator::for_animation(animation);
+    let not_animated = FrameDataIterator::for_animation_inverted(animation);
+
+    let frame_size = animated.to_size();
+    let expected_frame_data_size = frame_size * frame_count;
+    if animation.frame_data.bytes.len() != expected_frame_data_size {
+        return Err(Error::InvalidTagData(format!("animation `{name}` has {} byte(s) of frame data when it should be {expected_frame_data_size}", animation.frame_data.bytes.len())))
+    }
+
+    let default_size = not_animated.to_size();
+    if animation.default_data.bytes.len() != default_size {
+        return Err(Error::InvalidTagData(format!("animation `{name}` has {} byte(s) of default data when it should be {default_size}", animation.default_data.bytes.len())))
+    }
+
+    let mut base_frame = vec![JMANodeTransform { scale: 1.0, ..Default::default() }; node_count];
+    read_frame_data(not_animated, &mut base_frame, &animation.default_data.bytes, &mut 0)?;
+
+    let mut frames = Vec::with_capacity(frame_count);
+    let mut offset = 0;
+    for _ in 0..frame_count {
+        let mut frame = base_frame.clone();
+        read_frame_data(animated, &mut frame, &animation.frame_data.bytes, &mut offset)?;
+        frames.push(frame);
+    }
+
+    apply_frame_info(&mut frames, animation)?;
+
+    Ok(JMA {
+        node_list_checksum: animation.node_list_checksum,
+        nodes: nodes.iter().map(|n| JMANode {
+            name: n.name.to_string(),
+            first_child: n.first_child_node_index,
+            sibling_node: n.next_sibling_node_index
+        }).collect(),
+        frames,
+        ..Default::default()
+    })
+}
+
+/// JMA-family files generated by [`extract_jma_from_model_animations`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ExtractedAnimations {
+    /// Name, source type, and data of each extracted animation.
+    pub animations: Vec<(String, AnimationSourceType, JMA)>,
+
+    /// Animations that were skipped.
+    pub warnings: Vec<String>
+}
+
+/// Generate JMA-family files for every animation in a model_animations tag.
+///
+/// Each output is named after its animation, and the source type determines the extension. Compressed animations
+/// are skipped with a warning.
+pub fn extract_jma_from_model_animations(tag: &ModelAnimations) -> RinghopperResult<ExtractedAnimations> {
+    let mut output = ExtractedAnimations::default();
+    for animation in &tag.animations {
+        if animation.flags.compressed_data {
+            output.warnings.push(format!("animation `{}` was skipped because compressed animations are not supported", animation.name));
+            continue
+        }
+        let jma = animation_to_jma(animation, &tag.nodes.items)?;
+        output.animations.push((animation.name.to_string(), AnimationSourceType::from_animation(animation), jma));
+    }
+    Ok(output)
+}
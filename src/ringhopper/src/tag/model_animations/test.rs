@@ -0,0 +1,132 @@
+use definitions::{AnimationFrameInfoType, Model, ModelAnimations, ModelNode};
+use primitives::primitive::String32;
+use crate::data::jma::JMA;
+use super::jma::*;
+
+const TEST_JMA: &str = "16392\r
+3\r
+30\r
+1\r
+unnamedActor\r
+2\r
+1234\r
+frame root\r
+1\r
+-1\r
+bone\r
+-1\r
+-1\r
+0\t0\t0\r
+0\t0\t0\t1\r
+1\r
+0\t0\t10\r
+0\t0\t0\t1\r
+1\r
+50\t0\t0\r
+0\t0\t0\t1\r
+1\r
+0\t0\t10\r
+0\t0\t0.7071067812\t0.7071067812\r
+1\r
+100\t0\t0\r
+0\t0\t0\t1\r
+1\r
+0\t0\t10\r
+0\t0\t1\t0\r
+1\r
+";
+
+#[test]
+fn import_jma() {
+    let jma = JMA::from_text(TEST_JMA).expect("should parse");
+    assert_eq!(jma.nodes.len(), 2);
+    assert_eq!(jma.frames.len(), 3);
+    assert_eq!(jma.nodes[0].first_child, Some(1));
+
+    // Root movement is moved into frame info, so only the second node's rotation is animated.
+    let animation = animation_from_jma("test", AnimationSourceType::JMA, &jma, 5).unwrap();
+    assert_eq!(animation.frame_info_type, AnimationFrameInfoType::DxDy);
+    assert_eq!(animation.frame_info.bytes.len(), 3 * 8);
+    assert_eq!(animation.node_rotation_flag_data, [0b10, 0]);
+    assert_eq!(animation.node_transform_flag_data, [0, 0]);
+    assert_eq!(animation.node_scale_flag_data, [0, 0]);
+    assert_eq!(animation.frame_size, 8);
+    assert_eq!(animation.frame_data.bytes.len(), 3 * 8);
+    assert_eq!(animation.default_data.bytes.len(), (8 + 12 + 4) + (12 + 4));
+    assert_eq!(animation.node_list_checksum, 5);
+
+    // Without frame info, the root node's movement is kept.
+    let animation = animation_from_jma("test", AnimationSourceType::JMM, &jma, 5).unwrap();
+    assert_eq!(animation.frame_info_type, AnimationFrameInfoType::None);
+    assert!(animation.frame_info.bytes.is_empty());
+    assert_eq!(animation.node_transform_flag_data, [0b01, 0]);
+    assert_eq!(animation.frame_size, 8 + 12);
+
+    let mut tag = ModelAnimations::default();
+    assert_eq!(import_jma_into_tag(&mut tag, "first", AnimationSourceType::JMM, &jma, 5).unwrap(), 0);
+    assert_eq!(import_jma_into_tag(&mut tag, "second", AnimationSourceType::JMO, &jma, 5).unwrap(), 1);
+    assert_eq!(import_jma_into_tag(&mut tag, "first", AnimationSourceType::JMA, &jma, 5).unwrap(), 0);
+    assert_eq!(tag.nodes.items.len(), 2);
+    assert_eq!(tag.nodes.items[1].parent_node_index, Some(0));
+    assert_eq!(tag.animations.items.len(), 2);
+}
+
+#[test]
+fn export_jma() {
+    use primitives::primitive::Vector;
+
+    let jma = JMA::from_text(TEST_JMA).expect("should parse");
+    let mut tag = ModelAnimations::default();
+
+    for source_type in AnimationSourceType::ALL {
+        import_jma_into_tag(&mut tag, source_type.extension(), source_type, &jma, jma.node_list_checksum).unwrap();
+    }
+
+    let exported = extract_jma_from_model_animations(&tag).unwrap();
+    assert_eq!(exported.animations.len(), AnimationSourceType::ALL.len());
+    assert!(exported.warnings.is_empty());
+
+    for (name, source_type, exported_jma) in exported.animations {
+        assert_eq!(name, source_type.extension());
+        assert_eq!(exported_jma.nodes, jma.nodes);
+        assert_eq!(exported_jma.node_list_checksum, jma.node_list_checksum);
+        assert_eq!(exported_jma.frames.len(), jma.frames.len());
+
+        for (exported_frame, frame) in exported_jma.frames.iter().zip(jma.frames.iter()) {
+            for (e, o) in exported_frame.iter().zip(frame.iter()) {
+                assert!(e.position.distance_squared(&o.position) < 0.0001, "{name}: {:?} != {:?}", e.position, o.position);
+                assert!(e.rotation.distance_squared(&o.rotation) < 0.0001, "{name}: {:?} != {:?}", e.rotation, o.rotation);
+                assert_eq!(e.scale, o.scale);
+            }
+        }
+
+        // Writing and reading it back should be lossless.
+        assert_eq!(JMA::from_text(&exported_jma.to_text()).unwrap().nodes, exported_jma.nodes);
+    }
+
+    // Compressed animations are skipped, but the others are still extracted.
+    tag.animations.items[0].flags.compressed_data = true;
+    let exported = extract_jma_from_model_animations(&tag).unwrap();
+    assert_eq!(exported.animations.len(), AnimationSourceType::ALL.len() - 1);
+    assert_eq!(exported.warnings.len(), 1);
+    assert!(exported.animations.iter().all(|(name, _, _)| name != AnimationSourceType::ALL[0].extension()));
+}
+
+#[test]
+fn check_nodes_against_model() {
+    let jma = JMA::from_text(TEST_JMA).expect("should parse");
+
+    // The model's checksum is passed through as-is, even if it differs from the one in the JMA.
+    let mut model = Model::default();
+    model.nodes.items = jma.nodes.iter().map(|n| ModelNode { name: String32::from_str(&n.name).unwrap(), ..Default::default() }).collect();
+    model.node_list_checksum = 0x4D2F1A39;
+    assert_eq!(check_jma_nodes_against_model(&jma, &model).unwrap(), 0x4D2F1A39);
+
+    // Names must still match.
+    model.nodes.items[1].name = String32::from_str("not a bone").unwrap();
+    assert!(check_jma_nodes_against_model(&jma, &model).is_err());
+
+    // So must the node count.
+    model.nodes.items.pop();
+    assert!(check_jma_nodes_against_model(&jma, &model).is_err());
+}
@@ -0,0 +1,236 @@
+use std::collections::HashMap;
+use definitions::{ModelCollisionGeometry, ModelCollisionGeometryBSPSurfaceFlags, ModelCollisionGeometryMaterial, ModelCollisionGeometryNode, ModelCollisionGeometryPermutation, ModelCollisionGeometryRegion, ModelCollisionGeometrySphere};
+use primitives::error::{Error, RinghopperResult};
+use primitives::primitive::{String32, Vector, Vector3D};
+use crate::data::jms::{JMS, JMS_UNITS_PER_WORLD_UNIT};
+use super::collision_bsp::{build_collision_bsp, CollisionTriangle};
+
+/// Maximum number of nodes in a model_collision_geometry tag.
+pub const MAX_COLLISION_NODES: usize = 64;
+
+/// Maximum number of materials in a model_collision_geometry tag.
+pub const MAX_COLLISION_MATERIALS: usize = 32;
+
+/// Maximum number of regions in a model_collision_geometry tag.
+pub const MAX_COLLISION_REGIONS: usize = 8;
+
+/// Maximum number of permutations per region in a model_collision_geometry tag.
+pub const MAX_COLLISION_PERMUTATIONS: usize = 32;
+
+/// Markers starting with this name are converted into pathfinding spheres.
+pub const PATHFINDING_SPHERE_MARKER_PREFIX: &str = "pathfinder";
+
+/// Shader symbols that can be appended to a JMS material name.
+struct MaterialSymbols {
+    two_sided: bool,
+    render_only: bool,
+    collision_only: bool,
+    climbable: bool,
+    breakable: bool
+}
+
+/// Split a material name into its base name and symbols.
+fn parse_material_name(name: &str) -> (&str, MaterialSymbols) {
+    let base = name.trim_end_matches(|c: char| "%#!@*$^-&.".contains(c));
+    let symbols = &name[base.len()..];
+    (base, MaterialSymbols {
+        two_sided: symbols.contains('%'),
+        render_only: symbols.contains('!'),
+        collision_only: symbols.contains('@'),
+        climbable: symbols.contains('^'),
+        breakable: symbols.contains('-')
+    })
+}
+
+/// Compile a model_collision_geometry tag from JMS files.
+///
+/// Each JMS file is a permutation, and all files must have the same nodes. Every node may only have triangles from one
+/// region, and each triangle must be weighted entirely to one node. Geometry is transformed into the space of each node.
+///
+/// If `existing` is set, then any other data (such as material, region, and shield settings) is preserved for
+/// anything with a matching name.
+pub fn compile_collision_geometry(permutations: &[(String, JMS)], existing: Option<&ModelCollisionGeometry>) -> RinghopperResult<ModelCollisionGeometry> {
+    let Some((_, first)) = permutations.first() else {
+        return Err(Error::Other("no permutations were given".to_owned()))
+    };
+
+    if first.nodes.len() > MAX_COLLISION_NODES {
+        return Err(Error::Other(format!("too many nodes ({} > {MAX_COLLISION_NODES})", first.nodes.len())))
+    }
+    for (name, jms) in permutations {
+        if jms.nodes.len() != first.nodes.len() || jms.nodes.iter().zip(first.nodes.iter()).any(|(a, b)| a.name != b.name) {
+            return Err(Error::Other(format!("permutation `{name}` has different nodes than `{}`", permutations[0].0)))
+        }
+    }
+
+    let mut tag = existing.cloned().unwrap_or_default();
+    let old_materials = std::mem::take(&mut tag.materials.items);
+    let old_regions = std::mem::take(&mut tag.regions.items);
+    tag.nodes.items.clear();
+    tag.pathfinding_spheres.items.clear();
+
+    // Gather materials, regions, and the triangles of each node for each permutation.
+    let mut region_permutations: Vec<(String, Vec<usize>)> = Vec::new();
+    let mut node_triangles: Vec<HashMap<usize, Vec<CollisionTriangle>>> = vec![HashMap::new(); first.nodes.len()];
+    let mut node_regions: Vec<Option<usize>> = vec![None; first.nodes.len()];
+
+    for (permutation_index, (permutation_name, jms)) in permutations.iter().enumerate() {
+        let material_indices = jms.materials
+            .iter()
+            .map(|m| {
+                let (name, symbols) = parse_material_name(&m.name);
+                if symbols.render_only {
+                    return Ok(None)
+                }
+                let index = match tag.materials.items.iter().position(|o| o.name.as_str() == name) {
+                    Some(n) => n,
+                    None => {
+                        let material = old_materials
+                            .iter()
+                            .find(|o| o.name.as_str() == name)
+                            .cloned()
+                            .map(Ok)
+                            .unwrap_or_else(|| String32::from_str(name).map(|name| ModelCollisionGeometryMaterial { name, ..Default::default() }))?;
+                        tag.materials.items.push(material);
+                        tag.materials.items.len() - 1
+                    }
+                };
+                let flags = ModelCollisionGeometryBSPSurfaceFlags {
+                    two_sided: symbols.two_sided,
+                    invisible: symbols.collision_only,
+                    climbable: symbols.climbable,
+                    breakable: symbols.breakable
+                };
+                Ok(Some((index as u16, flags)))
+            })
+            .collect::<RinghopperResult<Vec<_>>>()?;
+
+        let transforms = jms.absolute_node_transforms()?;
+        let mut used_regions = Vec::new();
+
+        for triangle in &jms.triangles {
+            let material = *material_indices
+                .get(triangle.shader as usize)
+                .ok_or_else(|| Error::Other(format!("triangle in `{permutation_name}` has an invalid material")))?;
+            let Some((material, flags)) = material else {
+                continue
+            };
+
+            let vertices = triangle.vertices.map(|v| jms.vertices.get(v as usize));
+            let [Some(a), Some(b), Some(c)] = vertices else {
+                return Err(Error::Other(format!("triangle in `{permutation_name}` has an invalid vertex")))
+            };
+            let vertices = [a, b, c];
+
+            let node = vertices[0].node0;
+            if vertices.iter().any(|v| v.node0 != node || (v.node1.is_some() && v.node1_weight > 0.0)) {
+                return Err(Error::Other(format!("triangle in `{permutation_name}` is not weighted to exactly one node")))
+            }
+            let (rotation, position) = *transforms
+                .get(node as usize)
+                .ok_or_else(|| Error::Other(format!("vertex in `{permutation_name}` has an invalid node")))?;
+            let inverse = rotation.conjugate();
+
+            let region_name = &jms.regions
+                .get(triangle.region as usize)
+                .ok_or_else(|| Error::Other(format!("triangle in `{permutation_name}` has an invalid region")))?
+                .name;
+            let region = match region_permutations.iter().position(|r| &r.0 == region_name) {
+                Some(n) => n,
+                None => {
+                    region_permutations.push((region_name.to_owned(), Vec::new()));
+                    region_permutations.len() - 1
+                }
+            };
+            if !used_regions.contains(&region) {
+                used_regions.push(region);
+            }
+
+            match node_regions[node as usize] {
+                Some(r) if r != region => return Err(Error::Other(format!(
+                    "node `{}` has triangles from multiple regions (`{}` and `{region_name}`)",
+                    jms.nodes[node as usize].name,
+                    region_permutations[r].0
+                ))),
+                _ => node_regions[node as usize] = Some(region)
+            }
+
+            let local = |v: Vector3D| inverse.rotate_vector(v.scale(1.0 / JMS_UNITS_PER_WORLD_UNIT) - position);
+            let collision_triangle = CollisionTriangle {
+                vertices: vertices.map(|v| local(v.position)),
+                material: Some(material),
+                flags,
+                breakable_surface: -1
+            };
+
+            node_triangles[node as usize].entry(permutation_index).or_default().push(collision_triangle);
+        }
+
+        for region in used_regions {
+            region_permutations[region].1.push(permutation_index);
+        }
+
+        for marker in jms.markers.iter().filter(|m| m.name.starts_with(PATHFINDING_SPHERE_MARKER_PREFIX)) {
+            let sphere = ModelCollisionGeometrySphere {
+                node: Some(marker.node),
+                center: marker.position.scale(1.0 / JMS_UNITS_PER_WORLD_UNIT),
+                radius: marker.radius / JMS_UNITS_PER_WORLD_UNIT
+            };
+            if !tag.pathfinding_spheres.items.contains(&sphere) {
+                tag.pathfinding_spheres.items.push(sphere);
+            }
+        }
+    }
+
+    if tag.materials.items.len() > MAX_COLLISION_MATERIALS {
+        return Err(Error::Other(format!("too many materials ({} > {MAX_COLLISION_MATERIALS})", tag.materials.items.len())))
+    }
+    if region_permutations.len() > MAX_COLLISION_REGIONS {
+        return Err(Error::Other(format!("too many regions ({} > {MAX_COLLISION_REGIONS})", region_permutations.len())))
+    }
+
+    for (region_name, region_permutations) in &region_permutations {
+        if region_permutations.len() > MAX_COLLISION_PERMUTATIONS {
+            return Err(Error::Other(format!("region `{region_name}` has too many permutations ({} > {MAX_COLLISION_PERMUTATIONS})", region_permutations.len())))
+        }
+
+        let mut region = old_regions
+            .iter()
+            .find(|r| r.name.as_str() == region_name)
+            .cloned()
+            .map(Ok)
+            .unwrap_or_else(|| String32::from_str(region_name).map(|name| ModelCollisionGeometryRegion { name, ..Default::default() }))?;
+        region.permutations.items = region_permutations
+            .iter()
+            .map(|p| String32::from_str(&permutations[*p].0).map(|name| ModelCollisionGeometryPermutation { name }))
+            .collect::<RinghopperResult<Vec<_>>>()?;
+        tag.regions.items.push(region);
+    }
+
+    let parents = first.node_parents()?;
+    for (index, node) in first.nodes.iter().enumerate() {
+        let region = node_regions[index];
+        let mut bsps = Vec::new();
+        if let Some(region) = region {
+            for permutation in &region_permutations[region].1 {
+                let triangles = node_triangles[index].get(permutation).map(Vec::as_slice).unwrap_or_default();
+                let bsp = build_collision_bsp(triangles)
+                    .map_err(|e| Error::Other(format!("can't build BSP for node `{}` in permutation `{}`: {e}", node.name, permutations[*permutation].0)))?;
+                bsps.push(bsp);
+            }
+        }
+
+        let mut collision_node = ModelCollisionGeometryNode {
+            name: String32::from_str(&node.name)?,
+            region: region.map(|r| r as u16),
+            parent_node: parents[index],
+            next_sibling_node: node.sibling_node,
+            first_child_node: node.first_child,
+            ..Default::default()
+        };
+        collision_node.bsps.items = bsps;
+        tag.nodes.items.push(collision_node);
+    }
+
+    Ok(tag)
+}
@@ -0,0 +1,124 @@
+use definitions::{Physics, PhysicsInertialMatrix, PhysicsMassPoint};
+use primitives::error::{Error, RinghopperResult};
+use primitives::primitive::{Matrix3x3, String32, Vector, Vector3D};
+use crate::data::jms::{JMS, JMS_UNITS_PER_WORLD_UNIT};
+
+/// Maximum number of mass points in a physics tag.
+pub const MAX_MASS_POINTS: usize = 32;
+
+/// Replace the mass points of a physics tag with the markers of a JMS.
+///
+/// Every marker is a mass point. Its position and orientation are converted to model space, and its radius is used as
+/// the mass point's radius. Settings such as relative mass and friction are preserved for mass points with the same
+/// name; new mass points have a relative mass and density of 1.
+///
+/// The mass distribution is not recalculated; use [`calculate_mass_distribution`] afterward.
+pub fn import_mass_points_from_jms(physics: &mut Physics, jms: &JMS) -> RinghopperResult<()> {
+    if jms.markers.len() > MAX_MASS_POINTS {
+        return Err(Error::Other(format!("too many mass points ({} > {MAX_MASS_POINTS})", jms.markers.len())))
+    }
+
+    let transforms = jms.absolute_node_transforms()?;
+    let old_mass_points = std::mem::take(&mut physics.mass_points.items);
+
+    for marker in &jms.markers {
+        let (node_rotation, node_position) = *transforms
+            .get(marker.node as usize)
+            .ok_or_else(|| Error::Other(format!("marker `{}` has an invalid node", marker.name)))?;
+
+        let rotation = node_rotation.multiply(&marker.rotation);
+        let position = node_position + node_rotation.rotate_vector(marker.position.scale(1.0 / JMS_UNITS_PER_WORLD_UNIT));
+
+        let mut mass_point = old_mass_points
+            .iter()
+            .find(|m| m.name.as_str() == marker.name)
+            .cloned()
+            .map(Ok)
+            .unwrap_or_else(|| String32::from_str(&marker.name).map(|name| PhysicsMassPoint {
+                name,
+                relative_mass: 1.0,
+                relative_density: 1.0,
+                ..Default::default()
+            }))?;
+
+        mass_point.model_node = Some(marker.node);
+        mass_point.position = position;
+        mass_point.forward = rotation.rotate_vector(Vector3D { x: 1.0, y: 0.0, z: 0.0 }).normalize();
+        mass_point.up = rotation.rotate_vector(Vector3D { x: 0.0, y: 0.0, z: 1.0 }).normalize();
+        mass_point.radius = marker.radius / JMS_UNITS_PER_WORLD_UNIT;
+
+        physics.mass_points.items.push(mass_point);
+    }
+
+    Ok(())
+}
+
+/// Calculate the mass, density, center of mass, moments, and inertial matrices of a physics tag.
+///
+/// The tag's mass is distributed to each mass point by its relative mass, relative density, and volume (as a sphere of
+/// its radius), so denser and larger mass points get a larger share. Each mass point's density is scaled by relative
+/// density so that the mass-weighted average density equals the tag's density.
+///
+/// Mass points are treated as solid spheres when calculating the inertia tensor, which is then scaled by the tag's
+/// moment scale.
+pub fn calculate_mass_distribution(physics: &mut Physics) -> RinghopperResult<()> {
+    if physics.mass <= 0.0 {
+        return Err(Error::InvalidTagData("physics mass must be greater than zero".to_owned()))
+    }
+
+    let mass_points = &mut physics.mass_points.items;
+    let weight = |m: &PhysicsMassPoint| m.relative_mass * m.relative_density * 4.0 / 3.0 * std::f64::consts::PI * m.radius.powi(3);
+    let total_weight: f64 = mass_points.iter().map(weight).sum();
+    if total_weight <= 0.0 || !total_weight.is_finite() {
+        return Err(Error::InvalidTagData("mass points must have a positive total relative mass, relative density, and radius".to_owned()))
+    }
+
+    let mut center_of_mass = Vector3D::zero();
+    let mut total_relative_density = 0.0;
+    for mass_point in mass_points.iter_mut() {
+        mass_point.mass = physics.mass * weight(mass_point) / total_weight;
+        total_relative_density += mass_point.mass * mass_point.relative_density;
+        center_of_mass += mass_point.position.scale(mass_point.mass);
+    }
+
+    let density_scale = physics.density * physics.mass / total_relative_density;
+    for mass_point in mass_points.iter_mut() {
+        mass_point.density = density_scale * mass_point.relative_density;
+    }
+    let center_of_mass = center_of_mass.scale(1.0 / physics.mass);
+
+    let mut tensor = [[0.0f64; 3]; 3];
+    for mass_point in mass_points.iter() {
+        let offset = mass_point.position - center_of_mass;
+        let offset = [offset.x, offset.y, offset.z];
+        let sphere = 0.4 * mass_point.radius * mass_point.radius;
+        let distance_squared: f64 = offset.iter().map(|o| o * o).sum();
+
+        for (row, tensor_row) in tensor.iter_mut().enumerate() {
+            for (column, value) in tensor_row.iter_mut().enumerate() {
+                let diagonal = if row == column { sphere + distance_squared } else { 0.0 };
+                *value += mass_point.mass * (diagonal - offset[row] * offset[column]);
+            }
+        }
+    }
+
+    let row = |r: [f64; 3]| Vector3D { x: r[0], y: r[1], z: r[2] }.scale(physics.moment_scale);
+    let inertia = Matrix3x3 { vectors: tensor.map(row) };
+    let inverse = inertia
+        .inverse()
+        .ok_or_else(|| Error::InvalidTagData("inertial matrix is singular (is the moment scale zero?)".to_owned()))?;
+
+    physics.center_of_mass = center_of_mass;
+    physics.xx_moment = inertia.vectors[0].x;
+    physics.yy_moment = inertia.vectors[1].y;
+    physics.zz_moment = inertia.vectors[2].z;
+    physics.inertial_matrix_and_inverse.items = vec![
+        PhysicsInertialMatrix { matrix: inertia },
+        PhysicsInertialMatrix { matrix: inverse }
+    ];
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,113 @@
+use definitions::{Physics, PhysicsMassPoint};
+use primitives::primitive::{Quaternion, String32, Vector, Vector3D};
+use crate::data::jms::{JMS, JMSMarker, JMSNode};
+use super::*;
+
+fn mass_point(name: &str, relative_mass: f64, relative_density: f64, x: f64) -> PhysicsMassPoint {
+    sized_mass_point(name, relative_mass, relative_density, x, 0.5)
+}
+
+fn sized_mass_point(name: &str, relative_mass: f64, relative_density: f64, x: f64, radius: f64) -> PhysicsMassPoint {
+    PhysicsMassPoint {
+        name: String32::from_str(name).unwrap(),
+        relative_mass,
+        relative_density,
+        position: Vector3D { x, y: 0.0, z: 0.0 },
+        radius,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn mass_distribution() {
+    let mut physics = Physics { mass: 300.0, density: 2.0, moment_scale: 1.0, ..Default::default() };
+    physics.mass_points.items = vec![
+        mass_point("front", 2.0, 1.0, 2.0),
+        mass_point("back", 1.0, 4.0, -1.0)
+    ];
+    calculate_mass_distribution(&mut physics).unwrap();
+
+    // Relative mass * relative density is 2 for the front and 4 for the back, and they are the same size.
+    let [front, back] = [&physics.mass_points.items[0], &physics.mass_points.items[1]];
+    assert!((front.mass - 100.0).abs() < 0.00001);
+    assert!((back.mass - 200.0).abs() < 0.00001);
+
+    // The mass-weighted density should match the tag's density.
+    let average_density = (front.mass * front.density + back.mass * back.density) / physics.mass;
+    assert!((average_density - physics.density).abs() < 0.00001);
+    assert!((back.density / front.density - 4.0).abs() < 0.00001);
+
+    // (100 * 2 + 200 * -1) / 300 = 0
+    assert!(physics.center_of_mass.magnitude_squared() < 0.00001);
+
+    // Spheres only contribute along x; y and z also get the parallel axis term: 100 * 2^2 + 200 * 1^2 = 600
+    let sphere = 0.4 * 0.25 * 300.0;
+    assert!((physics.xx_moment - sphere).abs() < 0.00001);
+    assert!((physics.yy_moment - (sphere + 600.0)).abs() < 0.00001);
+    assert!((physics.zz_moment - (sphere + 600.0)).abs() < 0.00001);
+
+    // The matrix and its inverse should multiply to the identity matrix.
+    let [matrix, inverse] = [&physics.inertial_matrix_and_inverse.items[0].matrix, &physics.inertial_matrix_and_inverse.items[1].matrix];
+    for row in 0..3 {
+        for column in 0..3 {
+            let inverse_column = Vector3D {
+                x: [inverse.vectors[0].x, inverse.vectors[0].y, inverse.vectors[0].z][column],
+                y: [inverse.vectors[1].x, inverse.vectors[1].y, inverse.vectors[1].z][column],
+                z: [inverse.vectors[2].x, inverse.vectors[2].y, inverse.vectors[2].z][column]
+            };
+            let expected = if row == column { 1.0 } else { 0.0 };
+            assert!((matrix.vectors[row].dot(&inverse_column) - expected).abs() < 0.00001);
+        }
+    }
+}
+
+#[test]
+fn mass_distribution_by_volume() {
+    let mut physics = Physics { mass: 90.0, density: 1.0, moment_scale: 1.0, ..Default::default() };
+    physics.mass_points.items = vec![
+        sized_mass_point("small", 1.0, 1.0, 0.0, 0.5),
+        sized_mass_point("large", 1.0, 1.0, 1.0, 1.0)
+    ];
+    calculate_mass_distribution(&mut physics).unwrap();
+
+    // Twice the radius is eight times the volume.
+    let [small, large] = [&physics.mass_points.items[0], &physics.mass_points.items[1]];
+    assert!((small.mass - 10.0).abs() < 0.00001);
+    assert!((large.mass - 80.0).abs() < 0.00001);
+}
+
+#[test]
+fn zero_mass() {
+    let mut physics = Physics { mass: 0.0, density: 1.0, moment_scale: 1.0, ..Default::default() };
+    physics.mass_points.items = vec![mass_point("a", 1.0, 1.0, 0.0)];
+    assert!(calculate_mass_distribution(&mut physics).is_err());
+}
+
+#[test]
+fn import_markers() {
+    let yaw_90 = Quaternion { x: 0.0, y: 0.0, z: std::f64::consts::FRAC_1_SQRT_2, w: std::f64::consts::FRAC_1_SQRT_2 };
+    let jms = JMS {
+        nodes: vec![
+            JMSNode { name: "root".to_owned(), first_child: Some(1), rotation: yaw_90, position: Vector3D { x: 100.0, y: 0.0, z: 0.0 }, ..Default::default() },
+            JMSNode { name: "child".to_owned(), rotation: Quaternion { w: 1.0, ..Default::default() }, position: Vector3D { x: 100.0, y: 0.0, z: 0.0 }, ..Default::default() }
+        ],
+        markers: vec![
+            JMSMarker { name: "wheel".to_owned(), node: 1, rotation: Quaternion { w: 1.0, ..Default::default() }, radius: 50.0, ..Default::default() }
+        ],
+        ..Default::default()
+    };
+
+    let mut physics = Physics::default();
+    physics.mass_points.items = vec![mass_point("wheel", 3.0, 2.0, 0.0)];
+    import_mass_points_from_jms(&mut physics, &jms).unwrap();
+
+    let wheel = &physics.mass_points.items[0];
+    assert_eq!(3.0, wheel.relative_mass);
+    assert_eq!(Some(1), wheel.model_node);
+    assert_eq!(0.5, wheel.radius);
+
+    // The child is 1 world unit along the root's rotated x axis, which is y.
+    assert!(wheel.position.distance_squared(&Vector3D { x: 1.0, y: 1.0, z: 0.0 }) < 0.00001);
+    assert!(wheel.forward.distance_squared(&Vector3D { x: 0.0, y: 1.0, z: 0.0 }) < 0.00001);
+    assert!(wheel.up.distance_squared(&Vector3D { x: 0.0, y: 0.0, z: 1.0 }) < 0.00001);
+}
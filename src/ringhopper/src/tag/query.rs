@@ -0,0 +1,353 @@
+use std::cmp::Ordering;
+use std::fmt::Display;
+use std::str::FromStr;
+use primitives::dynamic::{DynamicTagData, DynamicTagDataType};
+use primitives::error::{Error, RinghopperResult};
+use primitives::primitive::{TagPath, TagReference};
+use regex::Regex;
+use serde_json::{Map, Number, Value};
+use crate::tag::json::{dynamic_to_value, write_value};
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Matches,
+    DoesNotMatch
+}
+
+impl Comparison {
+    /// Operators, longest first so that `<=` is not parsed as `<`.
+    const OPERATORS: [(&'static str, Comparison); 8] = [
+        ("==", Comparison::Equal),
+        ("!=", Comparison::NotEqual),
+        ("<=", Comparison::LessOrEqual),
+        (">=", Comparison::GreaterOrEqual),
+        ("=~", Comparison::Matches),
+        ("!~", Comparison::DoesNotMatch),
+        ("<", Comparison::Less),
+        (">", Comparison::Greater)
+    ];
+
+    fn is_negated(self) -> bool {
+        matches!(self, Comparison::NotEqual | Comparison::DoesNotMatch)
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Operand {
+    Value { text: String, number: Option<f64> },
+    Regex(Regex)
+}
+
+/// A condition on the fields reached by a matcher, such as `initial_velocity > 10`.
+///
+/// The right-hand side can be quoted with `"` to keep surrounding whitespace. Numbers are compared numerically and
+/// everything else is compared as text, with tag references compared by their path using `/` separators. `=~` and
+/// `!~` match a regular expression.
+///
+/// A predicate holds if any field reached by the matcher satisfies it. Negated comparisons (`!=` and `!~`) hold if no
+/// field satisfies the non-negated comparison.
+#[derive(Clone, Debug)]
+pub struct Predicate {
+    matcher: String,
+    comparison: Comparison,
+    operand: Operand
+}
+
+impl FromStr for Predicate {
+    type Err = Error;
+    fn from_str(s: &str) -> RinghopperResult<Self> {
+        let invalid = |reason: &str| Error::Other(format!("Invalid predicate `{s}`: {reason}"));
+
+        let operator_start = s.find(['=', '!', '<', '>', '~']).ok_or_else(|| invalid("expected a comparison such as `==` or `>`"))?;
+        let (matcher, rest) = s.split_at(operator_start);
+        let (operator, comparison) = Comparison::OPERATORS
+            .iter()
+            .find(|(operator, _)| rest.starts_with(operator))
+            .ok_or_else(|| invalid("unknown comparison"))?;
+
+        let matcher = matcher.trim();
+        if matcher.is_empty() {
+            return Err(invalid("expected a matcher before the comparison"))
+        }
+
+        let value = rest[operator.len()..].trim();
+        let value = value.strip_prefix('"').and_then(|v| v.strip_suffix('"')).unwrap_or(value);
+
+        let operand = match comparison {
+            Comparison::Matches | Comparison::DoesNotMatch => Operand::Regex(Regex::new(value).map_err(|e| invalid(&e.to_string()))?),
+            _ => Operand::Value { text: value.to_owned(), number: value.parse().ok() }
+        };
+
+        Ok(Predicate { matcher: matcher.to_owned(), comparison: *comparison, operand })
+    }
+}
+
+impl Predicate {
+    /// Get the matcher used to reach the fields being compared.
+    pub fn matcher(&self) -> &str {
+        &self.matcher
+    }
+
+    /// Evaluate the predicate against the tag.
+    ///
+    /// Returns an error if the matcher is not valid for the tag.
+    pub fn evaluate(&self, data: &dyn DynamicTagData) -> RinghopperResult<bool> {
+        validate(data, &self.matcher)?;
+
+        let mut satisfied = false;
+        data.foreach(&self.matcher, |matched| {
+            satisfied = self.compare(&value_of(matched.unwrap()));
+            !satisfied
+        });
+        Ok(satisfied != self.comparison.is_negated())
+    }
+
+    /// Compare a single value, ignoring negation.
+    fn compare(&self, value: &Value) -> bool {
+        let text = value_text(value);
+        let (operand_text, operand_number) = match &self.operand {
+            Operand::Regex(regex) => return regex.is_match(&text),
+            Operand::Value { text, number } => (text, number)
+        };
+
+        let ordering = match (value.as_f64(), operand_number) {
+            // Float fields are 32-bit, so compare them at that precision or e.g. 0.1 would never equal 0.1.
+            (Some(a), Some(b)) if value.is_f64() => (a as f32).partial_cmp(&(*b as f32)),
+            (Some(a), Some(b)) => a.partial_cmp(b),
+            _ => Some(text.as_str().cmp(operand_text.as_str()))
+        };
+        let Some(ordering) = ordering else {
+            return false
+        };
+
+        match self.comparison {
+            Comparison::Equal | Comparison::NotEqual => ordering == Ordering::Equal,
+            Comparison::Less => ordering == Ordering::Less,
+            Comparison::LessOrEqual => ordering != Ordering::Greater,
+            Comparison::Greater => ordering == Ordering::Greater,
+            Comparison::GreaterOrEqual => ordering != Ordering::Less,
+            Comparison::Matches | Comparison::DoesNotMatch => unreachable!()
+        }
+    }
+}
+
+fn validate(data: &dyn DynamicTagData, matcher: &str) -> RinghopperResult<()> {
+    data.validate_matcher(matcher).map_err(|e| Error::Other(format!("Invalid matcher `{matcher}`: {e}")))
+}
+
+fn value_of(data: &dyn DynamicTagData) -> Value {
+    match data.data_type() {
+        DynamicTagDataType::TagReference => match data.as_any().downcast_ref::<TagReference>().unwrap() {
+            TagReference::Set(path) => Value::String(path.to_zip_path()),
+            TagReference::Null(_) => Value::Null
+        },
+        _ => dynamic_to_value(data)
+    }
+}
+
+fn value_text(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        n => n.to_string()
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Aggregate {
+    Count,
+    Sum,
+    Min,
+    Max,
+    Mean
+}
+
+impl Aggregate {
+    const ALL: [(&'static str, Aggregate); 5] = [
+        ("count", Aggregate::Count),
+        ("sum", Aggregate::Sum),
+        ("min", Aggregate::Min),
+        ("max", Aggregate::Max),
+        ("mean", Aggregate::Mean)
+    ];
+
+    /// Get the name of the aggregate.
+    pub fn as_str(self) -> &'static str {
+        Self::ALL.iter().find(|(_, a)| *a == self).unwrap().0
+    }
+
+    /// Aggregate the values, ignoring any that are not numbers (except when counting).
+    fn apply(self, values: &[&Value]) -> Option<f64> {
+        if self == Aggregate::Count {
+            return Some(values.len() as f64)
+        }
+
+        let numbers: Vec<f64> = values.iter().filter_map(|v| v.as_f64()).collect();
+        if numbers.is_empty() {
+            return None
+        }
+        match self {
+            Aggregate::Count => unreachable!(),
+            Aggregate::Sum => Some(numbers.iter().sum()),
+            Aggregate::Min => numbers.into_iter().reduce(f64::min),
+            Aggregate::Max => numbers.into_iter().reduce(f64::max),
+            Aggregate::Mean => Some(numbers.iter().sum::<f64>() / numbers.len() as f64)
+        }
+    }
+}
+
+impl FromStr for Aggregate {
+    type Err = Error;
+    fn from_str(s: &str) -> RinghopperResult<Self> {
+        Self::ALL
+            .iter()
+            .find(|(name, _)| *name == s)
+            .map(|(_, a)| *a)
+            .ok_or_else(|| Error::Other(format!("Invalid aggregate `{s}`; expected one of: {}", Self::ALL.map(|a| a.0).join(", "))))
+    }
+}
+
+impl Display for Aggregate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A set of predicates which must all hold, along with the fields to select from each matching tag.
+#[derive(Clone, Debug, Default)]
+pub struct Query {
+    pub predicates: Vec<Predicate>,
+    pub columns: Vec<String>
+}
+
+/// A tag which satisfied a [`Query`], along with the values of its selected fields.
+#[derive(Clone, Debug)]
+pub struct QueryMatch {
+    path: TagPath,
+    values: Vec<Vec<Value>>
+}
+
+impl QueryMatch {
+    /// Get the path of the tag that matched.
+    pub fn path(&self) -> &TagPath {
+        &self.path
+    }
+}
+
+impl Query {
+    /// Evaluate the query against a tag, returning `None` if any predicate does not hold.
+    ///
+    /// Tags which do not have every field used by the query (e.g. tags of other groups) are not matched.
+    pub fn evaluate(&self, path: &TagPath, data: &dyn DynamicTagData) -> Option<QueryMatch> {
+        let has_fields = self.predicates.iter().map(Predicate::matcher).chain(self.columns.iter().map(String::as_str)).all(|m| data.validate_matcher(m).is_ok());
+        if !has_fields || !self.predicates.iter().all(|p| p.evaluate(data).unwrap()) {
+            return None
+        }
+
+        let mut values = Vec::with_capacity(self.columns.len());
+        for column in &self.columns {
+            let mut column_values = Vec::new();
+            data.foreach(column, |matched| {
+                column_values.push(value_of(matched.unwrap()));
+                true
+            });
+            values.push(column_values);
+        }
+
+        Some(QueryMatch { path: path.to_owned(), values })
+    }
+
+    /// Format the matches as a table with one row per tag.
+    ///
+    /// If `aggregate` is set, a single row is output instead, aggregating each column across all matches.
+    pub fn format_table(&self, matches: &[QueryMatch], aggregate: Option<Aggregate>) -> String {
+        let mut rows: Vec<Vec<String>> = Vec::new();
+
+        match aggregate {
+            Some(aggregate) => {
+                let mut header = vec!["tags".to_owned()];
+                header.extend(self.columns.iter().map(|c| format!("{aggregate}({c})")));
+                rows.push(header);
+
+                let mut row = vec![matches.len().to_string()];
+                row.extend(self.aggregate(matches, aggregate).into_iter().map(|v| v.map(|v| v.to_string()).unwrap_or_default()));
+                rows.push(row);
+            },
+            None => {
+                let mut header = vec!["path".to_owned()];
+                header.extend(self.columns.iter().cloned());
+                rows.push(header);
+
+                for m in matches {
+                    let mut row = vec![m.path.to_string()];
+                    row.extend(m.values.iter().map(|values| values.iter().map(value_text).collect::<Vec<String>>().join(", ")));
+                    rows.push(row);
+                }
+            }
+        }
+
+        let column_count = rows[0].len();
+        let widths: Vec<usize> = (0..column_count).map(|c| rows.iter().map(|r| r[c].chars().count()).max().unwrap()).collect();
+
+        let mut output = String::new();
+        for row in rows {
+            let line = row
+                .iter()
+                .zip(widths.iter())
+                .map(|(cell, width)| format!("{cell:width$}"))
+                .collect::<Vec<String>>()
+                .join(" | ");
+            output += line.trim_end();
+            output += "\n";
+        }
+        output
+    }
+
+    /// Format the matches as JSON.
+    ///
+    /// This is an array of objects with the `path` of each tag and its selected `fields`, or, if `aggregate` is set,
+    /// a single object with the number of `tags` and the aggregate of each field.
+    pub fn format_json(&self, matches: &[QueryMatch], aggregate: Option<Aggregate>) -> String {
+        let value = match aggregate {
+            Some(aggregate) => {
+                let mut fields = Map::new();
+                for (column, value) in self.columns.iter().zip(self.aggregate(matches, aggregate)) {
+                    fields.insert(column.clone(), value.and_then(Number::from_f64).map(Value::Number).unwrap_or(Value::Null));
+                }
+                let mut object = Map::new();
+                object.insert("tags".to_owned(), Value::Number(matches.len().into()));
+                object.insert(aggregate.to_string(), Value::Object(fields));
+                Value::Object(object)
+            },
+            None => Value::Array(matches.iter().map(|m| {
+                let mut object = Map::new();
+                object.insert("path".to_owned(), Value::String(m.path.to_string()));
+                if !self.columns.is_empty() {
+                    let fields = self.columns.iter().cloned().zip(m.values.iter().map(|v| Value::Array(v.clone()))).collect();
+                    object.insert("fields".to_owned(), Value::Object(fields));
+                }
+                Value::Object(object)
+            }).collect())
+        };
+
+        let mut output = String::new();
+        write_value(&mut output, &value, 0);
+        output.push('\n');
+        output
+    }
+
+    fn aggregate(&self, matches: &[QueryMatch], aggregate: Aggregate) -> Vec<Option<f64>> {
+        (0..self.columns.len())
+            .map(|c| aggregate.apply(&matches.iter().flat_map(|m| m.values[c].iter()).collect::<Vec<&Value>>()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,72 @@
+use definitions::{Weapon, WeaponTrigger};
+use primitives::primitive::{TagGroup, TagPath, TagReference};
+use super::{Aggregate, Predicate, Query};
+
+fn weapon() -> Weapon {
+    let mut weapon = Weapon::default();
+    for (rounds, projectile) in [(1, "weapons\\pistol\\bullet"), (4, "weapons\\shotgun\\pellet")] {
+        weapon.triggers.items.push(WeaponTrigger {
+            rounds_per_shot: rounds,
+            acceleration_time: 0.1,
+            projectile: TagReference::Set(TagPath::new(projectile, TagGroup::Projectile).unwrap()),
+            ..Default::default()
+        });
+    }
+    weapon
+}
+
+fn evaluate(predicate: &str) -> bool {
+    predicate.parse::<Predicate>().unwrap().evaluate(&weapon()).unwrap()
+}
+
+#[test]
+fn predicates() {
+    assert!(evaluate("triggers[*].rounds_per_shot > 3"));
+    assert!(evaluate("triggers[*].rounds_per_shot<=1"));
+    assert!(!evaluate("triggers[*].rounds_per_shot >= 5"));
+    assert!(evaluate("triggers[*].rounds_per_shot != 2"));
+    assert!(!evaluate("triggers[*].rounds_per_shot != 4"));
+    assert!(evaluate("triggers[0].projectile == weapons/pistol/bullet.projectile"));
+    assert!(evaluate("triggers[*].projectile =~ ^weapons/shotgun/"));
+    assert!(evaluate("triggers[*].projectile !~ rocket"));
+    assert!(evaluate("secondary_trigger_mode == normal"));
+    assert!(evaluate("label == \"\""));
+
+    assert!("triggers[*].rounds_per_shot".parse::<Predicate>().is_err());
+    assert!("> 3".parse::<Predicate>().is_err());
+    assert!("label =~ (".parse::<Predicate>().is_err());
+    assert!("not_a_field == 3".parse::<Predicate>().unwrap().evaluate(&weapon()).is_err());
+}
+
+#[test]
+fn float_predicates() {
+    assert!(evaluate("triggers[0].acceleration_time == 0.1"));
+    assert!(evaluate("triggers[0].acceleration_time <= 0.1"));
+    assert!(evaluate("triggers[0].acceleration_time >= 0.1"));
+    assert!(!evaluate("triggers[0].acceleration_time < 0.1"));
+    assert!(!evaluate("triggers[0].acceleration_time > 0.1"));
+    assert!(evaluate("triggers[0].acceleration_time > 0.09"));
+}
+
+#[test]
+fn query_output() {
+    let query = Query {
+        predicates: vec!["triggers[*].rounds_per_shot > 0".parse().unwrap()],
+        columns: vec!["triggers[*].rounds_per_shot".to_owned()]
+    };
+    let path = TagPath::new("weapons\\shotgun\\shotgun", TagGroup::Weapon).unwrap();
+    let matches = vec![query.evaluate(&path, &weapon()).unwrap()];
+
+    let table = query.format_table(&matches, None);
+    assert!(table.starts_with("path "));
+    assert!(table.lines().nth(1).unwrap().ends_with(" | 1, 4"));
+    assert!(query.format_table(&matches, Some(Aggregate::Sum)).ends_with("1    | 5\n"));
+
+    let json = query.format_json(&matches, Some(Aggregate::Max));
+    assert!(json.contains("\"triggers[*].rounds_per_shot\": 4"));
+
+    let mismatch = Query { predicates: vec!["triggers[*].rounds_per_shot > 4".parse().unwrap()], columns: vec![] };
+    assert!(mismatch.evaluate(&path, &weapon()).is_none());
+    let wrong_group = Query { predicates: vec!["initial_velocity > 0".parse().unwrap()], columns: vec![] };
+    assert!(wrong_group.evaluate(&path, &weapon()).is_none());
+}
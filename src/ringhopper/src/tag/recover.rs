@@ -6,20 +6,42 @@ use primitives::error::RinghopperResult;
 use primitives::primitive::{TagGroup, TagPath};
 use primitives::tag::PrimaryTagStructDyn;
 use crate::tag::bitmap::extract_compressed_color_plate_data;
+use crate::tag::model::jms::extract_jms_from_model;
+use crate::tag::model_animations::jma::extract_jma_from_model_animations;
+use crate::tag::scenario_structure_bsp::export::extract_structure_bsp_geometry;
 use crate::tag::unicode_string_list::UnicodeStringListFunctions;
 
-pub type RecoverFunction = fn(tag_path: &TagPath, tag_data: &Box<dyn PrimaryTagStructDyn>) -> RinghopperResult<Option<HashMap<PathBuf, Vec<u8>>>>;
+/// Files recovered from a tag.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RecoveredFiles {
+    /// Recovered data, keyed by its path relative to the data directory.
+    pub files: HashMap<PathBuf, Vec<u8>>,
+
+    /// Anything that could not be recovered.
+    pub warnings: Vec<String>
+}
+
+impl From<HashMap<PathBuf, Vec<u8>>> for RecoveredFiles {
+    fn from(files: HashMap<PathBuf, Vec<u8>>) -> Self {
+        Self { files, warnings: Vec::new() }
+    }
+}
+
+pub type RecoverFunction = fn(tag_path: &TagPath, tag_data: &Box<dyn PrimaryTagStructDyn>) -> RinghopperResult<Option<RecoveredFiles>>;
 
 pub fn get_recover_function(group: TagGroup) -> Option<RecoverFunction> {
     match group {
         TagGroup::Bitmap => Some(recover_bitmap),
+        TagGroup::Model | TagGroup::GBXModel => Some(recover_model),
+        TagGroup::ModelAnimations => Some(recover_model_animations),
         TagGroup::Scenario => Some(recover_scenario_scripts),
+        TagGroup::ScenarioStructureBSP => Some(recover_scenario_structure_bsp),
         TagGroup::UnicodeStringList => Some(recover_unicode_string_lists),
         _ => None
     }
 }
 
-fn recover_bitmap(tag_path: &TagPath, tag_data: &Box<dyn PrimaryTagStructDyn>) -> RinghopperResult<Option<HashMap<PathBuf, Vec<u8>>>> {
+fn recover_bitmap(tag_path: &TagPath, tag_data: &Box<dyn PrimaryTagStructDyn>) -> RinghopperResult<Option<RecoveredFiles>> {
     let bitmap = tag_data.as_any().downcast_ref::<Bitmap>().unwrap();
     let color_plate_data = match extract_compressed_color_plate_data(bitmap)? {
         Some(n) => n,
@@ -30,19 +52,69 @@ fn recover_bitmap(tag_path: &TagPath, tag_data: &Box<dyn PrimaryTagStructDyn>) -
     let mut fs = HashMap::new();
     fs.insert(result, color_plate_data.to_tiff());
 
-    Ok(Some(fs))
+    Ok(Some(fs.into()))
+}
+
+fn recover_model(tag_path: &TagPath, tag_data: &Box<dyn PrimaryTagStructDyn>) -> RinghopperResult<Option<RecoveredFiles>> {
+    let jms_files = extract_jms_from_model(tag_data.as_ref())?;
+    if jms_files.is_empty() {
+        return Ok(None)
+    }
+
+    let base_models_dir = PathBuf::from(tag_path.to_native_path()).parent().unwrap().join("models");
+    let mut fs = HashMap::new();
+    for (name, jms) in jms_files {
+        fs.insert(base_models_dir.join(format!("{name}.jms")), jms.to_text().into_bytes());
+    }
+
+    Ok(Some(fs.into()))
+}
+
+fn recover_model_animations(tag_path: &TagPath, tag_data: &Box<dyn PrimaryTagStructDyn>) -> RinghopperResult<Option<RecoveredFiles>> {
+    let model_animations = tag_data.get_ref::<ModelAnimations>().unwrap();
+    let extracted = extract_jma_from_model_animations(model_animations)?;
+    if extracted.animations.is_empty() && extracted.warnings.is_empty() {
+        return Ok(None)
+    }
+
+    let base_animations_dir = PathBuf::from(tag_path.to_native_path()).parent().unwrap().join("animations");
+    let mut fs = HashMap::new();
+    for (name, source_type, jma) in extracted.animations {
+        fs.insert(base_animations_dir.join(format!("{name}.{}", source_type.extension())), jma.to_text().into_bytes());
+    }
+
+    Ok(Some(RecoveredFiles { files: fs, warnings: extracted.warnings }))
+}
+
+fn recover_scenario_structure_bsp(tag_path: &TagPath, tag_data: &Box<dyn PrimaryTagStructDyn>) -> RinghopperResult<Option<RecoveredFiles>> {
+    let bsp = tag_data.get_ref::<ScenarioStructureBSP>().unwrap();
+    let geometry = extract_structure_bsp_geometry(bsp)?;
+
+    let base_models_dir = PathBuf::from(tag_path.to_native_path()).parent().unwrap().join("models");
+    let name = tag_path.base_name();
+    let mut fs = HashMap::new();
+    fs.insert(base_models_dir.join(format!("{name}.jms")), geometry.to_jms(false).to_text().into_bytes());
+    fs.insert(base_models_dir.join(format!("{name}.obj")), geometry.to_obj(false).to_text().into_bytes());
+
+    // Lightmap texture coordinates are a second UV channel, which neither format supports, so they get their own files.
+    if geometry.has_lightmap_texture_coordinates() {
+        fs.insert(base_models_dir.join(format!("{name} lightmap.jms")), geometry.to_jms(true).to_text().into_bytes());
+        fs.insert(base_models_dir.join(format!("{name} lightmap.obj")), geometry.to_obj(true).to_text().into_bytes());
+    }
+
+    Ok(Some(fs.into()))
 }
 
-fn recover_unicode_string_lists(tag_path: &TagPath, tag_data: &Box<dyn PrimaryTagStructDyn>) -> RinghopperResult<Option<HashMap<PathBuf, Vec<u8>>>> {
+fn recover_unicode_string_lists(tag_path: &TagPath, tag_data: &Box<dyn PrimaryTagStructDyn>) -> RinghopperResult<Option<RecoveredFiles>> {
     let unicode_string_list: &UnicodeStringList = tag_data.as_any().downcast_ref().unwrap();
     let data = unicode_string_list.as_text_data().map_err(|e| InvalidTagData(format!("{e:?}")))?;
     let result = PathBuf::from(tag_path.to_native_path()).with_extension("txt");
     let mut fs = HashMap::new();
     fs.insert(result, data);
-    Ok(Some(fs))
+    Ok(Some(fs.into()))
 }
 
-fn recover_scenario_scripts(tag_path: &TagPath, tag_data: &Box<dyn PrimaryTagStructDyn>) -> RinghopperResult<Option<HashMap<PathBuf, Vec<u8>>>> {
+fn recover_scenario_scripts(tag_path: &TagPath, tag_data: &Box<dyn PrimaryTagStructDyn>) -> RinghopperResult<Option<RecoveredFiles>> {
     let scenario: &Scenario = tag_data.as_any().downcast_ref().unwrap();
     if scenario.source_files.items.is_empty() {
         return Ok(None)
@@ -67,5 +139,5 @@ fn recover_scenario_scripts(tag_path: &TagPath, tag_data: &Box<dyn PrimaryTagStr
         fs.insert(path, data);
     }
 
-    Ok(Some(fs))
+    Ok(Some(fs.into()))
 }
@@ -7,6 +7,11 @@ use primitives::error::{Error, RinghopperResult};
 use primitives::parse::SimpleTagData;
 use primitives::primitive::{Data, ID, Index, String32};
 
+pub mod clean;
+pub mod convert_type;
+pub mod merge;
+pub mod placement;
+
 fn for_each_node_in_scenario<
     From: ByteOrder,
     T: FnMut(&mut [u8], &ScenarioScriptNodeTable),
@@ -488,3 +493,6 @@ fn check_bad_scripts(node: ID, extracted_nodes: &Vec<ScenarioScriptNode>, todo:
 
     Ok(())
 }
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,219 @@
+use std::collections::HashSet;
+use definitions::{Scenario, ScenarioScriptNode, ScenarioScriptValueType};
+use primitives::byteorder::BigEndian;
+use primitives::error::{Error, RinghopperResult};
+use primitives::parse::SimpleTagData;
+use primitives::primitive::{Index, ScenarioScriptNodeValue, String32, TagReference};
+use super::for_each_node_in_scenario;
+
+/// Entries removed from a scenario by [`clean_scenario`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ScenarioCleanReport {
+    /// Unreferenced palette entries, along with the palette they were removed from.
+    pub removed_palette_entries: Vec<(&'static str, TagReference)>,
+
+    /// Unreferenced object names.
+    pub removed_object_names: Vec<String32>
+}
+
+impl ScenarioCleanReport {
+    /// Return `true` if nothing was removed.
+    pub fn is_empty(&self) -> bool {
+        self.removed_palette_entries.is_empty() && self.removed_object_names.is_empty()
+    }
+}
+
+fn mark_used(used: &mut [bool], index: Index, what: &str) -> RinghopperResult<()> {
+    if let Some(index) = index {
+        *used
+            .get_mut(index as usize)
+            .ok_or_else(|| Error::InvalidTagData(format!("Scenario references out-of-bounds {what} #{index}")))? = true;
+    }
+    Ok(())
+}
+
+/// Remove unused items, returning the new index of each item and the removed items.
+fn remove_unused<T>(items: &mut Vec<T>, used: &[bool]) -> (Vec<Index>, Vec<T>) {
+    let mut map = Vec::with_capacity(items.len());
+    let mut kept = Vec::with_capacity(items.len());
+    let mut removed = Vec::new();
+    for (item, used) in std::mem::take(items).into_iter().zip(used) {
+        if *used {
+            map.push(Some(kept.len() as u16));
+            kept.push(item);
+        }
+        else {
+            map.push(None);
+            removed.push(item);
+        }
+    }
+    *items = kept;
+    (map, removed)
+}
+
+fn remap(map: &[Index], index: Index) -> Index {
+    index.and_then(|i| map[i as usize])
+}
+
+fn node_references_object_name(node: &ScenarioScriptNode) -> bool {
+    node.flags.is_primitive
+        && !node.flags.is_global
+        && !node.flags.is_local_variable
+        && i16::from(node.data) >= 0
+        && matches!(node._type,
+            ScenarioScriptValueType::ObjectName
+            | ScenarioScriptValueType::UnitName
+            | ScenarioScriptValueType::VehicleName
+            | ScenarioScriptValueType::WeaponName
+            | ScenarioScriptValueType::DeviceName
+            | ScenarioScriptValueType::SceneryName)
+}
+
+/// Get every token in script source files, lowercased, so object names used by uncompiled scripts can be found.
+///
+/// Comments and strings are included, which may keep names that aren't actually used, but never removes one that is.
+fn script_source_tokens(scenario: &Scenario) -> HashSet<String> {
+    scenario.source_files
+        .items
+        .iter()
+        .flat_map(|f| String::from_utf8_lossy(&f.source.bytes)
+            .split(|c: char| c.is_whitespace() || matches!(c, '(' | ')' | '"' | ';'))
+            .filter(|t| !t.is_empty())
+            .map(|t| t.to_ascii_lowercase())
+            .collect::<Vec<_>>())
+        .collect()
+}
+
+/// Remove palette entries and object names that nothing in the scenario references, renumbering everything that
+/// references them.
+///
+/// Object names used by compiled scripts are kept, and the scripts are updated to the new indices. If the scripts
+/// have not been compiled, object names which appear anywhere in the script source files are kept instead.
+pub fn clean_scenario(scenario: &mut Scenario) -> RinghopperResult<ScenarioCleanReport> {
+    let mut report = ScenarioCleanReport::default();
+
+    // Object names
+    let mut used_names = vec![false; scenario.object_names.items.len()];
+
+    macro_rules! mark_placement_names {
+        ($($block:ident),*) => {
+            $(for placement in &scenario.$block.items {
+                mark_used(&mut used_names, placement.name, "object name")?;
+            })*
+        };
+    }
+    mark_placement_names!(scenery, bipeds, vehicles, equipment, weapons, machines, controls, light_fixtures, sound_scenery);
+
+    for command in scenario.command_lists.items.iter().flat_map(|c| c.commands.items.iter()) {
+        mark_used(&mut used_names, command.object_name, "object name")?;
+    }
+    for participant in scenario.ai_conversations.items.iter().flat_map(|c| c.participants.items.iter()) {
+        mark_used(&mut used_names, participant.use_this_object, "object name")?;
+        mark_used(&mut used_names, participant.set_new_name, "object name")?;
+    }
+    for object in &scenario.scavenger_hunt_objects.items {
+        mark_used(&mut used_names, object.object_name_index, "object name")?;
+    }
+
+    let has_scripts = !scenario.script_syntax_data.bytes.is_empty();
+    if has_scripts {
+        let mut result = Ok(());
+        for_each_node_in_scenario::<BigEndian, _, _, _>(
+            scenario,
+            |_, _| (),
+            |_, node| if node_references_object_name(node) && result.is_ok() {
+                result = mark_used(&mut used_names, Some(i16::from(node.data) as u16), "object name");
+            },
+            |_| ()
+        )?;
+        result?;
+    }
+    else if !scenario.source_files.items.is_empty() {
+        let tokens = script_source_tokens(scenario);
+        for (name, used) in scenario.object_names.items.iter().zip(used_names.iter_mut()) {
+            *used |= tokens.contains(&name.name.as_str().to_ascii_lowercase());
+        }
+    }
+
+    let (name_map, removed_names) = remove_unused(&mut scenario.object_names.items, &used_names);
+    report.removed_object_names = removed_names.into_iter().map(|n| n.name).collect();
+
+    macro_rules! remap_names {
+        ($($block:ident),*) => {
+            $(for placement in &mut scenario.$block.items {
+                placement.name = remap(&name_map, placement.name);
+            })*
+        };
+    }
+    remap_names!(scenery, bipeds, vehicles, equipment, weapons, machines, controls, light_fixtures, sound_scenery);
+
+    for command in scenario.command_lists.items.iter_mut().flat_map(|c| c.commands.items.iter_mut()) {
+        command.object_name = remap(&name_map, command.object_name);
+    }
+    for participant in scenario.ai_conversations.items.iter_mut().flat_map(|c| c.participants.items.iter_mut()) {
+        participant.use_this_object = remap(&name_map, participant.use_this_object);
+        participant.set_new_name = remap(&name_map, participant.set_new_name);
+    }
+    for object in &mut scenario.scavenger_hunt_objects.items {
+        object.object_name_index = remap(&name_map, object.object_name_index);
+    }
+
+    if has_scripts && !report.removed_object_names.is_empty() {
+        for_each_node_in_scenario::<BigEndian, _, _, _>(
+            scenario,
+            |_, _| (),
+            |data, node| if node_references_object_name(node) {
+                let mut node = *node;
+                let index = remap(&name_map, Some(i16::from(node.data) as u16)).unwrap();
+                node.data = ScenarioScriptNodeValue::from(index as i16);
+                node.write::<BigEndian>(data, 0, data.len()).unwrap();
+            },
+            |_| ()
+        )?;
+    }
+
+    // Palettes
+    macro_rules! clean_palette {
+        ($palette:ident, $field:ident, $what:expr, $block:ident => $index:ident) => {{
+            let mut used = vec![false; scenario.$palette.items.len()];
+            for item in &scenario.$block.items {
+                mark_used(&mut used, item.$index, $what)?;
+            }
+            let (map, removed) = remove_unused(&mut scenario.$palette.items, &used);
+            report.removed_palette_entries.extend(removed.into_iter().map(|p| ($what, p.$field)));
+            for item in &mut scenario.$block.items {
+                item.$index = remap(&map, item.$index);
+            }
+        }};
+    }
+
+    clean_palette!(scenery_palette, name, "scenery palette", scenery => _type);
+    clean_palette!(biped_palette, name, "biped palette", bipeds => _type);
+    clean_palette!(vehicle_palette, name, "vehicle palette", vehicles => _type);
+    clean_palette!(equipment_palette, name, "equipment palette", equipment => _type);
+    clean_palette!(weapon_palette, name, "weapon palette", weapons => _type);
+    clean_palette!(machine_palette, name, "machine palette", machines => _type);
+    clean_palette!(control_palette, name, "control palette", controls => _type);
+    clean_palette!(light_fixture_palette, name, "light fixture palette", light_fixtures => _type);
+    clean_palette!(sound_scenery_palette, name, "sound scenery palette", sound_scenery => _type);
+    clean_palette!(decal_palette, reference, "decal palette", decals => decal_type);
+
+    // Actors can be placed by squads or by the squads' starting locations.
+    let mut used_actors = vec![false; scenario.actor_palette.items.len()];
+    for squad in scenario.encounters.items.iter().flat_map(|e| e.squads.items.iter()) {
+        mark_used(&mut used_actors, squad.actor_type, "actor palette")?;
+        for location in &squad.starting_locations.items {
+            mark_used(&mut used_actors, location.actor_type, "actor palette")?;
+        }
+    }
+    let (actor_map, removed_actors) = remove_unused(&mut scenario.actor_palette.items, &used_actors);
+    report.removed_palette_entries.extend(removed_actors.into_iter().map(|p| ("actor palette", p.reference)));
+    for squad in scenario.encounters.items.iter_mut().flat_map(|e| e.squads.items.iter_mut()) {
+        squad.actor_type = remap(&actor_map, squad.actor_type);
+        for location in &mut squad.starting_locations.items {
+            location.actor_type = remap(&actor_map, location.actor_type);
+        }
+    }
+
+    Ok(report)
+}
@@ -0,0 +1,114 @@
+use definitions::{Scenario, ScenarioSpawnType, ScenarioType};
+use primitives::dynamic::DynamicEnumImpl;
+use primitives::engine::Engine;
+use primitives::primitive::TagPath;
+use crate::tag::tree::TagTree;
+
+/// Changes made to a scenario by [`convert_scenario_type`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ScenarioTypeConversion {
+    /// Number of netgame flags removed.
+    pub removed_netgame_flags: usize,
+
+    /// Number of netgame equipment placements removed.
+    pub removed_netgame_equipment: usize,
+
+    /// Number of multiplayer starting equipment entries removed.
+    pub removed_starting_equipment: usize,
+
+    /// Number of player starting locations removed.
+    pub removed_player_starting_locations: usize,
+
+    /// Number of player starting locations whose spawn types were changed.
+    pub updated_player_starting_locations: usize,
+
+    /// Data that was kept but can't be used in the new scenario type.
+    pub warnings: Vec<String>
+}
+
+/// Get the tags the engine requires for building a scenario of the given type, besides the scenario tag.
+pub fn required_tags_for_scenario_type(engine: &Engine, scenario_type: ScenarioType) -> impl Iterator<Item = &'static str> {
+    let other: &'static [&'static str] = match scenario_type {
+        ScenarioType::Singleplayer => engine.required_tags.singleplayer,
+        ScenarioType::Multiplayer => engine.required_tags.multiplayer,
+        ScenarioType::UserInterface => engine.required_tags.user_interface,
+    };
+    engine.required_tags.all.iter().chain(other.iter()).copied()
+}
+
+/// Get all tags required by the engine for the given scenario type that are not present in the tag tree.
+pub fn get_missing_required_tags<T: TagTree + ?Sized>(tags: &T, engine: &Engine, scenario_type: ScenarioType) -> Vec<TagPath> {
+    required_tags_for_scenario_type(engine, scenario_type)
+        .map(|path| TagPath::from_path(path).unwrap())
+        .filter(|path| !tags.contains(path))
+        .collect()
+}
+
+fn warn_if_present(warnings: &mut Vec<String>, count: usize, what: &str, new_type: ScenarioType) {
+    if count > 0 {
+        warnings.push(format!("{count} {what} can't be used in a {} scenario", new_type.to_str()));
+    }
+}
+
+/// Convert the scenario to a different type.
+///
+/// Netgame components are stripped when converting away from multiplayer. When converting to multiplayer, player
+/// starting locations without any spawn types are made to spawn in all games. Player starting locations are removed
+/// entirely for user interface scenarios, since there is no player.
+///
+/// AI and cinematic data are never removed, but a warning is emitted for anything that can't exist in the new type.
+pub fn convert_scenario_type(scenario: &mut Scenario, new_type: ScenarioType) -> ScenarioTypeConversion {
+    let mut conversion = ScenarioTypeConversion::default();
+    scenario._type = new_type;
+
+    if new_type != ScenarioType::Multiplayer {
+        conversion.removed_netgame_flags = std::mem::take(&mut scenario.netgame_flags.items).len();
+        conversion.removed_netgame_equipment = std::mem::take(&mut scenario.netgame_equipment.items).len();
+        conversion.removed_starting_equipment = std::mem::take(&mut scenario.starting_equipment.items).len();
+    }
+
+    match new_type {
+        ScenarioType::Multiplayer => {
+            for location in &mut scenario.player_starting_locations.items {
+                let types = [location.type_0, location.type_1, location.type_2, location.type_3];
+                if types.iter().all(|t| *t == ScenarioSpawnType::None) {
+                    location.type_0 = ScenarioSpawnType::AllGames;
+                    conversion.updated_player_starting_locations += 1;
+                }
+            }
+            if scenario.player_starting_locations.items.is_empty() {
+                conversion.warnings.push("scenario has no player starting locations, so players will have nowhere to spawn".to_owned());
+            }
+        },
+        ScenarioType::Singleplayer => {
+            for location in &mut scenario.player_starting_locations.items {
+                let types = [&mut location.type_0, &mut location.type_1, &mut location.type_2, &mut location.type_3];
+                if types.iter().any(|t| **t != ScenarioSpawnType::None) {
+                    types.into_iter().for_each(|t| *t = ScenarioSpawnType::None);
+                    conversion.updated_player_starting_locations += 1;
+                }
+            }
+            if scenario.player_starting_locations.items.is_empty() {
+                conversion.warnings.push("scenario has no player starting locations, so the player will have nowhere to spawn".to_owned());
+            }
+        },
+        ScenarioType::UserInterface => {
+            conversion.removed_player_starting_locations = std::mem::take(&mut scenario.player_starting_locations.items).len();
+        }
+    }
+
+    let warnings = &mut conversion.warnings;
+    if new_type != ScenarioType::Singleplayer {
+        warn_if_present(warnings, scenario.encounters.items.len(), "AI encounter(s)", new_type);
+        warn_if_present(warnings, scenario.command_lists.items.len(), "AI command list(s)", new_type);
+        warn_if_present(warnings, scenario.ai_conversations.items.len(), "AI conversation(s)", new_type);
+    }
+    if new_type == ScenarioType::Multiplayer {
+        warn_if_present(warnings, scenario.recorded_animations.items.len(), "recorded animation(s)", new_type);
+        warn_if_present(warnings, scenario.cutscene_flags.items.len(), "cutscene flag(s)", new_type);
+        warn_if_present(warnings, scenario.cutscene_camera_points.items.len(), "cutscene camera point(s)", new_type);
+        warn_if_present(warnings, scenario.cutscene_titles.items.len(), "cutscene title(s)", new_type);
+    }
+
+    conversion
+}
@@ -0,0 +1,457 @@
+use std::collections::HashSet;
+use definitions::{Scenario, ScenarioScriptNode, ScenarioScriptNodeTable, ScenarioScriptValueType};
+use primitives::byteorder::BigEndian;
+use primitives::error::{Error, RinghopperResult};
+use primitives::parse::SimpleTagData;
+use primitives::primitive::{Data, ID, IDType, Index, ScenarioScriptNodeValue, String32, TagGroup, TagPath};
+use crate::tag::tree::TagTree;
+use super::{for_each_node_in_scenario, get_string_data_for_node};
+
+/// Maps indices in a child scenario to indices in its parent.
+struct IndexMap {
+    what: &'static str,
+    indices: Vec<u16>
+}
+
+impl IndexMap {
+    fn get(&self, index: Index) -> RinghopperResult<Index> {
+        let Some(index) = index else {
+            return Ok(None)
+        };
+        self.indices
+            .get(index as usize)
+            .map(|i| Some(*i))
+            .ok_or_else(|| Error::InvalidTagData(format!("Child scenario references out-of-bounds {} #{index}", self.what)))
+    }
+
+    /// Remap a script value whose lower 16 bits are an index into this map, preserving the upper bits.
+    fn get_script_value(&self, value: ScenarioScriptNodeValue) -> RinghopperResult<ScenarioScriptNodeValue> {
+        let index = i16::from(value);
+        if index < 0 {
+            return Ok(value)
+        }
+        let new_index = self.get(Some(index as u16))?.unwrap();
+        Ok(ScenarioScriptNodeValue { data: (value.data & 0xFFFF0000) | new_index as u32 })
+    }
+}
+
+fn to_index(index: usize, what: &str) -> RinghopperResult<u16> {
+    match u16::try_from(index) {
+        Ok(n) if n != u16::MAX => Ok(n),
+        _ => Err(Error::InvalidTagData(format!("Merged scenario has too many {what}")))
+    }
+}
+
+/// Append child items to the parent, reusing any parent item with the same key.
+///
+/// Items with no key are always appended.
+fn merge_by_key<T: Clone, K: PartialEq>(parent: &mut Vec<T>, child: &[T], what: &'static str, key: impl Fn(&T) -> Option<K>) -> RinghopperResult<IndexMap> {
+    let mut indices = Vec::with_capacity(child.len());
+    for item in child {
+        let existing = key(item).and_then(|k| parent.iter().position(|p| key(p).as_ref() == Some(&k)));
+        let index = match existing {
+            Some(n) => n,
+            None => {
+                parent.push(item.clone());
+                parent.len() - 1
+            }
+        };
+        indices.push(to_index(index, what)?);
+    }
+    Ok(IndexMap { what, indices })
+}
+
+/// Append child items to the parent, reusing any identical parent item with the same name.
+///
+/// Returns an error if a parent item has the same name but different contents, since one of them would be lost.
+fn merge_by_name<T: Clone + PartialEq>(parent: &mut Vec<T>, child: &[T], what: &'static str, name: impl Fn(&T) -> String32) -> RinghopperResult<IndexMap> {
+    for item in child {
+        let item_name = name(item);
+        if item_name.as_str().is_empty() {
+            continue
+        }
+        if let Some(existing) = parent.iter().find(|p| name(p) == item_name) {
+            if existing != item {
+                return Err(Error::InvalidTagData(format!("The parent and child scenarios have different {what} named `{item_name}`")))
+            }
+        }
+    }
+    merge_by_key(parent, child, what, |i| Some(name(i)).filter(|n| !n.as_str().is_empty()))
+}
+
+/// Merge a child scenario into a parent scenario.
+///
+/// Palettes, skies, structure BSPs, and script references are deduplicated by tag reference, and object names are
+/// deduplicated by name. Other named items (device groups, trigger volumes, cutscene flags, encounters, etc.) are
+/// deduplicated if they are identical, and it is an error for the two scenarios to define them differently. Every
+/// index in the child's placements, AI, and compiled scripts is remapped to the merged blocks.
+///
+/// The child's `child_scenarios` are not merged; use [`merge_child_scenarios`] to merge a whole hierarchy.
+///
+/// If the scenarios can't be merged, an error is returned and the parent is left unchanged.
+pub fn merge_child_scenario(parent: &mut Scenario, child: &Scenario) -> RinghopperResult<()> {
+    let mut merged = parent.clone();
+    merge_child_scenario_into(&mut merged, child)?;
+    *parent = merged;
+    Ok(())
+}
+
+fn merge_child_scenario_into(parent: &mut Scenario, child: &Scenario) -> RinghopperResult<()> {
+    macro_rules! merge_palette {
+        ($block:ident, $field:ident, $what:expr) => {
+            merge_by_key(&mut parent.$block.items, &child.$block.items, $what, |p| Some(p.$field.clone()))?
+        };
+    }
+
+    macro_rules! merge_named {
+        ($block:ident, $field:ident, $what:expr) => {
+            merge_by_name(&mut parent.$block.items, &child.$block.items, $what, |p| p.$field)?
+        };
+        ($block:ident, $items:expr, $field:ident, $what:expr) => {
+            merge_by_name(&mut parent.$block.items, &$items, $what, |p| p.$field)?
+        };
+    }
+
+    merge_palette!(skies, sky, "skies");
+    let bsps = merge_palette!(structure_bsps, structure_bsp, "structure BSPs");
+    let object_names = merge_by_key(&mut parent.object_names.items, &child.object_names.items, "object names", |o| Some(o.name).filter(|n| !n.as_str().is_empty()))?;
+    let device_groups = merge_named!(device_groups, name, "device groups");
+    let trigger_volumes = merge_named!(trigger_volumes, name, "trigger volumes");
+    let recorded_animations = merge_named!(recorded_animations, name, "recorded animations");
+    let cutscene_flags = merge_named!(cutscene_flags, name, "cutscene flags");
+    let cutscene_camera_points = merge_named!(cutscene_camera_points, name, "cutscene camera points");
+    let cutscene_titles = merge_named!(cutscene_titles, name, "cutscene titles");
+    let starting_profiles = merge_named!(player_starting_profile, name, "player starting profiles");
+
+    // Placements
+    //
+    // Object names are shared by name, so an object name can't be placed by both scenarios.
+    let mut placed_names = HashSet::new();
+    macro_rules! placed_names {
+        ($($block:ident),*) => {
+            $(placed_names.extend(parent.$block.items.iter().filter_map(|p| p.name));)*
+        };
+    }
+    placed_names!(scenery, bipeds, vehicles, equipment, weapons, machines, controls, light_fixtures, sound_scenery);
+
+    macro_rules! merge_placements {
+        ($block:ident, $palette:ident, $what:expr $(, $group:ident)*) => {{
+            let palette = merge_palette!($palette, name, $what);
+            for placement in &child.$block.items {
+                let mut placement = placement.clone();
+                placement._type = palette.get(placement._type)?;
+                placement.name = object_names.get(placement.name)?;
+                if let Some(name) = placement.name {
+                    if !placed_names.insert(name) {
+                        return Err(Error::InvalidTagData(format!("More than one object is placed with the name `{}`", parent.object_names.items[name as usize].name)))
+                    }
+                }
+                $(placement.$group = device_groups.get(placement.$group)?;)*
+                parent.$block.items.push(placement);
+            }
+        }};
+    }
+
+    merge_placements!(scenery, scenery_palette, "scenery palette entries");
+    merge_placements!(bipeds, biped_palette, "biped palette entries");
+    merge_placements!(vehicles, vehicle_palette, "vehicle palette entries");
+    merge_placements!(equipment, equipment_palette, "equipment palette entries");
+    merge_placements!(weapons, weapon_palette, "weapon palette entries");
+    merge_placements!(machines, machine_palette, "machine palette entries", power_group, position_group);
+    merge_placements!(controls, control_palette, "control palette entries", power_group, position_group);
+    merge_placements!(light_fixtures, light_fixture_palette, "light fixture palette entries", power_group, position_group);
+    merge_placements!(sound_scenery, sound_scenery_palette, "sound scenery palette entries");
+
+    let decal_palette = merge_palette!(decal_palette, reference, "decal palette entries");
+    for decal in &child.decals.items {
+        let mut decal = *decal;
+        decal.decal_type = decal_palette.get(decal.decal_type)?;
+        parent.decals.items.push(decal);
+    }
+    merge_palette!(detail_object_collection_palette, reference, "detail object collection palette entries");
+
+    for location in &child.player_starting_locations.items {
+        let mut location = *location;
+        location.bsp_index = bsps.get(location.bsp_index)?;
+        parent.player_starting_locations.items.push(location);
+    }
+    parent.netgame_flags.items.extend_from_slice(&child.netgame_flags.items);
+    parent.netgame_equipment.items.extend_from_slice(&child.netgame_equipment.items);
+    parent.starting_equipment.items.extend_from_slice(&child.starting_equipment.items);
+
+    for volume in &child.bsp_switch_trigger_volumes.items {
+        let mut volume = *volume;
+        volume.trigger_volume = trigger_volumes.get(volume.trigger_volume)?;
+        volume.source = bsps.get(volume.source)?;
+        volume.destination = bsps.get(volume.destination)?;
+        parent.bsp_switch_trigger_volumes.items.push(volume);
+    }
+
+    // AI
+    let actor_palette = merge_palette!(actor_palette, reference, "actor palette entries");
+    let ai_animations = merge_by_name(&mut parent.ai_animation_references.items, &child.ai_animation_references.items, "AI animation references", |a| a.animation_name)?;
+    let ai_scripts = merge_named!(ai_script_references, script_name, "AI script references");
+    let ai_recordings = merge_named!(ai_recording_references, recording_name, "AI recording references");
+
+    let mut command_lists = child.command_lists.items.clone();
+    for list in &mut command_lists {
+        list.manual_bsp_index = bsps.get(list.manual_bsp_index)?;
+        for command in &mut list.commands.items {
+            command.animation = ai_animations.get(command.animation)?;
+            command.script = ai_scripts.get(command.script)?;
+            command.recording = ai_recordings.get(command.recording)?;
+            command.object_name = object_names.get(command.object_name)?;
+        }
+    }
+    let command_lists = merge_named!(command_lists, command_lists, name, "command lists");
+
+    let mut encounters = child.encounters.items.clone();
+    for encounter in &mut encounters {
+        encounter.manual_bsp_index = bsps.get(encounter.manual_bsp_index)?;
+        for squad in &mut encounter.squads.items {
+            squad.actor_type = actor_palette.get(squad.actor_type)?;
+            for position in &mut squad.move_positions.items {
+                position.animation = ai_animations.get(position.animation)?;
+            }
+            for location in &mut squad.starting_locations.items {
+                location.actor_type = actor_palette.get(location.actor_type)?;
+                location.command_list = command_lists.get(location.command_list)?;
+            }
+        }
+        for location in &mut encounter.player_starting_locations.items {
+            location.bsp_index = bsps.get(location.bsp_index)?;
+        }
+    }
+    let encounters = merge_named!(encounters, encounters, name, "encounters");
+
+    let mut conversations = child.ai_conversations.items.clone();
+    for participant in conversations.iter_mut().flat_map(|c| c.participants.items.iter_mut()) {
+        participant.use_this_object = object_names.get(participant.use_this_object)?;
+        participant.set_new_name = object_names.get(participant.set_new_name)?;
+    }
+    let conversations = merge_named!(ai_conversations, conversations, name, "AI conversations");
+
+    // Scripts
+    merge_palette!(references, reference, "script references");
+    merge_named!(source_files, name, "script source files");
+
+    let maps = ScriptIndexMaps {
+        object_names,
+        device_groups,
+        trigger_volumes,
+        recorded_animations,
+        cutscene_flags,
+        cutscene_camera_points,
+        cutscene_titles,
+        starting_profiles,
+        command_lists,
+        encounters,
+        conversations
+    };
+    merge_scripts(parent, child, &maps)
+}
+
+/// Open and merge every child scenario referenced by a scenario, including any child scenarios they reference.
+///
+/// The merged child scenarios are removed from the scenario's `child_scenarios`.
+pub fn merge_child_scenarios<T: TagTree + ?Sized>(scenario: &mut Scenario, tags: &T) -> RinghopperResult<()> {
+    merge_child_scenarios_recursive(scenario, tags, &mut Vec::new())
+}
+
+fn merge_child_scenarios_recursive<T: TagTree + ?Sized>(scenario: &mut Scenario, tags: &T, stack: &mut Vec<TagPath>) -> RinghopperResult<()> {
+    let children: Vec<TagPath> = scenario.child_scenarios.items.iter().filter_map(|c| c.child_scenario.path()).map(|p| p.to_owned()).collect();
+    scenario.child_scenarios.items.clear();
+
+    for path in children {
+        if path.group() != TagGroup::Scenario {
+            return Err(Error::InvalidTagData(format!("Child scenario {path} is not a scenario")))
+        }
+        if stack.contains(&path) {
+            return Err(Error::InvalidTagData(format!("Child scenario {path} references itself")))
+        }
+
+        let tag = tags.open_tag_copy(&path)?;
+        let mut child: Scenario = tag.get_ref::<Scenario>().unwrap().to_owned();
+
+        stack.push(path.clone());
+        merge_child_scenarios_recursive(&mut child, tags, stack)?;
+        stack.pop();
+
+        merge_child_scenario(scenario, &child).map_err(|e| Error::InvalidTagData(format!("Failed to merge child scenario {path}: {e}")))?;
+    }
+
+    Ok(())
+}
+
+struct ScriptIndexMaps {
+    object_names: IndexMap,
+    device_groups: IndexMap,
+    trigger_volumes: IndexMap,
+    recorded_animations: IndexMap,
+    cutscene_flags: IndexMap,
+    cutscene_camera_points: IndexMap,
+    cutscene_titles: IndexMap,
+    starting_profiles: IndexMap,
+    command_lists: IndexMap,
+    encounters: IndexMap,
+    conversations: IndexMap
+}
+
+impl ScriptIndexMaps {
+    fn for_type(&self, value_type: ScenarioScriptValueType) -> Option<&IndexMap> {
+        match value_type {
+            ScenarioScriptValueType::ObjectName
+            | ScenarioScriptValueType::UnitName
+            | ScenarioScriptValueType::VehicleName
+            | ScenarioScriptValueType::WeaponName
+            | ScenarioScriptValueType::DeviceName
+            | ScenarioScriptValueType::SceneryName => Some(&self.object_names),
+            ScenarioScriptValueType::DeviceGroup => Some(&self.device_groups),
+            ScenarioScriptValueType::TriggerVolume => Some(&self.trigger_volumes),
+            ScenarioScriptValueType::CutsceneRecording => Some(&self.recorded_animations),
+            ScenarioScriptValueType::CutsceneFlag => Some(&self.cutscene_flags),
+            ScenarioScriptValueType::CutsceneCameraPoint => Some(&self.cutscene_camera_points),
+            ScenarioScriptValueType::CutsceneTitle => Some(&self.cutscene_titles),
+            ScenarioScriptValueType::StartingProfile => Some(&self.starting_profiles),
+            ScenarioScriptValueType::AiCommandList => Some(&self.command_lists),
+            ScenarioScriptValueType::Conversation => Some(&self.conversations),
+
+            // The encounter is stored in the lower 16 bits; the upper bits select a squad or platoon in it.
+            ScenarioScriptValueType::Ai => Some(&self.encounters),
+            _ => None
+        }
+    }
+}
+
+fn read_script_nodes(scenario: &Scenario) -> RinghopperResult<Option<(ScenarioScriptNodeTable, Vec<ScenarioScriptNode>)>> {
+    if scenario.script_syntax_data.bytes.is_empty() {
+        return Ok(None)
+    }
+
+    let mut syntax_data_only = Scenario { script_syntax_data: scenario.script_syntax_data.clone(), ..Default::default() };
+    let mut table = None;
+    let mut nodes = Vec::new();
+    for_each_node_in_scenario::<BigEndian, _, _, _>(
+        &mut syntax_data_only,
+        |_, t| table = Some(*t),
+        |_, node| nodes.push(*node),
+        |_| ()
+    )?;
+    Ok(Some((table.unwrap(), nodes)))
+}
+
+/// Append the child's compiled scripts and globals to the parent, remapping node IDs, string offsets, and any
+/// indices the nodes reference.
+fn merge_scripts(parent: &mut Scenario, child: &Scenario, maps: &ScriptIndexMaps) -> RinghopperResult<()> {
+    for script in &child.scripts {
+        if parent.scripts.items.iter().any(|s| s.name == script.name) {
+            return Err(Error::InvalidTagData(format!("The parent and child scenarios both have a script named `{}`", script.name)))
+        }
+    }
+    for global in &child.globals {
+        if parent.globals.items.iter().any(|g| g.name == global.name) {
+            return Err(Error::InvalidTagData(format!("The parent and child scenarios both have a global named `{}`", global.name)))
+        }
+    }
+
+    let Some((child_table, child_nodes)) = read_script_nodes(child)? else {
+        return Ok(())
+    };
+    let (table, mut nodes) = read_script_nodes(parent)?.unwrap_or((child_table, Vec::new()));
+
+    // Appended nodes are salted the same way as the parent's nodes so their IDs stay consistent with the table.
+    let salt = nodes.first()
+        .or(child_nodes.first())
+        .and_then(|n| ID::from_u32((n.salt as u32) << 16).salt())
+        .unwrap_or(IDType::ScriptNode.salt());
+    let node_id = |index: usize| ID::new(Some(index as u16), salt);
+
+    let node_offset = nodes.len();
+    let string_offset = parent.script_string_data.bytes.len() as u32;
+    let total_nodes = to_index(node_offset + child_nodes.len(), "script nodes")?;
+    let script_offset = parent.scripts.items.len();
+    let global_offset = parent.globals.items.len();
+    to_index(script_offset + child.scripts.items.len(), "scripts")?;
+    to_index(global_offset + child.globals.items.len(), "globals")?;
+
+    let remap_id = |id: ID| -> RinghopperResult<ID> {
+        let Some(index) = id.index() else {
+            return Ok(id)
+        };
+        if index as usize >= child_nodes.len() {
+            return Err(Error::InvalidTagData(format!("Child scenario script node #{index} is out-of-bounds; scripts need recompiled!")))
+        }
+        Ok(node_id(index as usize + node_offset))
+    };
+
+    for node in &child_nodes {
+        let mut node = *node;
+        node.salt = (node_id(nodes.len()).as_u32() >> 16) as u16;
+        node.next_node = remap_id(node.next_node)?;
+        if (node.string_offset as usize) < child.script_string_data.bytes.len() {
+            node.string_offset += string_offset;
+        }
+
+        if node.flags.is_script_call && (node.index_union as usize) < child.scripts.items.len() {
+            node.index_union += script_offset as u16;
+        }
+
+        if !node.flags.is_primitive {
+            node.data = remap_id(ID::from(node.data))?.into();
+        }
+        else if node.flags.is_global {
+            // Scenario globals are referenced by index; engine globals have the same name in both scenarios.
+            let name = get_string_data_for_node(child, &node)?;
+            if let Some(global) = child.globals.items.iter().position(|g| g.name.as_str() == name) {
+                if (node.data.data & 0x7FFF) as usize == global {
+                    node.data.data = (node.data.data & !0x7FFF) | (global + global_offset) as u32;
+                }
+            }
+        }
+        else if node.flags.is_local_variable {
+            // Parameters are indexed by the script they are in.
+        }
+        else if node._type == ScenarioScriptValueType::Script {
+            let script = i16::from(node.data);
+            if script >= 0 && (script as usize) < child.scripts.items.len() {
+                node.data = ScenarioScriptNodeValue::from(script + script_offset as i16);
+            }
+        }
+        else if let Some(map) = maps.for_type(node._type) {
+            node.data = map.get_script_value(node.data)?;
+        }
+
+        nodes.push(node);
+    }
+
+    for script in &child.scripts {
+        let mut script = script.clone();
+        script.root_expression_index = remap_id(script.root_expression_index)?;
+        parent.scripts.items.push(script);
+    }
+    for global in &child.globals {
+        let mut global = *global;
+        global.initialization_expression_index = remap_id(global.initialization_expression_index)?;
+        parent.globals.items.push(global);
+    }
+
+    // Write out the merged table
+    let mut table = table;
+    table.size = total_nodes;
+    table.count = total_nodes;
+    table.maximum_count = table.maximum_count.max(child_table.maximum_count).max(total_nodes);
+    table.next_id = (node_id(total_nodes as usize).as_u32() >> 16) as u16;
+
+    let header_size = ScenarioScriptNodeTable::simple_size();
+    let node_size = ScenarioScriptNode::simple_size();
+    let mut syntax_data = vec![0u8; header_size + table.maximum_count as usize * node_size];
+    table.write::<BigEndian>(&mut syntax_data, 0, header_size)?;
+    for (index, node) in nodes.iter().enumerate() {
+        let offset = header_size + index * node_size;
+        node.write::<BigEndian>(&mut syntax_data, offset, offset + node_size)?;
+    }
+    parent.script_syntax_data = Data::new(syntax_data);
+    parent.script_string_data.bytes.extend_from_slice(&child.script_string_data.bytes);
+
+    Ok(())
+}
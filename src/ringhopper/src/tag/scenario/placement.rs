@@ -0,0 +1,611 @@
+use std::fmt::Write;
+use std::str::FromStr;
+use definitions::{Scenario, ScenarioNetgameFlagType, ScenarioObjectName, ScenarioObjectPlacement, ScenarioSpawnType};
+use primitives::dynamic::{DynamicEnumImpl, DynamicTagData};
+use primitives::error::{Error, RinghopperResult};
+use primitives::primitive::{Angle, Euler3D, Index, String32, TagGroup, TagPath, TagReference, Vector3D};
+use serde_json::{Map, Value};
+
+/// Kind of placement in a scenario.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ScenarioPlacementKind {
+    Scenery,
+    Biped,
+    Vehicle,
+    Equipment,
+    Weapon,
+    Machine,
+    Control,
+    LightFixture,
+    SoundScenery,
+    NetgameFlag,
+    NetgameEquipment,
+    PlayerStartingLocation
+}
+
+impl ScenarioPlacementKind {
+    const ALL: [ScenarioPlacementKind; 12] = [
+        Self::Scenery,
+        Self::Biped,
+        Self::Vehicle,
+        Self::Equipment,
+        Self::Weapon,
+        Self::Machine,
+        Self::Control,
+        Self::LightFixture,
+        Self::SoundScenery,
+        Self::NetgameFlag,
+        Self::NetgameEquipment,
+        Self::PlayerStartingLocation
+    ];
+
+    /// Get the name of the kind as written in placement files.
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Scenery => "scenery",
+            Self::Biped => "biped",
+            Self::Vehicle => "vehicle",
+            Self::Equipment => "equipment",
+            Self::Weapon => "weapon",
+            Self::Machine => "machine",
+            Self::Control => "control",
+            Self::LightFixture => "light_fixture",
+            Self::SoundScenery => "sound_scenery",
+            Self::NetgameFlag => "netgame_flag",
+            Self::NetgameEquipment => "netgame_equipment",
+            Self::PlayerStartingLocation => "player_starting_location"
+        }
+    }
+
+}
+
+impl FromStr for ScenarioPlacementKind {
+    type Err = Error;
+    fn from_str(kind: &str) -> RinghopperResult<Self> {
+        Self::ALL.into_iter().find(|k| k.as_str() == kind).ok_or_else(|| Error::Other(format!("`{kind}` is not a kind of placement")))
+    }
+}
+
+/// A placement in a scenario, independent of palette and object name indices.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScenarioPlacement {
+    pub kind: ScenarioPlacementKind,
+
+    /// Palette entry for objects, or item collection for netgame equipment.
+    pub tag: Option<TagPath>,
+
+    /// Object name, if any.
+    pub name: Option<String>,
+
+    pub position: Vector3D,
+
+    /// Rotation in radians; only the yaw is used for netgame flags, netgame equipment, and starting locations.
+    pub rotation: Euler3D,
+
+    /// Team index, netgame flag usage ID, or multiplayer team of a vehicle.
+    pub team: Option<u16>,
+
+    /// Flags which are set, written as `<bitfield>.<flag>`.
+    pub flags: Vec<String>,
+
+    /// Netgame flag type, or the game types a netgame item or starting location spawns in.
+    pub types: Vec<String>
+}
+
+impl ScenarioPlacement {
+    fn new(kind: ScenarioPlacementKind) -> Self {
+        Self {
+            kind,
+            tag: None,
+            name: None,
+            position: Vector3D::default(),
+            rotation: Euler3D::default(),
+            team: None,
+            flags: Vec::new(),
+            types: Vec::new()
+        }
+    }
+}
+
+fn get_flags(bitfield_name: &str, bitfield: &dyn DynamicTagData, flags: &mut Vec<String>) {
+    for flag in bitfield.fields() {
+        if bitfield.get_field(flag).and_then(|f| f.as_any().downcast_ref::<bool>()).copied().unwrap_or(false) {
+            flags.push(format!("{bitfield_name}.{flag}"));
+        }
+    }
+}
+
+fn set_flags(bitfield_name: &str, bitfield: &mut dyn DynamicTagData, flags: &[String]) -> RinghopperResult<()> {
+    for flag in bitfield.fields() {
+        *bitfield.get_field_mut(flag).unwrap().as_any_mut().downcast_mut::<bool>().unwrap() = false;
+    }
+    for flag in flags.iter().filter_map(|f| f.strip_prefix(bitfield_name).and_then(|f| f.strip_prefix('.'))) {
+        let value = bitfield
+            .get_field_mut(flag)
+            .and_then(|f| f.as_any_mut().downcast_mut::<bool>())
+            .ok_or_else(|| Error::Other(format!("`{flag}` is not a flag in `{bitfield_name}`")))?;
+        *value = true;
+    }
+    Ok(())
+}
+
+fn check_flags(placement: &ScenarioPlacement, bitfields: &[&str]) -> RinghopperResult<()> {
+    for flag in &placement.flags {
+        let bitfield = flag.split('.').next().unwrap();
+        if !bitfields.contains(&bitfield) {
+            return Err(Error::Other(format!("`{flag}` is not a flag for {} placements", placement.kind.as_str())))
+        }
+    }
+    Ok(())
+}
+
+fn get_type<T: DynamicEnumImpl>(placement: &ScenarioPlacement, index: usize) -> RinghopperResult<T> {
+    let Some(value) = placement.types.get(index) else {
+        return Err(Error::Other(format!("{} placements need at least {} type(s)", placement.kind.as_str(), index + 1)))
+    };
+    T::from_str(value).ok_or_else(|| Error::Other(format!("`{value}` is not a valid type for {} placements", placement.kind.as_str())))
+}
+
+fn object_name(scenario: &Scenario, name: Index) -> Option<String> {
+    name.and_then(|n| scenario.object_names.items.get(n as usize)).map(|n| n.name.to_string())
+}
+
+fn find_object_name(scenario: &mut Scenario, name: &Option<String>) -> RinghopperResult<Index> {
+    let Some(name) = name else {
+        return Ok(None)
+    };
+    let name = String32::from_str(name)?;
+    let index = match scenario.object_names.items.iter().position(|n| n.name == name) {
+        Some(n) => n,
+        None => {
+            scenario.object_names.items.push(ScenarioObjectName { name, ..Default::default() });
+            scenario.object_names.items.len() - 1
+        }
+    };
+    Ok(Some(index as u16))
+}
+
+fn check_tag_group(tag: &TagPath, allowed: &[TagGroup], what: &str) -> RinghopperResult<()> {
+    if allowed.contains(&tag.group()) {
+        return Ok(())
+    }
+    let allowed: Vec<&str> = allowed.iter().map(|g| g.as_str()).collect();
+    Err(Error::Other(format!("`{tag}` cannot be used in the {what} (allowed groups are: {})", allowed.join(", "))))
+}
+
+fn find_palette_entry<T: Default + DynamicTagData>(palette: &mut Vec<T>, tag: &Option<TagPath>, palette_name: &str) -> RinghopperResult<Index> {
+    let Some(tag) = tag else {
+        return Ok(None)
+    };
+
+    let allowed = T::default().get_metadata_for_field("name").and_then(|m| m.allowed_references).unwrap_or_default();
+    check_tag_group(tag, allowed, palette_name)?;
+
+    let reference = |entry: &T| entry.get_field("name").and_then(|f| f.as_any().downcast_ref::<TagReference>()).and_then(|r| r.path()) == Some(tag);
+    let index = match palette.iter().position(reference) {
+        Some(n) => n,
+        None => {
+            let mut entry = T::default();
+            *entry.get_field_mut("name").unwrap().as_any_mut().downcast_mut::<TagReference>().unwrap() = TagReference::Set(tag.clone());
+            palette.push(entry);
+            palette.len() - 1
+        }
+    };
+    Ok(Some(index as u16))
+}
+
+fn export_object_placement(placement: &ScenarioObjectPlacement, record: &mut ScenarioPlacement) {
+    record.position = placement.position;
+    record.rotation = placement.rotation;
+    get_flags("not_placed", &placement.not_placed, &mut record.flags);
+}
+
+fn import_object_placement(record: &ScenarioPlacement, placement: &mut ScenarioObjectPlacement) -> RinghopperResult<()> {
+    placement.position = record.position;
+    placement.rotation = record.rotation;
+    set_flags("not_placed", &mut placement.not_placed, &record.flags)
+}
+
+fn export_game_types(types: [ScenarioSpawnType; 4], record: &mut ScenarioPlacement) {
+    record.types = types.iter().map(|t| t.to_str().to_owned()).collect();
+}
+
+fn import_game_types(record: &ScenarioPlacement) -> RinghopperResult<[ScenarioSpawnType; 4]> {
+    Ok([get_type(record, 0)?, get_type(record, 1)?, get_type(record, 2)?, get_type(record, 3)?])
+}
+
+/// Get all object placements, netgame flags, netgame equipment, and player starting locations in a scenario.
+pub fn export_placements(scenario: &Scenario) -> Vec<ScenarioPlacement> {
+    let mut placements = Vec::new();
+
+    macro_rules! export_objects {
+        ($kind:ident, $block:ident, $palette:ident, $($placement:ident).+ $(, $bitfield:literal => $($flags:ident).+)*) => {
+            for item in &scenario.$block.items {
+                let mut record = ScenarioPlacement::new(ScenarioPlacementKind::$kind);
+                record.tag = item._type.and_then(|t| scenario.$palette.items.get(t as usize)).and_then(|p| p.name.path()).map(|p| p.to_owned());
+                record.name = object_name(scenario, item.name);
+                export_object_placement(&item.$($placement).+, &mut record);
+                $(get_flags($bitfield, &item.$($flags).+, &mut record.flags);)*
+                placements.push(record);
+            }
+        };
+    }
+
+    export_objects!(Scenery, scenery, scenery_palette, placement);
+    export_objects!(Biped, bipeds, biped_palette, properties.placement, "flags" => properties.flags);
+    for item in &scenario.vehicles.items {
+        let mut record = ScenarioPlacement::new(ScenarioPlacementKind::Vehicle);
+        record.tag = item._type.and_then(|t| scenario.vehicle_palette.items.get(t as usize)).and_then(|p| p.name.path()).map(|p| p.to_owned());
+        record.name = object_name(scenario, item.name);
+        record.team = u16::try_from(item.multiplayer_team_index).ok();
+        export_object_placement(&item.properties.placement, &mut record);
+        get_flags("flags", &item.properties.flags, &mut record.flags);
+        get_flags("multiplayer_spawn_flags", &item.multiplayer_spawn_flags, &mut record.flags);
+        placements.push(record);
+    }
+    export_objects!(Equipment, equipment, equipment_palette, placement, "misc_flags" => misc_flags);
+    export_objects!(Weapon, weapons, weapon_palette, placement, "flags" => flags);
+    export_objects!(Machine, machines, machine_palette, placement, "device_flags" => device_flags, "machine_flags" => machine_flags);
+    export_objects!(Control, controls, control_palette, placement, "device_flags" => device_flags, "control_flags" => control_flags);
+    export_objects!(LightFixture, light_fixtures, light_fixture_palette, placement, "device_flags" => device_flags);
+    export_objects!(SoundScenery, sound_scenery, sound_scenery_palette, placement);
+
+    for flag in &scenario.netgame_flags.items {
+        let mut record = ScenarioPlacement::new(ScenarioPlacementKind::NetgameFlag);
+        record.position = flag.position;
+        record.rotation.yaw = flag.facing;
+        record.team = flag.usage_id;
+        record.types = vec![flag._type.to_str().to_owned()];
+        placements.push(record);
+    }
+
+    for equipment in &scenario.netgame_equipment.items {
+        let mut record = ScenarioPlacement::new(ScenarioPlacementKind::NetgameEquipment);
+        record.tag = equipment.item_collection.path().map(|p| p.to_owned());
+        record.position = equipment.position;
+        record.rotation.yaw = equipment.facing;
+        record.team = equipment.team_index;
+        get_flags("flags", &equipment.flags, &mut record.flags);
+        export_game_types([equipment.type_0, equipment.type_1, equipment.type_2, equipment.type_3], &mut record);
+        placements.push(record);
+    }
+
+    for location in &scenario.player_starting_locations.items {
+        let mut record = ScenarioPlacement::new(ScenarioPlacementKind::PlayerStartingLocation);
+        record.position = location.position;
+        record.rotation.yaw = location.facing;
+        record.team = location.team_index;
+        export_game_types([location.type_0, location.type_1, location.type_2, location.type_3], &mut record);
+        placements.push(record);
+    }
+
+    placements
+}
+
+/// Replace placements in a scenario.
+///
+/// Only kinds of placements present in `placements` are replaced. For each kind, the nth placement updates the nth
+/// existing placement of that kind so fields which are not exported (e.g. rounds loaded) are kept. Palettes and
+/// object names are extended as needed.
+///
+/// If any placement is invalid, an error is returned and the scenario is left unchanged.
+pub fn import_placements(scenario: &mut Scenario, placements: &[ScenarioPlacement]) -> RinghopperResult<()> {
+    // Palettes and object names are extended while importing, so work on a copy.
+    let mut imported = scenario.clone();
+    import_placements_into(&mut imported, placements)?;
+    *scenario = imported;
+    Ok(())
+}
+
+fn import_placements_into(scenario: &mut Scenario, placements: &[ScenarioPlacement]) -> RinghopperResult<()> {
+    let of_kind = |kind: ScenarioPlacementKind| placements.iter().filter(move |p| p.kind == kind);
+    let has_kind = |kind: ScenarioPlacementKind| of_kind(kind).next().is_some();
+
+    macro_rules! import_objects {
+        ($kind:ident, $block:ident, $palette:ident, $($placement:ident).+ $(, $bitfield:literal => $($flags:ident).+)*) => {
+            if has_kind(ScenarioPlacementKind::$kind) {
+                let mut items = Vec::new();
+                for (index, record) in of_kind(ScenarioPlacementKind::$kind).enumerate() {
+                    check_flags(record, &["not_placed" $(, $bitfield)*])?;
+                    let mut item = scenario.$block.items.get(index).cloned().unwrap_or_default();
+                    item._type = find_palette_entry(&mut scenario.$palette.items, &record.tag, &stringify!($palette).replace('_', " "))?;
+                    item.name = find_object_name(scenario, &record.name)?;
+                    import_object_placement(record, &mut item.$($placement).+)?;
+                    $(set_flags($bitfield, &mut item.$($flags).+, &record.flags)?;)*
+                    items.push(item);
+                }
+                scenario.$block.items = items;
+            }
+        };
+    }
+
+    import_objects!(Scenery, scenery, scenery_palette, placement);
+    import_objects!(Biped, bipeds, biped_palette, properties.placement, "flags" => properties.flags);
+    import_objects!(Vehicle, vehicles, vehicle_palette, properties.placement, "flags" => properties.flags, "multiplayer_spawn_flags" => multiplayer_spawn_flags);
+    if has_kind(ScenarioPlacementKind::Vehicle) {
+        for (vehicle, record) in scenario.vehicles.items.iter_mut().zip(of_kind(ScenarioPlacementKind::Vehicle)) {
+            vehicle.multiplayer_team_index = match record.team {
+                Some(team) => i8::try_from(team).map_err(|_| Error::Other(format!("team {team} is out of range for vehicle placements (maximum {})", i8::MAX)))?,
+                None => -1
+            };
+        }
+    }
+    import_objects!(Equipment, equipment, equipment_palette, placement, "misc_flags" => misc_flags);
+    import_objects!(Weapon, weapons, weapon_palette, placement, "flags" => flags);
+    import_objects!(Machine, machines, machine_palette, placement, "device_flags" => device_flags, "machine_flags" => machine_flags);
+    import_objects!(Control, controls, control_palette, placement, "device_flags" => device_flags, "control_flags" => control_flags);
+    import_objects!(LightFixture, light_fixtures, light_fixture_palette, placement, "device_flags" => device_flags);
+    import_objects!(SoundScenery, sound_scenery, sound_scenery_palette, placement);
+
+    if has_kind(ScenarioPlacementKind::NetgameFlag) {
+        let mut flags = Vec::new();
+        for (index, record) in of_kind(ScenarioPlacementKind::NetgameFlag).enumerate() {
+            check_flags(record, &[])?;
+            let mut flag = scenario.netgame_flags.items.get(index).cloned().unwrap_or_default();
+            flag.position = record.position;
+            flag.facing = record.rotation.yaw;
+            flag.usage_id = record.team;
+            flag._type = get_type::<ScenarioNetgameFlagType>(record, 0)?;
+            flags.push(flag);
+        }
+        scenario.netgame_flags.items = flags;
+    }
+
+    if has_kind(ScenarioPlacementKind::NetgameEquipment) {
+        let mut items = Vec::new();
+        for (index, record) in of_kind(ScenarioPlacementKind::NetgameEquipment).enumerate() {
+            check_flags(record, &["flags"])?;
+            let mut equipment = scenario.netgame_equipment.items.get(index).cloned().unwrap_or_default();
+            equipment.item_collection = match &record.tag {
+                Some(tag) => {
+                    check_tag_group(tag, &[TagGroup::ItemCollection], "netgame equipment")?;
+                    TagReference::Set(tag.clone())
+                },
+                None => TagReference::Null(TagGroup::ItemCollection)
+            };
+            equipment.position = record.position;
+            equipment.facing = record.rotation.yaw;
+            equipment.team_index = record.team;
+            set_flags("flags", &mut equipment.flags, &record.flags)?;
+            [equipment.type_0, equipment.type_1, equipment.type_2, equipment.type_3] = import_game_types(record)?;
+            items.push(equipment);
+        }
+        scenario.netgame_equipment.items = items;
+    }
+
+    if has_kind(ScenarioPlacementKind::PlayerStartingLocation) {
+        let mut locations = Vec::new();
+        for (index, record) in of_kind(ScenarioPlacementKind::PlayerStartingLocation).enumerate() {
+            check_flags(record, &[])?;
+            let mut location = scenario.player_starting_locations.items.get(index).cloned().unwrap_or_default();
+            location.position = record.position;
+            location.facing = record.rotation.yaw;
+            location.team_index = record.team;
+            [location.type_0, location.type_1, location.type_2, location.type_3] = import_game_types(record)?;
+            locations.push(location);
+        }
+        scenario.player_starting_locations.items = locations;
+    }
+
+    Ok(())
+}
+
+const CSV_HEADER: &str = "kind,tag,name,x,y,z,yaw,pitch,roll,team,flags,types";
+
+fn csv_escape(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    }
+    else {
+        value.to_owned()
+    }
+}
+
+/// Split a CSV line into fields.
+fn csv_split(line: &str) -> RinghopperResult<Vec<String>> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut chars = line.chars().peekable();
+    let mut quoted = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            },
+            '"' if quoted => quoted = false,
+            '"' if field.is_empty() => quoted = true,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c)
+        }
+    }
+    if quoted {
+        return Err(Error::Other(format!("unterminated quote in `{line}`")))
+    }
+    fields.push(field);
+    Ok(fields)
+}
+
+fn parse_number<T: std::str::FromStr>(value: &str, what: &str) -> RinghopperResult<T> {
+    value.trim().parse().map_err(|_| Error::Other(format!("`{value}` is not a valid {what}")))
+}
+
+fn parse_tag_path(path: &str) -> RinghopperResult<TagPath> {
+    TagPath::from_path(path).map_err(|_| Error::Other(format!("`{path}` is not a valid tag path with an extension")))
+}
+
+/// Write placements as CSV, with one placement per line.
+///
+/// Flags and types are separated by spaces.
+pub fn placements_to_csv(placements: &[ScenarioPlacement]) -> String {
+    let mut csv = String::new();
+    csv += CSV_HEADER;
+    csv += "\n";
+    for p in placements {
+        writeln!(
+            &mut csv,
+            "{},{},{},{},{},{},{},{},{},{},{},{}",
+            p.kind.as_str(),
+            csv_escape(&p.tag.as_ref().map(|t| t.to_string()).unwrap_or_default()),
+            csv_escape(p.name.as_deref().unwrap_or_default()),
+            p.position.x as f32,
+            p.position.y as f32,
+            p.position.z as f32,
+            p.rotation.yaw.angle,
+            p.rotation.pitch.angle,
+            p.rotation.roll.angle,
+            p.team.map(|t| t.to_string()).unwrap_or_default(),
+            p.flags.join(" "),
+            p.types.join(" ")
+        ).unwrap();
+    }
+    csv
+}
+
+/// Read placements written by [`placements_to_csv`].
+pub fn placements_from_csv(csv: &str) -> RinghopperResult<Vec<ScenarioPlacement>> {
+    let mut lines = csv.lines().enumerate().filter(|(_, l)| !l.trim().is_empty());
+    match lines.next() {
+        Some((_, header)) if header.trim() == CSV_HEADER => (),
+        _ => return Err(Error::Other(format!("expected the header `{CSV_HEADER}`")))
+    }
+
+    let mut placements = Vec::new();
+    for (line_number, line) in lines {
+        let parse_line = || -> RinghopperResult<ScenarioPlacement> {
+            let fields = csv_split(line)?;
+            if fields.len() != 12 {
+                return Err(Error::Other(format!("expected 12 fields, got {}", fields.len())))
+            }
+            let kind = fields[0].parse()?;
+            let mut placement = ScenarioPlacement::new(kind);
+            if !fields[1].is_empty() {
+                placement.tag = Some(parse_tag_path(&fields[1])?);
+            }
+            if !fields[2].is_empty() {
+                placement.name = Some(fields[2].clone());
+            }
+            placement.position = Vector3D {
+                x: parse_number::<f32>(&fields[3], "coordinate")? as f64,
+                y: parse_number::<f32>(&fields[4], "coordinate")? as f64,
+                z: parse_number::<f32>(&fields[5], "coordinate")? as f64
+            };
+            placement.rotation = Euler3D {
+                yaw: Angle { angle: parse_number(&fields[6], "angle")? },
+                pitch: Angle { angle: parse_number(&fields[7], "angle")? },
+                roll: Angle { angle: parse_number(&fields[8], "angle")? }
+            };
+            if !fields[9].trim().is_empty() {
+                placement.team = Some(parse_number(&fields[9], "team")?);
+            }
+            placement.flags = fields[10].split_whitespace().map(|f| f.to_owned()).collect();
+            placement.types = fields[11].split_whitespace().map(|f| f.to_owned()).collect();
+            Ok(placement)
+        };
+        placements.push(parse_line().map_err(|e| Error::Other(format!("line {}: {e}", line_number + 1)))?);
+    }
+
+    Ok(placements)
+}
+
+/// Convert to a JSON number, keeping the shortest representation of the `f32` rather than of its `f64` conversion.
+fn json_float(value: f32) -> Value {
+    Value::from(value.to_string().parse::<f64>().unwrap_or(value as f64))
+}
+
+/// Write placements as a JSON array.
+pub fn placements_to_json(placements: &[ScenarioPlacement]) -> String {
+    let placements: Vec<Value> = placements.iter().map(|p| {
+        let mut object = Map::new();
+        object.insert("kind".to_owned(), Value::from(p.kind.as_str()));
+        if let Some(tag) = &p.tag {
+            object.insert("tag".to_owned(), Value::from(tag.to_string()));
+        }
+        if let Some(name) = &p.name {
+            object.insert("name".to_owned(), Value::from(name.as_str()));
+        }
+        object.insert("position".to_owned(), Value::from([p.position.x, p.position.y, p.position.z].map(|c| json_float(c as f32)).to_vec()));
+        object.insert("rotation".to_owned(), Value::from([p.rotation.yaw, p.rotation.pitch, p.rotation.roll].map(|a| json_float(a.angle)).to_vec()));
+        if let Some(team) = p.team {
+            object.insert("team".to_owned(), Value::from(team));
+        }
+        if !p.flags.is_empty() {
+            object.insert("flags".to_owned(), Value::from(p.flags.clone()));
+        }
+        if !p.types.is_empty() {
+            object.insert("types".to_owned(), Value::from(p.types.clone()));
+        }
+        Value::Object(object)
+    }).collect();
+
+    let mut json = serde_json::to_string_pretty(&placements).expect("placements should always serialize");
+    json += "\n";
+    json
+}
+
+/// Read placements written by [`placements_to_json`].
+///
+/// Only `kind` and `position` are required.
+pub fn placements_from_json(json: &str) -> RinghopperResult<Vec<ScenarioPlacement>> {
+    let value: Value = serde_json::from_str(json).map_err(|e| Error::Other(format!("invalid JSON: {e}")))?;
+    let Value::Array(items) = value else {
+        return Err(Error::Other("expected an array of placements".to_owned()))
+    };
+
+    let mut placements = Vec::with_capacity(items.len());
+    for (index, item) in items.iter().enumerate() {
+        let parse_item = || -> RinghopperResult<ScenarioPlacement> {
+            let string = |key: &str| -> RinghopperResult<Option<&str>> {
+                match item.get(key) {
+                    None | Some(Value::Null) => Ok(None),
+                    Some(Value::String(s)) => Ok(Some(s.as_str())),
+                    Some(_) => Err(Error::Other(format!("`{key}` must be a string")))
+                }
+            };
+            let numbers = |key: &str| -> RinghopperResult<Option<[f64; 3]>> {
+                let Some(value) = item.get(key) else {
+                    return Ok(None)
+                };
+                let numbers: Option<Vec<f64>> = value.as_array().map(|a| a.iter().filter_map(|n| n.as_f64()).collect());
+                match numbers {
+                    Some(n) if n.len() == 3 => Ok(Some([n[0], n[1], n[2]])),
+                    _ => Err(Error::Other(format!("`{key}` must be an array of three numbers")))
+                }
+            };
+            let strings = |key: &str| -> RinghopperResult<Vec<String>> {
+                let Some(value) = item.get(key) else {
+                    return Ok(Vec::new())
+                };
+                let strings: Option<Vec<String>> = value.as_array().and_then(|a| a.iter().map(|s| s.as_str().map(|s| s.to_owned())).collect());
+                strings.ok_or_else(|| Error::Other(format!("`{key}` must be an array of strings")))
+            };
+
+            let kind = string("kind")?.ok_or_else(|| Error::Other("missing `kind`".to_owned()))?;
+            let kind = kind.parse()?;
+            let mut placement = ScenarioPlacement::new(kind);
+            placement.tag = string("tag")?.map(parse_tag_path).transpose()?;
+            placement.name = string("name")?.map(|n| n.to_owned());
+
+            let [x, y, z] = numbers("position")?.ok_or_else(|| Error::Other("missing `position`".to_owned()))?;
+            placement.position = Vector3D { x: x as f32 as f64, y: y as f32 as f64, z: z as f32 as f64 };
+            if let Some([yaw, pitch, roll]) = numbers("rotation")? {
+                placement.rotation = Euler3D {
+                    yaw: Angle { angle: yaw as f32 },
+                    pitch: Angle { angle: pitch as f32 },
+                    roll: Angle { angle: roll as f32 }
+                };
+            }
+
+            placement.team = match item.get("team") {
+                None | Some(Value::Null) => None,
+                Some(team) => Some(team.as_u64().and_then(|t| u16::try_from(t).ok()).ok_or_else(|| Error::Other("`team` must be an integer".to_owned()))?)
+            };
+            placement.flags = strings("flags")?;
+            placement.types = strings("types")?;
+            Ok(placement)
+        };
+        placements.push(parse_item().map_err(|e| Error::Other(format!("placement #{index}: {e}")))?);
+    }
+
+    Ok(placements)
+}
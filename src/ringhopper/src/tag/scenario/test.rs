@@ -0,0 +1,329 @@
+use definitions::{Scenario, ScenarioType, ScenarioActorPalette, ScenarioNetgameFlags, ScenarioNetgameFlagType, ScenarioPlayerStartingLocation, ScenarioSpawnType, ScenarioVehicle, ScenarioVehiclePalette, ScenarioEncounter, ScenarioGlobal, ScenarioObjectName, ScenarioScenery, ScenarioSceneryPalette, ScenarioScriptNode, ScenarioScriptNodeFlags, ScenarioScriptNodeTable, ScenarioScriptValueType, ScenarioSourceFile, ScenarioSquad, ScenarioTriggerVolume};
+use primitives::byteorder::BigEndian;
+use primitives::parse::SimpleTagData;
+use primitives::primitive::{Angle, Data, ID, IDType, String32, TagGroup, TagPath, TagReference, Vector3D};
+use super::clean::clean_scenario;
+use super::convert_type::convert_scenario_type;
+use super::merge::merge_child_scenario;
+use super::placement::{export_placements, import_placements, placements_from_csv, placements_from_json, placements_to_csv, placements_to_json, ScenarioPlacementKind};
+use super::for_each_node_in_scenario;
+
+fn reference(path: &str, group: TagGroup) -> TagReference {
+    TagReference::Set(TagPath::new(path, group).unwrap())
+}
+
+fn name(name: &str) -> String32 {
+    String32::from_str(name).unwrap()
+}
+
+/// Add a global whose initialization expression is a single primitive node.
+fn add_global(scenario: &mut Scenario, global_name: &str, value_type: ScenarioScriptValueType, value: i16) {
+    let mut nodes = Vec::new();
+    for_each_node_in_scenario::<BigEndian, _, _, _>(scenario, |_, _| (), |_, node| nodes.push(*node), |_| ()).ok();
+
+    let index = nodes.len() as u16;
+    let string_offset = scenario.script_string_data.bytes.len() as u32;
+    scenario.script_string_data.bytes.extend_from_slice(b"value\x00");
+    nodes.push(ScenarioScriptNode {
+        salt: (ID::new(Some(index), IDType::ScriptNode.salt()).as_u32() >> 16) as u16,
+        _type: value_type,
+        flags: ScenarioScriptNodeFlags { is_primitive: true, ..Default::default() },
+        next_node: ID::null(),
+        string_offset,
+        data: value.into(),
+        ..Default::default()
+    });
+
+    let table = ScenarioScriptNodeTable {
+        maximum_count: nodes.len() as u16,
+        element_size: ScenarioScriptNode::simple_size() as u16,
+        one: 1,
+        size: nodes.len() as u16,
+        count: nodes.len() as u16,
+        ..Default::default()
+    };
+    let header_size = ScenarioScriptNodeTable::simple_size();
+    let node_size = ScenarioScriptNode::simple_size();
+    let mut data = vec![0u8; header_size + nodes.len() * node_size];
+    table.write::<BigEndian>(&mut data, 0, header_size).unwrap();
+    for (i, node) in nodes.iter().enumerate() {
+        let offset = header_size + i * node_size;
+        node.write::<BigEndian>(&mut data, offset, offset + node_size).unwrap();
+    }
+    scenario.script_syntax_data = Data::new(data);
+
+    scenario.globals.items.push(ScenarioGlobal {
+        name: name(global_name),
+        _type: value_type,
+        initialization_expression_index: ID::new(Some(index), IDType::ScriptNode.salt()),
+        ..Default::default()
+    });
+}
+
+fn parent_scenario() -> Scenario {
+    let mut parent = Scenario::default();
+    parent.scenery_palette.items.push(ScenarioSceneryPalette { name: reference("scenery\\rock", TagGroup::Scenery), ..Default::default() });
+    parent.object_names.items.push(ScenarioObjectName { name: name("door"), ..Default::default() });
+    parent.scenery.items.push(ScenarioScenery { _type: Some(0), name: Some(0), ..Default::default() });
+    parent.trigger_volumes.items.push(ScenarioTriggerVolume { name: name("start"), ..Default::default() });
+    parent.actor_palette.items.push(ScenarioActorPalette { reference: reference("characters\\grunt", TagGroup::ActorVariant), ..Default::default() });
+    add_global(&mut parent, "parent_count", ScenarioScriptValueType::Short, 5);
+    parent
+}
+
+fn child_scenario() -> Scenario {
+    let mut child = Scenario::default();
+    child.scenery_palette.items.push(ScenarioSceneryPalette { name: reference("scenery\\tree", TagGroup::Scenery), ..Default::default() });
+    child.scenery_palette.items.push(ScenarioSceneryPalette { name: reference("scenery\\rock", TagGroup::Scenery), ..Default::default() });
+    child.object_names.items.push(ScenarioObjectName { name: name("crate"), ..Default::default() });
+    child.object_names.items.push(ScenarioObjectName { name: name("door"), ..Default::default() });
+    child.scenery.items.push(ScenarioScenery { _type: Some(1), name: Some(0), ..Default::default() });
+    child.scenery.items.push(ScenarioScenery { _type: Some(0), name: None, ..Default::default() });
+    child.trigger_volumes.items.push(ScenarioTriggerVolume { name: name("start"), ..Default::default() });
+    child.trigger_volumes.items.push(ScenarioTriggerVolume { name: name("end"), ..Default::default() });
+    child.actor_palette.items.push(ScenarioActorPalette { reference: reference("characters\\elite", TagGroup::ActorVariant), ..Default::default() });
+    child.actor_palette.items.push(ScenarioActorPalette { reference: reference("characters\\grunt", TagGroup::ActorVariant), ..Default::default() });
+
+    let mut encounter = ScenarioEncounter { name: name("ambush"), ..Default::default() };
+    encounter.squads.items.push(ScenarioSquad { name: name("grunts"), actor_type: Some(1), ..Default::default() });
+    child.encounters.items.push(encounter);
+
+    add_global(&mut child, "child_object", ScenarioScriptValueType::ObjectName, 0);
+    add_global(&mut child, "child_volume", ScenarioScriptValueType::TriggerVolume, 1);
+    child
+}
+
+#[test]
+fn merge_scenarios() {
+    let mut parent = parent_scenario();
+    merge_child_scenario(&mut parent, &child_scenario()).unwrap();
+
+    // Palettes and object names are deduplicated.
+    let palette: Vec<String> = parent.scenery_palette.items.iter().map(|p| p.name.path().unwrap().to_string()).collect();
+    assert_eq!(vec!["scenery/rock.scenery", "scenery/tree.scenery"], palette);
+    let names: Vec<&str> = parent.object_names.items.iter().map(|n| n.name.as_str()).collect();
+    assert_eq!(vec!["door", "crate"], names);
+    assert_eq!(2, parent.actor_palette.items.len());
+
+    // Placements are remapped.
+    let scenery: Vec<_> = parent.scenery.items.iter().map(|s| (s._type, s.name)).collect();
+    assert_eq!(vec![(Some(0), Some(0)), (Some(0), Some(1)), (Some(1), None)], scenery);
+
+    // Identical trigger volumes are merged.
+    let volumes: Vec<&str> = parent.trigger_volumes.items.iter().map(|t| t.name.as_str()).collect();
+    assert_eq!(vec!["start", "end"], volumes);
+
+    assert_eq!(Some(0), parent.encounters.items[0].squads.items[0].actor_type);
+
+    // Scripts are appended and their nodes are remapped.
+    let mut table = None;
+    let mut nodes = Vec::new();
+    for_each_node_in_scenario::<BigEndian, _, _, _>(&mut parent, |_, t| table = Some(*t), |_, node| nodes.push(*node), |_| ()).unwrap();
+    assert_eq!(3, nodes.len());
+
+    // Each node's salt matches the IDs referencing it, and the table's next ID follows on from the last node.
+    for global in &parent.globals.items {
+        let id = global.initialization_expression_index;
+        assert_eq!((id.as_u32() >> 16) as u16, nodes[id.index().unwrap() as usize].salt);
+    }
+    assert_eq!((ID::new(Some(3), IDType::ScriptNode.salt()).as_u32() >> 16) as u16, table.unwrap().next_id);
+
+    let globals: Vec<&str> = parent.globals.items.iter().map(|g| g.name.as_str()).collect();
+    assert_eq!(vec!["parent_count", "child_object", "child_volume"], globals);
+
+    let value_of = |global: usize| {
+        let node = &nodes[parent.globals.items[global].initialization_expression_index.index().unwrap() as usize];
+        assert_eq!(global * 6, node.string_offset as usize);
+        i16::from(node.data)
+    };
+    assert_eq!(5, value_of(0));
+    assert_eq!(1, value_of(1));
+    assert_eq!(1, value_of(2));
+    assert_eq!(18, parent.script_string_data.bytes.len());
+}
+
+#[test]
+fn merge_conflicting_scenarios() {
+    let mut parent = parent_scenario();
+    let mut child = child_scenario();
+    child.trigger_volumes.items[0].starting_corner.x = 1.0;
+    assert!(merge_child_scenario(&mut parent, &child).is_err());
+
+    // The parent is left unchanged even though the palettes and placements merged before the globals conflicted.
+    let mut parent = parent_scenario();
+    let mut child = child_scenario();
+    child.globals.items[0].name = name("parent_count");
+    assert!(merge_child_scenario(&mut parent, &child).is_err());
+    assert_eq!(parent_scenario(), parent);
+
+    // Both scenarios place an object named `door`.
+    let mut parent = parent_scenario();
+    let mut child = child_scenario();
+    child.scenery.items[1].name = Some(1);
+    assert!(merge_child_scenario(&mut parent, &child).is_err());
+    assert_eq!(parent_scenario(), parent);
+}
+
+#[test]
+fn clean_unused_entries() {
+    let mut scenario = child_scenario();
+    scenario.object_names.items.insert(0, ScenarioObjectName { name: name("unused"), ..Default::default() });
+    for scenery in &mut scenario.scenery.items {
+        scenery.name = scenery.name.map(|n| n + 1);
+    }
+    scenario.scenery.items.pop();
+
+    // Only the script references "door".
+    for_each_node_in_scenario::<BigEndian, _, _, _>(&mut scenario, |_, _| (), |data, node| if node._type == ScenarioScriptValueType::ObjectName {
+        let mut node = *node;
+        node.data = 2i16.into();
+        node.write::<BigEndian>(data, 0, data.len()).unwrap();
+    }, |_| ()).unwrap();
+
+    let report = clean_scenario(&mut scenario).unwrap();
+    assert_eq!(vec![name("unused")], report.removed_object_names);
+    let removed: Vec<_> = report.removed_palette_entries.iter().map(|(what, p)| (*what, p.path().unwrap().to_string())).collect();
+    assert_eq!(vec![("scenery palette", "scenery/tree.scenery".to_owned()), ("actor palette", "characters/elite.actor_variant".to_owned())], removed);
+
+    let names: Vec<&str> = scenario.object_names.items.iter().map(|n| n.name.as_str()).collect();
+    assert_eq!(vec!["crate", "door"], names);
+    assert_eq!(Some(0), scenario.scenery.items[0]._type);
+    assert_eq!(Some(0), scenario.scenery.items[0].name);
+    assert_eq!(Some(0), scenario.encounters.items[0].squads.items[0].actor_type);
+
+    let mut values = Vec::new();
+    for_each_node_in_scenario::<BigEndian, _, _, _>(&mut scenario, |_, _| (), |_, node| values.push((node._type, i16::from(node.data))), |_| ()).unwrap();
+    assert_eq!(vec![(ScenarioScriptValueType::ObjectName, 1), (ScenarioScriptValueType::TriggerVolume, 1)], values);
+
+    assert!(clean_scenario(&mut scenario).unwrap().is_empty());
+}
+
+#[test]
+fn clean_uncompiled_scripts() {
+    let mut scenario = Scenario::default();
+    for n in ["gate", "Bridge", "unused"] {
+        scenario.object_names.items.push(ScenarioObjectName { name: name(n), ..Default::default() });
+    }
+    scenario.source_files.items.push(ScenarioSourceFile {
+        name: name("mission"),
+        source: Data::new(b"(script startup mission\n    (object_create gate)\n    (object_destroy bridge))\n".to_vec())
+    });
+
+    // Without compiled scripts, names used in the source are kept.
+    let report = clean_scenario(&mut scenario).unwrap();
+    assert_eq!(vec![name("unused")], report.removed_object_names);
+    let names: Vec<&str> = scenario.object_names.items.iter().map(|n| n.name.as_str()).collect();
+    assert_eq!(vec!["gate", "Bridge"], names);
+}
+
+fn placement_scenario() -> Scenario {
+    let mut scenario = parent_scenario();
+    scenario.scenery.items[0].placement.position = Vector3D { x: 1.5, y: -2.25, z: 0.1f32 as f64 };
+    scenario.scenery.items[0].placement.rotation.yaw = Angle { angle: 1.25 };
+    scenario.scenery.items[0].placement.not_placed.automatically = true;
+    scenario.vehicle_palette.items.push(ScenarioVehiclePalette { name: reference("vehicles\\warthog, mp", TagGroup::Vehicle), ..Default::default() });
+    scenario.vehicles.items.push(ScenarioVehicle { _type: Some(0), multiplayer_team_index: 1, ..Default::default() });
+    scenario.vehicles.items[0].multiplayer_spawn_flags.slayer_default = true;
+    scenario.netgame_flags.items.push(ScenarioNetgameFlags { _type: ScenarioNetgameFlagType::TeleportTo, usage_id: Some(3), ..Default::default() });
+    scenario.player_starting_locations.items.push(ScenarioPlayerStartingLocation { team_index: Some(1), type_0: ScenarioSpawnType::Slayer, ..Default::default() });
+    scenario
+}
+
+#[test]
+fn placement_round_trip() {
+    let scenario = placement_scenario();
+    let placements = export_placements(&scenario);
+    let kinds: Vec<_> = placements.iter().map(|p| p.kind).collect();
+    assert_eq!(vec![ScenarioPlacementKind::Scenery, ScenarioPlacementKind::Vehicle, ScenarioPlacementKind::NetgameFlag, ScenarioPlacementKind::PlayerStartingLocation], kinds);
+    assert_eq!(Some("door"), placements[0].name.as_deref());
+    assert_eq!(vec!["not_placed.automatically".to_owned()], placements[0].flags);
+
+    assert_eq!(placements, placements_from_json(&placements_to_json(&placements)).unwrap());
+    assert_eq!(placements, placements_from_csv(&placements_to_csv(&placements)).unwrap());
+
+    // Importing into a scenario with no placements recreates the palettes and object names.
+    let mut imported = Scenario::default();
+    import_placements(&mut imported, &placements).unwrap();
+    assert_eq!(placements, export_placements(&imported));
+    assert_eq!(scenario.scenery, imported.scenery);
+    assert_eq!(scenario.netgame_flags, imported.netgame_flags);
+
+    // Importing into the original scenario changes nothing.
+    let mut reimported = scenario.clone();
+    import_placements(&mut reimported, &placements).unwrap();
+    assert_eq!(scenario, reimported);
+}
+
+#[test]
+fn placement_import_extends_palettes() {
+    let mut scenario = placement_scenario();
+    let placements = placements_from_json(r#"[
+        {"kind": "scenery", "tag": "scenery/rock.scenery", "name": "door", "position": [0, 0, 0]},
+        {"kind": "scenery", "tag": "scenery/tree.scenery", "name": "tree_1", "position": [1, 2, 3], "rotation": [0.5, 0, 0]}
+    ]"#).unwrap();
+    import_placements(&mut scenario, &placements).unwrap();
+
+    assert_eq!(2, scenario.scenery_palette.items.len());
+    assert_eq!(2, scenario.object_names.items.len());
+    assert_eq!(Some(1), scenario.scenery.items[1]._type);
+    assert_eq!(Some(1), scenario.scenery.items[1].name);
+    assert_eq!(1, scenario.vehicles.items.len());
+
+    let bad = placements_from_json(r#"[{"kind": "scenery", "position": [0, 0, 0], "flags": ["machine_flags.one_sided"]}]"#).unwrap();
+    assert!(import_placements(&mut scenario, &bad).is_err());
+
+    // Palettes only accept their own groups.
+    let bad = placements_from_json(r#"[{"kind": "scenery", "tag": "vehicles/warthog.vehicle", "position": [0, 0, 0]}]"#).unwrap();
+    let error = import_placements(&mut scenario, &bad).unwrap_err().to_string();
+    assert!(error.ends_with("warthog.vehicle` cannot be used in the scenery palette (allowed groups are: scenery)"), "{error}");
+    let bad = placements_from_json(r#"[{"kind": "netgame_equipment", "tag": "weapons/pistol.weapon", "position": [0, 0, 0]}]"#).unwrap();
+    assert!(import_placements(&mut scenario, &bad).is_err());
+    assert_eq!(2, scenario.scenery_palette.items.len());
+}
+
+#[test]
+fn placement_import_failure_leaves_scenario_unchanged() {
+    let scenario = placement_scenario();
+
+    // The scenery placements are valid and would extend the palette, but the netgame flag is not.
+    let bad = placements_from_json(r#"[
+        {"kind": "scenery", "tag": "scenery/tree.scenery", "name": "tree_1", "position": [1, 2, 3]},
+        {"kind": "netgame_flag", "position": [0, 0, 0], "types": ["not_a_flag_type"]}
+    ]"#).unwrap();
+    let mut imported = scenario.clone();
+    assert!(import_placements(&mut imported, &bad).is_err());
+    assert_eq!(scenario, imported);
+
+    // Vehicle teams are signed bytes, so large teams can't be stored.
+    let bad = placements_from_json(r#"[{"kind": "vehicle", "tag": "vehicles/warthog.vehicle", "position": [0, 0, 0], "team": 200}]"#).unwrap();
+    assert!(import_placements(&mut imported, &bad).is_err());
+    assert_eq!(scenario, imported);
+    assert!(placements_from_json(r#"[{"kind": "spaceship", "position": [0, 0, 0]}]"#).is_err());
+}
+
+#[test]
+fn convert_scenario_types() {
+    let mut scenario = placement_scenario();
+    scenario._type = ScenarioType::Multiplayer;
+    scenario.encounters.items.push(ScenarioEncounter::default());
+
+    let mut singleplayer = scenario.clone();
+    let conversion = convert_scenario_type(&mut singleplayer, ScenarioType::Singleplayer);
+    assert_eq!(ScenarioType::Singleplayer, singleplayer._type);
+    assert_eq!(1, conversion.removed_netgame_flags);
+    assert_eq!(1, conversion.updated_player_starting_locations);
+    assert!(singleplayer.netgame_flags.items.is_empty());
+    assert_eq!(ScenarioSpawnType::None, singleplayer.player_starting_locations.items[0].type_0);
+    assert!(conversion.warnings.is_empty());
+
+    let conversion = convert_scenario_type(&mut singleplayer, ScenarioType::Multiplayer);
+    assert_eq!(1, conversion.updated_player_starting_locations);
+    assert_eq!(ScenarioSpawnType::AllGames, singleplayer.player_starting_locations.items[0].type_0);
+    assert_eq!(1, conversion.warnings.len());
+
+    let mut user_interface = scenario;
+    let conversion = convert_scenario_type(&mut user_interface, ScenarioType::UserInterface);
+    assert_eq!(1, conversion.removed_player_starting_locations);
+    assert!(user_interface.player_starting_locations.items.is_empty());
+    assert_eq!(1, conversion.warnings.len());
+}
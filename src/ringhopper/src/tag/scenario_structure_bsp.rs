@@ -3,6 +3,11 @@ use primitives::byteorder::LittleEndian;
 use primitives::error::{Error, OverflowCheck, RinghopperResult};
 use primitives::parse::{RawStructIteratorInfallible, SimpleTagData};
 
+pub mod clusters;
+pub mod compile;
+pub mod export;
+pub mod lightmap;
+
 /// Detect if compressed or uncompressed vertices are missing and attempt to restore them.
 pub fn recompress_scenario_structure_bsp_vertices(bsp: &mut ScenarioStructureBSP) -> RinghopperResult<bool> {
     let mut fixed = false;
@@ -153,3 +158,6 @@ pub fn get_compressed_vertices_for_bsp_material(material: &ScenarioStructureBSPM
         compressed_vertices
     ))
 }
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,181 @@
+use std::fmt::Write;
+use definitions::ScenarioStructureBSP;
+use primitives::primitive::{Index, TagReference};
+
+/// Summary of a cluster in a scenario_structure_bsp tag.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ClusterSummary {
+    /// Number of rendered triangles in the cluster.
+    pub triangle_count: usize,
+
+    /// Number of subclusters, which divide the cluster's surfaces into smaller boxes for culling.
+    pub subcluster_count: usize,
+
+    /// Portals connecting this cluster to other clusters, along with the cluster on the other side.
+    pub portals: Vec<(u16, u16)>,
+
+    /// Index of the sky in the scenario's sky palette.
+    pub sky: Index,
+
+    /// Name of the fog palette entry assigned to the cluster, if any.
+    pub fog: Option<String>,
+
+    /// Name of the weather palette entry assigned to the cluster, if any.
+    pub weather: Option<String>,
+
+    /// Name of the background sound palette entry assigned to the cluster, if any.
+    pub background_sound: Option<String>,
+
+    /// Name of the sound environment palette entry assigned to the cluster, if any.
+    pub sound_environment: Option<String>
+}
+
+/// Visibility information for a scenario_structure_bsp tag.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ClusterReport {
+    pub clusters: Vec<ClusterSummary>,
+
+    /// Groups of clusters that can see each other through portals, largest first.
+    pub connected_groups: Vec<Vec<u16>>,
+
+    /// Problems with portals and palette assignments.
+    pub problems: Vec<String>,
+
+    /// Things worth knowing about which are not necessarily problems, such as clusters without a sound environment.
+    pub notes: Vec<String>
+}
+
+/// Gather cluster, portal, and palette information from a scenario_structure_bsp tag.
+///
+/// Clusters are connected by the front and back clusters of each portal. Clusters which are not in the largest connected
+/// group cannot be seen from (or heard from) most of the level, and portals which are not listed by the clusters they
+/// connect are reported as leaks.
+pub fn analyze_clusters(bsp: &ScenarioStructureBSP) -> ClusterReport {
+    let mut report = ClusterReport::default();
+    let cluster_count = bsp.clusters.items.len();
+
+    let palette_name = |names: Vec<(&str, &TagReference)>, index: Index, what: &str, cluster: usize, problems: &mut Vec<String>| -> Option<String> {
+        let index = index? as usize;
+        match names.get(index) {
+            Some((name, _)) if !name.is_empty() => Some(name.to_string()),
+            Some((_, reference)) => Some(reference.path().map(|p| p.to_string()).unwrap_or_else(|| format!("#{index}"))),
+            None => {
+                problems.push(format!("cluster #{cluster} references out-of-bounds {what} #{index}"));
+                None
+            }
+        }
+    };
+
+    for (index, cluster) in bsp.clusters.items.iter().enumerate() {
+        let mut summary = ClusterSummary {
+            triangle_count: cluster.surface_indices.items.len(),
+            subcluster_count: cluster.subclusters.items.len(),
+            sky: cluster.sky,
+            ..Default::default()
+        };
+
+        summary.fog = palette_name(bsp.fog_palette.items.iter().map(|p| (p.name.as_str(), &p.fog)).collect(), cluster.fog, "fog", index, &mut report.problems);
+        summary.weather = palette_name(bsp.weather_palette.items.iter().map(|p| (p.name.as_str(), &p.particle_system)).collect(), cluster.weather, "weather", index, &mut report.problems);
+        summary.background_sound = palette_name(bsp.background_sound_palette.items.iter().map(|p| (p.name.as_str(), &p.background_sound)).collect(), cluster.background_sound, "background sound", index, &mut report.problems);
+        summary.sound_environment = palette_name(bsp.sound_environment_palette.items.iter().map(|p| (p.name.as_str(), &p.sound_environment)).collect(), cluster.sound_environment, "sound environment", index, &mut report.problems);
+        if summary.sound_environment.is_none() {
+            report.notes.push(format!("cluster #{index} has no sound environment"));
+        }
+
+        for portal_index in cluster.portals.items.iter().filter_map(|p| p.portal) {
+            let Some(portal) = bsp.cluster_portals.items.get(portal_index as usize) else {
+                report.problems.push(format!("cluster #{index} references out-of-bounds portal #{portal_index}"));
+                continue
+            };
+            let other = match (portal.front_cluster, portal.back_cluster) {
+                (Some(f), Some(b)) if f as usize == index => b,
+                (Some(f), Some(b)) if b as usize == index => f,
+                _ => {
+                    report.problems.push(format!("cluster #{index} lists portal #{portal_index}, which does not connect to it"));
+                    continue
+                }
+            };
+            summary.portals.push((portal_index, other));
+        }
+
+        report.clusters.push(summary);
+    }
+
+    let mut adjacent = vec![Vec::new(); cluster_count];
+    for (index, portal) in bsp.cluster_portals.items.iter().enumerate() {
+        let (Some(front), Some(back)) = (portal.front_cluster, portal.back_cluster) else {
+            report.problems.push(format!("portal #{index} is missing a cluster"));
+            continue
+        };
+        if front as usize >= cluster_count || back as usize >= cluster_count {
+            report.problems.push(format!("portal #{index} references an out-of-bounds cluster"));
+            continue
+        }
+        if front == back {
+            report.problems.push(format!("portal #{index} connects cluster #{front} to itself"));
+        }
+        adjacent[front as usize].push(back as usize);
+        adjacent[back as usize].push(front as usize);
+        for cluster in [front, back] {
+            if !report.clusters[cluster as usize].portals.iter().any(|p| p.0 as usize == index) {
+                report.problems.push(format!("portal #{index} leaks: cluster #{cluster} does not list it"));
+            }
+        }
+    }
+
+    // Find connected groups of clusters.
+    let mut group_of = vec![None; cluster_count];
+    for start in 0..cluster_count {
+        if group_of[start].is_some() {
+            continue
+        }
+        let group_index = report.connected_groups.len();
+        let mut group = Vec::new();
+        let mut stack = vec![start];
+        group_of[start] = Some(group_index);
+        while let Some(cluster) = stack.pop() {
+            group.push(cluster as u16);
+            for &other in &adjacent[cluster] {
+                if group_of[other].is_none() {
+                    group_of[other] = Some(group_index);
+                    stack.push(other);
+                }
+            }
+        }
+        group.sort();
+        report.connected_groups.push(group);
+    }
+    report.connected_groups.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+
+    for group in report.connected_groups.iter().skip(1) {
+        let clusters: Vec<String> = group.iter().map(|c| format!("#{c}")).collect();
+        report.problems.push(format!("cluster(s) {} cannot be reached from the rest of the level", clusters.join(", ")));
+    }
+
+    report
+}
+
+impl ClusterReport {
+    /// Write the cluster graph in the DOT format used by Graphviz.
+    ///
+    /// Each cluster is a node labeled with its triangle count, and each portal is an edge.
+    pub fn to_dot(&self) -> String {
+        let mut output = String::new();
+        output += "graph clusters {\n";
+        for (index, cluster) in self.clusters.iter().enumerate() {
+            let mut label = format!("cluster #{index}\\n{} triangle(s)", cluster.triangle_count);
+            if let Some(sound_environment) = &cluster.sound_environment {
+                write!(&mut label, "\\n{}", sound_environment.replace('\\', "\\\\").replace('"', "\\\"")).unwrap();
+            }
+            writeln!(&mut output, "    cluster{index} [label=\"{label}\"];").unwrap();
+        }
+        for (index, cluster) in self.clusters.iter().enumerate() {
+            // Only write each portal once.
+            for &(portal, other) in cluster.portals.iter().filter(|p| p.1 as usize >= index) {
+                writeln!(&mut output, "    cluster{index} -- cluster{other} [label=\"portal #{portal}\"];").unwrap();
+            }
+        }
+        output += "}\n";
+        output
+    }
+}
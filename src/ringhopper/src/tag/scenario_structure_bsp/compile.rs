@@ -0,0 +1,841 @@
+use std::collections::HashMap;
+use definitions::{ModelCollisionGeometryBSP, ModelCollisionGeometryBSPSurfaceFlags, ModelVertexType, ScenarioStructureBSP, ScenarioStructureBSPBreakableSurface, ScenarioStructureBSPCluster, ScenarioStructureBSPClusterPortal, ScenarioStructureBSPClusterPortalIndex, ScenarioStructureBSPClusterPortalVertex, ScenarioStructureBSPClusterSurfaceIndex, ScenarioStructureBSPCollisionMaterial, ScenarioStructureBSPFogPlane, ScenarioStructureBSPFogPlaneVertex, ScenarioStructureBSPFogRegion, ScenarioStructureBSPLeaf, ScenarioStructureBSPLightmap, ScenarioStructureBSPMarker, ScenarioStructureBSPMaterial, ScenarioStructureBSPMaterialUncompressedRenderedVertex, ScenarioStructureBSPSubcluster, ScenarioStructureBSPSubclusterSurfaceIndex, ScenarioStructureBSPSurface, ScenarioStructureBSPSurfaceReference, ScenarioStructureBSPWeatherPolyhedron, ScenarioStructureBSPWeatherPolyhedronPlane};
+use primitives::byteorder::LittleEndian;
+use primitives::error::{Error, RinghopperResult};
+use primitives::parse::SimpleTagData;
+use primitives::primitive::{Bounds, Plane3D, String32, TagPath, TagReference, Vector, Vector2D, Vector3D};
+use crate::data::jms::{JMS, JMS_UNITS_PER_WORLD_UNIT};
+use crate::tag::collision_bsp::{BSP_LEAF_FLAG, BSP_NULL, BSP_PLANE_EPSILON, BSPCellFace, build_collision_bsp_with_splitters, bsp_leaf_for_point, CollisionTriangle, plane_for_triangle, PointWelder, ProjectionAxis};
+use super::recompress_scenario_structure_bsp_vertices;
+
+/// Maximum number of surfaces in a subcluster.
+pub const MAX_SUBCLUSTER_SURFACES: usize = 128;
+
+/// Maximum number of vertices in a cluster portal.
+pub const MAX_PORTAL_VERTICES: usize = 128;
+
+/// Maximum number of planes in a weather polyhedron.
+pub const MAX_WEATHER_POLYHEDRON_PLANES: usize = 16;
+
+/// Distance to offset points from a surface when finding which cluster it faces.
+const CLUSTER_PROBE_DISTANCE: f64 = 0.01;
+
+/// How a JMS material is used in a structure BSP.
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum MaterialUsage {
+    /// Rendered and/or collideable surface using a shader.
+    Shader { render: bool, collision: bool },
+
+    /// Cluster portal (`+portal`, `+exactportal`, or any material with `.`)
+    Portal,
+
+    /// Weather polyhedron (`+weatherpoly`)
+    WeatherPolyhedron,
+
+    /// Sky (`+sky`); this is collideable but not rendered, and clusters facing it can see the sky.
+    Sky,
+
+    /// Seam sealer (`+seamsealer`); this is collideable but not rendered.
+    SeamSealer,
+
+    /// Fog plane (any material with `$`)
+    FogPlane
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Material<'a> {
+    shader_name: &'a str,
+    usage: MaterialUsage,
+    flags: ModelCollisionGeometryBSPSurfaceFlags,
+    ai_deafening: bool
+}
+
+fn parse_material(name: &str) -> Material<'_> {
+    let base = name.trim_end_matches(|c: char| "%#!@*$^-&.".contains(c));
+    let symbols = &name[base.len()..];
+
+    let usage = match base {
+        "+portal" | "+exactportal" => MaterialUsage::Portal,
+        "+weatherpoly" => MaterialUsage::WeatherPolyhedron,
+        "+sky" => MaterialUsage::Sky,
+        "+seamsealer" => MaterialUsage::SeamSealer,
+        _ if symbols.contains('.') => MaterialUsage::Portal,
+        _ if symbols.contains('$') => MaterialUsage::FogPlane,
+        _ => MaterialUsage::Shader {
+            render: !symbols.contains('@'),
+            collision: !symbols.contains('!')
+        }
+    };
+
+    Material {
+        shader_name: base,
+        usage,
+        flags: ModelCollisionGeometryBSPSurfaceFlags {
+            two_sided: symbols.contains('%'),
+            invisible: symbols.contains('@') || matches!(usage, MaterialUsage::Sky | MaterialUsage::SeamSealer),
+            climbable: symbols.contains('^'),
+            breakable: symbols.contains('-')
+        },
+        ai_deafening: symbols.contains('&')
+    }
+}
+
+/// Triangle with its vertices in world units.
+struct LevelTriangle<'a> {
+    vertices: [Vector3D; 3],
+    normals: [Vector3D; 3],
+    texture_coordinates: [Vector2D; 3],
+    material: Material<'a>
+}
+
+/// Compile a scenario_structure_bsp tag from a level JMS.
+///
+/// Materials are resolved to shader tags with `resolve_shader`, which is given the material name without any shader
+/// symbols. Special materials (`+portal`, `+exactportal`, `+sky`, `+seamsealer`, and `+weatherpoly`) and fog planes
+/// (`$`) do not need shaders.
+///
+/// Every collideable surface, other than two-sided surfaces, must form a closed mesh, and every render surface must be
+/// inside of a cluster. Render geometry is placed in a single lightmap with no lightmap vertices. Leaves are shared
+/// with the collision BSP, and each leaf references the render surfaces inside of it.
+///
+/// If `existing` is set, palettes, fog regions, and default lighting values are preserved.
+pub fn compile_structure_bsp(
+    jms: &JMS,
+    existing: Option<&ScenarioStructureBSP>,
+    resolve_shader: &dyn Fn(&str) -> RinghopperResult<TagPath>
+) -> RinghopperResult<ScenarioStructureBSP> {
+    let mut bsp = ScenarioStructureBSP::default();
+    if let Some(existing) = existing {
+        bsp.lightmaps_bitmap = existing.lightmaps_bitmap.clone();
+        bsp.vehicle_floor = existing.vehicle_floor;
+        bsp.vehicle_ceiling = existing.vehicle_ceiling;
+        bsp.default_ambient_color = existing.default_ambient_color;
+        bsp.default_distant_light_0_color = existing.default_distant_light_0_color;
+        bsp.default_distant_light_0_direction = existing.default_distant_light_0_direction;
+        bsp.default_distant_light_1_color = existing.default_distant_light_1_color;
+        bsp.default_distant_light_1_direction = existing.default_distant_light_1_direction;
+        bsp.default_reflection_tint = existing.default_reflection_tint;
+        bsp.default_shadow_vector = existing.default_shadow_vector;
+        bsp.default_shadow_color = existing.default_shadow_color;
+        bsp.lens_flares = existing.lens_flares.clone();
+        bsp.fog_palette = existing.fog_palette.clone();
+        bsp.weather_palette = existing.weather_palette.clone();
+        bsp.background_sound_palette = existing.background_sound_palette.clone();
+        bsp.sound_environment_palette = existing.sound_environment_palette.clone();
+    }
+
+    let materials: Vec<Material> = jms.materials.iter().map(|m| parse_material(&m.name)).collect();
+    let triangles = level_triangles(jms, &materials)?;
+
+    // Resolve each shader once.
+    let mut shaders: HashMap<&str, TagPath> = HashMap::new();
+    for triangle in &triangles {
+        if let MaterialUsage::Shader { .. } = triangle.material.usage {
+            let name = triangle.material.shader_name;
+            if !shaders.contains_key(name) {
+                shaders.insert(name, resolve_shader(name)?);
+            }
+        }
+    }
+
+    compile_world_bounds(&mut bsp, jms);
+    compile_markers(&mut bsp, jms)?;
+
+    // Collision
+    let mut collision_material_indices: HashMap<&str, u16> = HashMap::new();
+    let mut collision_triangles = Vec::new();
+    let mut breakable_triangles = Vec::new();
+    let mut portals = Vec::new();
+    let mut portals_deafening = Vec::new();
+    for triangle in &triangles {
+        let material = match triangle.material.usage {
+            MaterialUsage::Shader { collision: true, .. } => {
+                let name = triangle.material.shader_name;
+                let next_index = collision_material_indices.len() as u16;
+                let index = *collision_material_indices.entry(name).or_insert_with(|| {
+                    bsp.collision_materials.items.push(ScenarioStructureBSPCollisionMaterial {
+                        shader: TagReference::Set(shaders[name].clone()),
+                        ..Default::default()
+                    });
+                    next_index
+                });
+                Some(index)
+            },
+            MaterialUsage::Sky | MaterialUsage::SeamSealer => None,
+            MaterialUsage::Portal => {
+                portals.push(triangle.vertices);
+                portals_deafening.push(triangle.material.ai_deafening);
+                continue
+            },
+            _ => continue
+        };
+
+        let breakable_surface = if triangle.material.flags.breakable {
+            breakable_triangles.push(triangle);
+            i8::try_from(breakable_triangles.len() - 1)
+                .map_err(|_| Error::Other("too many breakable surfaces".to_owned()))?
+        }
+        else {
+            -1
+        };
+
+        let collision_triangle = CollisionTriangle {
+            vertices: triangle.vertices,
+            material,
+            flags: triangle.material.flags,
+            breakable_surface
+        };
+        collision_triangles.push(collision_triangle);
+    }
+
+    let partition = build_collision_bsp_with_splitters(&collision_triangles, &portals)
+        .map_err(|e| Error::Other(format!("can't build collision BSP: {e}")))?;
+
+    for (index, triangle) in breakable_triangles.iter().enumerate() {
+        let centroid = triangle_centroid(&triangle.vertices);
+        let collision_surface_index = partition.bsp.surfaces.items
+            .iter()
+            .position(|s| s.breakable_surface as isize == index as isize)
+            .map(|s| s as i32)
+            .unwrap_or(-1);
+        bsp.breakable_surfaces.items.push(ScenarioStructureBSPBreakableSurface {
+            centroid,
+            radius: triangle.vertices.iter().map(|v| v.distance_squared(&centroid).sqrt()).fold(0.0, f64::max),
+            collision_surface_index
+        });
+    }
+
+    // Clusters
+    let leaf_clusters = compile_clusters(&mut bsp, &partition, &portals, &portals_deafening)?;
+    let cluster_for_point = |point: Vector3D| bsp_leaf_for_point(&partition.bsp, point).map(|leaf| leaf_clusters[leaf as usize]);
+    let cluster_for_triangle = |vertices: &[Vector3D; 3]| {
+        let centroid = triangle_centroid(vertices);
+        let normal = plane_for_triangle(vertices).map(|p| p.vector).unwrap_or_default();
+        cluster_for_point(centroid + normal.scale(CLUSTER_PROBE_DISTANCE))
+            .or_else(|| cluster_for_point(centroid - normal.scale(CLUSTER_PROBE_DISTANCE)))
+    };
+
+    for triangle in triangles.iter().filter(|t| t.material.usage == MaterialUsage::Sky) {
+        if let Some(cluster) = cluster_for_triangle(&triangle.vertices) {
+            bsp.clusters.items[cluster].sky = Some(0);
+        }
+    }
+
+    bsp.leaves.items = leaf_clusters
+        .iter()
+        .map(|cluster| ScenarioStructureBSPLeaf { cluster: Some(*cluster as u16), ..Default::default() })
+        .collect();
+
+    // Rendering
+    let render_triangles: Vec<&LevelTriangle> = triangles
+        .iter()
+        .filter(|t| matches!(t.material.usage, MaterialUsage::Shader { render: true, .. }))
+        .collect();
+    let mut cluster_surfaces: Vec<Vec<(usize, [Vector3D; 3])>> = vec![Vec::new(); bsp.clusters.items.len()];
+    let mut render_surfaces = Vec::new();
+    compile_render_geometry(&mut bsp, &render_triangles, &shaders, |surface, triangle| {
+        let cluster = cluster_for_triangle(&triangle.vertices).ok_or_else(|| Error::Other(format!(
+            "surface at {:?} is not in any cluster (it may be outside of the level or inside solid space)",
+            triangle_centroid(&triangle.vertices)
+        )))?;
+        cluster_surfaces[cluster].push((surface, triangle.vertices));
+        render_surfaces.push((surface, triangle));
+        Ok(())
+    })?;
+    compile_rendering_bsp(&mut bsp, &partition.bsp, &render_surfaces)?;
+    bsp.collision_bsp.items.push(partition.bsp);
+    for (cluster, surfaces) in bsp.clusters.items.iter_mut().zip(cluster_surfaces) {
+        compile_cluster_surfaces(cluster, &surfaces);
+    }
+    recompress_scenario_structure_bsp_vertices(&mut bsp)?;
+
+    // Weather and fog
+    compile_weather_polyhedra(&mut bsp, &triangles)?;
+    compile_fog_planes(&mut bsp, &triangles, existing)?;
+
+    Ok(bsp)
+}
+
+fn level_triangles<'a>(jms: &JMS, materials: &[Material<'a>]) -> RinghopperResult<Vec<LevelTriangle<'a>>> {
+    let mut triangles = Vec::with_capacity(jms.triangles.len());
+    for triangle in &jms.triangles {
+        let material = *materials
+            .get(triangle.shader as usize)
+            .ok_or_else(|| Error::Other("triangle has an invalid material".to_owned()))?;
+        let vertices = triangle.vertices.map(|v| jms.vertices.get(v as usize));
+        let [Some(a), Some(b), Some(c)] = vertices else {
+            return Err(Error::Other("triangle has an invalid vertex".to_owned()))
+        };
+        let vertices = [a, b, c];
+
+        triangles.push(LevelTriangle {
+            vertices: vertices.map(|v| v.position.scale(1.0 / JMS_UNITS_PER_WORLD_UNIT)),
+            normals: vertices.map(|v| v.normal),
+            texture_coordinates: vertices.map(|v| Vector2D { x: v.texture_coordinates.x, y: 1.0 - v.texture_coordinates.y }),
+            material
+        });
+    }
+    Ok(triangles)
+}
+
+fn triangle_centroid(vertices: &[Vector3D; 3]) -> Vector3D {
+    (vertices[0] + vertices[1] + vertices[2]).scale(1.0 / 3.0)
+}
+
+fn compile_world_bounds(bsp: &mut ScenarioStructureBSP, jms: &JMS) {
+    let mut positions = jms.vertices.iter().map(|v| v.position.scale(1.0 / JMS_UNITS_PER_WORLD_UNIT));
+    let Some(first) = positions.next() else {
+        return
+    };
+    let (mut min, mut max) = (first, first);
+    for p in positions {
+        min = Vector3D { x: min.x.min(p.x), y: min.y.min(p.y), z: min.z.min(p.z) };
+        max = Vector3D { x: max.x.max(p.x), y: max.y.max(p.y), z: max.z.max(p.z) };
+    }
+    bsp.world_bounds_x = Bounds { lower: min.x, upper: max.x };
+    bsp.world_bounds_y = Bounds { lower: min.y, upper: max.y };
+    bsp.world_bounds_z = Bounds { lower: min.z, upper: max.z };
+}
+
+fn compile_markers(bsp: &mut ScenarioStructureBSP, jms: &JMS) -> RinghopperResult<()> {
+    let transforms = jms.absolute_node_transforms()?;
+    for marker in &jms.markers {
+        let (node_rotation, node_position) = transforms.get(marker.node as usize).copied().unwrap_or_default();
+        bsp.markers.items.push(ScenarioStructureBSPMarker {
+            name: String32::from_str(&marker.name)?,
+            rotation: node_rotation.multiply(&marker.rotation),
+            position: node_position + node_rotation.rotate_vector(marker.position.scale(1.0 / JMS_UNITS_PER_WORLD_UNIT))
+        });
+    }
+    Ok(())
+}
+
+struct DisjointSet {
+    parents: Vec<usize>
+}
+
+impl DisjointSet {
+    fn new(count: usize) -> Self {
+        Self { parents: (0..count).collect() }
+    }
+
+    fn find(&mut self, mut index: usize) -> usize {
+        while self.parents[index] != index {
+            self.parents[index] = self.parents[self.parents[index]];
+            index = self.parents[index];
+        }
+        index
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.find(a), self.find(b));
+        self.parents[a.max(b)] = a.min(b);
+    }
+}
+
+/// Group leaves into clusters separated by portals, and generate cluster portals.
+///
+/// Returns the cluster of each leaf.
+fn compile_clusters(
+    bsp: &mut ScenarioStructureBSP,
+    partition: &crate::tag::collision_bsp::CollisionBSPPartition,
+    portals: &[[Vector3D; 3]],
+    portals_deafening: &[bool]
+) -> RinghopperResult<Vec<usize>> {
+    let collision = &partition.bsp;
+    let leaf_count = partition.leaf_cells.len();
+
+    // Find which leaves touch each other through each node.
+    let mut node_faces: Vec<[Vec<(usize, &BSPCellFace)>; 2]> = vec![[Vec::new(), Vec::new()]; collision.bsp3d_nodes.items.len()];
+    for (leaf, cell) in partition.leaf_cells.iter().enumerate() {
+        for face in &cell.faces {
+            if let Some((node, front)) = face.node {
+                node_faces[node as usize][front as usize].push((leaf, face));
+            }
+        }
+    }
+
+    let mut adjacent = Vec::new();
+    for (node, [back, front]) in node_faces.iter().enumerate() {
+        let normal = collision.planes.items[collision.bsp3d_nodes.items[node].plane as usize].plane.vector;
+        for (front_leaf, front_face) in front {
+            for (back_leaf, back_face) in back {
+                if polygons_overlap(&front_face.points, &back_face.points, normal) {
+                    adjacent.push((node, *front_leaf, *back_leaf, front_face, back_face));
+                }
+            }
+        }
+    }
+
+    let mut sets = DisjointSet::new(leaf_count);
+    for (node, front_leaf, back_leaf, _, _) in &adjacent {
+        if partition.node_splitters[*node].is_empty() {
+            sets.union(*front_leaf, *back_leaf);
+        }
+    }
+
+    let mut cluster_indices = HashMap::new();
+    let leaf_clusters: Vec<usize> = (0..leaf_count)
+        .map(|leaf| {
+            let root = sets.find(leaf);
+            let next = cluster_indices.len();
+            *cluster_indices.entry(root).or_insert(next)
+        })
+        .collect();
+    bsp.clusters.items = (0..cluster_indices.len()).map(|_| ScenarioStructureBSPCluster::default()).collect();
+
+    // Generate a portal for each pair of clusters that touch through a portal.
+    let mut portal_lookup: HashMap<(usize, usize, usize), Vec<usize>> = HashMap::new();
+    let mut portal_order = Vec::new();
+    for (node, front_leaf, back_leaf, front_face, back_face) in &adjacent {
+        let splitters = &partition.node_splitters[*node];
+        let (front_cluster, back_cluster) = (leaf_clusters[*front_leaf], leaf_clusters[*back_leaf]);
+        if splitters.is_empty() || front_cluster == back_cluster {
+            continue
+        }
+
+        let normal = collision.planes.items[collision.bsp3d_nodes.items[*node].plane as usize].plane.vector;
+        let key = (*node, front_cluster, back_cluster);
+        let entry = portal_lookup.entry(key).or_insert_with(|| {
+            portal_order.push(key);
+            Vec::new()
+        });
+        for s in splitters {
+            if !entry.contains(s) && (polygons_overlap(&portals[*s], &front_face.points, normal) || polygons_overlap(&portals[*s], &back_face.points, normal)) {
+                entry.push(*s);
+            }
+        }
+    }
+
+    for key in portal_order {
+        let (node, front_cluster, back_cluster) = key;
+        let splitters = &portal_lookup[&key];
+        let plane_index = collision.bsp3d_nodes.items[node].plane;
+        let plane = collision.planes.items[plane_index as usize].plane;
+
+        let points: Vec<Vector3D> = splitters.iter().flat_map(|s| portals[*s]).collect();
+        let hull = convex_hull_on_plane(&points, &plane);
+        if hull.len() > MAX_PORTAL_VERTICES {
+            return Err(Error::Other(format!("portal has too many vertices ({} > {MAX_PORTAL_VERTICES})", hull.len())))
+        }
+        let centroid = hull.iter().fold(Vector3D::zero(), |a, b| a + *b).scale(1.0 / hull.len().max(1) as f64);
+
+        let portal_index = bsp.cluster_portals.items.len() as u16;
+        let mut portal = ScenarioStructureBSPClusterPortal {
+            front_cluster: Some(front_cluster as u16),
+            back_cluster: Some(back_cluster as u16),
+            plane_index: plane_index as i32,
+            centroid,
+            bounding_radius: hull.iter().map(|p| p.distance_squared(&centroid).sqrt()).fold(0.0, f64::max),
+            ..Default::default()
+        };
+        portal.flags.ai_cant_hear_through_this_shit = splitters.iter().any(|s| portals_deafening[*s]);
+        portal.vertices.items = hull.into_iter().map(|point| ScenarioStructureBSPClusterPortalVertex { point }).collect();
+        bsp.cluster_portals.items.push(portal);
+
+        for cluster in [front_cluster, back_cluster] {
+            bsp.clusters.items[cluster].portals.items.push(ScenarioStructureBSPClusterPortalIndex { portal: Some(portal_index) });
+        }
+    }
+
+    Ok(leaf_clusters)
+}
+
+fn signed_area(points: &[Vector2D]) -> f64 {
+    let mut area = 0.0;
+    for i in 0..points.len() {
+        let (a, b) = (points[i], points[(i + 1) % points.len()]);
+        area += a.x * b.y - b.x * a.y;
+    }
+    area / 2.0
+}
+
+/// Return `true` if two convex polygons on the same plane overlap by a non-zero area.
+fn polygons_overlap(a: &[Vector3D], b: &[Vector3D], normal: Vector3D) -> bool {
+    let axis = ProjectionAxis::for_normal(normal);
+    let mut subject: Vec<Vector2D> = a.iter().map(|p| axis.project(*p)).collect();
+    let clip: Vec<Vector2D> = b.iter().map(|p| axis.project(*p)).collect();
+    let orientation = signed_area(&clip).signum();
+
+    for i in 0..clip.len() {
+        let (c0, c1) = (clip[i], clip[(i + 1) % clip.len()]);
+        let side = |p: Vector2D| orientation * ((c1.x - c0.x) * (p.y - c0.y) - (c1.y - c0.y) * (p.x - c0.x));
+
+        let mut output = Vec::with_capacity(subject.len() + 1);
+        for j in 0..subject.len() {
+            let (p, q) = (subject[j], subject[(j + 1) % subject.len()]);
+            let (sp, sq) = (side(p), side(q));
+            if sp >= 0.0 {
+                output.push(p);
+            }
+            if (sp >= 0.0) != (sq >= 0.0) {
+                output.push(p + (q - p).scale(sp / (sp - sq)));
+            }
+        }
+        subject = output;
+        if subject.len() < 3 {
+            return false
+        }
+    }
+
+    signed_area(&subject).abs() > BSP_PLANE_EPSILON
+}
+
+/// Get the convex hull of points on a plane, wound counterclockwise when looking at the front of the plane.
+pub(crate) fn convex_hull_on_plane(points: &[Vector3D], plane: &Plane3D) -> Vec<Vector3D> {
+    let mut welder = PointWelder::default();
+    for p in points {
+        welder.add(*p);
+    }
+    let points = welder.into_points();
+
+    let axis = ProjectionAxis::for_normal(plane.vector);
+    let mut sorted: Vec<(Vector2D, Vector3D)> = points.iter().map(|p| (axis.project(*p), *p)).collect();
+    sorted.sort_by(|a, b| a.0.x.total_cmp(&b.0.x).then(a.0.y.total_cmp(&b.0.y)));
+    if sorted.len() < 3 {
+        return sorted.into_iter().map(|p| p.1).collect()
+    }
+
+    // Monotone chain
+    let cross = |o: Vector2D, a: Vector2D, b: Vector2D| (a.x - o.x) * (b.y - o.y) - (a.y - o.y) * (b.x - o.x);
+    let mut hull: Vec<(Vector2D, Vector3D)> = Vec::with_capacity(sorted.len() * 2);
+    for pass in 0..2 {
+        let start = hull.len();
+        let iter: Box<dyn Iterator<Item = &(Vector2D, Vector3D)>> = if pass == 0 { Box::new(sorted.iter()) } else { Box::new(sorted.iter().rev()) };
+        for p in iter {
+            while hull.len() >= start + 2 && cross(hull[hull.len() - 2].0, hull[hull.len() - 1].0, p.0) <= 0.0 {
+                hull.pop();
+            }
+            hull.push(*p);
+        }
+        hull.pop();
+    }
+
+    // The projection may mirror the plane, so flip the winding to match the normal.
+    let mut hull: Vec<Vector3D> = hull.into_iter().map(|p| p.1).collect();
+    if hull.len() >= 3 {
+        let normal = (hull[1] - hull[0]).cross(&(hull[2] - hull[0]));
+        if normal.dot(&plane.vector) < 0.0 {
+            hull.reverse();
+        }
+    }
+    hull
+}
+
+#[derive(Default)]
+struct RenderVertex {
+    position: Vector3D,
+    normal: Vector3D,
+    texture_coords: Vector2D,
+    tangent: Vector3D,
+    binormal: Vector3D
+}
+
+/// Generate materials and surfaces for render geometry.
+///
+/// `on_surface` is called with the index and triangle of each surface.
+fn compile_render_geometry<'t, 'm>(
+    bsp: &mut ScenarioStructureBSP,
+    triangles: &[&'t LevelTriangle<'m>],
+    shaders: &HashMap<&str, TagPath>,
+    mut on_surface: impl FnMut(usize, &'t LevelTriangle<'m>) -> RinghopperResult<()>
+) -> RinghopperResult<()> {
+    // Sort by shader so that materials are always generated in the same order.
+    let mut shader_names: Vec<&str> = triangles.iter().map(|t| t.material.shader_name).collect();
+    shader_names.sort_unstable();
+    shader_names.dedup();
+
+    let mut lightmap = ScenarioStructureBSPLightmap::default();
+
+    for shader_name in shader_names {
+        let mut remaining = triangles.iter().filter(|t| t.material.shader_name == shader_name).peekable();
+
+        // Surfaces use 16-bit vertex indices, so split materials with too many vertices.
+        while remaining.peek().is_some() {
+            let mut vertices: Vec<RenderVertex> = Vec::new();
+            let mut vertex_lookup: HashMap<[u64; 8], u16> = HashMap::new();
+            let mut surfaces: Vec<[u16; 3]> = Vec::new();
+            let mut normal_sum = Vector3D::zero();
+
+            while let Some(triangle) = remaining.next_if(|_| vertices.len() + 3 <= u16::MAX as usize) {
+                let mut indices = [0u16; 3];
+                for (i, index) in indices.iter_mut().enumerate() {
+                    let position = triangle.vertices[i];
+                    let normal = triangle.normals[i];
+                    let texture_coords = triangle.texture_coordinates[i];
+                    let key = [position.x, position.y, position.z, normal.x, normal.y, normal.z, texture_coords.x, texture_coords.y].map(f64::to_bits);
+                    *index = *vertex_lookup.entry(key).or_insert_with(|| {
+                        vertices.push(RenderVertex { position, normal, texture_coords, ..Default::default() });
+                        (vertices.len() - 1) as u16
+                    });
+                }
+
+                // Accumulate tangents and binormals from the texture coordinates.
+                let [p0, p1, p2] = triangle.vertices;
+                let [t0, t1, t2] = triangle.texture_coordinates;
+                let (e1, e2) = (p1 - p0, p2 - p0);
+                let (du1, dv1, du2, dv2) = (t1.x - t0.x, t1.y - t0.y, t2.x - t0.x, t2.y - t0.y);
+                let determinant = du1 * dv2 - du2 * dv1;
+                if determinant.abs() > f64::EPSILON {
+                    let tangent = (e1.scale(dv2) - e2.scale(dv1)).scale(1.0 / determinant);
+                    let binormal = (e2.scale(du1) - e1.scale(du2)).scale(1.0 / determinant);
+                    for i in indices {
+                        vertices[i as usize].tangent += tangent;
+                        vertices[i as usize].binormal += binormal;
+                    }
+                }
+
+                normal_sum += e1.cross(&e2);
+                on_surface(bsp.surfaces.items.len() + surfaces.len(), *triangle)?;
+                surfaces.push(indices);
+            }
+
+            let first_surface = bsp.surfaces.items.len();
+            bsp.surfaces.items.extend(surfaces.iter().map(|s| ScenarioStructureBSPSurface {
+                vertex0_index: Some(s[0]),
+                vertex1_index: Some(s[1]),
+                vertex2_index: Some(s[2])
+            }));
+
+            let centroid = vertices.iter().fold(Vector3D::zero(), |a, v| a + v.position).scale(1.0 / vertices.len() as f64);
+            let plane_normal = if normal_sum.magnitude_squared() > 0.0 { normal_sum.normalize() } else { Vector3D::zero() };
+            let coplanar = vertices.iter().all(|v| (plane_normal.dot(&v.position) - plane_normal.dot(&centroid)).abs() < BSP_PLANE_EPSILON);
+
+            let mut uncompressed_vertices = Vec::with_capacity(vertices.len() * ScenarioStructureBSPMaterialUncompressedRenderedVertex::simple_size());
+            for vertex in &vertices {
+                let (tangent, binormal) = orthonormalize_tangents(vertex);
+                let vertex = ScenarioStructureBSPMaterialUncompressedRenderedVertex {
+                    position: vertex.position,
+                    normal: vertex.normal,
+                    binormal,
+                    tangent,
+                    texture_coords: vertex.texture_coords
+                };
+                uncompressed_vertices.extend_from_slice(vertex.as_bytes::<LittleEndian>().unwrap().bytes());
+            }
+
+            let mut material = ScenarioStructureBSPMaterial {
+                shader: TagReference::Set(shaders[shader_name].clone()),
+                surfaces: first_surface as i32,
+                surface_count: surfaces.len() as i32,
+                centroid,
+                plane: Plane3D { vector: plane_normal, d: plane_normal.dot(&centroid) },
+                ..Default::default()
+            };
+            material.flags.coplanar = coplanar;
+            material.rendered_vertices.vertex_type = ModelVertexType::EnvironmentUncompressed;
+            material.rendered_vertices.vertex_count = vertices.len() as u32;
+            material.lightmap_vertices.vertex_type = ModelVertexType::EnvironmentLightmapUncompressed;
+            material.uncompressed_vertices.bytes = uncompressed_vertices;
+            lightmap.materials.items.push(material);
+        }
+    }
+
+    if !lightmap.materials.items.is_empty() {
+        bsp.lightmaps.items.push(lightmap);
+    }
+
+    Ok(())
+}
+
+/// Make a vertex's tangent and binormal perpendicular to its normal and each other.
+fn orthonormalize_tangents(vertex: &RenderVertex) -> (Vector3D, Vector3D) {
+    let normal = vertex.normal;
+    let mut tangent = vertex.tangent - normal.scale(normal.dot(&vertex.tangent));
+    if tangent.magnitude_squared() < f64::EPSILON {
+        // Pick any perpendicular vector if there is no usable texture mapping.
+        let axis = if normal.x.abs() < 0.9 { Vector3D { x: 1.0, y: 0.0, z: 0.0 } } else { Vector3D { x: 0.0, y: 1.0, z: 0.0 } };
+        tangent = axis - normal.scale(normal.dot(&axis));
+    }
+    let tangent = tangent.normalize();
+
+    let mut binormal = normal.cross(&tangent);
+    if binormal.dot(&vertex.binormal) < 0.0 {
+        binormal = binormal.scale(-1.0);
+    }
+    (tangent, binormal)
+}
+
+/// Add surfaces to a cluster and split them into subclusters.
+fn compile_cluster_surfaces(cluster: &mut ScenarioStructureBSPCluster, surfaces: &[(usize, [Vector3D; 3])]) {
+    cluster.surface_indices.items = surfaces
+        .iter()
+        .map(|s| ScenarioStructureBSPClusterSurfaceIndex { index: s.0 as i32 })
+        .collect();
+
+    for chunk in surfaces.chunks(MAX_SUBCLUSTER_SURFACES) {
+        let mut points = chunk.iter().flat_map(|s| s.1);
+        let first = points.next().unwrap();
+        let (mut min, mut max) = (first, first);
+        for p in points {
+            min = Vector3D { x: min.x.min(p.x), y: min.y.min(p.y), z: min.z.min(p.z) };
+            max = Vector3D { x: max.x.max(p.x), y: max.y.max(p.y), z: max.z.max(p.z) };
+        }
+
+        let mut subcluster = ScenarioStructureBSPSubcluster {
+            world_bounds_x: Bounds { lower: min.x, upper: max.x },
+            world_bounds_y: Bounds { lower: min.y, upper: max.y },
+            world_bounds_z: Bounds { lower: min.z, upper: max.z },
+            ..Default::default()
+        };
+        subcluster.surface_indices.items = chunk
+            .iter()
+            .map(|s| ScenarioStructureBSPSubclusterSurfaceIndex { index: s.0 as i32 })
+            .collect();
+        cluster.subclusters.items.push(subcluster);
+    }
+}
+
+/// Generate the surface references of each leaf.
+///
+/// Each render surface is referenced by every leaf it passes through, along with the collision BSP 3D node the leaf
+/// is a child of. Surfaces lying on a node's plane go to the side they face, or both sides if they are two-sided.
+///
+/// The BSP's nodes are not generated, since the layout of their data has not been verified against tool-compiled
+/// BSPs.
+fn compile_rendering_bsp(bsp: &mut ScenarioStructureBSP, collision: &ModelCollisionGeometryBSP, surfaces: &[(usize, &LevelTriangle)]) -> RinghopperResult<()> {
+    let mut leaf_surfaces: Vec<Vec<ScenarioStructureBSPSurfaceReference>> = vec![Vec::new(); bsp.leaves.items.len()];
+    if !collision.bsp3d_nodes.items.is_empty() {
+        for (surface, triangle) in surfaces {
+            let vertices = &triangle.vertices;
+            let normal = plane_for_triangle(vertices).map(|p| p.vector).unwrap_or_default();
+            let mut stack = vec![0u32];
+            while let Some(node_index) = stack.pop() {
+                let node = collision.bsp3d_nodes
+                    .items
+                    .get(node_index as usize)
+                    .ok_or_else(|| Error::Other(format!("collision BSP node #{node_index} is out of bounds")))?;
+                let mut plane = collision.planes
+                    .items
+                    .get((node.plane & !BSP_LEAF_FLAG) as usize)
+                    .ok_or_else(|| Error::Other(format!("collision BSP node #{node_index} has an out of bounds plane")))?
+                    .plane;
+                if node.plane & BSP_LEAF_FLAG != 0 {
+                    plane = Plane3D { vector: -plane.vector, d: -plane.d };
+                }
+
+                let distances = vertices.map(|v| v.distance_from_plane(&plane));
+                let mut front = distances.iter().any(|d| *d > BSP_PLANE_EPSILON);
+                let mut back = distances.iter().any(|d| *d < -BSP_PLANE_EPSILON);
+                if !front && !back {
+                    let two_sided = triangle.material.flags.two_sided;
+                    front = two_sided || normal.dot(&plane.vector) >= 0.0;
+                    back = two_sided || !front;
+                }
+
+                for (child, on_side) in [(node.front_child, front), (node.back_child, back)] {
+                    if !on_side || child == BSP_NULL {
+                        continue
+                    }
+                    if child & BSP_LEAF_FLAG != 0 {
+                        let leaf = leaf_surfaces
+                            .get_mut((child & !BSP_LEAF_FLAG) as usize)
+                            .ok_or_else(|| Error::Other(format!("collision BSP node #{node_index} has an out of bounds leaf")))?;
+                        leaf.push(ScenarioStructureBSPSurfaceReference { surface: *surface as i32, node: node_index as i32 });
+                    }
+                    else if child > node_index {
+                        // Children always come after their parents, which also guards against cycles.
+                        stack.push(child);
+                    }
+                }
+            }
+        }
+    }
+
+    for (leaf, references) in bsp.leaves.items.iter_mut().zip(leaf_surfaces) {
+        leaf.surface_reference_count = i16::try_from(references.len())
+            .map_err(|_| Error::Other("too many surfaces in a single BSP leaf".to_owned()))?;
+        leaf.surface_references = i32::try_from(bsp.leaf_surfaces.items.len())
+            .map_err(|_| Error::Other("too many leaf surface references".to_owned()))?;
+        bsp.leaf_surfaces.items.extend(references);
+    }
+
+    Ok(())
+}
+
+/// Group triangles of the given usage into connected pieces.
+fn connected_groups<'a>(triangles: &'a [LevelTriangle], usage: MaterialUsage) -> Vec<Vec<&'a LevelTriangle<'a>>> {
+    let triangles: Vec<&LevelTriangle> = triangles.iter().filter(|t| t.material.usage == usage).collect();
+    let mut welder = PointWelder::default();
+    let indices: Vec<[u32; 3]> = triangles.iter().map(|t| t.vertices.map(|v| welder.add(v))).collect();
+
+    let mut sets = DisjointSet::new(welder.into_points().len());
+    for [a, b, c] in &indices {
+        sets.union(*a as usize, *b as usize);
+        sets.union(*a as usize, *c as usize);
+    }
+
+    let mut groups: Vec<Vec<&LevelTriangle>> = Vec::new();
+    let mut group_indices = HashMap::new();
+    for (triangle, [a, _, _]) in triangles.into_iter().zip(indices) {
+        let root = sets.find(a as usize);
+        let group = *group_indices.entry(root).or_insert_with(|| {
+            groups.push(Vec::new());
+            groups.len() - 1
+        });
+        groups[group].push(triangle);
+    }
+    groups
+}
+
+fn compile_weather_polyhedra(bsp: &mut ScenarioStructureBSP, triangles: &[LevelTriangle]) -> RinghopperResult<()> {
+    for group in connected_groups(triangles, MaterialUsage::WeatherPolyhedron) {
+        let mut planes: Vec<Plane3D> = Vec::new();
+        for triangle in &group {
+            let Some(plane) = plane_for_triangle(&triangle.vertices) else {
+                continue
+            };
+            let duplicate = planes.iter().any(|p| p.vector.dot(&plane.vector) > 1.0 - BSP_PLANE_EPSILON && (p.d - plane.d).abs() < BSP_PLANE_EPSILON);
+            if !duplicate {
+                planes.push(plane);
+            }
+        }
+        if planes.len() > MAX_WEATHER_POLYHEDRON_PLANES {
+            return Err(Error::Other(format!("weather polyhedron has too many planes ({} > {MAX_WEATHER_POLYHEDRON_PLANES})", planes.len())))
+        }
+
+        let points: Vec<Vector3D> = group.iter().flat_map(|t| t.vertices).collect();
+        let center = points.iter().fold(Vector3D::zero(), |a, b| a + *b).scale(1.0 / points.len() as f64);
+
+        let mut polyhedron = ScenarioStructureBSPWeatherPolyhedron {
+            bounding_sphere_center: center,
+            bounding_sphere_radius: points.iter().map(|p| p.distance_squared(&center).sqrt()).fold(0.0, f64::max),
+            ..Default::default()
+        };
+        polyhedron.planes.items = planes.into_iter().map(|plane| ScenarioStructureBSPWeatherPolyhedronPlane { plane }).collect();
+        bsp.weather_polyhedra.items.push(polyhedron);
+    }
+    Ok(())
+}
+
+fn compile_fog_planes(bsp: &mut ScenarioStructureBSP, triangles: &[LevelTriangle], existing: Option<&ScenarioStructureBSP>) -> RinghopperResult<()> {
+    for group in connected_groups(triangles, MaterialUsage::FogPlane) {
+        let Some(plane) = group.iter().find_map(|t| plane_for_triangle(&t.vertices)) else {
+            continue
+        };
+        let points: Vec<Vector3D> = group.iter().flat_map(|t| t.vertices).collect();
+        if points.iter().any(|p| p.distance_from_plane(&plane).abs() > BSP_PLANE_EPSILON * 10.0) {
+            return Err(Error::Other("fog plane is not planar".to_owned()))
+        }
+
+        // Each fog plane gets its own region so fog can be assigned to it in the palette.
+        let region_index = bsp.fog_regions.items.len();
+        let region = existing
+            .and_then(|e| e.fog_regions.items.get(region_index))
+            .cloned()
+            .unwrap_or(ScenarioStructureBSPFogRegion::default());
+        bsp.fog_regions.items.push(region);
+
+        let mut fog_plane = ScenarioStructureBSPFogPlane {
+            front_region: Some(region_index as u16),
+            plane,
+            ..Default::default()
+        };
+        fog_plane.vertices.items = convex_hull_on_plane(&points, &plane)
+            .into_iter()
+            .map(|point| ScenarioStructureBSPFogPlaneVertex { point })
+            .collect();
+        bsp.fog_planes.items.push(fog_plane);
+    }
+    Ok(())
+}
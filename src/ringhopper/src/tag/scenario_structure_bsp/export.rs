@@ -0,0 +1,303 @@
+use definitions::{ScenarioStructureBSP, ScenarioStructureBSPMaterial, ScenarioStructureBSPMaterialUncompressedLightmapVertex, ScenarioStructureBSPMaterialUncompressedRenderedVertex};
+use primitives::error::{Error, RinghopperResult};
+use primitives::primitive::{Quaternion, Vector, Vector2D, Vector3D};
+use crate::data::jms::{JMS, JMS_UNITS_PER_WORLD_UNIT, JMSMarker, JMSMaterial, JMSNode, JMSRegion, JMSTriangle, JMSVertex};
+use crate::data::obj::{OBJ, OBJFace, OBJObject, OBJVertex};
+use crate::tag::collision_bsp::{BSP_LEAF_FLAG, collision_surface_vertices};
+use super::{decompress_lightmap_bsp_vertex, decompress_rendered_bsp_vertex, get_compressed_vertices_for_bsp_material, get_uncompressed_vertices_for_bsp_material};
+
+/// Name of the material used for cluster portals.
+pub const PORTAL_MATERIAL: &str = "+portal";
+
+#[derive(Clone, Debug)]
+struct ExtractedVertex {
+    position: Vector3D,
+    normal: Vector3D,
+    texture_coordinates: Vector2D,
+    lightmap_texture_coordinates: Vector2D
+}
+
+#[derive(Clone, Debug)]
+struct ExtractedFace {
+    material: String,
+    vertices: Vec<u32>,
+
+    /// Full path of the shader tag, without the extension, for render geometry.
+    shader_path: Option<String>
+}
+
+/// Geometry extracted from a scenario_structure_bsp tag, in world units.
+///
+/// Texture coordinates are stored with the V axis flipped, matching JMS and OBJ files. Lightmap texture coordinates are
+/// offset along the U axis by the index of their lightmap page, so each page occupies its own UV tile.
+#[derive(Clone, Debug, Default)]
+pub struct ExtractedStructureBSP {
+    vertices: Vec<ExtractedVertex>,
+    render: Vec<ExtractedFace>,
+    collision: Vec<ExtractedFace>,
+    portals: Vec<ExtractedFace>,
+    markers: Vec<(String, Quaternion, Vector3D)>,
+    has_lightmap_texture_coordinates: bool
+}
+
+/// Extract render geometry, collision geometry, portals, and markers from a scenario_structure_bsp tag.
+///
+/// Render geometry is read from the uncompressed vertices of each lightmap material, falling back to the compressed
+/// vertices if they are missing (such as with some BSPs extracted from cache files). Materials are named after their
+/// shader.
+///
+/// Collision surfaces are named after their collision material's shader with the `@` (collision only) symbol, along
+/// with `%`, `^`, and `-` for two-sided, climbable, and breakable surfaces, respectively. Cluster portals use
+/// [`PORTAL_MATERIAL`].
+pub fn extract_structure_bsp_geometry(bsp: &ScenarioStructureBSP) -> RinghopperResult<ExtractedStructureBSP> {
+    let mut extracted = ExtractedStructureBSP::default();
+
+    for lightmap in &bsp.lightmaps {
+        let page = lightmap.bitmap.unwrap_or_default();
+        for material in &lightmap.materials {
+            extract_material(bsp, material, page, &mut extracted)?;
+        }
+    }
+
+    if let Some(collision_bsp) = bsp.collision_bsp.items.first() {
+        for (surface_index, surface) in collision_bsp.surfaces.items.iter().enumerate() {
+            let points = collision_surface_vertices(collision_bsp, surface_index as u32)?;
+            let plane_index = surface.plane & !BSP_LEAF_FLAG;
+            let plane = collision_bsp.planes
+                .items
+                .get(plane_index as usize)
+                .ok_or_else(|| Error::InvalidTagData(format!("collision surface #{surface_index} references out-of-bounds plane #{plane_index}")))?;
+            let normal = if surface.plane & BSP_LEAF_FLAG != 0 { -plane.plane.vector } else { plane.plane.vector };
+
+            let shader = surface.material
+                .and_then(|m| bsp.collision_materials.items.get(m as usize))
+                .and_then(|m| m.shader.path())
+                .map(|p| p.base_name())
+                .unwrap_or("default");
+            let mut material = format!("{shader}@");
+            for (flag, symbol) in [(surface.flags.two_sided, '%'), (surface.flags.climbable, '^'), (surface.flags.breakable, '-')] {
+                if flag {
+                    material.push(symbol);
+                }
+            }
+
+            let face = extracted.add_polygon(&points, normal, material);
+            extracted.collision.push(face);
+        }
+    }
+
+    for portal in &bsp.cluster_portals {
+        let points: Vec<Vector3D> = portal.vertices.items.iter().map(|v| v.point).collect();
+        if points.len() < 3 {
+            continue
+        }
+        let normal = (points[1] - points[0]).cross(&(points[2] - points[0])).normalize();
+        let face = extracted.add_polygon(&points, normal, PORTAL_MATERIAL.to_owned());
+        extracted.portals.push(face);
+    }
+
+    extracted.markers = bsp.markers
+        .items
+        .iter()
+        .map(|m| (m.name.to_string(), m.rotation, m.position))
+        .collect();
+
+    Ok(extracted)
+}
+
+fn extract_material(bsp: &ScenarioStructureBSP, material: &ScenarioStructureBSPMaterial, page: u16, extracted: &mut ExtractedStructureBSP) -> RinghopperResult<()> {
+    let (rendered, lightmap): (Vec<ScenarioStructureBSPMaterialUncompressedRenderedVertex>, Vec<ScenarioStructureBSPMaterialUncompressedLightmapVertex>) =
+        if !material.uncompressed_vertices.bytes.is_empty() {
+            let (rendered, lightmap) = get_uncompressed_vertices_for_bsp_material(material)?;
+            (rendered.collect(), lightmap.collect())
+        }
+        else {
+            let (rendered, lightmap) = get_compressed_vertices_for_bsp_material(material)?;
+            (rendered.map(decompress_rendered_bsp_vertex).collect(), lightmap.map(decompress_lightmap_bsp_vertex).collect())
+        };
+
+    if !lightmap.is_empty() {
+        extracted.has_lightmap_texture_coordinates = true;
+    }
+
+    let first_vertex = extracted.vertices.len() as u32;
+    for (index, vertex) in rendered.iter().enumerate() {
+        let lightmap_texture_coordinates = lightmap
+            .get(index)
+            .map(|l| Vector2D { x: l.texture_coords.x + page as f64, y: l.texture_coords.y })
+            .unwrap_or_default();
+        extracted.vertices.push(ExtractedVertex {
+            position: vertex.position,
+            normal: vertex.normal,
+            texture_coordinates: flip_v(vertex.texture_coords),
+            lightmap_texture_coordinates: flip_v(lightmap_texture_coordinates)
+        });
+    }
+
+    let shader = material.shader.path().map(|p| p.base_name()).unwrap_or("default");
+    let shader_path = material.shader.path().map(|p| p.path()).unwrap_or("default");
+    let first_surface = usize::try_from(material.surfaces).map_err(|_| Error::InvalidTagData(format!("material `{shader}` has a negative surface index")))?;
+    let surface_count = usize::try_from(material.surface_count).map_err(|_| Error::InvalidTagData(format!("material `{shader}` has a negative surface count")))?;
+    let surfaces = first_surface
+        .checked_add(surface_count)
+        .and_then(|end| bsp.surfaces.items.get(first_surface..end))
+        .ok_or_else(|| Error::InvalidTagData(format!("material `{shader}` references out-of-bounds surfaces")))?;
+
+    for surface in surfaces {
+        let indices = [surface.vertex0_index, surface.vertex1_index, surface.vertex2_index];
+        let Some(indices) = indices.into_iter().collect::<Option<Vec<u16>>>() else {
+            continue
+        };
+        if indices.iter().any(|i| *i as usize >= rendered.len()) {
+            return Err(Error::InvalidTagData(format!("material `{shader}` has a surface with out-of-bounds vertices")))
+        }
+        extracted.render.push(ExtractedFace {
+            material: shader.to_owned(),
+            vertices: indices.into_iter().map(|i| first_vertex + i as u32).collect(),
+            shader_path: Some(shader_path.to_owned())
+        });
+    }
+
+    Ok(())
+}
+
+fn flip_v(texture_coordinates: Vector2D) -> Vector2D {
+    Vector2D { x: texture_coordinates.x, y: 1.0 - texture_coordinates.y }
+}
+
+impl ExtractedStructureBSP {
+    /// Return `true` if the BSP has lightmap texture coordinates.
+    ///
+    /// Lightmap texture coordinates are only present if the BSP was lightmapped.
+    pub fn has_lightmap_texture_coordinates(&self) -> bool {
+        self.has_lightmap_texture_coordinates
+    }
+
+    fn add_polygon(&mut self, points: &[Vector3D], normal: Vector3D, material: String) -> ExtractedFace {
+        let first_vertex = self.vertices.len() as u32;
+        self.vertices.extend(points.iter().map(|position| ExtractedVertex {
+            position: *position,
+            normal,
+            texture_coordinates: Vector2D::default(),
+            lightmap_texture_coordinates: Vector2D::default()
+        }));
+        ExtractedFace { material, vertices: (first_vertex..first_vertex + points.len() as u32).collect(), shader_path: None }
+    }
+
+    fn texture_coordinates(&self, vertex: &ExtractedVertex, lightmap: bool) -> Vector2D {
+        if lightmap { vertex.lightmap_texture_coordinates } else { vertex.texture_coordinates }
+    }
+
+    fn material_name<'a>(&self, face: &'a ExtractedFace, lightmap: bool) -> &'a str {
+        match (&face.shader_path, lightmap) {
+            (Some(path), true) => path,
+            _ => &face.material
+        }
+    }
+
+    /// Convert the geometry into a JMS.
+    ///
+    /// Render geometry, collision geometry, and portals are placed in the `render`, `collision`, and `portals` regions,
+    /// respectively. Polygons are triangulated as triangle fans.
+    ///
+    /// If `lightmap` is set, only render geometry is included, using lightmap texture coordinates, and materials are
+    /// named after the full path of their shader tag so that shaders with the same name in different directories can
+    /// be told apart.
+    pub fn to_jms(&self, lightmap: bool) -> JMS {
+        let mut jms = JMS {
+            nodes: vec![JMSNode {
+                name: "frame".to_owned(),
+                rotation: Quaternion { w: 1.0, ..Default::default() },
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        jms.vertices = self.vertices
+            .iter()
+            .map(|v| JMSVertex {
+                position: v.position.scale(JMS_UNITS_PER_WORLD_UNIT),
+                normal: v.normal,
+                texture_coordinates: self.texture_coordinates(v, lightmap),
+                ..Default::default()
+            })
+            .collect();
+
+        for (name, faces) in self.groups(lightmap) {
+            let region = jms.regions.len() as u16;
+            jms.regions.push(JMSRegion { name: name.to_owned() });
+
+            for face in faces {
+                let material = self.material_name(face, lightmap);
+                let shader = match jms.materials.iter().position(|m| m.name == material) {
+                    Some(n) => n,
+                    None => {
+                        jms.materials.push(JMSMaterial { name: material.to_owned(), tif_path: "<none>".to_owned() });
+                        jms.materials.len() - 1
+                    }
+                } as u16;
+
+                for i in 1..face.vertices.len().saturating_sub(1) {
+                    jms.triangles.push(JMSTriangle {
+                        region,
+                        shader,
+                        vertices: [face.vertices[0], face.vertices[i], face.vertices[i + 1]]
+                    });
+                }
+            }
+        }
+
+        if !lightmap {
+            jms.markers = self.markers
+                .iter()
+                .map(|(name, rotation, position)| JMSMarker {
+                    name: name.to_owned(),
+                    region: None,
+                    node: 0,
+                    rotation: *rotation,
+                    position: position.scale(JMS_UNITS_PER_WORLD_UNIT),
+                    radius: 1.0
+                })
+                .collect();
+        }
+
+        jms
+    }
+
+    /// Convert the geometry into an OBJ.
+    ///
+    /// Render geometry, collision geometry, and portals are placed in the `render`, `collision`, and `portals` objects,
+    /// respectively.
+    ///
+    /// If `lightmap` is set, only render geometry is included, using lightmap texture coordinates, and materials are
+    /// named after the full path of their shader tag.
+    pub fn to_obj(&self, lightmap: bool) -> OBJ {
+        let vertices = self.vertices
+            .iter()
+            .map(|v| OBJVertex {
+                position: v.position,
+                normal: v.normal,
+                texture_coordinates: self.texture_coordinates(v, lightmap)
+            })
+            .collect();
+
+        let objects = self.groups(lightmap)
+            .into_iter()
+            .map(|(name, faces)| OBJObject {
+                name: name.to_owned(),
+                faces: faces.iter().map(|f| OBJFace { material: self.material_name(f, lightmap).to_owned(), vertices: f.vertices.clone() }).collect()
+            })
+            .collect();
+
+        OBJ { vertices, objects }
+    }
+
+    fn groups(&self, lightmap: bool) -> Vec<(&'static str, &[ExtractedFace])> {
+        if lightmap {
+            vec![("render", &self.render)]
+        }
+        else {
+            vec![("render", &self.render), ("collision", &self.collision), ("portals", &self.portals)]
+        }
+    }
+}
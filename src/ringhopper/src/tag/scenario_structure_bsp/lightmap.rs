@@ -0,0 +1,1052 @@
+use std::collections::HashMap;
+use std::ops::Range;
+use definitions::{Bitmap, BitmapData, BitmapDataFormat, BitmapDataType, BitmapFormat, BitmapGroupSequence, BitmapType, BitmapUsage, ModelCollisionGeometryBSP, ModelVertexType, ScenarioStructureBSP, ScenarioStructureBSPLightmap, ScenarioStructureBSPMaterial, ScenarioStructureBSPMaterialUncompressedLightmapVertex, ScenarioStructureBSPMaterialUncompressedRenderedVertex, ScenarioStructureBSPSurface, Shader, Sky};
+use primitives::byteorder::LittleEndian;
+use primitives::error::{Error, RinghopperResult};
+use primitives::parse::SimpleTagData;
+use primitives::primitive::{ColorARGBIntBytes, ColorRGBFloat, TagGroup, TagPath, Vector, Vector2D, Vector3D};
+use crate::data::bitmap::Image;
+use crate::data::jms::{JMS, JMS_UNITS_PER_WORLD_UNIT};
+use crate::tag::collision_bsp::bsp_trace_ray;
+use super::{decompress_lightmap_bsp_vertex, decompress_rendered_bsp_vertex, get_compressed_vertices_for_bsp_material, get_uncompressed_vertices_for_bsp_material, recompress_scenario_structure_bsp_vertices};
+
+/// Number of texels around each chart that are filled in to prevent bleeding when filtering.
+const CHART_PADDING: u32 = 2;
+
+/// Minimum cosine of the angle between a chart's first triangle and any other triangle in the chart.
+const CHART_NORMAL_THRESHOLD: f64 = 0.98;
+
+/// Distance to offset rays from surfaces so they do not hit the surface they start on.
+const RAY_OFFSET: f64 = 0.01;
+
+/// Ray result for rays that hit nothing that can be lit (such as collision-only surfaces).
+const RAY_HIT_NOTHING: u32 = u32::MAX;
+
+/// Ray result for rays that reached the sky.
+const RAY_HIT_SKY: u32 = u32::MAX - 1;
+
+type Light = [f64; 3];
+
+/// Settings for baking lightmaps.
+#[derive(Clone, Debug)]
+pub struct LightmapSettings {
+    /// Number of lightmap texels per world unit.
+    pub texels_per_world_unit: f64,
+
+    /// Maximum width and height of each lightmap page.
+    pub max_page_size: u32,
+
+    /// Number of rays cast from each texel to gather ambient and bounced light.
+    pub samples: usize,
+
+    /// Number of times light bounces off of surfaces.
+    pub bounces: usize,
+
+    /// Fraction of incoming light that is reflected by surfaces.
+    pub reflectance: f64,
+
+    /// Number of threads to use.
+    pub threads: usize
+}
+
+impl Default for LightmapSettings {
+    fn default() -> Self {
+        Self {
+            texels_per_world_unit: 8.0,
+            max_page_size: 1024,
+            samples: 64,
+            bounces: 2,
+            reflectance: 0.5,
+            threads: 1
+        }
+    }
+}
+
+/// Light sources used for baking lightmaps.
+#[derive(Clone, Debug, Default)]
+pub struct LightmapLighting {
+    /// Light emitted by surfaces using each shader.
+    pub shader_emission: HashMap<TagPath, ColorRGBFloat>,
+
+    /// Light coming from the sky.
+    ///
+    /// Sky light enters through collision surfaces without a collision material (such as `+sky` surfaces) and through
+    /// any openings in the level.
+    pub sky: LightmapSky
+}
+
+/// Light coming from the sky.
+#[derive(Clone, Debug, Default)]
+pub struct LightmapSky {
+    /// Light coming from every direction.
+    pub ambient: ColorRGBFloat,
+
+    /// Directional lights, such as the sun.
+    pub lights: Vec<LightmapSkyLight>
+}
+
+/// Directional light coming from the sky.
+#[derive(Clone, Debug, Default)]
+pub struct LightmapSkyLight {
+    /// Direction toward the light.
+    pub direction: Vector3D,
+
+    /// Color of the light, multiplied by its power.
+    pub color: ColorRGBFloat
+}
+
+impl LightmapSky {
+    /// Get the outdoor lighting of a sky tag.
+    pub fn from_sky(sky: &Sky) -> Self {
+        let ambient = &sky.outdoor_ambient_radiosity;
+        let lights = sky.lights
+            .items
+            .iter()
+            .map(|l| {
+                let radiosity = &l.radiosity;
+                let (yaw, pitch) = (radiosity.direction.yaw.angle as f64, radiosity.direction.pitch.angle as f64);
+                LightmapSkyLight {
+                    direction: Vector3D { x: pitch.cos() * yaw.cos(), y: pitch.cos() * yaw.sin(), z: pitch.sin() },
+                    color: scale_color(&radiosity.color, radiosity.power)
+                }
+            })
+            .collect();
+
+        Self { ambient: scale_color(&ambient.color, ambient.power), lights }
+    }
+}
+
+/// Get the light emitted by a shader, if any.
+pub fn shader_emitted_light(shader: &Shader) -> Option<ColorRGBFloat> {
+    let radiosity = &shader.radiosity;
+    (radiosity.power > 0.0).then(|| scale_color(&radiosity.color_of_emitted_light, radiosity.power))
+}
+
+fn scale_color(color: &ColorRGBFloat, scale: f64) -> ColorRGBFloat {
+    ColorRGBFloat { red: color.red * scale, green: color.green * scale, blue: color.blue * scale }
+}
+
+fn light_from_color(color: &ColorRGBFloat) -> Light {
+    [color.red, color.green, color.blue]
+}
+
+fn luminance(light: &Light) -> f64 {
+    0.2126 * light[0] + 0.7152 * light[1] + 0.0722 * light[2]
+}
+
+/// Get two directions perpendicular to a normal and each other.
+fn tangent_basis(normal: Vector3D) -> (Vector3D, Vector3D) {
+    let hint = if normal.z.abs() < 0.9 { Vector3D { x: 0.0, y: 0.0, z: 1.0 } } else { Vector3D { x: 1.0, y: 0.0, z: 0.0 } };
+    let u = hint.cross(&normal).normalize();
+    let v = normal.cross(&u);
+    (u, v)
+}
+
+fn triangle_normal(points: [Vector3D; 3]) -> Vector3D {
+    let normal = (points[1] - points[0]).cross(&(points[2] - points[0]));
+    if normal.magnitude_squared() > 0.0 { normal.normalize() } else { Vector3D::zero() }
+}
+
+/// Cosine-weighted hemisphere directions around +z, which are the same for every texel so results are reproducible.
+fn hemisphere_samples(count: usize) -> Vec<Vector3D> {
+    (0..count)
+        .map(|i| {
+            let u1 = (i as f64 + 0.5) / count as f64;
+            let u2 = (i as u32).reverse_bits() as f64 / (u32::MAX as f64 + 1.0);
+            let radius = u1.sqrt();
+            let angle = 2.0 * std::f64::consts::PI * u2;
+            Vector3D { x: radius * angle.cos(), y: radius * angle.sin(), z: (1.0 - u1).sqrt() }
+        })
+        .collect()
+}
+
+/// Run `f` for every index in `0..count`, splitting the work across threads.
+///
+/// Results are always in order, so they do not depend on the number of threads.
+fn parallel_map<T: Send, F: Fn(usize) -> T + Sync>(count: usize, threads: usize, f: F) -> Vec<T> {
+    let chunk_size = count.div_ceil(threads.max(1)).max(1);
+    std::thread::scope(|scope| {
+        let f = &f;
+        let handles: Vec<_> = (0..count)
+            .step_by(chunk_size)
+            .map(|start| scope.spawn(move || (start..(start + chunk_size).min(count)).map(f).collect::<Vec<T>>()))
+            .collect();
+        handles.into_iter().flat_map(|h| h.join().unwrap()).collect()
+    })
+}
+
+/// A group of connected, nearly coplanar triangles which are mapped to one rectangle in a lightmap page.
+struct Chart {
+    triangles: Vec<usize>,
+    min: Vector2D,
+    max: Vector2D,
+    size: [u32; 2],
+    offset: [u32; 2]
+}
+
+struct MaterialLayout {
+    vertices: Vec<ScenarioStructureBSPMaterialUncompressedRenderedVertex>,
+
+    /// Chart of each vertex and its position projected onto the chart's plane.
+    vertex_charts: Vec<(usize, Vector2D)>,
+
+    triangles: Vec<[u16; 3]>,
+    charts: Vec<Chart>,
+    texels_per_world_unit: f64,
+    emission: Light,
+    page: usize
+}
+
+impl MaterialLayout {
+    fn new(material: &ScenarioStructureBSPMaterial, surfaces: &[ScenarioStructureBSPSurface], emission: Light, texels_per_world_unit: f64) -> RinghopperResult<Self> {
+        let vertices = material_rendered_vertices(material)?;
+        let triangles = material_triangles(surfaces, vertices.len())?;
+
+        let mut layout = Self {
+            vertices: Vec::new(),
+            vertex_charts: Vec::new(),
+            triangles: triangles.clone(),
+            charts: Vec::new(),
+            texels_per_world_unit,
+            emission,
+            page: 0
+        };
+
+        // Vertices shared between charts are duplicated so each can have its own lightmap texture coordinates.
+        for chart_triangles in build_charts(&vertices, &triangles) {
+            let chart_index = layout.charts.len();
+            let normal = chart_triangles
+                .iter()
+                .fold(Vector3D::zero(), |n, t| n + triangle_normal(triangles[*t].map(|i| vertices[i as usize].position)));
+            let normal = if normal.magnitude_squared() > 0.0 { normal.normalize() } else { Vector3D { x: 0.0, y: 0.0, z: 1.0 } };
+            let (u, v) = tangent_basis(normal);
+
+            let mut remap: HashMap<u16, u16> = HashMap::new();
+            let mut min = Vector2D { x: f64::MAX, y: f64::MAX };
+            let mut max = Vector2D { x: f64::MIN, y: f64::MIN };
+            for &t in &chart_triangles {
+                for (k, old) in triangles[t].into_iter().enumerate() {
+                    let new = match remap.get(&old) {
+                        Some(n) => *n,
+                        None => {
+                            let new = u16::try_from(layout.vertices.len())
+                                .map_err(|_| Error::Other("too many vertices in material after splitting lightmap charts".to_owned()))?;
+                            let vertex = vertices[old as usize];
+                            let projected = Vector2D { x: vertex.position.dot(&u), y: vertex.position.dot(&v) };
+                            min = Vector2D { x: min.x.min(projected.x), y: min.y.min(projected.y) };
+                            max = Vector2D { x: max.x.max(projected.x), y: max.y.max(projected.y) };
+                            layout.vertices.push(vertex);
+                            layout.vertex_charts.push((chart_index, projected));
+                            remap.insert(old, new);
+                            new
+                        }
+                    };
+                    layout.triangles[t][k] = new;
+                }
+            }
+
+            layout.charts.push(Chart { triangles: chart_triangles, min, max, size: [0, 0], offset: [0, 0] });
+        }
+
+        layout.resize_charts();
+        Ok(layout)
+    }
+
+    fn resize_charts(&mut self) {
+        for chart in &mut self.charts {
+            let extent = chart.max - chart.min;
+            let texels = |e: f64| (e * self.texels_per_world_unit).ceil() as u32 + 1 + 2 * CHART_PADDING;
+            chart.size = [texels(extent.x), texels(extent.y)];
+        }
+    }
+
+    /// Get the position of a vertex in its lightmap page, in texels.
+    fn vertex_texel_coordinates(&self, vertex: usize) -> Vector2D {
+        let (chart, projected) = self.vertex_charts[vertex];
+        let chart = &self.charts[chart];
+        let local = (projected - chart.min).scale(self.texels_per_world_unit);
+        let corner = CHART_PADDING as f64 + 0.5;
+        Vector2D { x: chart.offset[0] as f64 + corner + local.x, y: chart.offset[1] as f64 + corner + local.y }
+    }
+}
+
+/// Group triangles that share vertices and face nearly the same direction.
+fn build_charts(vertices: &[ScenarioStructureBSPMaterialUncompressedRenderedVertex], triangles: &[[u16; 3]]) -> Vec<Vec<usize>> {
+    let mut vertex_triangles: Vec<Vec<usize>> = vec![Vec::new(); vertices.len()];
+    for (t, triangle) in triangles.iter().enumerate() {
+        for i in triangle {
+            vertex_triangles[*i as usize].push(t);
+        }
+    }
+
+    let normals: Vec<Vector3D> = triangles.iter().map(|t| triangle_normal(t.map(|i| vertices[i as usize].position))).collect();
+    let mut assigned = vec![false; triangles.len()];
+    let mut charts = Vec::new();
+
+    for seed in 0..triangles.len() {
+        if assigned[seed] {
+            continue
+        }
+        assigned[seed] = true;
+
+        let seed_normal = normals[seed];
+        let mut chart = vec![seed];
+        let mut next = 0;
+        while let Some(&t) = chart.get(next) {
+            next += 1;
+            for i in triangles[t] {
+                for &neighbor in &vertex_triangles[i as usize] {
+                    let degenerate = normals[neighbor].magnitude_squared() == 0.0;
+                    if !assigned[neighbor] && (degenerate || normals[neighbor].dot(&seed_normal) >= CHART_NORMAL_THRESHOLD) {
+                        assigned[neighbor] = true;
+                        chart.push(neighbor);
+                    }
+                }
+            }
+        }
+
+        charts.push(chart);
+    }
+
+    charts
+}
+
+/// Packs rectangles into rows.
+#[derive(Clone)]
+struct ShelfPacker {
+    max_size: u32,
+    x: u32,
+    y: u32,
+    shelf_height: u32,
+    used_width: u32
+}
+
+impl ShelfPacker {
+    fn new(max_size: u32) -> Self {
+        Self { max_size, x: 0, y: 0, shelf_height: 0, used_width: 0 }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.used_width == 0
+    }
+
+    fn used_height(&self) -> u32 {
+        self.y + self.shelf_height
+    }
+
+    fn place(&mut self, size: [u32; 2]) -> Option<[u32; 2]> {
+        let [width, height] = size;
+        if width > self.max_size {
+            return None
+        }
+        if self.x + width > self.max_size {
+            self.y += self.shelf_height;
+            self.x = 0;
+            self.shelf_height = 0;
+        }
+        if self.y + height > self.max_size {
+            return None
+        }
+
+        let position = [self.x, self.y];
+        self.x += width;
+        self.shelf_height = self.shelf_height.max(height);
+        self.used_width = self.used_width.max(self.x);
+        Some(position)
+    }
+
+    /// Place every chart of a material, returning false (and leaving the packer unchanged) if they do not all fit.
+    fn place_material(&mut self, layout: &mut MaterialLayout) -> bool {
+        let mut order: Vec<usize> = (0..layout.charts.len()).collect();
+        order.sort_by_key(|c| std::cmp::Reverse(layout.charts[*c].size[1]));
+
+        let mut packer = self.clone();
+        let mut offsets = Vec::with_capacity(order.len());
+        for c in order {
+            match packer.place(layout.charts[c].size) {
+                Some(offset) => offsets.push((c, offset)),
+                None => return false
+            }
+        }
+
+        for (c, offset) in offsets {
+            layout.charts[c].offset = offset;
+        }
+        *self = packer;
+        true
+    }
+}
+
+/// A lightmap texel and the point on a surface it covers.
+struct Texel {
+    position: Vector3D,
+    normal: Vector3D,
+    surface_normal: Vector3D,
+    emission: Light
+}
+
+struct Page {
+    width: u32,
+    height: u32,
+    materials: Vec<usize>
+}
+
+/// Finds the texel nearest to a point.
+struct TexelGrid {
+    cell_size: f64,
+    cells: HashMap<[i64; 3], Vec<u32>>
+}
+
+impl TexelGrid {
+    fn new(texels: &[Texel], cell_size: f64) -> Self {
+        let mut grid = Self { cell_size, cells: HashMap::new() };
+        for (i, texel) in texels.iter().enumerate() {
+            grid.cells.entry(grid.cell(texel.position)).or_default().push(i as u32);
+        }
+        grid
+    }
+
+    fn cell(&self, point: Vector3D) -> [i64; 3] {
+        [point.x, point.y, point.z].map(|c| (c / self.cell_size).floor() as i64)
+    }
+
+    fn nearest(&self, texels: &[Texel], point: Vector3D, normal: Vector3D) -> Option<u32> {
+        let [x, y, z] = self.cell(point);
+        let mut best: Option<(f64, u32)> = None;
+        for cx in x - 1..=x + 1 {
+            for cy in y - 1..=y + 1 {
+                for cz in z - 1..=z + 1 {
+                    for &i in self.cells.get(&[cx, cy, cz]).map(Vec::as_slice).unwrap_or_default() {
+                        let texel = &texels[i as usize];
+                        if texel.surface_normal.dot(&normal) < 0.5 {
+                            continue
+                        }
+                        let distance = texel.position.distance_squared(&point);
+                        if best.is_none_or(|(d, _)| distance < d) {
+                            best = Some((distance, i));
+                        }
+                    }
+                }
+            }
+        }
+        best.map(|(_, i)| i)
+    }
+}
+
+struct Scene<'a> {
+    collision: Option<&'a ModelCollisionGeometryBSP>,
+    texels: &'a [Texel],
+    grid: TexelGrid,
+    ray_length: f64
+}
+
+impl Scene<'_> {
+    /// Cast a ray, returning the texel it hit, [`RAY_HIT_SKY`], or [`RAY_HIT_NOTHING`].
+    fn cast(&self, origin: Vector3D, direction: Vector3D) -> u32 {
+        let Some(collision) = self.collision else {
+            return RAY_HIT_SKY
+        };
+        let Some(hit) = bsp_trace_ray(collision, origin, origin + direction.scale(self.ray_length)) else {
+            return RAY_HIT_SKY
+        };
+        if hit.plane.is_none() {
+            return RAY_HIT_NOTHING
+        }
+        if let Some(surface) = hit.surface.and_then(|s| collision.surfaces.items.get(s as usize)) {
+            if surface.material.is_none() {
+                return RAY_HIT_SKY
+            }
+        }
+        self.grid.nearest(self.texels, hit.point, hit.normal).unwrap_or(RAY_HIT_NOTHING)
+    }
+}
+
+/// Direct light and gathered rays for a texel.
+struct TexelRays {
+    direct: Light,
+    direct_direction: Vector3D,
+    hits: Vec<u32>
+}
+
+/// Bake lightmaps for a structure BSP, returning the lightmap bitmap.
+///
+/// Every material is unwrapped into charts of connected, nearly coplanar triangles, splitting vertices where charts
+/// meet. Charts are packed into lightmap pages in material order, and each page becomes one lightmap.
+///
+/// Light from the sky and emissive shaders is gathered by tracing rays against the collision BSP, then bounced off of
+/// surfaces. Lightmap vertices store the texture coordinates and the dominant incoming light direction. The result does
+/// not depend on the number of threads.
+pub fn bake_lightmaps(bsp: &mut ScenarioStructureBSP, lighting: &LightmapLighting, settings: &LightmapSettings) -> RinghopperResult<Bitmap> {
+    if settings.texels_per_world_unit.is_nan() || settings.texels_per_world_unit <= 0.0 || settings.max_page_size == 0 {
+        return Err(Error::Other("lightmap resolution and page size must be greater than zero".to_owned()))
+    }
+
+    let materials: Vec<ScenarioStructureBSPMaterial> = std::mem::take(&mut bsp.lightmaps.items)
+        .into_iter()
+        .flat_map(|l| l.materials.items)
+        .collect();
+
+    // Unwrap and pack charts.
+    let mut layouts = Vec::with_capacity(materials.len());
+    let mut surface_ranges = Vec::with_capacity(materials.len());
+    for material in &materials {
+        let range = material_surface_range(bsp, material)?;
+
+        let emission = material.shader
+            .path()
+            .and_then(|p| lighting.shader_emission.get(p))
+            .map(light_from_color)
+            .unwrap_or_default();
+
+        layouts.push(MaterialLayout::new(material, &bsp.surfaces.items[range.clone()], emission, settings.texels_per_world_unit)?);
+        surface_ranges.push(range);
+    }
+
+    let mut pages: Vec<Page> = Vec::new();
+    let mut packer = ShelfPacker::new(settings.max_page_size);
+    let mut page_materials = Vec::new();
+    for (index, layout) in layouts.iter_mut().enumerate() {
+        while !packer.place_material(layout) {
+            if packer.is_empty() {
+                // Reduce the resolution until the material fits on a page by itself.
+                if layout.texels_per_world_unit < 1e-6 {
+                    return Err(Error::Other("material has too many lightmap charts to fit on a page".to_owned()))
+                }
+                layout.texels_per_world_unit *= 0.5;
+                layout.resize_charts();
+            }
+            else {
+                pages.push(finish_page(&packer, std::mem::take(&mut page_materials)));
+                packer = ShelfPacker::new(settings.max_page_size);
+            }
+        }
+        layout.page = pages.len();
+        page_materials.push(index);
+    }
+    if !page_materials.is_empty() {
+        pages.push(finish_page(&packer, page_materials));
+    }
+
+    // Find the surface point under each texel.
+    let mut texels = Vec::new();
+    let mut page_texels: Vec<Vec<Option<u32>>> = Vec::with_capacity(pages.len());
+    for page in &pages {
+        let mut coverage = vec![None; (page.width * page.height) as usize];
+        for &m in &page.materials {
+            rasterize_material(&layouts[m], page, &mut coverage, &mut texels);
+        }
+        page_texels.push(coverage);
+    }
+
+    // Light them.
+    let collision = bsp.collision_bsp.items.first();
+    let ray_length = level_size(collision, &texels) + 1.0;
+    let scene = Scene {
+        collision,
+        texels: &texels,
+        grid: TexelGrid::new(&texels, 2.0 / settings.texels_per_world_unit),
+        ray_length
+    };
+    let (light, directions) = light_texels(&scene, lighting, settings);
+
+    // Write the pages, filling in the padding around each chart.
+    let mut bitmap = new_lightmap_bitmap();
+    let mut page_directions = Vec::with_capacity(pages.len());
+    for (page_index, page) in pages.iter().enumerate() {
+        let mut pixels: Vec<Option<(Light, Vector3D)>> = page_texels[page_index]
+            .iter()
+            .map(|t| t.map(|t| (light[t as usize], directions[t as usize])))
+            .collect();
+        dilate(&mut pixels, page.width as usize, page.height as usize, CHART_PADDING as usize + 1);
+
+        let colors = pixels.iter().map(|p| p.map(|p| p.0).unwrap_or_default().map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8));
+        push_lightmap_page(&mut bitmap, page.width as u16, page.height as u16, colors);
+
+        page_directions.push(pixels.into_iter().map(|p| p.map(|p| p.1)).collect::<Vec<_>>());
+    }
+
+    // Write the vertices and surfaces.
+    let mut lightmaps: Vec<ScenarioStructureBSPLightmap> = (0..pages.len())
+        .map(|p| ScenarioStructureBSPLightmap { bitmap: Some(p as u16), ..Default::default() })
+        .collect();
+
+    for ((mut material, layout), range) in materials.into_iter().zip(layouts).zip(surface_ranges) {
+        let page = &pages[layout.page];
+        let directions = &page_directions[layout.page];
+
+        let mut lightmap_vertices = Vec::with_capacity(layout.vertices.len());
+        for (index, vertex) in layout.vertices.iter().enumerate() {
+            let coordinates = layout.vertex_texel_coordinates(index);
+            let x = (coordinates.x.floor().max(0.0) as usize).min(page.width as usize - 1);
+            let y = (coordinates.y.floor().max(0.0) as usize).min(page.height as usize - 1);
+            let direction = directions[x + y * page.width as usize]
+                .filter(|d| d.magnitude_squared() > 0.0)
+                .unwrap_or(vertex.normal);
+
+            lightmap_vertices.push(ScenarioStructureBSPMaterialUncompressedLightmapVertex {
+                normal: direction,
+                texture_coords: Vector2D { x: coordinates.x / page.width as f64, y: coordinates.y / page.height as f64 }
+            });
+        }
+
+        for (surface, triangle) in bsp.surfaces.items[range].iter_mut().zip(&layout.triangles) {
+            surface.vertex0_index = Some(triangle[0]);
+            surface.vertex1_index = Some(triangle[1]);
+            surface.vertex2_index = Some(triangle[2]);
+        }
+
+        set_material_vertices(&mut material, &layout.vertices, &lightmap_vertices);
+        lightmaps[layout.page].materials.items.push(material);
+    }
+
+    bsp.lightmaps.items = lightmaps;
+    recompress_scenario_structure_bsp_vertices(bsp)?;
+
+    Ok(bitmap)
+}
+
+/// Position rounded to a thousandth of a world unit, used for matching imported triangles to surfaces.
+type PositionKey = [i64; 3];
+
+/// Lightmap texture coordinates at each corner of an imported triangle.
+type ImportedTriangle = [(PositionKey, Vector2D); 3];
+
+fn position_key(position: Vector3D) -> PositionKey {
+    [position.x, position.y, position.z].map(|c| (c * 1000.0).round() as i64)
+}
+
+/// Import externally baked lightmaps into a structure BSP, returning the lightmap bitmap.
+///
+/// `jms` contains the BSP's render geometry with lightmap texture coordinates and materials named after the full path
+/// of their shader tag (such as the lightmap JMS written when recovering a BSP), and `pages` contains one image per
+/// lightmap page. Triangles are matched to surfaces by shader path and vertex positions, so they do not need to be in
+/// any particular order. Incident light directions are kept from the BSP's existing lightmap vertices.
+///
+/// The whole part of the U coordinate selects the page, so page 1 spans U coordinates 1 to 2, and so on. Every surface
+/// of a material must be on the same page. Materials with no matching triangles are not lightmapped.
+pub fn import_lightmaps(bsp: &mut ScenarioStructureBSP, jms: &JMS, pages: &[Image]) -> RinghopperResult<Bitmap> {
+    if pages.is_empty() {
+        return Err(Error::Other("no lightmap pages were given".to_owned()))
+    }
+
+    let mut bitmap = new_lightmap_bitmap();
+    for (index, page) in pages.iter().enumerate() {
+        let dimension = |d: usize| u16::try_from(d).ok().filter(|d| *d > 0);
+        let (Some(width), Some(height)) = (dimension(page.width), dimension(page.height)) else {
+            return Err(Error::Other(format!("lightmap page #{index} has invalid dimensions {}x{}", page.width, page.height)))
+        };
+        let colors = page.data.iter().map(|c| {
+            let c: ColorARGBIntBytes = (*c).into();
+            [c.red, c.green, c.blue]
+        });
+        push_lightmap_page(&mut bitmap, width, height, colors);
+    }
+
+    // Corners of each imported triangle, looked up by shader and sorted corner positions.
+    let mut imported: HashMap<(&str, [PositionKey; 3]), Vec<ImportedTriangle>> = HashMap::new();
+    for (index, triangle) in jms.triangles.iter().enumerate() {
+        let material = jms.materials
+            .get(triangle.shader as usize)
+            .ok_or_else(|| Error::Other(format!("triangle #{index} has an out-of-bounds material")))?;
+        let mut corners: ImportedTriangle = Default::default();
+        for (corner, vertex) in corners.iter_mut().zip(triangle.vertices) {
+            let vertex = jms.vertices
+                .get(vertex as usize)
+                .ok_or_else(|| Error::Other(format!("triangle #{index} has an out-of-bounds vertex")))?;
+            let coordinates = vertex.texture_coordinates;
+            *corner = (position_key(vertex.position.scale(1.0 / JMS_UNITS_PER_WORLD_UNIT)), Vector2D { x: coordinates.x, y: 1.0 - coordinates.y });
+        }
+        let mut key = corners.map(|c| c.0);
+        key.sort();
+        imported.entry((material.name.as_str(), key)).or_default().push(corners);
+    }
+
+    let materials: Vec<ScenarioStructureBSPMaterial> = std::mem::take(&mut bsp.lightmaps.items)
+        .into_iter()
+        .flat_map(|l| l.materials.items)
+        .collect();
+
+    let mut lightmaps: Vec<ScenarioStructureBSPLightmap> = (0..pages.len())
+        .map(|p| ScenarioStructureBSPLightmap { bitmap: Some(p as u16), ..Default::default() })
+        .collect();
+    let mut unlit = ScenarioStructureBSPLightmap::default();
+
+    for mut material in materials {
+        let shader = material.shader.path().map(|p| p.path()).unwrap_or("default").to_owned();
+        let range = material_surface_range(bsp, &material)?;
+        let rendered = material_rendered_vertices(&material)?;
+        let existing_lightmap = material_lightmap_vertices(&material)?;
+        let triangles = material_triangles(&bsp.surfaces.items[range.clone()], rendered.len())?;
+
+        let mut page = None;
+        let mut coordinates = Vec::with_capacity(triangles.len());
+        for triangle in &triangles {
+            let keys = triangle.map(|i| position_key(rendered[i as usize].position));
+            let mut sorted = keys;
+            sorted.sort();
+            let Some(corners) = imported.get(&(shader.as_str(), sorted)).and_then(|c| c.first()) else {
+                continue
+            };
+            let uv = keys.map(|k| corners.iter().find(|c| c.0 == k).unwrap().1);
+
+            let tile = ((uv[0].x + uv[1].x + uv[2].x) / 3.0).floor();
+            if !(0.0..pages.len() as f64).contains(&tile) {
+                return Err(Error::Other(format!("material `{shader}` has lightmap texture coordinates outside of the {} page(s)", pages.len())))
+            }
+            if page.is_some_and(|p| p != tile as usize) {
+                return Err(Error::Other(format!("material `{shader}` spans more than one lightmap page")))
+            }
+            page = Some(tile as usize);
+            coordinates.push(uv.map(|c| Vector2D { x: c.x - tile, y: c.y }));
+        }
+
+        let Some(page) = page else {
+            set_material_vertices(&mut material, &rendered, &[]);
+            unlit.materials.items.push(material);
+            continue
+        };
+        if coordinates.len() != triangles.len() {
+            return Err(Error::Other(format!("{} surface(s) of material `{shader}` are missing from the lightmap geometry", triangles.len() - coordinates.len())))
+        }
+
+        // Vertices are split wherever the lightmap texture coordinates differ.
+        let mut remap: HashMap<(u16, [u64; 2]), u16> = HashMap::new();
+        let mut new_rendered = Vec::new();
+        let mut new_lightmap = Vec::new();
+        for (surface, (triangle, uv)) in bsp.surfaces.items[range].iter_mut().zip(triangles.iter().zip(coordinates)) {
+            let mut indices = [0u16; 3];
+            for k in 0..3 {
+                let old = triangle[k];
+                let key = (old, [uv[k].x.to_bits(), uv[k].y.to_bits()]);
+                indices[k] = match remap.get(&key) {
+                    Some(n) => *n,
+                    None => {
+                        let new = u16::try_from(new_rendered.len())
+                            .map_err(|_| Error::Other(format!("too many vertices in material `{shader}` after splitting lightmap seams")))?;
+                        let vertex = rendered[old as usize];
+                        new_rendered.push(vertex);
+                        let normal = existing_lightmap.get(old as usize).map(|l| l.normal).unwrap_or(vertex.normal);
+                        new_lightmap.push(ScenarioStructureBSPMaterialUncompressedLightmapVertex { normal, texture_coords: uv[k] });
+                        remap.insert(key, new);
+                        new
+                    }
+                };
+            }
+            surface.vertex0_index = Some(indices[0]);
+            surface.vertex1_index = Some(indices[1]);
+            surface.vertex2_index = Some(indices[2]);
+        }
+
+        set_material_vertices(&mut material, &new_rendered, &new_lightmap);
+        lightmaps[page].materials.items.push(material);
+    }
+
+    lightmaps.retain(|l| !l.materials.items.is_empty());
+    if !unlit.materials.items.is_empty() {
+        lightmaps.push(unlit);
+    }
+    bsp.lightmaps.items = lightmaps;
+    recompress_scenario_structure_bsp_vertices(bsp)?;
+
+    Ok(bitmap)
+}
+
+fn new_lightmap_bitmap() -> Bitmap {
+    Bitmap {
+        _type: BitmapType::_2dTextures,
+        encoding_format: BitmapFormat::_32Bit,
+        usage: BitmapUsage::LightMap,
+        ..Default::default()
+    }
+}
+
+/// Add a page to a lightmap bitmap as its own sequence, with pixels given as RGB.
+fn push_lightmap_page<I: IntoIterator<Item = [u8; 3]>>(bitmap: &mut Bitmap, width: u16, height: u16, pixels: I) {
+    let pixel_data_offset = bitmap.processed_pixel_data.bytes.len();
+    for [r, g, b] in pixels {
+        bitmap.processed_pixel_data.bytes.extend_from_slice(&[b, g, r, 0xFF]);
+    }
+
+    let page_index = bitmap.bitmap_data.items.len();
+    let mut data = BitmapData {
+        signature: TagGroup::Bitmap,
+        width,
+        height,
+        depth: 1,
+        _type: BitmapDataType::_2dTexture,
+        format: BitmapDataFormat::X8R8G8B8,
+        pixel_data_offset: pixel_data_offset as u32,
+        pixel_data_size: (bitmap.processed_pixel_data.bytes.len() - pixel_data_offset) as u32,
+        ..Default::default()
+    };
+    data.flags.power_of_two_dimensions = width.is_power_of_two() && height.is_power_of_two();
+    bitmap.bitmap_data.items.push(data);
+    bitmap.bitmap_group_sequence.items.push(BitmapGroupSequence {
+        first_bitmap_index: Some(page_index as u16),
+        bitmap_count: 1,
+        ..Default::default()
+    });
+}
+
+/// Replace a material's vertices with uncompressed vertices.
+///
+/// Compressed vertices are cleared, so they need to be regenerated afterward.
+fn set_material_vertices(
+    material: &mut ScenarioStructureBSPMaterial,
+    rendered: &[ScenarioStructureBSPMaterialUncompressedRenderedVertex],
+    lightmap: &[ScenarioStructureBSPMaterialUncompressedLightmapVertex]
+) {
+    let mut bytes = Vec::with_capacity(
+        rendered.len() * ScenarioStructureBSPMaterialUncompressedRenderedVertex::simple_size()
+        + lightmap.len() * ScenarioStructureBSPMaterialUncompressedLightmapVertex::simple_size()
+    );
+    for vertex in rendered {
+        bytes.extend_from_slice(vertex.as_bytes::<LittleEndian>().unwrap().bytes());
+    }
+    for vertex in lightmap {
+        bytes.extend_from_slice(vertex.as_bytes::<LittleEndian>().unwrap().bytes());
+    }
+
+    material.rendered_vertices.vertex_type = ModelVertexType::EnvironmentUncompressed;
+    material.rendered_vertices.vertex_count = rendered.len() as u32;
+    material.lightmap_vertices.vertex_type = ModelVertexType::EnvironmentLightmapUncompressed;
+    material.lightmap_vertices.vertex_count = lightmap.len() as u32;
+    material.uncompressed_vertices.bytes = bytes;
+    material.compressed_vertices.bytes.clear();
+}
+
+/// Get the rendered vertices of a material, decompressing them if there are no uncompressed vertices.
+fn material_rendered_vertices(material: &ScenarioStructureBSPMaterial) -> RinghopperResult<Vec<ScenarioStructureBSPMaterialUncompressedRenderedVertex>> {
+    if !material.uncompressed_vertices.bytes.is_empty() {
+        Ok(get_uncompressed_vertices_for_bsp_material(material)?.0.collect())
+    }
+    else {
+        Ok(get_compressed_vertices_for_bsp_material(material)?.0.map(decompress_rendered_bsp_vertex).collect())
+    }
+}
+
+/// Get the lightmap vertices of a material, which is empty if the material is not lightmapped.
+fn material_lightmap_vertices(material: &ScenarioStructureBSPMaterial) -> RinghopperResult<Vec<ScenarioStructureBSPMaterialUncompressedLightmapVertex>> {
+    if !material.uncompressed_vertices.bytes.is_empty() {
+        Ok(get_uncompressed_vertices_for_bsp_material(material)?.1.collect())
+    }
+    else {
+        Ok(get_compressed_vertices_for_bsp_material(material)?.1.map(decompress_lightmap_bsp_vertex).collect())
+    }
+}
+
+/// Get the vertex indices of each surface, making sure they are in bounds.
+fn material_triangles(surfaces: &[ScenarioStructureBSPSurface], vertex_count: usize) -> RinghopperResult<Vec<[u16; 3]>> {
+    surfaces
+        .iter()
+        .map(|surface| {
+            let indices = [surface.vertex0_index, surface.vertex1_index, surface.vertex2_index];
+            let Some(indices) = indices.into_iter().collect::<Option<Vec<u16>>>() else {
+                return Err(Error::InvalidTagData("surface has a null vertex index".to_owned()))
+            };
+            if indices.iter().any(|i| *i as usize >= vertex_count) {
+                return Err(Error::InvalidTagData("surface has an out-of-bounds vertex index".to_owned()))
+            }
+            Ok([indices[0], indices[1], indices[2]])
+        })
+        .collect()
+}
+
+/// Get the surfaces of a material, making sure they are in bounds.
+fn material_surface_range(bsp: &ScenarioStructureBSP, material: &ScenarioStructureBSPMaterial) -> RinghopperResult<Range<usize>> {
+    let first = usize::try_from(material.surfaces).ok();
+    let count = usize::try_from(material.surface_count).ok();
+    first
+        .zip(count)
+        .map(|(first, count)| first..first.saturating_add(count))
+        .filter(|r| r.end <= bsp.surfaces.items.len())
+        .ok_or_else(|| Error::InvalidTagData("material references out-of-bounds surfaces".to_owned()))
+}
+
+fn finish_page(packer: &ShelfPacker, materials: Vec<usize>) -> Page {
+    Page {
+        width: packer.used_width.max(1).next_power_of_two(),
+        height: packer.used_height().max(1).next_power_of_two(),
+        materials
+    }
+}
+
+fn rasterize_material(layout: &MaterialLayout, page: &Page, coverage: &mut [Option<u32>], texels: &mut Vec<Texel>) {
+    let width = page.width as usize;
+    for chart in &layout.charts {
+        for &t in &chart.triangles {
+            let indices = layout.triangles[t].map(|i| i as usize);
+            let coordinates = indices.map(|i| layout.vertex_texel_coordinates(i));
+            let positions = indices.map(|i| layout.vertices[i].position);
+            let normals = indices.map(|i| layout.vertices[i].normal);
+            let surface_normal = triangle_normal(positions);
+
+            let (a, b, c) = (coordinates[0], coordinates[1], coordinates[2]);
+            let area = (b.x - a.x) * (c.y - a.y) - (c.x - a.x) * (b.y - a.y);
+            let barycentric = |p: Vector2D| -> Option<[f64; 3]> {
+                if area.abs() < f64::EPSILON {
+                    return None
+                }
+                let w1 = ((p.x - a.x) * (c.y - a.y) - (c.x - a.x) * (p.y - a.y)) / area;
+                let w2 = ((b.x - a.x) * (p.y - a.y) - (p.x - a.x) * (b.y - a.y)) / area;
+                Some([1.0 - w1 - w2, w1, w2])
+            };
+
+            let mut add_texel = |x: usize, y: usize, weights: [f64; 3], coverage: &mut [Option<u32>]| {
+                let pixel = x + y * width;
+                if coverage[pixel].is_some() {
+                    return false
+                }
+                let position = (0..3).fold(Vector3D::zero(), |p, i| p + positions[i].scale(weights[i]));
+                let normal = (0..3).fold(Vector3D::zero(), |n, i| n + normals[i].scale(weights[i]));
+                let normal = if normal.magnitude_squared() > 0.0 { normal.normalize() } else { surface_normal };
+                coverage[pixel] = Some(texels.len() as u32);
+                texels.push(Texel { position, normal, surface_normal, emission: layout.emission });
+                true
+            };
+
+            let min_x = a.x.min(b.x).min(c.x).floor().max(0.0) as usize;
+            let min_y = a.y.min(b.y).min(c.y).floor().max(0.0) as usize;
+            let max_x = (a.x.max(b.x).max(c.x).ceil() as usize).min(page.width as usize);
+            let max_y = (a.y.max(b.y).max(c.y).ceil() as usize).min(page.height as usize);
+
+            let mut covered_any = false;
+            for y in min_y..max_y {
+                for x in min_x..max_x {
+                    let center = Vector2D { x: x as f64 + 0.5, y: y as f64 + 0.5 };
+                    if let Some(weights) = barycentric(center).filter(|w| w.iter().all(|w| *w >= 0.0)) {
+                        covered_any |= add_texel(x, y, weights, coverage);
+                    }
+                }
+            }
+
+            // Make sure triangles smaller than a texel still get lit.
+            if !covered_any {
+                let centroid = (a + b + c).scale(1.0 / 3.0);
+                let x = (centroid.x.max(0.0) as usize).min(page.width as usize - 1);
+                let y = (centroid.y.max(0.0) as usize).min(page.height as usize - 1);
+                add_texel(x, y, [1.0 / 3.0; 3], coverage);
+            }
+        }
+    }
+}
+
+fn level_size(collision: Option<&ModelCollisionGeometryBSP>, texels: &[Texel]) -> f64 {
+    let points = collision
+        .map(|c| c.vertices.items.iter().map(|v| v.point).collect::<Vec<_>>())
+        .unwrap_or_default();
+    let mut min = Vector3D { x: f64::MAX, y: f64::MAX, z: f64::MAX };
+    let mut max = Vector3D { x: f64::MIN, y: f64::MIN, z: f64::MIN };
+    for p in points.into_iter().chain(texels.iter().map(|t| t.position)) {
+        min = Vector3D { x: min.x.min(p.x), y: min.y.min(p.y), z: min.z.min(p.z) };
+        max = Vector3D { x: max.x.max(p.x), y: max.y.max(p.y), z: max.z.max(p.z) };
+    }
+    if min.x > max.x { 0.0 } else { max.distance_squared(&min).sqrt() }
+}
+
+/// Calculate the incoming light and its dominant direction for every texel.
+fn light_texels(scene: &Scene, lighting: &LightmapLighting, settings: &LightmapSettings) -> (Vec<Light>, Vec<Vector3D>) {
+    let texels = scene.texels;
+    let samples = hemisphere_samples(settings.samples);
+    let ambient = light_from_color(&lighting.sky.ambient);
+
+    let world_samples = |normal: Vector3D| {
+        let (u, v) = tangent_basis(normal);
+        samples.iter().map(move |s| (u.scale(s.x) + v.scale(s.y) + normal.scale(s.z)).normalize())
+    };
+
+    // Rays don't change between bounces, so cast them once.
+    let rays: Vec<TexelRays> = parallel_map(texels.len(), settings.threads, |i| {
+        let texel = &texels[i];
+        let origin = texel.position + texel.surface_normal.scale(RAY_OFFSET);
+
+        let mut direct = [0.0; 3];
+        let mut direct_direction = Vector3D::zero();
+        for light in &lighting.sky.lights {
+            let direction = light.direction.normalize();
+            let cosine = texel.normal.dot(&direction);
+            if cosine <= 0.0 || scene.cast(origin, direction) != RAY_HIT_SKY {
+                continue
+            }
+            let contribution = light_from_color(&light.color).map(|c| c * cosine);
+            direct_direction += direction.scale(luminance(&contribution));
+            for c in 0..3 {
+                direct[c] += contribution[c];
+            }
+        }
+
+        let hits = world_samples(texel.normal).map(|d| scene.cast(origin, d)).collect();
+        TexelRays { direct, direct_direction, hits }
+    });
+
+    let gather = |outgoing: &[Light], i: usize, direction: &mut Option<Vector3D>| -> Light {
+        let rays = &rays[i];
+        let mut total = [0.0; 3];
+        for (hit, sample_direction) in rays.hits.iter().zip(world_samples(texels[i].normal)) {
+            let incoming = match *hit {
+                RAY_HIT_SKY => ambient,
+                RAY_HIT_NOTHING => continue,
+                t => outgoing[t as usize]
+            };
+            if let Some(direction) = direction.as_mut() {
+                *direction += sample_direction.scale(luminance(&incoming));
+            }
+            for c in 0..3 {
+                total[c] += incoming[c];
+            }
+        }
+        let count = rays.hits.len().max(1) as f64;
+        [0, 1, 2].map(|c| rays.direct[c] + total[c] / count)
+    };
+
+    let mut outgoing: Vec<Light> = texels.iter().map(|t| t.emission).collect();
+    for _ in 0..settings.bounces {
+        let incoming = parallel_map(texels.len(), settings.threads, |i| gather(&outgoing, i, &mut None));
+        outgoing = texels
+            .iter()
+            .zip(incoming)
+            .map(|(t, l)| [0, 1, 2].map(|c| t.emission[c] + settings.reflectance * l[c]))
+            .collect();
+    }
+
+    parallel_map(texels.len(), settings.threads, |i| {
+        let mut direction = Some(rays[i].direct_direction);
+        let light = gather(&outgoing, i, &mut direction);
+        let direction = direction.unwrap();
+        (light, if direction.magnitude_squared() > 0.0 { direction.normalize() } else { Vector3D::zero() })
+    }).into_iter().unzip()
+}
+
+/// Fill in empty pixels next to filled pixels with the average of their neighbors.
+fn dilate(pixels: &mut [Option<(Light, Vector3D)>], width: usize, height: usize, passes: usize) {
+    for _ in 0..passes {
+        let previous = pixels.to_vec();
+        for y in 0..height {
+            for x in 0..width {
+                if previous[x + y * width].is_some() {
+                    continue
+                }
+
+                let mut count = 0;
+                let mut light = [0.0; 3];
+                let mut direction = Vector3D::zero();
+                for ny in y.saturating_sub(1)..(y + 2).min(height) {
+                    for nx in x.saturating_sub(1)..(x + 2).min(width) {
+                        if let Some((l, d)) = previous[nx + ny * width] {
+                            count += 1;
+                            direction += d;
+                            for c in 0..3 {
+                                light[c] += l[c];
+                            }
+                        }
+                    }
+                }
+
+                if count > 0 {
+                    let direction = if direction.magnitude_squared() > 0.0 { direction.normalize() } else { direction };
+                    pixels[x + y * width] = Some((light.map(|l| l / count as f64), direction));
+                }
+            }
+        }
+    }
+}
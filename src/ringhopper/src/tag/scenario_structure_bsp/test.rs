@@ -0,0 +1,433 @@
+use definitions::ScenarioStructureBSPMaterialUncompressedLightmapVertex;
+use primitives::byteorder::LittleEndian;
+use primitives::parse::SimpleTagData;
+use primitives::primitive::{ColorARGBInt, TagGroup, TagPath, Vector, Vector2D, Vector3D};
+use crate::data::bitmap::Image;
+use crate::data::jms::{JMS, JMS_UNITS_PER_WORLD_UNIT, JMSMarker, JMSMaterial, JMSNode, JMSRegion, JMSTriangle, JMSVertex};
+use crate::tag::collision_bsp::{bsp_leaf_for_point, bsp_point_in_solid};
+use crate::tag::collision_bsp::check::{check_collision_bsp, CollisionBSPIssue};
+use super::clusters::analyze_clusters;
+use super::compile::compile_structure_bsp;
+use super::export::{extract_structure_bsp_geometry, PORTAL_MATERIAL};
+use super::lightmap::{bake_lightmaps, import_lightmaps, LightmapLighting, LightmapSettings, LightmapSkyLight};
+use super::get_uncompressed_vertices_for_bsp_material;
+use super::get_compressed_vertices_for_bsp_material;
+
+fn v(x: f64, y: f64, z: f64) -> Vector3D {
+    Vector3D { x, y, z }
+}
+
+/// Add a quad, given in world units, as two triangles.
+fn add_quad(jms: &mut JMS, points: [Vector3D; 4], shader: u16) {
+    let normal = (points[1] - points[0]).cross(&(points[2] - points[0])).normalize();
+    let first = jms.vertices.len() as u32;
+    let uvs = [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)];
+    for (point, (x, y)) in points.into_iter().zip(uvs) {
+        jms.vertices.push(JMSVertex {
+            position: point.scale(100.0),
+            normal,
+            texture_coordinates: Vector2D { x, y },
+            ..Default::default()
+        });
+    }
+    jms.triangles.push(JMSTriangle { region: 0, shader, vertices: [first, first + 1, first + 2] });
+    jms.triangles.push(JMSTriangle { region: 0, shader, vertices: [first, first + 2, first + 3] });
+}
+
+/// Add a box, with its faces pointing outward if `outward` is set, or inward otherwise.
+fn add_box(jms: &mut JMS, min: Vector3D, max: Vector3D, shader: u16, outward: bool) {
+    let c = |x: bool, y: bool, z: bool| v(
+        if x { max.x } else { min.x },
+        if y { max.y } else { min.y },
+        if z { max.z } else { min.z }
+    );
+    let quads = [
+        [c(false, false, false), c(false, true, false), c(true, true, false), c(true, false, false)],
+        [c(false, false, true), c(true, false, true), c(true, true, true), c(false, true, true)],
+        [c(false, false, false), c(true, false, false), c(true, false, true), c(false, false, true)],
+        [c(false, true, false), c(false, true, true), c(true, true, true), c(true, true, false)],
+        [c(false, false, false), c(false, false, true), c(false, true, true), c(false, true, false)],
+        [c(true, false, false), c(true, true, false), c(true, true, true), c(true, false, true)]
+    ];
+    for mut quad in quads {
+        if !outward {
+            quad.reverse();
+        }
+        add_quad(jms, quad, shader);
+    }
+}
+
+fn test_level() -> JMS {
+    let mut jms = JMS {
+        nodes: vec![JMSNode { name: "frame".to_owned(), rotation: Default::default(), ..Default::default() }],
+        regions: vec![JMSRegion { name: "unnamed".to_owned() }],
+        markers: vec![JMSMarker { name: "spawn".to_owned(), position: v(100.0, 100.0, 50.0), ..Default::default() }],
+        ..Default::default()
+    };
+    jms.nodes[0].rotation.w = 1.0;
+    jms.markers[0].rotation.w = 1.0;
+    for name in ["floor", "+portal", "fog$", "+weatherpoly"] {
+        jms.materials.push(JMSMaterial { name: name.to_owned(), tif_path: "<none>".to_owned() });
+    }
+
+    // A 4x2x2 room split in half by a portal, with a fog plane and weather polyhedron inside
+    add_box(&mut jms, v(0.0, 0.0, 0.0), v(4.0, 2.0, 2.0), 0, false);
+    add_quad(&mut jms, [v(2.0, 0.0, 0.0), v(2.0, 2.0, 0.0), v(2.0, 2.0, 2.0), v(2.0, 0.0, 2.0)], 1);
+    add_quad(&mut jms, [v(0.5, 0.5, 1.5), v(1.5, 0.5, 1.5), v(1.5, 1.5, 1.5), v(0.5, 1.5, 1.5)], 2);
+    add_box(&mut jms, v(3.0, 0.5, 0.5), v(3.5, 1.0, 1.0), 3, true);
+
+    jms
+}
+
+fn resolve_shader(name: &str) -> primitives::error::RinghopperResult<TagPath> {
+    TagPath::new(&format!("levels\\test\\shaders\\{name}"), TagGroup::ShaderEnvironment)
+}
+
+#[test]
+fn compile_room() {
+    let bsp = compile_structure_bsp(&test_level(), None, &resolve_shader).unwrap();
+
+    // Collision
+    let collision = &bsp.collision_bsp.items[0];
+    assert!(!bsp_point_in_solid(collision, v(1.0, 1.0, 1.0)));
+    assert!(!bsp_point_in_solid(collision, v(3.9, 1.9, 0.1)));
+    assert!(bsp_point_in_solid(collision, v(5.0, 1.0, 1.0)));
+    assert!(bsp_point_in_solid(collision, v(1.0, -0.5, 1.0)));
+    assert_eq!(1, bsp.collision_materials.items.len());
+    assert_eq!(collision.leaves.items.len(), bsp.leaves.items.len());
+    assert_eq!(Vec::<CollisionBSPIssue>::new(), check_collision_bsp(collision, bsp.collision_materials.items.len()));
+
+    // Clusters
+    assert_eq!(2, bsp.clusters.items.len());
+    assert_eq!(1, bsp.cluster_portals.items.len());
+    let cluster_at = |point| {
+        let leaf = bsp_leaf_for_point(collision, point).unwrap();
+        bsp.leaves.items[leaf as usize].cluster.unwrap()
+    };
+    assert_eq!(cluster_at(v(0.5, 0.5, 0.5)), cluster_at(v(1.5, 1.5, 1.5)));
+    assert_eq!(cluster_at(v(2.5, 0.5, 0.5)), cluster_at(v(3.9, 1.5, 1.9)));
+    assert_ne!(cluster_at(v(1.0, 1.0, 1.0)), cluster_at(v(3.0, 1.0, 1.0)));
+
+    let portal = &bsp.cluster_portals.items[0];
+    assert_eq!(4, portal.vertices.items.len());
+    assert!(portal.centroid.distance_squared(&v(2.0, 1.0, 1.0)) < 0.00001);
+    assert_ne!(portal.front_cluster, portal.back_cluster);
+    for cluster in &bsp.clusters.items {
+        assert_eq!(1, cluster.portals.items.len());
+    }
+
+    // Every surface should be in exactly one cluster.
+    let mut cluster_surfaces: Vec<i32> = bsp.clusters.items.iter().flat_map(|c| c.surface_indices.items.iter().map(|s| s.index)).collect();
+    cluster_surfaces.sort();
+    assert_eq!((0..bsp.surfaces.items.len() as i32).collect::<Vec<_>>(), cluster_surfaces);
+
+    // Every surface is referenced by a leaf in its cluster. Surfaces crossing the portal are also referenced by leaves
+    // in the other cluster.
+    let mut referenced_surfaces = Vec::new();
+    for leaf in &bsp.leaves.items {
+        let first = leaf.surface_references as usize;
+        for reference in &bsp.leaf_surfaces.items[first..first + leaf.surface_reference_count as usize] {
+            assert!((reference.node as usize) < collision.bsp3d_nodes.items.len());
+            referenced_surfaces.push((leaf.cluster.unwrap() as usize, reference.surface));
+        }
+    }
+    for (cluster_index, cluster) in bsp.clusters.items.iter().enumerate() {
+        for surface in &cluster.surface_indices.items {
+            assert!(referenced_surfaces.contains(&(cluster_index, surface.index)));
+        }
+    }
+
+    // Rendering
+    assert_eq!(12, bsp.surfaces.items.len());
+    let material = &bsp.lightmaps.items[0].materials.items[0];
+    assert_eq!(24, material.rendered_vertices.vertex_count);
+    assert_eq!(24, get_compressed_vertices_for_bsp_material(material).unwrap().0.count());
+
+    // Other stuff
+    assert_eq!(1, bsp.fog_planes.items.len());
+    assert_eq!(4, bsp.fog_planes.items[0].vertices.items.len());
+    assert_eq!(1, bsp.weather_polyhedra.items.len());
+    assert_eq!(6, bsp.weather_polyhedra.items[0].planes.items.len());
+    assert_eq!(1, bsp.markers.items.len());
+    assert!(bsp.markers.items[0].position.distance_squared(&v(1.0, 1.0, 0.5)) < 0.00001);
+}
+
+#[test]
+fn compile_leaky_room() {
+    let mut jms = test_level();
+    jms.triangles.remove(0);
+    assert!(compile_structure_bsp(&jms, None, &resolve_shader).is_err());
+}
+
+#[test]
+fn compile_two_sided() {
+    let mut jms = test_level();
+    jms.materials.push(JMSMaterial { name: "fence%".to_owned(), tif_path: "<none>".to_owned() });
+    add_quad(&mut jms, [v(1.0, 0.5, 0.5), v(1.0, 1.5, 0.5), v(1.0, 1.5, 1.5), v(1.0, 0.5, 1.5)], 4);
+    let bsp = compile_structure_bsp(&jms, None, &resolve_shader).unwrap();
+
+    // Both sides of the fence are open, and leaves on both sides reference its surfaces.
+    let collision = &bsp.collision_bsp.items[0];
+    assert!(collision.surfaces.items.iter().any(|s| s.flags.two_sided));
+    for x in [0.9, 1.1] {
+        let leaf = bsp_leaf_for_point(collision, v(x, 1.0, 1.0)).expect("should be in empty space");
+        assert!(collision.leaves.items[leaf as usize].flags.contains_double_sided_surfaces);
+        let leaf = &bsp.leaves.items[leaf as usize];
+        let first = leaf.surface_references as usize;
+        let references = &bsp.leaf_surfaces.items[first..first + leaf.surface_reference_count as usize];
+
+        // Materials are sorted by shader, so the fence's surfaces come first.
+        assert!(references.iter().any(|r| r.surface < 2), "leaf at x = {x} should reference the fence");
+    }
+
+    // Surfaces that aren't in any cluster can't be compiled.
+    add_quad(&mut jms, [v(10.0, 0.5, 0.5), v(10.0, 1.5, 0.5), v(10.0, 1.5, 1.5), v(10.0, 0.5, 1.5)], 4);
+    let error = compile_structure_bsp(&jms, None, &resolve_shader).unwrap_err();
+    assert!(error.to_string().contains("not in any cluster"), "{error}");
+}
+
+#[test]
+fn extract_room() {
+    let mut bsp = compile_structure_bsp(&test_level(), None, &resolve_shader).unwrap();
+
+    // Drop the uncompressed vertices, like some BSPs extracted from cache files.
+    for material in &mut bsp.lightmaps.items[0].materials {
+        material.uncompressed_vertices.bytes.clear();
+    }
+
+    let geometry = extract_structure_bsp_geometry(&bsp).unwrap();
+    assert!(!geometry.has_lightmap_texture_coordinates());
+
+    let jms = geometry.to_jms(false);
+    let region_triangles = |name: &str| {
+        let region = jms.regions.iter().position(|r| r.name == name).unwrap() as u16;
+        jms.triangles.iter().filter(|t| t.region == region).count()
+    };
+    assert_eq!(12, region_triangles("render"));
+    assert!(region_triangles("collision") >= 12);
+    assert_eq!(2, region_triangles("portals"));
+    assert!(jms.materials.iter().any(|m| m.name == "floor"));
+    assert!(jms.materials.iter().any(|m| m.name == "floor@"));
+    assert!(jms.materials.iter().any(|m| m.name == PORTAL_MATERIAL));
+    assert_eq!(1, jms.markers.len());
+
+    // Render geometry should match what went in, with texture coordinates flipped back.
+    let source = test_level();
+    for triangle in jms.triangles.iter().filter(|t| t.region == 0) {
+        for vertex in triangle.vertices.map(|v| &jms.vertices[v as usize]) {
+            assert!(source.vertices.iter().any(|s| {
+                s.position.distance_squared(&vertex.position) < 0.0001
+                    && (s.texture_coordinates.x - vertex.texture_coordinates.x).abs() < 0.0001
+                    && (s.texture_coordinates.y - vertex.texture_coordinates.y).abs() < 0.0001
+            }));
+        }
+    }
+
+    let obj = geometry.to_obj(false);
+    assert_eq!(3, obj.objects.len());
+    assert_eq!(12, obj.objects[0].faces.len());
+    assert_eq!(4, obj.objects[2].faces[0].vertices.len());
+    assert!(obj.to_text().contains("usemtl +portal\nf "));
+}
+
+/// A 4x2x2 room with a sky ceiling.
+fn sky_room() -> JMS {
+    let mut jms = JMS {
+        nodes: vec![JMSNode { name: "frame".to_owned(), ..Default::default() }],
+        regions: vec![JMSRegion { name: "unnamed".to_owned() }],
+        ..Default::default()
+    };
+    jms.nodes[0].rotation.w = 1.0;
+    for name in ["floor", "+sky"] {
+        jms.materials.push(JMSMaterial { name: name.to_owned(), tif_path: "<none>".to_owned() });
+    }
+    add_box(&mut jms, v(0.0, 0.0, 0.0), v(4.0, 2.0, 2.0), 0, false);
+
+    // The second quad of a box is its top.
+    jms.triangles[2].shader = 1;
+    jms.triangles[3].shader = 1;
+    jms
+}
+
+#[test]
+fn bake_sky_room() {
+    let lighting = LightmapLighting {
+        sky: super::lightmap::LightmapSky {
+            ambient: [0.1, 0.1, 0.1].into(),
+            lights: vec![LightmapSkyLight { direction: v(0.0, 0.0, 1.0), color: [1.0, 0.9, 0.8].into() }]
+        },
+        ..Default::default()
+    };
+    let bake = |threads| {
+        let mut bsp = compile_structure_bsp(&sky_room(), None, &resolve_shader).unwrap();
+        let settings = LightmapSettings { texels_per_world_unit: 2.0, samples: 16, bounces: 1, threads, ..Default::default() };
+        let bitmap = bake_lightmaps(&mut bsp, &lighting, &settings).unwrap();
+        (bsp, bitmap)
+    };
+
+    let (bsp, bitmap) = bake(1);
+    assert_eq!(bitmap, bake(3).1, "results should not depend on the number of threads");
+
+    assert_eq!(1, bsp.lightmaps.items.len());
+    assert_eq!(Some(0), bsp.lightmaps.items[0].bitmap);
+    let data = &bitmap.bitmap_data.items[0];
+    assert_eq!(data.width as usize * data.height as usize * 4, bitmap.processed_pixel_data.bytes.len());
+
+    let material = &bsp.lightmaps.items[0].materials.items[0];
+    assert_eq!(material.rendered_vertices.vertex_count, material.lightmap_vertices.vertex_count);
+    assert!(!material.compressed_vertices.bytes.is_empty());
+
+    // The floor faces the sun, but the walls don't.
+    let (rendered, lightmap) = get_uncompressed_vertices_for_bsp_material(material).unwrap();
+    let pixel_at = |uv: Vector2D| {
+        let x = ((uv.x * data.width as f64) as usize).min(data.width as usize - 1);
+        let y = ((uv.y * data.height as f64) as usize).min(data.height as usize - 1);
+        let offset = (x + y * data.width as usize) * 4;
+        bitmap.processed_pixel_data.bytes[offset + 2]
+    };
+    let mut floor_checked = false;
+    for (r, l) in rendered.zip(lightmap) {
+        assert!((0.0..=1.0).contains(&l.texture_coords.x) && (0.0..=1.0).contains(&l.texture_coords.y));
+        if r.normal.z > 0.9 {
+            assert!(pixel_at(l.texture_coords) > 200);
+            assert!(l.normal.z > 0.9, "light on the floor should come from above");
+            floor_checked = true;
+        }
+        else if r.normal.z.abs() < 0.1 {
+            assert!(pixel_at(l.texture_coords) < 200);
+        }
+    }
+    assert!(floor_checked);
+}
+
+#[test]
+fn import_room() {
+    let mut bsp = compile_structure_bsp(&test_level(), None, &resolve_shader).unwrap();
+
+    // Lay out the render geometry on the second page, listing triangles in reverse to show order doesn't matter.
+    let lightmap_uv = |p: Vector3D| Vector2D { x: 1.0 + (p.x + p.z) * 0.01, y: (p.y + p.z) * 0.01 };
+    let mut jms = extract_structure_bsp_geometry(&bsp).unwrap().to_jms(true);
+    jms.triangles.reverse();
+    for vertex in &mut jms.vertices {
+        let uv = lightmap_uv(vertex.position.scale(1.0 / JMS_UNITS_PER_WORLD_UNIT));
+        vertex.texture_coordinates = Vector2D { x: uv.x, y: 1.0 - uv.y };
+    }
+
+    let pages = [(4, 4), (8, 2)].map(|(width, height)| Image { width, height, data: vec![ColorARGBInt { color: 0xFF102030 }; width * height] });
+    let bitmap = import_lightmaps(&mut bsp, &jms, &pages).unwrap();
+    assert_eq!(2, bitmap.bitmap_data.items.len());
+    assert_eq!(8, bitmap.bitmap_data.items[1].width);
+    assert_eq!((4 * 4 + 8 * 2) * 4, bitmap.processed_pixel_data.bytes.len());
+    assert_eq!([0x30, 0x20, 0x10], bitmap.processed_pixel_data.bytes[0..3]);
+
+    // Only the second page has anything on it.
+    assert_eq!(1, bsp.lightmaps.items.len());
+    assert_eq!(Some(1), bsp.lightmaps.items[0].bitmap);
+    for material in &bsp.lightmaps.items[0].materials {
+        assert!(!material.compressed_vertices.bytes.is_empty());
+        let (rendered, lightmap) = get_uncompressed_vertices_for_bsp_material(material).unwrap();
+        assert_eq!(material.rendered_vertices.vertex_count, material.lightmap_vertices.vertex_count);
+        for (r, l) in rendered.zip(lightmap) {
+            let expected = lightmap_uv(r.position);
+            assert!((expected.x - 1.0 - l.texture_coords.x).abs() < 0.0001);
+            assert!((expected.y - l.texture_coords.y).abs() < 0.0001);
+        }
+    }
+
+    // Exporting puts the page back into the U coordinate.
+    let geometry = extract_structure_bsp_geometry(&bsp).unwrap();
+    assert!(geometry.has_lightmap_texture_coordinates());
+    let exported = geometry.to_jms(true);
+    assert!(exported.triangles.iter().flat_map(|t| t.vertices).all(|v| exported.vertices[v as usize].texture_coordinates.x >= 1.0));
+
+    // Every surface needs lightmap texture coordinates.
+    let mut bsp = compile_structure_bsp(&test_level(), None, &resolve_shader).unwrap();
+    jms.triangles.pop();
+    assert!(import_lightmaps(&mut bsp, &jms, &pages).is_err());
+}
+
+#[test]
+fn reimport_room() {
+    // Give half of the floor a shader with the same name in another directory.
+    let mut level = test_level();
+    level.materials.push(JMSMaterial { name: "other".to_owned(), tif_path: "<none>".to_owned() });
+    level.triangles[0].shader = 4;
+    level.triangles[1].shader = 4;
+    let resolve = |name: &str| match name {
+        "other" => TagPath::new("levels\\test\\other\\floor", TagGroup::ShaderEnvironment),
+        name => resolve_shader(name)
+    };
+    let mut bsp = compile_structure_bsp(&level, None, &resolve).unwrap();
+
+    // Lightmap geometry is named after the full shader paths, so each shader can go on its own page.
+    let mut jms = extract_structure_bsp_geometry(&bsp).unwrap().to_jms(true);
+    let mut names: Vec<&str> = jms.materials.iter().map(|m| m.name.as_str()).collect();
+    names.sort();
+    assert_eq!(vec!["levels\\test\\other\\floor", "levels\\test\\shaders\\floor"], names);
+    for triangle in jms.triangles.clone() {
+        let page = if jms.materials[triangle.shader as usize].name.contains("other") { 0.0 } else { 1.0 };
+        for vertex in triangle.vertices {
+            jms.vertices[vertex as usize].texture_coordinates = Vector2D { x: page + 0.5, y: 0.5 };
+        }
+    }
+
+    let pages = [(4, 4), (4, 4)].map(|(width, height)| Image { width, height, data: vec![ColorARGBInt { color: 0xFF102030 }; width * height] });
+    import_lightmaps(&mut bsp, &jms, &pages).unwrap();
+    assert_eq!(2, bsp.lightmaps.items.len());
+    for lightmap in &bsp.lightmaps {
+        let other = lightmap.materials.items[0].shader.path().unwrap().path().contains("other");
+        assert_eq!(Some(if other { 0 } else { 1 }), lightmap.bitmap);
+    }
+
+    // Importing again keeps the incident light directions.
+    let incident = v(0.0, 0.6, 0.8);
+    for material in bsp.lightmaps.items.iter_mut().flat_map(|l| l.materials.items.iter_mut()) {
+        let (rendered, lightmap) = get_uncompressed_vertices_for_bsp_material(material).unwrap();
+        let mut bytes = Vec::new();
+        for vertex in rendered {
+            bytes.extend_from_slice(vertex.as_bytes::<LittleEndian>().unwrap().bytes());
+        }
+        for vertex in lightmap {
+            bytes.extend_from_slice(ScenarioStructureBSPMaterialUncompressedLightmapVertex { normal: incident, ..vertex }.as_bytes::<LittleEndian>().unwrap().bytes());
+        }
+        material.uncompressed_vertices.bytes = bytes;
+    }
+    import_lightmaps(&mut bsp, &jms, &pages).unwrap();
+    for material in bsp.lightmaps.items.iter().flat_map(|l| l.materials.items.iter()) {
+        let (_, lightmap) = get_uncompressed_vertices_for_bsp_material(material).unwrap();
+        for vertex in lightmap {
+            assert!(vertex.normal.distance_squared(&incident) < 0.00001);
+        }
+    }
+}
+
+#[test]
+fn cluster_report() {
+    let mut bsp = compile_structure_bsp(&test_level(), None, &resolve_shader).unwrap();
+
+    let report = analyze_clusters(&bsp);
+    assert_eq!(2, report.clusters.len());
+    assert_eq!(12, report.clusters.iter().map(|c| c.triangle_count).sum::<usize>());
+    assert_eq!(vec![(0, 1)], report.clusters[0].portals);
+    assert_eq!(vec![vec![0, 1]], report.connected_groups);
+    assert!(report.problems.is_empty());
+    assert_eq!(vec!["cluster #0 has no sound environment", "cluster #1 has no sound environment"], report.notes);
+    assert!(report.to_dot().contains("    cluster0 -- cluster1 [label=\"portal #0\"];\n"));
+
+    // A portal missing from the clusters' portal lists leaks, but the portal itself still connects them.
+    bsp.clusters.items[1].portals.items.clear();
+    let report = analyze_clusters(&bsp);
+    assert_eq!(vec![vec![0, 1]], report.connected_groups);
+    assert!(report.problems.contains(&"portal #0 leaks: cluster #1 does not list it".to_owned()));
+
+    bsp.clusters.items[0].portals.items.clear();
+    let report = analyze_clusters(&bsp);
+    assert_eq!(vec![vec![0, 1]], report.connected_groups);
+    assert!(report.problems.contains(&"portal #0 leaks: cluster #0 does not list it".to_owned()));
+
+    // Without the portal, the clusters are cut off from each other.
+    bsp.cluster_portals.items.clear();
+    let report = analyze_clusters(&bsp);
+    assert_eq!(vec![vec![0], vec![1]], report.connected_groups);
+    assert!(report.problems.contains(&"cluster(s) #1 cannot be reached from the rest of the level".to_owned()));
+}
@@ -0,0 +1,35 @@
+use definitions::*;
+use primitives::primitive::TagGroup;
+use primitives::tag::PrimaryTagStructDyn;
+
+macro_rules! get_base_shader_tag_memes {
+    ($tag:expr, $as_any:tt, $downcast:tt, $map:expr) => {{
+        let group = $tag.group();
+        let any = $tag.$as_any();
+        match group {
+            TagGroup::Shader => any.$downcast(),
+            TagGroup::ShaderEnvironment => any.$downcast::<ShaderEnvironment>().map($map),
+            TagGroup::ShaderModel => any.$downcast::<ShaderModel>().map($map),
+            TagGroup::ShaderTransparentChicago => any.$downcast::<ShaderTransparentChicago>().map($map),
+            TagGroup::ShaderTransparentChicagoExtended => any.$downcast::<ShaderTransparentChicagoExtended>().map($map),
+            TagGroup::ShaderTransparentGeneric => any.$downcast::<ShaderTransparentGeneric>().map($map),
+            TagGroup::ShaderTransparentGlass => any.$downcast::<ShaderTransparentGlass>().map($map),
+            TagGroup::ShaderTransparentMeter => any.$downcast::<ShaderTransparentMeter>().map($map),
+            TagGroup::ShaderTransparentPlasma => any.$downcast::<ShaderTransparentPlasma>().map($map),
+            TagGroup::ShaderTransparentWater => any.$downcast::<ShaderTransparentWater>().map($map),
+            _ => None
+        }
+    }};
+}
+
+/// Get a reference to the base shader struct of the tag if the tag is a shader tag.
+#[must_use]
+pub fn downcast_base_shader(tag: &dyn PrimaryTagStructDyn) -> Option<&Shader> {
+    get_base_shader_tag_memes!(tag, as_any, downcast_ref, |s| &s.shader)
+}
+
+/// Get a mutable reference to the base shader struct of the tag if the tag is a shader tag.
+#[must_use]
+pub fn downcast_base_shader_mut(tag: &mut dyn PrimaryTagStructDyn) -> Option<&mut Shader> {
+    get_base_shader_tag_memes!(tag, as_any_mut, downcast_mut, |s| &mut s.shader)
+}
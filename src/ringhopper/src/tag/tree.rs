@@ -6,6 +6,8 @@ use crc64::crc64;
 use primitives::error::{Error, RinghopperResult};
 use primitives::primitive::{TagGroup, TagPath, TagReference, HALO_PATH_SEPARATOR};
 use primitives::tag::{ParseStrictness, PrimaryTagStructDyn};
+use crate::tag::dependency::for_each_dependency;
+use crate::tag::dependency::index::DependencyIndex;
 
 /// Tag tree implementation for traversing and loading/saving tags.
 pub trait TagTree {
@@ -24,6 +26,19 @@ pub trait TagTree {
         self.open_tag_copy(path).map(|b| Arc::new(Mutex::new(b)))
     }
 
+    /// Get all tag references in the tag.
+    ///
+    /// Null references may be omitted if the references are looked up from an index rather than the tag itself.
+    ///
+    /// Tag trees that load tags from files should override this to avoid reading the whole tag.
+    ///
+    /// If this is not overridden, the tag will be opened with [`TagTree::open_tag_shared`].
+    fn open_tag_references(&self, path: &TagPath) -> RinghopperResult<Vec<TagReference>> {
+        let tag = self.open_tag_shared(path)?;
+        let tag = tag.lock().unwrap();
+        Ok(all_references_in_tag(tag.as_ref()))
+    }
+
     /// Get all files in the path.
     ///
     /// Returns `None` if the path does not exist.
@@ -50,6 +65,12 @@ pub trait TagTree {
     fn get_all_tags_with_filter(&self, filter: Option<&TagFilter>) -> Vec<TagPath>;
 }
 
+fn all_references_in_tag(tag: &dyn PrimaryTagStructDyn) -> Vec<TagReference> {
+    let mut references = Vec::new();
+    for_each_dependency(tag.as_dynamic(), |r| references.push(r.clone()));
+    references
+}
+
 /// Specify the type of tag tree.
 pub enum TreeType {
     LooseTags,
@@ -479,6 +500,15 @@ impl<T: TagTree> TagTree for CachingTagTree<T> {
         cache.insert(path.clone(), cached.clone());
         Ok(cached)
     }
+    fn open_tag_references(&self, path: &TagPath) -> RinghopperResult<Vec<TagReference>> {
+        // The cached tag may have been modified, so it takes precedence. Otherwise, don't bother caching it.
+        let cached = self.tag_cache.lock().unwrap().get(path).cloned();
+        let Some(tag) = cached else {
+            return self.inner.open_tag_references(path)
+        };
+        let tag = tag.lock().unwrap();
+        Ok(all_references_in_tag(tag.as_ref()))
+    }
     fn files_in_path(&self, path: &str) -> Option<Vec<TagTreeItem>> {
         self.inner.files_in_path(path)
     }
@@ -510,7 +540,8 @@ impl<T: TagTree> TagTree for CachingTagTree<T> {
 pub struct VirtualTagsDirectory {
     directories: Vec<PathBuf>,
     strictness: ParseStrictness,
-    cow_output: Option<PathBuf>
+    cow_output: Option<PathBuf>,
+    dependency_index: Option<Arc<DependencyIndex>>
 }
 
 impl VirtualTagsDirectory {
@@ -531,7 +562,7 @@ impl VirtualTagsDirectory {
             }
         }
 
-        Ok(Self { directories, strictness: ParseStrictness::Strict, cow_output })
+        Ok(Self { directories, strictness: ParseStrictness::Strict, cow_output, dependency_index: None })
     }
 
     /// Get the path to the directory at the given index.
@@ -544,6 +575,23 @@ impl VirtualTagsDirectory {
         self.strictness = strictness
     }
 
+    /// Get the strictness for opening tags.
+    pub fn get_strictness(&self) -> ParseStrictness {
+        self.strictness
+    }
+
+    /// Set the dependency index for looking up tag references.
+    ///
+    /// Tags that were modified since the index was last updated are still read from the tags directory.
+    pub fn set_dependency_index(&mut self, index: Option<Arc<DependencyIndex>>) {
+        self.dependency_index = index
+    }
+
+    /// Get the dependency index, if one was set.
+    pub fn get_dependency_index(&self) -> Option<&DependencyIndex> {
+        self.dependency_index.as_deref()
+    }
+
     /// Write the tag to the desired tags directory.
     ///
     /// Note that if there is a cow, `directory` will be ignored and the cow will be used instead.
@@ -602,7 +650,7 @@ impl VirtualTagsDirectory {
         None
     }
 
-    fn hash_file(file: &[u8]) -> u64 {
+    pub(crate) fn hash_file(file: &[u8]) -> u64 {
         crc64(u64::MAX, file)
     }
 }
@@ -617,6 +665,15 @@ impl TagTree for VirtualTagsDirectory {
         tag.metadata_mut().hash = Some(hash);
         Ok(tag)
     }
+    fn open_tag_references(&self, path: &TagPath) -> RinghopperResult<Vec<TagReference>> {
+        let (directory, file_path) = self.path_for_tag(path).ok_or_else(|| Error::TagNotFound(path.clone()))?;
+        if let Some(dependencies) = self.dependency_index.as_ref().and_then(|i| i.get_current_dependencies(path, directory, &file_path)) {
+            return Ok(dependencies.iter().map(|d| TagReference::Set(d.clone())).collect())
+        }
+        let file = std::fs::read(&file_path).map_err(|e| Error::FailedToReadFile(file_path, e))?;
+        ringhopper_structs::scan_any_tag_references_from_file_buffer(&file)
+            .map_err(|e| Error::FailedToReadTag(path.clone(), vec![e]))
+    }
     fn files_in_path(&self, path: &str) -> Option<Vec<TagTreeItem>> {
         let mut result = Vec::new();
         let mut success = false;
@@ -721,6 +778,10 @@ impl<T: TagTree + Send> TagTree for AtomicTagTree<T> {
         self.inner.lock().unwrap().open_tag_shared(path)
     }
 
+    fn open_tag_references(&self, path: &TagPath) -> RinghopperResult<Vec<TagReference>> {
+        self.inner.lock().unwrap().open_tag_references(path)
+    }
+
     fn files_in_path(&self, _path: &str) -> Option<Vec<TagTreeItem>> {
         unimplemented!("files_in_path not implemented for AtomicTagTree")
     }
@@ -759,6 +820,10 @@ impl<T: TagTree> TagTree for Arc<T> {
         self.as_ref().open_tag_shared(path)
     }
 
+    fn open_tag_references(&self, path: &TagPath) -> RinghopperResult<Vec<TagReference>> {
+        self.as_ref().open_tag_references(path)
+    }
+
     fn files_in_path(&self, path: &str) -> Option<Vec<TagTreeItem>> {
         self.as_ref().files_in_path(path)
     }
@@ -822,6 +887,10 @@ impl TagTree for Box<dyn TagTree + Send + Sync> {
         self.as_ref().open_tag_shared(path)
     }
 
+    fn open_tag_references(&self, path: &TagPath) -> RinghopperResult<Vec<TagReference>> {
+        self.as_ref().open_tag_references(path)
+    }
+
     fn files_in_path(&self, path: &str) -> Option<Vec<TagTreeItem>> {
         self.as_ref().files_in_path(path)
     }
@@ -860,6 +929,10 @@ impl TagTree for Arc<dyn TagTree + Send + Sync> {
         self.as_ref().open_tag_shared(path)
     }
 
+    fn open_tag_references(&self, path: &TagPath) -> RinghopperResult<Vec<TagReference>> {
+        self.as_ref().open_tag_references(path)
+    }
+
     fn files_in_path(&self, path: &str) -> Option<Vec<TagTreeItem>> {
         self.as_ref().files_in_path(path)
     }
@@ -12,6 +12,9 @@ mod particle_system;
 mod particle;
 pub(crate) mod scenario_structure_bsp;
 mod floats;
+pub(crate) mod indices;
+mod limits;
+mod model_collision_geometry;
 
 use std::collections::HashMap;
 use std::num::NonZeroUsize;
@@ -38,7 +41,8 @@ use self::{
     sound::*,
     particle_system::*,
     scenario::*,
-    scenario_structure_bsp::*
+    scenario_structure_bsp::*,
+    model_collision_geometry::*
 };
 
 use super::dependency::recursively_get_dependencies_for_map;
@@ -159,8 +163,9 @@ impl<T: TagTree + Send + Sync + 'static> ScenarioContext<T> {
                 let group = path.group();
 
                 floats::check_bad_floats(tag, &mut result);
+                limits::check_ranges_and_limits(tag, self.engine, &mut result);
 
-                // TODO: Verify indices
+                indices::check_indices(tag, &mut result);
                 verify_dependencies(tag, path, self, &mut result);
 
                 // Verify supergroups
@@ -185,6 +190,7 @@ impl<T: TagTree + Send + Sync + 'static> ScenarioContext<T> {
                     TagGroup::Particle => verify_particle(tag, path, self, &mut result),
                     TagGroup::Scenario => verify_scenario(tag, path, self, &mut result),
                     TagGroup::ScenarioStructureBSP => verify_scenario_structure_bsp(tag, path, self, &mut result),
+                    TagGroup::ModelCollisionGeometry => verify_model_collision_geometry(tag, path, self, &mut result),
                     _ => ()
                 }
             },
@@ -237,3 +243,6 @@ impl<T: TagTree + Send + Sync + 'static> ScenarioContext<T> {
         }
     }
 }
+
+#[cfg(test)]
+mod test;
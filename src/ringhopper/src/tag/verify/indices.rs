@@ -0,0 +1,83 @@
+use primitives::dynamic::DynamicTagData;
+use primitives::primitive::Index;
+use primitives::tag::PrimaryTagStructDyn;
+use crate::tag::verify::TagResult;
+
+pub(crate) struct InvalidIndex {
+    /// Matcher path to the index
+    pub path: String,
+
+    /// Value of the index
+    pub index: u16,
+
+    /// Name of the reflexive being indexed
+    pub reflexive: &'static str,
+
+    /// Number of entries in the reflexive
+    pub count: usize
+}
+
+/// Find all indices which are out of bounds of the reflexive they index.
+pub(crate) fn find_invalid_indices(data: &dyn DynamicTagData) -> Vec<InvalidIndex> {
+    fn recursion<'a>(data: &'a dyn DynamicTagData, parents: &mut Vec<&'a dyn DynamicTagData>, path: &mut String, invalid: &mut Vec<InvalidIndex>) {
+        parents.push(data);
+
+        for field_name in data.fields() {
+            let field = data.get_field(field_name).unwrap();
+            let len = path.len();
+            if !path.is_empty() {
+                path.push('.');
+            }
+            *path += field_name;
+
+            let metadata = data.get_metadata_for_field(field_name);
+            let index = field.as_any().downcast_ref::<Index>().copied().flatten();
+            let reference = metadata.filter(|m| !m.cache_only).and_then(|m| m.index_reference);
+            if let (Some(index), Some(reference)) = (index, reference) {
+                let reflexive = parents
+                    .iter()
+                    .rev()
+                    .find(|p| reference.is_parent(**p))
+                    .and_then(|p| p.get_field(reference.reflexive))
+                    .and_then(|r| r.as_array());
+                if let Some(reflexive) = reflexive {
+                    if index as usize >= reflexive.len() {
+                        invalid.push(InvalidIndex { path: path.clone(), index, reflexive: reference.reflexive, count: reflexive.len() });
+                    }
+                }
+            }
+
+            if let Some(array) = field.as_array() {
+                for i in 0..array.len() {
+                    let len = path.len();
+                    *path += &format!("[{i}]");
+                    recursion(array.get_at_index(i).unwrap(), parents, path, invalid);
+                    path.truncate(len);
+                }
+            }
+            else if !field.fields().is_empty() {
+                recursion(field, parents, path, invalid);
+            }
+
+            path.truncate(len);
+        }
+
+        parents.pop();
+    }
+
+    let mut invalid = Vec::new();
+    recursion(data, &mut Vec::new(), &mut String::new(), &mut invalid);
+    invalid
+}
+
+pub fn check_indices(tag: &dyn PrimaryTagStructDyn, result: &mut TagResult) {
+    for i in find_invalid_indices(tag.as_dynamic()) {
+        result.errors.push(format!(
+            "{path} ({index}) is out of bounds for {reflexive} ({count} entries). This can be automatically fixed.",
+            path = i.path,
+            index = i.index,
+            reflexive = i.reflexive,
+            count = i.count
+        ));
+    }
+}
@@ -0,0 +1,100 @@
+use primitives::dynamic::{DynamicTagData, DynamicTagDataType, SimplePrimitiveType, TagFieldMetadata};
+use primitives::engine::Engine;
+use primitives::primitive::{Data, FileData};
+use primitives::tag::PrimaryTagStructDyn;
+use crate::tag::verify::TagResult;
+
+/// Check that values are within their field's minimum and maximum, and that reflexives and data do not exceed the
+/// engine's limits.
+///
+/// Exceeding an engine limit is an error, but values outside of the minimum and maximum are only warnings, as those
+/// ranges are enforced by the editor rather than the engine.
+pub fn check_ranges_and_limits(tag: &dyn PrimaryTagStructDyn, engine: &Engine, result: &mut TagResult) {
+    fn recursion(data: &dyn DynamicTagData, path: &mut String, engine: &Engine, result: &mut TagResult) {
+        for field_name in data.fields() {
+            let field = data.get_field(field_name).unwrap();
+            let len = path.len();
+            if !path.is_empty() {
+                path.push('.');
+            }
+            *path += field_name;
+
+            if let Some(metadata) = data.get_metadata_for_field(field_name) {
+                if !metadata.cache_only {
+                    check_field(field, &metadata, path, engine, result);
+                }
+            }
+
+            if let Some(array) = field.as_array() {
+                for i in 0..array.len() {
+                    let len = path.len();
+                    *path += &format!("[{i}]");
+                    recursion(array.get_at_index(i).unwrap(), path, engine, result);
+                    path.truncate(len);
+                }
+            }
+            else if !field.fields().is_empty() {
+                recursion(field, path, engine, result);
+            }
+
+            path.truncate(len);
+        }
+    }
+
+    recursion(tag.as_dynamic(), &mut String::new(), engine, result);
+}
+
+fn check_field(field: &dyn DynamicTagData, metadata: &TagFieldMetadata, path: &str, engine: &Engine, result: &mut TagResult) {
+    let length = match field.data_type() {
+        DynamicTagDataType::Reflexive => Some(field.as_array().unwrap().len()),
+        DynamicTagDataType::Data => Some(field.as_any().downcast_ref::<Data>().unwrap().bytes.len()),
+        DynamicTagDataType::FileData => Some(field.as_any().downcast_ref::<FileData>().unwrap().bytes.len()),
+        _ => None
+    };
+
+    if let (Some(length), Some(limit)) = (length, metadata.limit) {
+        let limit = limit.for_engine(engine);
+        if length > limit {
+            let unit = if field.data_type() == DynamicTagDataType::Reflexive { "entries" } else { "bytes" };
+            result.errors.push(format!("{path} has {length} {unit}, exceeding the limit of {limit} for {}", engine.display_name));
+        }
+    }
+
+    if metadata.minimum.is_none() && metadata.maximum.is_none() {
+        return
+    }
+
+    let values = match length {
+        Some(length) => vec![length as f64],
+        None => numeric_values(field)
+    };
+    for value in values {
+        if metadata.minimum.is_some_and(|m| value < m) || metadata.maximum.is_some_and(|m| value > m) {
+            let minimum = metadata.minimum.map(|m| m.to_string()).unwrap_or_else(|| "-inf".to_owned());
+            let maximum = metadata.maximum.map(|m| m.to_string()).unwrap_or_else(|| "inf".to_owned());
+            result.warnings.push(format!("{path} ({value}) is outside of the allowed range of [{minimum}, {maximum}]"));
+        }
+    }
+}
+
+fn numeric_values(field: &dyn DynamicTagData) -> Vec<f64> {
+    macro_rules! value {
+        ($t:ty) => { vec![*field.as_any().downcast_ref::<$t>().unwrap() as f64] };
+    }
+
+    match field.data_type() {
+        DynamicTagDataType::SimplePrimitive(t) => match t {
+            SimplePrimitiveType::I8 => value!(i8),
+            SimplePrimitiveType::U8 => value!(u8),
+            SimplePrimitiveType::I16 => value!(i16),
+            SimplePrimitiveType::U16 => value!(u16),
+            SimplePrimitiveType::I32 => value!(i32),
+            SimplePrimitiveType::U32 => value!(u32),
+            SimplePrimitiveType::Float => value!(f64),
+            _ => Vec::new()
+        },
+        // Bounds
+        DynamicTagDataType::Block => field.fields().iter().flat_map(|f| numeric_values(field.get_field(f).unwrap())).collect(),
+        _ => Vec::new()
+    }
+}
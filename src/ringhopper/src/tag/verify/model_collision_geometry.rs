@@ -0,0 +1,27 @@
+use primitives::{primitive::TagPath, tag::PrimaryTagStructDyn};
+use ringhopper_structs::{ModelCollisionGeometry, ModelCollisionGeometryBSP};
+use crate::tag::collision_bsp::check::{check_collision_bsp, CollisionBSPIssueSeverity};
+use crate::tag::tree::TagTree;
+use super::{ScenarioContext, TagResult};
+
+pub fn verify_model_collision_geometry<T: TagTree + Send + Sync>(tag: &dyn PrimaryTagStructDyn, _path: &TagPath, _context: &ScenarioContext<T>, result: &mut TagResult) {
+    let model_collision_geometry: &ModelCollisionGeometry = tag.as_any().downcast_ref().unwrap();
+    let material_count = model_collision_geometry.materials.items.len();
+
+    // BSPs are relative to their node, so say which node it is.
+    for node in &model_collision_geometry.nodes {
+        for (bsp_index, bsp) in ziperator!(node.bsps) {
+            report_collision_bsp_issues(bsp, material_count, &format!("Node `{}` BSP #{bsp_index}", node.name), result);
+        }
+    }
+}
+
+pub(crate) fn report_collision_bsp_issues(bsp: &ModelCollisionGeometryBSP, material_count: usize, what: &str, result: &mut TagResult) {
+    for issue in check_collision_bsp(bsp, material_count) {
+        let message = format!("{what}: {issue}");
+        match issue.severity {
+            CollisionBSPIssueSeverity::Error => result.errors.push(message),
+            CollisionBSPIssueSeverity::Warning => result.warnings.push(message)
+        }
+    }
+}
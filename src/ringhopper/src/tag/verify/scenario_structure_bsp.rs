@@ -4,10 +4,15 @@ use ringhopper_structs::*;
 use crate::tag::tree::TagTree;
 
 use super::{ScenarioContext, TagResult};
+use super::model_collision_geometry::report_collision_bsp_issues;
 
 pub fn verify_scenario_structure_bsp<T: TagTree + Send + Sync + 'static>(tag: &dyn PrimaryTagStructDyn, _path: &TagPath, context: &ScenarioContext<T>, result: &mut TagResult) {
     let scenario_structure_bsp: &ScenarioStructureBSP = tag.as_any().downcast_ref().unwrap();
 
+    for bsp in &scenario_structure_bsp.collision_bsp {
+        report_collision_bsp_issues(bsp, scenario_structure_bsp.collision_materials.items.len(), "Collision BSP", result);
+    }
+
     // Check this before we can proceed.
     if !check_scenario_structure_bsp_vertex_data_size_correct(scenario_structure_bsp) {
         result.errors.push("BSP material(s) contain bad lightmap/render size(s). This tag needs remade.".to_owned());
@@ -0,0 +1,56 @@
+use definitions::{GBXModel, Globals, GlobalsGrenade, ModelNode, Weapon, WeaponMagazine, WeaponTrigger};
+use primitives::primitive::TagPath;
+use ringhopper_engines::ALL_SUPPORTED_ENGINES;
+use crate::tag::bludgeon::bludgeon_tag;
+use crate::tag::result::TagResult;
+use super::indices::find_invalid_indices;
+use super::limits::check_ranges_and_limits;
+
+/// Get the errors and warnings for grenades.
+fn check(globals: &Globals, engine: &str) -> (Vec<String>, Vec<String>) {
+    let engine = ALL_SUPPORTED_ENGINES.iter().find(|e| e.name == engine).unwrap();
+    let mut result = TagResult::default();
+    check_ranges_and_limits(globals, engine, &mut result);
+    let grenades = |issues: Vec<String>| issues.into_iter().filter(|e| e.starts_with("grenades")).collect();
+    (grenades(result.errors), grenades(result.warnings))
+}
+
+#[test]
+fn ranges_and_limits() {
+    let mut globals = Globals::default();
+    let (errors, warnings) = check(&globals, "pc-retail");
+    assert!(errors.is_empty());
+    assert_eq!(1, warnings.len(), "grenades should have a minimum of 2 entries");
+
+    globals.grenades.items.resize(3, GlobalsGrenade::default());
+    let (errors, warnings) = check(&globals, "pc-retail");
+    assert_eq!(1, errors.len(), "pc-retail should be limited to 2 grenades");
+    assert!(warnings.is_empty());
+    assert_eq!((Vec::new(), Vec::new()), check(&globals, "mcc-cea"));
+
+    // Editor ranges are only warnings
+    globals.grenades.items[1].maximum_count = 128;
+    assert_eq!((Vec::new(), vec!["grenades[1].maximum_count (128) is outside of the allowed range of [-inf, 127]".to_owned()]), check(&globals, "mcc-cea"));
+}
+
+#[test]
+fn invalid_indices() {
+    let mut weapon = Weapon::default();
+    weapon.magazines.items.push(WeaponMagazine::default());
+    for magazine in [Some(0), Some(1), None] {
+        weapon.triggers.items.push(WeaponTrigger { magazine, ..Default::default() });
+    }
+
+    let invalid: Vec<String> = find_invalid_indices(&weapon).into_iter().map(|i| i.path).collect();
+    assert_eq!(vec!["triggers[1].magazine"], invalid);
+
+    bludgeon_tag(&mut weapon, &TagPath::from_path("weapons\\test\\test.weapon").unwrap(), &mut Vec::new());
+    assert_eq!(None, weapon.triggers.items[1].magazine);
+    assert_eq!(Some(0), weapon.triggers.items[0].magazine);
+    assert!(find_invalid_indices(&weapon).is_empty());
+
+    // Structs shared between tags (model nodes are also used by gbxmodels)
+    let mut gbxmodel = GBXModel::default();
+    gbxmodel.nodes.items.push(ModelNode { parent_node_index: Some(1), ..Default::default() });
+    assert_eq!(1, find_invalid_indices(&gbxmodel).len());
+}